@@ -10,12 +10,13 @@ use clap::{clap_derive::ArgEnum, Parser};
 use fixed::types::I80F48;
 use marginfi::state::marginfi_account::TRANSFER_AUTHORITY_ALLOWED_FLAG;
 use marginfi::{
+    constants::{CURVE_POINTS, MAX_ORACLE_KEYS},
     prelude::*,
     state::{
         marginfi_account::{Balance, LendingAccount, MarginfiAccount, FLASHLOAN_ENABLED_FLAG},
         marginfi_group::{
-            Bank, BankConfig, BankConfigOpt, BankOperationalState, InterestRateConfig,
-            InterestRateConfigOpt, OracleConfig, RiskTier, WrappedI80F48,
+            rate_to_u32, Bank, BankConfig, BankConfigOpt, BankOperationalState, InterestRateConfig,
+            InterestRateConfigOpt, OracleConfig, RatePoint, RiskTier, WrappedI80F48,
         },
         price::OracleSetup,
     },
@@ -100,6 +101,25 @@ pub enum GroupCommand {
     },
     Update {
         admin: Option<Pubkey>,
+        #[clap(long)]
+        guardian: Option<Pubkey>,
+        #[clap(long)]
+        risk_admin: Option<Pubkey>,
+        #[clap(long)]
+        emissions_admin: Option<Pubkey>,
+        #[clap(long)]
+        fee_collector: Option<Pubkey>,
+        #[clap(long)]
+        bankruptcy_operator: Option<Pubkey>,
+        #[clap(
+            long,
+            help = "Delay in seconds before a queued bank config can be executed"
+        )]
+        config_delay: Option<u64>,
+    },
+    SetPauseState {
+        #[clap(help = "0: not paused, 1: banks paused, 2: all user instructions paused")]
+        pause_state: u8,
     },
     AddBank {
         #[clap(long)]
@@ -197,6 +217,7 @@ pub enum GroupCommand {
 pub enum RiskTierArg {
     Collateral,
     Isolated,
+    IsolatedCollateral,
 }
 
 impl From<RiskTierArg> for RiskTier {
@@ -204,6 +225,7 @@ impl From<RiskTierArg> for RiskTier {
         match value {
             RiskTierArg::Collateral => RiskTier::Collateral,
             RiskTierArg::Isolated => RiskTier::Isolated,
+            RiskTierArg::IsolatedCollateral => RiskTier::IsolatedCollateral,
         }
     }
 }
@@ -214,6 +236,7 @@ pub enum OracleTypeArg {
     SwitchboardLegacy,
     PythPushOracle,
     SwitchboardPull,
+    Fixed,
 }
 
 impl From<OracleTypeArg> for OracleSetup {
@@ -223,6 +246,7 @@ impl From<OracleTypeArg> for OracleSetup {
             OracleTypeArg::SwitchboardLegacy => OracleSetup::SwitchboardV2,
             OracleTypeArg::PythPushOracle => OracleSetup::PythPushOracle,
             OracleTypeArg::SwitchboardPull => OracleSetup::SwitchboardPull,
+            OracleTypeArg::Fixed => OracleSetup::Fixed,
         }
     }
 }
@@ -289,12 +313,44 @@ pub enum BankCommand {
         pf_ir: Option<f64>,
         #[clap(long, help = "Protocol origination fee")]
         pf_or: Option<f64>,
+        #[clap(
+            long,
+            help = "Interest rate curve type, 0 = legacy (opr_ur, p_ir, m_ir), 1 = multi-point, 2 = adaptive (legacy with a moving p_ir)"
+        )]
+        ir_curve_type: Option<u8>,
+        #[clap(long, help = "Multi-point curve interest rate at 0% utilization")]
+        ir_zero_util_rate: Option<f64>,
+        #[clap(long, help = "Multi-point curve interest rate at 100% utilization")]
+        ir_hundred_util_rate: Option<f64>,
+        #[clap(
+            long,
+            help = "Multi-point curve breakpoints as utilization:rate, sorted by utilization (e.g. 0.8:0.1 0.9:1)"
+        )]
+        ir_points: Option<Vec<String>>,
+        #[clap(long, help = "Adaptive curve lowest plateau interest rate")]
+        ir_adaptive_min_plateau_rate: Option<f64>,
+        #[clap(long, help = "Adaptive curve highest plateau interest rate")]
+        ir_adaptive_max_plateau_rate: Option<f64>,
+        #[clap(long, help = "Adaptive curve max plateau interest rate change per day")]
+        ir_adaptive_speed: Option<f64>,
+        #[clap(long, help = "Cap on the lending rate, 0 to disable")]
+        ir_max_lending_rate: Option<f64>,
+        #[clap(long, help = "Cap on the borrowing rate (fees included), 0 to disable")]
+        ir_max_borrowing_rate: Option<f64>,
         #[clap(long, arg_enum, help = "Bank risk tier")]
         risk_tier: Option<RiskTierArg>,
         #[clap(long, arg_enum, help = "Bank oracle type")]
         oracle_type: Option<OracleTypeArg>,
         #[clap(long, help = "Bank oracle account")]
         oracle_key: Option<Pubkey>,
+        #[clap(
+            long,
+            arg_enum,
+            help = "Fallback oracle type, used while the primary is stale"
+        )]
+        fallback_oracle_type: Option<OracleTypeArg>,
+        #[clap(long, help = "Fallback oracle account")]
+        fallback_oracle_key: Option<Pubkey>,
         #[clap(long, help = "Soft USD init limit")]
         usd_init_limit: Option<u64>,
         #[clap(long, help = "Oracle max age in seconds, 0 to use default value (60s)")]
@@ -304,6 +360,125 @@ pub enum BankCommand {
             help = "Permissionless bad debt settlement, if true the group admin is not required to settle bad debt"
         )]
         permissionless_bad_debt_settlement: Option<bool>,
+        #[clap(long, help = "Liquidator fee in bps, replaces the default value (250)")]
+        liquidation_liquidator_fee_bps: Option<u16>,
+        #[clap(
+            long,
+            help = "Liquidation insurance fee in bps, replaces the default value (250)"
+        )]
+        liquidation_insurance_fee_bps: Option<u16>,
+        #[clap(
+            long,
+            help = "Liquidator fee in bps charged to deeply unhealthy accounts, at or above the liquidator fee"
+        )]
+        liquidation_max_liquidator_fee_bps: Option<u16>,
+        #[clap(
+            long,
+            help = "Shortfall (bps of liabilities) at which the max liquidator fee applies, replaces the default value (1000)"
+        )]
+        liquidation_fee_ramp_shortfall_bps: Option<u16>,
+        #[clap(
+            long,
+            help = "Max share (bps) of a liability repaid per liquidation, 0 to disable the close factor"
+        )]
+        liquidation_close_factor_bps: Option<u16>,
+        #[clap(
+            long,
+            help = "Shortfall (bps of liabilities) past which the close factor no longer applies, 0 to always apply it"
+        )]
+        liquidation_close_factor_shortfall_bps: Option<u16>,
+        #[clap(
+            long,
+            help = "APR added to the borrowing rate to lock fixed-term borrow rates"
+        )]
+        fixed_term_premium: Option<f64>,
+        #[clap(
+            long,
+            help = "Longest fixed-term borrow allowed in seconds, 0 to disable fixed-term borrows"
+        )]
+        fixed_term_max_duration: Option<u64>,
+        #[clap(
+            long,
+            help = "Max spread of a median oracle's feeds, or band of a peg, in basis points"
+        )]
+        oracle_max_deviation_bps: Option<u16>,
+        #[clap(long, help = "Price in USD of fixed and pegged oracle setups")]
+        fixed_price: Option<f64>,
+        #[clap(
+            long,
+            help = "Window of the program maintained TWAP in seconds, 0 to disable"
+        )]
+        twap_window: Option<u64>,
+        #[clap(long, help = "Oracle confidence multiple, 0 for the feed's default")]
+        oracle_conf_interval_multiple: Option<f64>,
+        #[clap(
+            long,
+            help = "Max oracle confidence interval as a share of the price, 0 for 5%"
+        )]
+        oracle_max_conf_interval: Option<f64>,
+        #[clap(
+            long,
+            help = "Oracle confidence interval mode, 0 = cap wide intervals, 1 = reject the price"
+        )]
+        oracle_conf_interval_mode: Option<u8>,
+        #[clap(long, help = "E-mode category of correlated banks, 0 for none")]
+        emode_category: Option<u16>,
+        #[clap(long, help = "E-mode asset weight init")]
+        emode_asset_weight_init: Option<f64>,
+        #[clap(long, help = "E-mode asset weight maint")]
+        emode_asset_weight_maint: Option<f64>,
+        #[clap(long, help = "E-mode liability weight init")]
+        emode_liability_weight_init: Option<f64>,
+        #[clap(long, help = "E-mode liability weight maint")]
+        emode_liability_weight_maint: Option<f64>,
+        #[clap(long, help = "Pair rules class of the bank, 0 to 63")]
+        pair_class: Option<u8>,
+        #[clap(
+            long,
+            help = "Bitmask of the classes of liabilities the bank's deposits may back, 0 for any"
+        )]
+        collateral_for_classes: Option<u64>,
+        #[clap(
+            long,
+            help = "Bitmask of the classes of deposits that may back the liabilities, 0 for any"
+        )]
+        accepted_collateral_classes: Option<u64>,
+        #[clap(
+            long,
+            help = "Max USD debt backed by the bank's deposits, for isolated collateral"
+        )]
+        isolated_debt_ceiling: Option<u64>,
+        #[clap(long, help = "Max USD value of the bank's deposits, 0 for no limit")]
+        deposit_limit_usd: Option<u64>,
+        #[clap(long, help = "Max USD value of the bank's liabilities, 0 for no limit")]
+        borrow_limit_usd: Option<u64>,
+        #[clap(
+            long,
+            help = "Outflow limit window in seconds, 0 to disable the outflow limit"
+        )]
+        outflow_window: Option<u64>,
+        #[clap(
+            long,
+            help = "Max native amount leaving the vault in the window, 0 for no limit"
+        )]
+        outflow_limit: Option<u64>,
+        #[clap(
+            long,
+            help = "Max outflow in the window in bps of deposits, 0 for no limit"
+        )]
+        outflow_limit_bps: Option<u16>,
+        #[clap(
+            long,
+            help = "Max price move between recorded prices in bps, 0 to disable"
+        )]
+        price_guard_max_move_bps: Option<u16>,
+        #[clap(
+            long,
+            help = "Max seconds between recorded prices compared by the price guard"
+        )]
+        price_guard_interval: Option<u64>,
+        #[clap(long, help = "Reset a tripped price guard")]
+        price_guard_reset: Option<bool>,
     },
     InspectPriceOracle {
         bank_pk: Pubkey,
@@ -450,6 +625,19 @@ pub enum AccountCommand {
         #[clap(long)]
         account_migration_enabled: bool,
     },
+    ConfigureLimits {
+        account_pk: Pubkey,
+        #[clap(
+            long,
+            help = "Max USD value of the account's liabilities, 0 for no limit"
+        )]
+        max_liability_value: u64,
+        #[clap(
+            long = "allowed-bank",
+            help = "Bank the account may use, any bank if none"
+        )]
+        allowed_banks: Vec<Pubkey>,
+    },
 }
 
 #[derive(Debug, Parser)]
@@ -597,7 +785,31 @@ fn group(subcmd: GroupCommand, global_options: &GlobalOptions) -> Result<()> {
             override_existing_profile_group,
         } => processor::group_create(config, profile, admin, override_existing_profile_group),
 
-        GroupCommand::Update { admin } => processor::group_configure(config, profile, admin),
+        GroupCommand::Update {
+            admin,
+            guardian,
+            risk_admin,
+            emissions_admin,
+            fee_collector,
+            bankruptcy_operator,
+            config_delay,
+        } => processor::group_configure(
+            config,
+            profile,
+            GroupConfig {
+                admin,
+                guardian,
+                risk_admin,
+                emissions_admin,
+                fee_collector,
+                bankruptcy_operator,
+                config_delay,
+            },
+        ),
+
+        GroupCommand::SetPauseState { pause_state } => {
+            processor::group_set_pause_state(config, profile, pause_state)
+        }
 
         GroupCommand::AddBank {
             mint: bank_mint,
@@ -734,12 +946,54 @@ fn bank(subcmd: BankCommand, global_options: &GlobalOptions) -> Result<()> {
             pf_fa,
             pf_ir,
             pf_or,
+            ir_curve_type,
+            ir_zero_util_rate,
+            ir_hundred_util_rate,
+            ir_points,
+            ir_adaptive_min_plateau_rate,
+            ir_adaptive_max_plateau_rate,
+            ir_adaptive_speed,
+            ir_max_lending_rate,
+            ir_max_borrowing_rate,
             risk_tier,
             oracle_type,
             oracle_key,
+            fallback_oracle_type,
+            fallback_oracle_key,
             usd_init_limit,
             oracle_max_age,
             permissionless_bad_debt_settlement,
+            liquidation_liquidator_fee_bps,
+            liquidation_insurance_fee_bps,
+            liquidation_max_liquidator_fee_bps,
+            liquidation_fee_ramp_shortfall_bps,
+            liquidation_close_factor_bps,
+            liquidation_close_factor_shortfall_bps,
+            fixed_term_premium,
+            fixed_term_max_duration,
+            oracle_max_deviation_bps,
+            fixed_price,
+            twap_window,
+            oracle_conf_interval_multiple,
+            oracle_max_conf_interval,
+            oracle_conf_interval_mode,
+            emode_category,
+            emode_asset_weight_init,
+            emode_asset_weight_maint,
+            emode_liability_weight_init,
+            emode_liability_weight_maint,
+            pair_class,
+            collateral_for_classes,
+            accepted_collateral_classes,
+            isolated_debt_ceiling,
+            deposit_limit_usd,
+            borrow_limit_usd,
+            outflow_window,
+            outflow_limit,
+            outflow_limit_bps,
+            price_guard_max_move_bps,
+            price_guard_interval,
+            price_guard_reset,
         } => {
             let bank = config
                 .mfi_program
@@ -769,11 +1023,15 @@ fn bank(subcmd: BankCommand, global_options: &GlobalOptions) -> Result<()> {
                             .into(),
                         keys: [
                             x,
-                            Pubkey::default(),
+                            fallback_oracle_key.unwrap_or_default(),
                             Pubkey::default(),
                             Pubkey::default(),
                             Pubkey::default(),
                         ],
+                        source_setups: [OracleSetup::None; MAX_ORACLE_KEYS],
+                        fallback_setup: fallback_oracle_type
+                            .map(|x| x.into())
+                            .unwrap_or(OracleSetup::None),
                     }),
                     interest_rate_config: Some(InterestRateConfigOpt {
                         optimal_utilization_rate: opr_ur.map(|x| I80F48::from_num(x).into()),
@@ -784,11 +1042,62 @@ fn bank(subcmd: BankCommand, global_options: &GlobalOptions) -> Result<()> {
                         protocol_fixed_fee_apr: pf_fa.map(|x| I80F48::from_num(x).into()),
                         protocol_ir_fee: pf_ir.map(|x| I80F48::from_num(x).into()),
                         protocol_origination_fee: pf_or.map(|x| I80F48::from_num(x).into()),
+                        curve_type: ir_curve_type,
+                        zero_util_rate: ir_zero_util_rate.map(|x| rate_to_u32(I80F48::from_num(x))),
+                        hundred_util_rate: ir_hundred_util_rate
+                            .map(|x| rate_to_u32(I80F48::from_num(x))),
+                        points: ir_points.map(|points| parse_rate_points(&points)),
+                        adaptive_min_plateau_rate: ir_adaptive_min_plateau_rate
+                            .map(|x| rate_to_u32(I80F48::from_num(x))),
+                        adaptive_max_plateau_rate: ir_adaptive_max_plateau_rate
+                            .map(|x| rate_to_u32(I80F48::from_num(x))),
+                        adaptive_speed: ir_adaptive_speed.map(|x| rate_to_u32(I80F48::from_num(x))),
+                        max_lending_rate: ir_max_lending_rate
+                            .map(|x| rate_to_u32(I80F48::from_num(x))),
+                        max_borrowing_rate: ir_max_borrowing_rate
+                            .map(|x| rate_to_u32(I80F48::from_num(x))),
                     }),
                     risk_tier: risk_tier.map(|x| x.into()),
                     total_asset_value_init_limit: usd_init_limit,
                     oracle_max_age,
                     permissionless_bad_debt_settlement,
+                    liquidation_liquidator_fee_bps,
+                    liquidation_max_liquidator_fee_bps,
+                    liquidation_fee_ramp_shortfall_bps,
+                    liquidation_insurance_fee_bps,
+                    liquidation_close_factor_bps,
+                    liquidation_close_factor_shortfall_bps,
+                    fixed_term_premium: fixed_term_premium.map(|x| I80F48::from_num(x).into()),
+                    fixed_term_max_duration,
+                    oracle_max_deviation_bps,
+                    fixed_price: fixed_price.map(|x| I80F48::from_num(x).into()),
+                    twap_window,
+                    oracle_conf_interval_multiple: oracle_conf_interval_multiple
+                        .map(|x| I80F48::from_num(x).into()),
+                    oracle_max_conf_interval: oracle_max_conf_interval
+                        .map(|x| I80F48::from_num(x).into()),
+                    oracle_conf_interval_mode,
+                    emode_category,
+                    emode_asset_weight_init: emode_asset_weight_init
+                        .map(|x| I80F48::from_num(x).into()),
+                    emode_asset_weight_maint: emode_asset_weight_maint
+                        .map(|x| I80F48::from_num(x).into()),
+                    emode_liability_weight_init: emode_liability_weight_init
+                        .map(|x| I80F48::from_num(x).into()),
+                    emode_liability_weight_maint: emode_liability_weight_maint
+                        .map(|x| I80F48::from_num(x).into()),
+                    pair_class,
+                    collateral_for_classes,
+                    accepted_collateral_classes,
+                    isolated_debt_ceiling,
+                    deposit_limit_usd,
+                    borrow_limit_usd,
+                    outflow_window,
+                    outflow_limit,
+                    outflow_limit_bps,
+                    price_guard_max_move_bps,
+                    price_guard_interval,
+                    price_guard_reset,
                 },
             )
         }
@@ -843,6 +1152,28 @@ fn bank(subcmd: BankCommand, global_options: &GlobalOptions) -> Result<()> {
     }
 }
 
+/// Parses `utilization:rate` pairs into multi-point curve breakpoints, unused points are zeroed.
+fn parse_rate_points(points: &[String]) -> [RatePoint; CURVE_POINTS] {
+    assert!(
+        points.len() <= CURVE_POINTS,
+        "At most {} curve points can be provided",
+        CURVE_POINTS
+    );
+
+    let mut rate_points = [RatePoint::default(); CURVE_POINTS];
+    for (rate_point, point) in rate_points.iter_mut().zip(points) {
+        let (util, rate) = point
+            .split_once(':')
+            .expect("Curve points must be formatted as utilization:rate");
+        *rate_point = RatePoint::new(
+            I80F48::from_num(util.parse::<f64>().expect("Invalid utilization")),
+            I80F48::from_num(rate.parse::<f64>().expect("Invalid rate")),
+        );
+    }
+
+    rate_points
+}
+
 fn inspect_padding() -> Result<()> {
     println!("MarginfiGroup: {}", MarginfiGroup::type_layout());
     println!("GroupConfig: {}", GroupConfig::type_layout());
@@ -990,6 +1321,17 @@ fn process_account_subcmd(subcmd: AccountCommand, global_options: &GlobalOptions
 
             process_set_user_flag(config, &profile, account_pk, flag)
         }
+        AccountCommand::ConfigureLimits {
+            account_pk,
+            max_liability_value,
+            allowed_banks,
+        } => processor::process_configure_account_limits(
+            config,
+            &profile,
+            account_pk,
+            max_liability_value,
+            allowed_banks,
+        ),
     }?;
 
     Ok(())
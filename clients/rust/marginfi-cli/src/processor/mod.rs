@@ -8,7 +8,7 @@ use {
         config::Config,
         profile::{self, get_cli_config_dir, load_profile, CliConfig, Profile},
         utils::{
-            bank_to_oracle_key, calc_emissions_rate, create_oracle_key_array,
+            bank_to_oracle_keys, calc_emissions_rate, create_oracle_key_array,
             find_bank_emssions_auth_pda, find_bank_emssions_token_account_pda,
             find_bank_vault_authority_pda, find_bank_vault_pda, find_fee_state_pda,
             load_observation_account_metas, process_transaction, EXP_10_I80F48,
@@ -256,7 +256,7 @@ pub fn group_create(
     Ok(())
 }
 
-pub fn group_configure(config: Config, profile: Profile, admin: Option<Pubkey>) -> Result<()> {
+pub fn group_configure(config: Config, profile: Profile, group_config: GroupConfig) -> Result<()> {
     let rpc_client = config.mfi_program.rpc();
 
     if profile.marginfi_group.is_none() {
@@ -275,7 +275,7 @@ pub fn group_configure(config: Config, profile: Profile, admin: Option<Pubkey>)
             admin: config.authority(),
         })
         .args(marginfi::instruction::MarginfiGroupConfigure {
-            config: GroupConfig { admin },
+            config: group_config,
         })
         .instructions()?;
 
@@ -292,6 +292,41 @@ pub fn group_configure(config: Config, profile: Profile, admin: Option<Pubkey>)
     Ok(())
 }
 
+pub fn group_set_pause_state(config: Config, profile: Profile, pause_state: u8) -> Result<()> {
+    let rpc_client = config.mfi_program.rpc();
+    let marginfi_group_pubkey = profile.marginfi_group.ok_or_else(|| {
+        anyhow!(
+            "Marginfi group does not exist for profile [{}]",
+            profile.name
+        )
+    })?;
+
+    let set_pause_state_ixs = config
+        .mfi_program
+        .request()
+        .accounts(marginfi::accounts::MarginfiGroupSetPauseState {
+            marginfi_group: marginfi_group_pubkey,
+            signer: config.authority(),
+        })
+        .args(marginfi::instruction::MarginfiGroupSetPauseState { pause_state })
+        .instructions()?;
+
+    let recent_blockhash = rpc_client.get_latest_blockhash().unwrap();
+    let message = Message::new(&set_pause_state_ixs, Some(&config.authority()));
+    let mut transaction = Transaction::new_unsigned(message);
+    transaction.partial_sign(&config.get_signers(false), recent_blockhash);
+
+    match process_transaction(&transaction, &rpc_client, config.get_tx_mode()) {
+        Ok(sig) => println!("Group pause state updated (sig: {})", sig),
+        Err(err) => {
+            println!("Error during group pause state update:\n{:#?}", err);
+            return Err(anyhow!("Error during group pause state update"));
+        }
+    };
+
+    Ok(())
+}
+
 #[allow(clippy::too_many_arguments)]
 
 pub fn group_add_bank(
@@ -991,6 +1026,48 @@ pub fn process_set_user_flag(
     Ok(())
 }
 
+pub fn process_configure_account_limits(
+    config: Config,
+    profile: &Profile,
+    marginfi_account_pk: Pubkey,
+    max_liability_value: u64,
+    allowed_banks: Vec<Pubkey>,
+) -> Result<()> {
+    let rpc_client = config.mfi_program.rpc();
+
+    let ix = Instruction {
+        accounts: marginfi::accounts::ConfigureAccountLimits {
+            marginfi_account: marginfi_account_pk,
+            marginfi_group: profile.marginfi_group.unwrap(),
+            admin: config.authority(),
+        }
+        .to_account_metas(Some(true)),
+        data: marginfi::instruction::ConfigureAccountLimits {
+            max_liability_value,
+            allowed_banks,
+        }
+        .data(),
+        program_id: config.program_id,
+    };
+
+    let recent_blockhash = rpc_client.get_latest_blockhash().unwrap();
+
+    let signing_keypairs = config.get_signers(false);
+
+    let message = Message::new(&[ix], Some(&config.authority()));
+
+    let mut transaction = Transaction::new_unsigned(message);
+
+    transaction.partial_sign(&signing_keypairs, recent_blockhash);
+
+    match process_transaction(&transaction, &rpc_client, config.get_tx_mode()) {
+        Ok(sig) => println!("Account limits configured (sig: {})", sig),
+        Err(err) => println!("Error during account limits configuration:\n{:#?}", err),
+    };
+
+    Ok(())
+}
+
 pub fn initialize_fee_state(
     config: Config,
     admin: Pubkey,
@@ -1242,16 +1319,23 @@ pub fn bank_inspect_price_oracle(config: Config, bank_pk: Pubkey) -> Result<()>
     use marginfi::state::price::{OraclePriceType, PriceBias};
 
     let bank: Bank = config.mfi_program.account(bank_pk)?;
-    let mut price_oracle_account = config
+    let oracle_keys = bank_to_oracle_keys(&bank.config, PYTH_PUSH_PYTH_SPONSORED_SHARD_ID);
+    let mut oracle_accounts = config
         .mfi_program
         .rpc()
-        .get_account(&bank.config.oracle_keys[0])?;
-    let price_oracle_ai =
-        (&bank.config.oracle_keys[0], &mut price_oracle_account).into_account_info();
+        .get_multiple_accounts(&oracle_keys)?
+        .into_iter()
+        .map(|account| account.unwrap())
+        .collect::<Vec<_>>();
+    let oracle_ais = oracle_keys
+        .iter()
+        .zip(oracle_accounts.iter_mut())
+        .map(|(oracle_key, oracle_account)| (oracle_key, oracle_account).into_account_info())
+        .collect::<Vec<_>>();
 
-    let opfa = OraclePriceFeedAdapter::try_from_bank_config_with_max_age(
-        &bank.config,
-        &[price_oracle_ai],
+    let opfa = OraclePriceFeedAdapter::try_from_bank_with_max_age(
+        &bank,
+        &oracle_ais,
         &Clock::default(),
         u64::MAX,
     )
@@ -2094,6 +2178,15 @@ pub fn marginfi_account_deposit(
         ix.accounts
             .push(AccountMeta::new_readonly(bank.mint, false));
     }
+    // The USD deposit limit is checked with the bank's oracle
+    if bank.is_deposit_limit_usd_active() {
+        ix.accounts.push(AccountMeta::new_readonly(bank_pk, false));
+        ix.accounts.extend(
+            bank_to_oracle_keys(&bank.config, PYTH_PUSH_PYTH_SPONSORED_SHARD_ID)
+                .into_iter()
+                .map(|oracle_key| AccountMeta::new_readonly(oracle_key, false)),
+        );
+    }
 
     let recent_blockhash = rpc_client.get_latest_blockhash().unwrap();
     let tx = Transaction::new_signed_with_payer(
@@ -2378,18 +2471,20 @@ pub fn marginfi_account_liquidate(
 
     let oracle_accounts = vec![asset_bank.config, liability_bank.config]
         .into_iter()
-        .map(|bank_config| {
-            let oracle_key = bank_to_oracle_key(&bank_config, PYTH_PUSH_PYTH_SPONSORED_SHARD_ID);
-            AccountMeta::new_readonly(oracle_key, false)
+        .flat_map(|bank_config| {
+            bank_to_oracle_keys(&bank_config, PYTH_PUSH_PYTH_SPONSORED_SHARD_ID)
+                .into_iter()
+                .map(|oracle_key| AccountMeta::new_readonly(oracle_key, false))
         });
 
     ix.accounts.extend(oracle_accounts);
 
     let oracle_accounts = vec![asset_bank.config, liability_bank.config]
         .into_iter()
-        .map(|bank_config| {
-            let oracle_key = bank_to_oracle_key(&bank_config, PYTH_PUSH_PYTH_SPONSORED_SHARD_ID);
-            AccountMeta::new_readonly(oracle_key, false)
+        .flat_map(|bank_config| {
+            bank_to_oracle_keys(&bank_config, PYTH_PUSH_PYTH_SPONSORED_SHARD_ID)
+                .into_iter()
+                .map(|oracle_key| AccountMeta::new_readonly(oracle_key, false))
         });
 
     ix.accounts.extend(oracle_accounts);
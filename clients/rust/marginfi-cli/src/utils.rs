@@ -65,19 +65,23 @@ pub fn process_transaction(
     }
 }
 
-pub fn bank_to_oracle_key(bank_config: &BankConfig, shard_id: u16) -> Pubkey {
-    let oracle_key_or_price_feed_id = bank_config.oracle_keys.first().unwrap();
-
-    match bank_config.oracle_setup {
-        marginfi::state::price::OracleSetup::PythPushOracle => {
-            PythPushOraclePriceFeed::find_oracle_address(
-                shard_id,
-                bank_config.get_pyth_push_oracle_feed_id().unwrap(),
-            )
-            .0
-        }
-        _ => *oracle_key_or_price_feed_id,
-    }
+pub fn bank_to_oracle_keys(bank_config: &BankConfig, shard_id: u16) -> Vec<Pubkey> {
+    bank_config
+        .get_oracle_sources()
+        .into_iter()
+        .map(
+            |(oracle_setup, oracle_key_or_price_feed_id)| match oracle_setup {
+                marginfi::state::price::OracleSetup::PythPushOracle => {
+                    PythPushOraclePriceFeed::find_oracle_address(
+                        shard_id,
+                        &oracle_key_or_price_feed_id.to_bytes(),
+                    )
+                    .0
+                }
+                _ => oracle_key_or_price_feed_id,
+            },
+        )
+        .collect()
 }
 
 pub fn find_bank_vault_pda(
@@ -185,20 +189,21 @@ pub fn load_observation_account_metas(
         .iter()
         .zip(bank_pks.iter())
         .flat_map(|(bank, bank_pk)| {
-            let oracle_key = bank_to_oracle_key(&bank.config, PYTH_PUSH_PYTH_SPONSORED_SHARD_ID);
-
-            vec![
-                AccountMeta {
-                    pubkey: *bank_pk,
-                    is_signer: false,
-                    is_writable: false,
-                },
-                AccountMeta {
-                    pubkey: oracle_key,
-                    is_signer: false,
-                    is_writable: false,
-                },
-            ]
+            let oracle_keys = bank_to_oracle_keys(&bank.config, PYTH_PUSH_PYTH_SPONSORED_SHARD_ID);
+
+            let mut account_metas = vec![AccountMeta {
+                pubkey: *bank_pk,
+                is_signer: false,
+                is_writable: false,
+            }];
+
+            account_metas.extend(oracle_keys.into_iter().map(|oracle_key| AccountMeta {
+                pubkey: oracle_key,
+                is_signer: false,
+                is_writable: false,
+            }));
+
+            account_metas
         })
         .collect::<Vec<_>>();
     account_metas
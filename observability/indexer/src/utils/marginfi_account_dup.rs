@@ -70,7 +70,7 @@ impl BankAccountWithPriceFeed2 {
         bank: &Bank,
     ) -> anyhow::Result<I80F48> {
         match bank.config.risk_tier {
-            RiskTier::Collateral => {
+            RiskTier::Collateral | RiskTier::IsolatedCollateral => {
                 let price_feed = &self.price_feed;
                 let mut asset_weight = bank
                     .config
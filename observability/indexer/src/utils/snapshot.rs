@@ -223,50 +223,53 @@ impl Snapshot {
                 let mut accounts_to_fetch =
                     vec![bank.liquidity_vault, bank.insurance_vault, bank.fee_vault];
 
-                match bank.config.oracle_setup {
-                    OracleSetup::None => (),
-                    OracleSetup::PythLegacy => {
-                        let oracle_address = bank.config.oracle_keys[0];
-                        self.routing_lookup
-                            .insert(oracle_address, AccountRoutingType::PriceFeedPyth);
-                        accounts_to_fetch.push(oracle_address);
-                    }
-                    OracleSetup::SwitchboardV2 => {
-                        let oracle_address = bank.config.oracle_keys[0];
-                        self.routing_lookup
-                            .insert(oracle_address, AccountRoutingType::PriceFeedSwitchboard);
-                        accounts_to_fetch.push(oracle_address);
-                    }
-                    OracleSetup::PythPushOracle => {
-                        let feed_id = bank.config.oracle_keys[0].to_bytes();
-                        let (pyth_sponsored_oracle_address, _) =
-                            PythPushOraclePriceFeed::find_oracle_address(
-                                PYTH_PUSH_PYTH_SPONSORED_SHARD_ID,
-                                &feed_id,
+                for (oracle_setup, oracle_key) in bank.config.get_oracle_sources() {
+                    match oracle_setup {
+                        OracleSetup::None
+                        | OracleSetup::Median
+                        | OracleSetup::Fixed
+                        | OracleSetup::Peg
+                        | OracleSetup::StakePool => (),
+                        OracleSetup::PythLegacy => {
+                            self.routing_lookup
+                                .insert(oracle_key, AccountRoutingType::PriceFeedPyth);
+                            accounts_to_fetch.push(oracle_key);
+                        }
+                        OracleSetup::SwitchboardV2 => {
+                            self.routing_lookup
+                                .insert(oracle_key, AccountRoutingType::PriceFeedSwitchboard);
+                            accounts_to_fetch.push(oracle_key);
+                        }
+                        OracleSetup::PythPushOracle => {
+                            let feed_id = oracle_key.to_bytes();
+                            let (pyth_sponsored_oracle_address, _) =
+                                PythPushOraclePriceFeed::find_oracle_address(
+                                    PYTH_PUSH_PYTH_SPONSORED_SHARD_ID,
+                                    &feed_id,
+                                );
+                            let (mfi_sponsored_oracle_address, _) =
+                                PythPushOraclePriceFeed::find_oracle_address(
+                                    PYTH_PUSH_MARGINFI_SPONSORED_SHARD_ID,
+                                    &feed_id,
+                                );
+
+                            self.routing_lookup.insert(
+                                pyth_sponsored_oracle_address,
+                                AccountRoutingType::PriceFeedPythPushOracle,
                             );
-                        let (mfi_sponsored_oracle_address, _) =
-                            PythPushOraclePriceFeed::find_oracle_address(
-                                PYTH_PUSH_MARGINFI_SPONSORED_SHARD_ID,
-                                &feed_id,
+                            self.routing_lookup.insert(
+                                mfi_sponsored_oracle_address,
+                                AccountRoutingType::PriceFeedPythPushOracle,
                             );
 
-                        self.routing_lookup.insert(
-                            pyth_sponsored_oracle_address,
-                            AccountRoutingType::PriceFeedPythPushOracle,
-                        );
-                        self.routing_lookup.insert(
-                            mfi_sponsored_oracle_address,
-                            AccountRoutingType::PriceFeedPythPushOracle,
-                        );
-
-                        accounts_to_fetch.push(pyth_sponsored_oracle_address);
-                        accounts_to_fetch.push(mfi_sponsored_oracle_address);
-                    }
-                    OracleSetup::SwitchboardPull => {
-                        let oracle_address = bank.config.oracle_keys[0];
-                        self.routing_lookup
-                            .insert(oracle_address, AccountRoutingType::PriceFeedSwitchboardPull);
-                        accounts_to_fetch.push(oracle_address);
+                            accounts_to_fetch.push(pyth_sponsored_oracle_address);
+                            accounts_to_fetch.push(mfi_sponsored_oracle_address);
+                        }
+                        OracleSetup::SwitchboardPull => {
+                            self.routing_lookup
+                                .insert(oracle_key, AccountRoutingType::PriceFeedSwitchboardPull);
+                            accounts_to_fetch.push(oracle_key);
+                        }
                     }
                 }
 
@@ -385,6 +388,7 @@ impl Snapshot {
                     *account_pubkey,
                     OracleData::SwitchboardPull(SwitchboardPullPriceFeed {
                         feed: Box::new((&pf).into()),
+                        conf_interval_config: ConfIntervalConfig::default(),
                     }),
                 );
             }
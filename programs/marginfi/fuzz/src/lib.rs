@@ -1120,7 +1120,7 @@ mod tests {
             let remaining_accounts =
                 margin_account.get_remaining_accounts(&bank_map, vec![], vec![]);
 
-            let re = RiskEngine::new(&marginfi_account, aisls(&remaining_accounts)).unwrap();
+            let re = RiskEngine::new(&marginfi_account, &[], aisls(&remaining_accounts)).unwrap();
 
             let health = re
                 .get_account_health(
@@ -1180,7 +1180,7 @@ mod tests {
             let remaining_accounts =
                 margin_account.get_remaining_accounts(&bank_map, vec![], vec![]);
 
-            let re = RiskEngine::new(&marginfi_account, aisls(&remaining_accounts)).unwrap();
+            let re = RiskEngine::new(&marginfi_account, &[], aisls(&remaining_accounts)).unwrap();
 
             let health = re
                 .get_account_health(
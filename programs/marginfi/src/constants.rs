@@ -17,6 +17,8 @@ pub const FEE_STATE_SEED: &str = "feestate";
 pub const EMISSIONS_AUTH_SEED: &str = "emissions_auth_seed";
 pub const EMISSIONS_TOKEN_ACCOUNT_SEED: &str = "emissions_token_account_seed";
 
+pub const PENDING_BANK_CONFIG_SEED: &str = "pending_bank_config";
+
 cfg_if::cfg_if! {
     if #[cfg(feature = "devnet")] {
         pub const PYTH_ID: Pubkey = pubkey!("gSbePebfvPy7tRqimPoVecS2UsBvYv46ynrzWocc92s");
@@ -36,17 +38,41 @@ cfg_if::cfg_if! {
     }
 }
 
-/// TODO: Make these variable per bank
+/// SPL stake pool program, owner of the pools priced by `OracleSetup::StakePool`
+pub const SPL_STAKE_POOL_ID: Pubkey = pubkey!("SPoo1Ku8WFXoNDMHPsrGSTSG1Y47rzgn41SLUNakuHy");
+
+/// Default liquidation fees, used by banks that don't configure their own
+/// (see `LIQUIDATION_LIQUIDATOR_FEE_SET_FLAG` and `LIQUIDATION_INSURANCE_FEE_SET_FLAG`).
 pub const LIQUIDATION_LIQUIDATOR_FEE: I80F48 = I80F48!(0.025);
 pub const LIQUIDATION_INSURANCE_FEE: I80F48 = I80F48!(0.025);
 
+/// `BankConfig::liquidation_fee_flags`: the bank configured its own liquidator fee, which may be 0
+pub const LIQUIDATION_LIQUIDATOR_FEE_SET_FLAG: u8 = 1 << 0;
+/// `BankConfig::liquidation_fee_flags`: the bank configured its own insurance fee, which may be 0
+pub const LIQUIDATION_INSURANCE_FEE_SET_FLAG: u8 = 1 << 1;
+/// `BankConfig::liquidation_fee_flags`: the bank configured a max liquidator fee, enabling the
+/// liquidator fee ramp
+pub const LIQUIDATION_MAX_LIQUIDATOR_FEE_SET_FLAG: u8 = 1 << 2;
+/// `BankConfig::liquidation_fee_flags`: the bank configured its own fee ramp shortfall, which may
+/// be 0
+pub const LIQUIDATION_FEE_RAMP_SHORTFALL_SET_FLAG: u8 = 1 << 3;
+
+/// Default shortfall (as a share of maintenance-weighted liabilities) at which the liquidator fee
+/// reaches `BankConfig::liquidation_max_liquidator_fee_bps`, used until the bank configures its
+/// own (see `LIQUIDATION_FEE_RAMP_SHORTFALL_SET_FLAG`).
+pub const LIQUIDATION_FEE_RAMP_SHORTFALL: I80F48 = I80F48!(0.1);
+
 /// The default fee, in native SOL in native decimals (i.e. lamports) used in testing
 pub const INIT_BANK_ORIGINATION_FEE_DEFAULT: u32 = 10000;
 
 pub const SECONDS_PER_YEAR: I80F48 = I80F48!(31_536_000);
+pub const SECONDS_PER_DAY: I80F48 = I80F48!(86_400);
 
 pub const MAX_PYTH_ORACLE_AGE: u64 = 60;
 pub const MAX_SWB_ORACLE_AGE: u64 = 3 * 60;
+/// Epochs a stake pool's exchange rate may lag behind the clock. Stake pools are only updated by a
+/// crank after each epoch boundary, the rate of the previous epoch is used until then.
+pub const MAX_STAKE_POOL_EPOCH_LAG: u64 = 1;
 
 /// Range that contains 95% price data distribution
 ///
@@ -56,11 +82,31 @@ pub const CONF_INTERVAL_MULTIPLE: I80F48 = I80F48!(2.12);
 pub const STD_DEV_MULTIPLE: I80F48 = I80F48!(1.96);
 /// Maximum confidence interval allowed
 pub const MAX_CONF_INTERVAL: I80F48 = I80F48!(0.05);
+/// `Bank::oracle_conf_interval_mode`, confidence intervals wider than the max are capped
+pub const CONF_INTERVAL_MODE_CAP: u8 = 0;
+/// `Bank::oracle_conf_interval_mode`, prices with a confidence interval wider than the max are
+/// rejected
+pub const CONF_INTERVAL_MODE_REJECT: u8 = 1;
 
 pub const USDC_EXPONENT: i32 = 6;
 
 pub const MAX_ORACLE_KEYS: usize = 5;
 
+/// `InterestRateConfig::curve_type`, two-segment curve built from `optimal_utilization_rate`,
+/// `plateau_interest_rate` and `max_interest_rate`
+pub const INTEREST_CURVE_LEGACY: u8 = 0;
+/// `InterestRateConfig::curve_type`, curve interpolated between `zero_util_rate`, up to
+/// `CURVE_POINTS` breakpoints and `hundred_util_rate`
+pub const INTEREST_CURVE_MULTI_POINT: u8 = 1;
+/// `InterestRateConfig::curve_type`, same shape as `INTEREST_CURVE_LEGACY`, but the plateau rate
+/// moves toward the rate that keeps utilization at `optimal_utilization_rate`
+/// (see `Bank::adaptive_plateau_rate`)
+pub const INTEREST_CURVE_ADAPTIVE: u8 = 2;
+/// Max number of (utilization, rate) breakpoints in a multi-point interest rate curve
+pub const CURVE_POINTS: usize = 5;
+/// APR represented by a `u32::MAX` rate in a multi-point interest rate curve, 10 = 1000%
+pub const MAX_CURVE_RATE: I80F48 = I80F48!(10);
+
 /// Any balance below 1 SPL token amount is treated as none,
 /// this is to account for any artifacts resulting from binary fraction arithemtic.
 pub const EMPTY_BALANCE_THRESHOLD: I80F48 = I80F48!(1);
@@ -141,6 +187,16 @@ pub const EXP_10: [i128; MAX_EXP_10] = [
 /// Value where total_asset_value_init_limit is considered inactive
 pub const TOTAL_ASSET_VALUE_INIT_LIMIT_INACTIVE: u64 = 0;
 
+/// Value where deposit_limit_usd and borrow_limit_usd are considered inactive
+pub const USD_LIMIT_INACTIVE: u64 = 0;
+
+/// `MarginfiGroup::pause_state`, the group is not paused
+pub const GROUP_PAUSE_STATE_NONE: u8 = 0;
+/// `MarginfiGroup::pause_state`, every bank of the group behaves as `BankOperationalState::Paused`
+pub const GROUP_PAUSE_STATE_BANKS: u8 = 1;
+/// `MarginfiGroup::pause_state`, every user instruction of the group is paused
+pub const GROUP_PAUSE_STATE_ALL: u8 = 2;
+
 /// For testing, this is a typical program fee.
 pub const PROTOCOL_FEE_RATE_DEFAULT: I80F48 = I80F48!(0.025);
 /// For testing, this is a typical program fee.
@@ -98,6 +98,32 @@ pub enum MarginfiError {
     T22MintRequired,
     #[msg("Invalid ATA for global fee account")] // 6048
     InvalidFeeAta,
+    #[msg("Invalid fixed-term borrow")] // 6049
+    InvalidFixedTermBorrow,
+    #[msg("Fixed-term borrow not found")] // 6050
+    FixedTermBorrowNotFound,
+    #[msg("Fixed-term borrow slots are full")] // 6051
+    FixedTermBorrowSlotsFull,
+    #[msg("Fixed-term borrow is not matured and the account is healthy")] // 6052
+    FixedTermBorrowNotConvertible,
+    #[msg("Oracle sources diverge beyond the bank's max deviation")] // 6053
+    OracleSourcesDiverged,
+    #[msg("Oracle confidence interval is wider than the bank's max")] // 6054
+    OracleConfidenceTooWide,
+    #[msg("Bank not allowed for the account by the group admin")] // 6055
+    AccountBankNotAllowed,
+    #[msg("Account liabilities exceed the limit set by the group admin")] // 6056
+    AccountLiabilityLimitExceeded,
+    #[msg("Bank outflow limit exceeded for the current window")] // 6057
+    BankOutflowLimitExceeded,
+    #[msg("Bank price guard tripped on a sharp oracle price move")] // 6058
+    BankPriceGuardTripped,
+    #[msg("Group paused by the guardian")] // 6059
+    GroupPaused,
+    #[msg("Bank config must be queued and executed after the group's config delay")] // 6060
+    BankConfigTimelocked,
+    #[msg("Queued bank config can't be executed yet")] // 6061
+    BankConfigNotReady,
 }
 
 impl From<MarginfiError> for ProgramError {
@@ -1,4 +1,7 @@
-use crate::{prelude::*, state::marginfi_group::BankConfigOpt};
+use crate::{
+    prelude::*,
+    state::{marginfi_group::BankConfigOpt, price::OracleSource},
+};
 use anchor_lang::prelude::*;
 
 // Event headers
@@ -30,6 +33,12 @@ pub struct MarginfiGroupConfigureEvent {
     pub config: GroupConfig,
 }
 
+#[event]
+pub struct MarginfiGroupSetPauseStateEvent {
+    pub header: GroupEventHeader,
+    pub pause_state: u8,
+}
+
 #[event]
 pub struct LendingPoolBankCreateEvent {
     pub header: GroupEventHeader,
@@ -53,6 +62,8 @@ pub struct LendingPoolBankAccrueInterestEvent {
     pub delta: u64,
     pub fees_collected: f64,
     pub insurance_collected: f64,
+    /// Plateau rate after this accrual, moves over time for adaptive curves
+    pub plateau_interest_rate: f64,
 }
 
 #[event]
@@ -117,6 +128,42 @@ pub struct LendingAccountWithdrawEvent {
     pub close_balance: bool,
 }
 
+#[event]
+pub struct LendingAccountBorrowFixedTermEvent {
+    pub header: AccountEventHeader,
+    pub bank: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub rate: f64,
+    pub maturity: i64,
+}
+
+#[event]
+pub struct LendingAccountRepayFixedTermEvent {
+    pub header: AccountEventHeader,
+    pub bank: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct LendingAccountConvertFixedTermEvent {
+    pub header: AccountEventHeader,
+    pub bank: Pubkey,
+    pub mint: Pubkey,
+    pub amount: f64,
+}
+
+/// Emitted by the risk engine for balances in banks with a fallback oracle, see
+/// `BankConfig::oracle_fallback_setup`.
+#[event]
+pub struct LendingAccountOracleSourceEvent {
+    pub marginfi_group: Pubkey,
+    pub marginfi_account_authority: Pubkey,
+    pub bank: Pubkey,
+    pub oracle_source: OracleSource,
+}
+
 #[derive(AnchorSerialize, AnchorDeserialize)]
 pub struct LiquidationBalances {
     pub liquidatee_asset_balance: f64,
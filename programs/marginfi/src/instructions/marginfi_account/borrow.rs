@@ -5,7 +5,10 @@ use crate::{
     math_error,
     prelude::{MarginfiError, MarginfiGroup, MarginfiResult},
     state::{
-        marginfi_account::{BankAccountWrapper, MarginfiAccount, RiskEngine, DISABLED_FLAG},
+        marginfi_account::{
+            BankAccountWrapper, MarginfiAccount, RiskEngine, DELEGATE_BORROW_PERMISSION,
+            DISABLED_FLAG, IN_FLASHLOAN_FLAG,
+        },
         marginfi_group::{Bank, BankVaultType},
     },
     utils,
@@ -15,13 +18,19 @@ use anchor_spl::token_interface::{TokenAccount, TokenInterface};
 use fixed::types::I80F48;
 use solana_program::{clock::Clock, sysvar::Sysvar};
 
-/// 1. Accrue interest
+/// 1. Accrue interest, and record the bank's price for its price guard
 /// 2. Create the user's bank account for the asset borrowed if it does not exist yet
 /// 3. Record liability increase in the bank account
-/// 4. Transfer funds from the bank's liquidity vault to the signer's token account
-/// 5. Verify that the user account is in a healthy state
+/// 4. Record the outflow against the bank's outflow limit (checked at the end of a flashloan
+///    instead, when in one), then transfer funds from the bank's liquidity vault to the signer's
+///    token account
+/// 5. Verify that the bank's USD borrow limit is not exceeded, if active
+/// 6. Verify that the user account is in a healthy state, without the deposits in banks whose
+///    price guard tripped
+/// 7. Verify that the user account's liabilities are within the limit set by the group admin
 ///
-/// Will error if there is an existing asset <=> withdrawing is not allowed.
+/// Will error if there is an existing asset <=> withdrawing is not allowed, or if the bank's price
+/// guard tripped.
 pub fn lending_account_borrow<'info>(
     mut ctx: Context<'_, '_, 'info, 'info, LendingAccountBorrow<'info>>,
     amount: u64,
@@ -43,7 +52,8 @@ pub fn lending_account_borrow<'info>(
         token_program.key,
     )?;
 
-    let mut marginfi_account = marginfi_account_loader.load_mut()?;
+    let (mut marginfi_account, mut extended_balances) =
+        MarginfiAccount::load_mut_extended(marginfi_account_loader)?;
     let group = &marginfi_group_loader.load()?;
     let program_fee_rate: I80F48 = group.fee_state_cache.program_fee_rate.into();
 
@@ -52,6 +62,9 @@ pub fn lending_account_borrow<'info>(
         MarginfiError::AccountDisabled
     );
 
+    marginfi_account.check_bank_allowed(&bank_loader.key())?;
+    let marginfi_account_in_flashloan = marginfi_account.get_flag(IN_FLASHLOAN_FLAG);
+
     bank_loader.load_mut()?.accrue_interest(
         clock.unix_timestamp,
         group,
@@ -70,10 +83,28 @@ pub fn lending_account_borrow<'info>(
             .protocol_origination_fee
             .into();
 
+        // The bank's oracle accounts are passed for the health check, except in a flashloan
+        if bank.is_price_guard_active() && !marginfi_account_in_flashloan {
+            let price = utils::get_bank_price(
+                &bank_loader.key(),
+                &bank,
+                ctx.remaining_accounts,
+                &clock,
+                None,
+            )?;
+            bank.update_price_guard(price, clock.unix_timestamp)?;
+        }
+
+        check!(
+            !bank.is_price_guard_tripped(clock.unix_timestamp),
+            MarginfiError::BankPriceGuardTripped
+        );
+
         let mut bank_account = BankAccountWrapper::find_or_create(
             &bank_loader.key(),
             &mut bank,
             &mut marginfi_account.lending_account,
+            &mut extended_balances,
         )?;
 
         // User needs to borrow amount + fee to receive amount
@@ -97,11 +128,23 @@ pub fn lending_account_borrow<'info>(
             origination_fee_u64 = origination_fee.checked_to_num().ok_or_else(math_error!())?;
 
             // Incurs a borrow that includes the origination fee (but withdraws just the amt)
-            bank_account.borrow(I80F48::from_num(amount_pre_fee) + origination_fee)?;
+            bank_account.borrow(I80F48::from_num(amount_pre_fee) + origination_fee, group)?;
         } else {
             // Incurs a borrow for the amount without any fee
             origination_fee_u64 = 0;
-            bank_account.borrow(I80F48::from_num(amount_pre_fee))?;
+            bank_account.borrow(I80F48::from_num(amount_pre_fee), group)?;
+        }
+
+        // Flashloan borrows are checked against the outflow limit once the flashloan ends, net of
+        // the repays made in it
+        if marginfi_account_in_flashloan {
+            bank_account
+                .bank
+                .record_flashloan_outflow(amount_pre_fee, clock.unix_timestamp)?;
+        } else {
+            bank_account
+                .bank
+                .record_outflow(amount_pre_fee, clock.unix_timestamp)?;
         }
 
         bank_account.withdraw_spl_transfer(
@@ -136,36 +179,31 @@ pub fn lending_account_borrow<'info>(
     {
         let mut bank = bank_loader.load_mut()?;
 
-        if !origination_fee.is_zero() {
-            let mut bank_fees_after: I80F48 = bank.collected_group_fees_outstanding.into();
-
-            if !program_fee_rate.is_zero() {
-                // Some portion of the origination fee to goes to program fees
-                let program_fee_amount: I80F48 = origination_fee
-                    .checked_mul(program_fee_rate)
-                    .ok_or_else(math_error!())?;
-                // The remainder of the origination fee goes to group fees
-                bank_fees_after = bank_fees_after
-                    .saturating_add(origination_fee.saturating_sub(program_fee_amount));
-
-                // Update the bank's program fees
-                let program_fees_before: I80F48 = bank.collected_program_fees_outstanding.into();
-                bank.collected_program_fees_outstanding = program_fees_before
-                    .saturating_add(program_fee_amount)
-                    .into();
-            } else {
-                // If program fee rate is zero, add the full origination fee to group fees
-                bank_fees_after = bank_fees_after.saturating_add(origination_fee);
-            }
-
-            // Update the bank's group fees
-            bank.collected_group_fees_outstanding = bank_fees_after.into();
+        bank.collect_origination_fee(origination_fee, program_fee_rate)?;
+
+        if bank.is_borrow_limit_usd_active() {
+            let price = utils::get_bank_limit_price(
+                &bank_loader.key(),
+                &bank,
+                ctx.remaining_accounts,
+                &clock,
+            )?;
+            bank.check_borrow_limit_usd(price)?;
         }
     }
 
     // Check account health, if below threshold fail transaction
     // Assuming `ctx.remaining_accounts` holds only oracle accounts
-    RiskEngine::check_account_init_health(&marginfi_account, ctx.remaining_accounts)?;
+    RiskEngine::check_account_borrow_health(
+        &marginfi_account,
+        &extended_balances,
+        ctx.remaining_accounts,
+    )?;
+    RiskEngine::check_account_liability_limit(
+        &marginfi_account,
+        &extended_balances,
+        ctx.remaining_accounts,
+    )?;
 
     Ok(())
 }
@@ -181,7 +219,11 @@ pub struct LendingAccountBorrow<'info> {
     pub marginfi_account: AccountLoader<'info, MarginfiAccount>,
 
     #[account(
-        address = marginfi_account.load() ?.authority,
+        constraint = marginfi_account.load()?.can_sign_outflow(
+            signer.key,
+            DELEGATE_BORROW_PERMISSION,
+            &destination_token_account.owner,
+        ),
     )]
     pub signer: Signer<'info>,
 
@@ -3,10 +3,11 @@ use anchor_lang::prelude::*;
 use crate::{check, state::marginfi_account::MarginfiAccount, MarginfiError, MarginfiResult};
 
 pub fn close_account(ctx: Context<MarginfiAccountClose>) -> MarginfiResult {
-    let marginfi_account = &ctx.accounts.marginfi_account.load()?;
+    let (marginfi_account, extended_balances) =
+        MarginfiAccount::load_extended(&ctx.accounts.marginfi_account)?;
 
     check!(
-        marginfi_account.can_be_closed(),
+        marginfi_account.can_be_closed(&extended_balances),
         MarginfiError::IllegalAction,
         "Account cannot be closed"
     );
@@ -4,7 +4,9 @@ use crate::{
     check,
     prelude::*,
     state::{
-        marginfi_account::{BankAccountWrapper, MarginfiAccount, DISABLED_FLAG},
+        marginfi_account::{
+            BankAccountWrapper, MarginfiAccount, DELEGATE_CLOSE_BALANCE_PERMISSION, DISABLED_FLAG,
+        },
         marginfi_group::Bank,
     },
 };
@@ -17,7 +19,8 @@ pub fn lending_account_close_balance(ctx: Context<LendingAccountCloseBalance>) -
         ..
     } = ctx.accounts;
 
-    let mut marginfi_account = marginfi_account.load_mut()?;
+    let (mut marginfi_account, mut extended_balances) =
+        MarginfiAccount::load_mut_extended(marginfi_account)?;
     let mut bank = bank_loader.load_mut()?;
 
     check!(
@@ -25,6 +28,8 @@ pub fn lending_account_close_balance(ctx: Context<LendingAccountCloseBalance>) -
         MarginfiError::AccountDisabled
     );
 
+    marginfi_group_loader.load()?.assert_not_paused()?;
+
     bank.accrue_interest(
         Clock::get()?.unix_timestamp,
         &*marginfi_group_loader.load()?,
@@ -36,6 +41,7 @@ pub fn lending_account_close_balance(ctx: Context<LendingAccountCloseBalance>) -
         &bank_loader.key(),
         &mut bank,
         &mut marginfi_account.lending_account,
+        &mut extended_balances,
     )?;
 
     bank_account.close_balance()?;
@@ -54,7 +60,9 @@ pub struct LendingAccountCloseBalance<'info> {
     pub marginfi_account: AccountLoader<'info, MarginfiAccount>,
 
     #[account(
-        address = marginfi_account.load()?.authority,
+        constraint = marginfi_account
+            .load()?
+            .can_sign(signer.key, DELEGATE_CLOSE_BALANCE_PERMISSION),
     )]
     pub signer: Signer<'info>,
 
@@ -0,0 +1,35 @@
+use crate::{prelude::*, state::marginfi_account::MarginfiAccount};
+use anchor_lang::prelude::*;
+
+/// Register a delegate signing the account's instructions allowed by `permissions`, a bitmask of
+/// the `DELEGATE_*_PERMISSION` constants, in place of the authority.
+///
+/// A default `delegate` removes the delegate. Transferring the account authority also does.
+pub fn set_account_delegate(
+    ctx: Context<MarginfiAccountSetDelegate>,
+    delegate: Pubkey,
+    permissions: u64,
+) -> MarginfiResult {
+    let mut marginfi_account = ctx.accounts.marginfi_account.load_mut()?;
+
+    marginfi_account.set_delegate(delegate, permissions)?;
+
+    msg!(
+        "Set account delegate {:?} with permissions {:b}",
+        delegate,
+        permissions
+    );
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct MarginfiAccountSetDelegate<'info> {
+    #[account(mut)]
+    pub marginfi_account: AccountLoader<'info, MarginfiAccount>,
+
+    #[account(
+        address = marginfi_account.load()?.authority,
+    )]
+    pub authority: Signer<'info>,
+}
@@ -4,7 +4,9 @@ use crate::{
     events::{AccountEventHeader, LendingAccountDepositEvent},
     prelude::*,
     state::{
-        marginfi_account::{BankAccountWrapper, MarginfiAccount, DISABLED_FLAG},
+        marginfi_account::{
+            BankAccountWrapper, MarginfiAccount, DELEGATE_DEPOSIT_PERMISSION, DISABLED_FLAG,
+        },
         marginfi_group::Bank,
     },
     utils,
@@ -20,6 +22,9 @@ use solana_program::sysvar::Sysvar;
 /// 3. Record asset increase in the bank account
 /// 4. Transfer funds from the signer's token account to the bank's liquidity vault
 ///
+/// If the bank's USD deposit limit is active, the bank and its oracle accounts must be passed in
+/// the remaining accounts, as for the risk engine.
+///
 /// Will error if there is an existing liability <=> repaying is not allowed.
 pub fn lending_account_deposit<'info>(
     mut ctx: Context<'_, '_, 'info, 'info, LendingAccountDeposit<'info>>,
@@ -43,13 +48,16 @@ pub fn lending_account_deposit<'info>(
     )?;
 
     let mut bank = bank_loader.load_mut()?;
-    let mut marginfi_account = marginfi_account_loader.load_mut()?;
+    let (mut marginfi_account, mut extended_balances) =
+        MarginfiAccount::load_mut_extended(marginfi_account_loader)?;
 
     check!(
         !marginfi_account.get_flag(DISABLED_FLAG),
         MarginfiError::AccountDisabled
     );
 
+    marginfi_account.check_bank_allowed(&bank_loader.key())?;
+
     bank.accrue_interest(
         clock.unix_timestamp,
         &*marginfi_group_loader.load()?,
@@ -61,9 +69,24 @@ pub fn lending_account_deposit<'info>(
         &bank_loader.key(),
         &mut bank,
         &mut marginfi_account.lending_account,
+        &mut extended_balances,
     )?;
 
-    bank_account.deposit(I80F48::from_num(amount))?;
+    bank_account.deposit(I80F48::from_num(amount), &*marginfi_group_loader.load()?)?;
+
+    if bank_account.bank.is_deposit_limit_usd_active() {
+        let price = utils::get_bank_limit_price(
+            &bank_loader.key(),
+            bank_account.bank,
+            ctx.remaining_accounts,
+            &clock,
+        )?;
+        bank_account.bank.check_deposit_limit_usd(price)?;
+    }
+
+    bank_account
+        .bank
+        .record_inflow(amount, clock.unix_timestamp);
 
     let amount_pre_fee = maybe_bank_mint
         .as_ref()
@@ -109,7 +132,7 @@ pub struct LendingAccountDeposit<'info> {
     pub marginfi_account: AccountLoader<'info, MarginfiAccount>,
 
     #[account(
-        address = marginfi_account.load()?.authority,
+        constraint = marginfi_account.load()?.can_sign(signer.key, DELEGATE_DEPOSIT_PERMISSION),
     )]
     pub signer: Signer<'info>,
 
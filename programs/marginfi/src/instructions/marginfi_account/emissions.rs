@@ -9,7 +9,10 @@ use crate::{
     debug,
     prelude::{MarginfiError, MarginfiResult},
     state::{
-        marginfi_account::{BankAccountWrapper, MarginfiAccount, DISABLED_FLAG},
+        marginfi_account::{
+            BankAccountWrapper, MarginfiAccount, DELEGATE_WITHDRAW_EMISSIONS_PERMISSION,
+            DISABLED_FLAG,
+        },
         marginfi_group::{Bank, MarginfiGroup},
     },
 };
@@ -17,19 +20,23 @@ use crate::{
 pub fn lending_account_withdraw_emissions<'info>(
     ctx: Context<'_, '_, 'info, 'info, LendingAccountWithdrawEmissions<'info>>,
 ) -> MarginfiResult {
-    let mut marginfi_account = ctx.accounts.marginfi_account.load_mut()?;
+    let (mut marginfi_account, mut extended_balances) =
+        MarginfiAccount::load_mut_extended(&ctx.accounts.marginfi_account)?;
 
     check!(
         !marginfi_account.get_flag(DISABLED_FLAG),
         MarginfiError::AccountDisabled
     );
 
+    ctx.accounts.marginfi_group.load()?.assert_not_paused()?;
+
     let mut bank = ctx.accounts.bank.load_mut()?;
 
     let mut balance = BankAccountWrapper::find(
         ctx.accounts.bank.to_account_info().key,
         &mut bank,
         &mut marginfi_account.lending_account,
+        &mut extended_balances,
     )?;
 
     // Settle emissions
@@ -75,7 +82,11 @@ pub struct LendingAccountWithdrawEmissions<'info> {
     pub marginfi_account: AccountLoader<'info, MarginfiAccount>,
 
     #[account(
-        address = marginfi_account.load()?.authority,
+        constraint = marginfi_account.load()?.can_sign_outflow(
+            signer.key,
+            DELEGATE_WITHDRAW_EMISSIONS_PERMISSION,
+            &destination_account.owner,
+        ),
     )]
     pub signer: Signer<'info>,
 
@@ -121,13 +132,15 @@ pub struct LendingAccountWithdrawEmissions<'info> {
 pub fn lending_account_settle_emissions(
     ctx: Context<LendingAccountSettleEmissions>,
 ) -> MarginfiResult {
-    let mut marginfi_account = ctx.accounts.marginfi_account.load_mut()?;
+    let (mut marginfi_account, mut extended_balances) =
+        MarginfiAccount::load_mut_extended(&ctx.accounts.marginfi_account)?;
     let mut bank = ctx.accounts.bank.load_mut()?;
 
     let mut balance = BankAccountWrapper::find(
         ctx.accounts.bank.to_account_info().key,
         &mut bank,
         &mut marginfi_account.lending_account,
+        &mut extended_balances,
     )?;
 
     balance.claim_emissions(Clock::get()?.unix_timestamp.try_into().unwrap())?;
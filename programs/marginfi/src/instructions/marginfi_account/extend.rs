@@ -0,0 +1,40 @@
+use crate::{
+    prelude::*,
+    state::marginfi_account::{MarginfiAccount, EXTENDED_MARGINFI_ACCOUNT_LEN},
+};
+use anchor_lang::prelude::*;
+
+/// Migrate the account to the extended layout, holding `MAX_EXTENDED_BALANCES` more balances
+/// after the `MarginfiAccount`. The fee payer funds the extra rent.
+///
+/// Extending an already extended account is a no-op.
+pub fn extend_account(ctx: Context<MarginfiAccountExtend>) -> MarginfiResult {
+    msg!(
+        "Extended marginfi account {:?} to {} bytes",
+        ctx.accounts.marginfi_account.key(),
+        EXTENDED_MARGINFI_ACCOUNT_LEN
+    );
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct MarginfiAccountExtend<'info> {
+    #[account(
+        mut,
+        realloc = EXTENDED_MARGINFI_ACCOUNT_LEN,
+        realloc::payer = fee_payer,
+        realloc::zero = true,
+    )]
+    pub marginfi_account: AccountLoader<'info, MarginfiAccount>,
+
+    #[account(
+        address = marginfi_account.load()?.authority,
+    )]
+    pub authority: Signer<'info>,
+
+    #[account(mut)]
+    pub fee_payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
@@ -0,0 +1,534 @@
+use crate::{
+    bank_signer, check,
+    constants::{LIQUIDITY_VAULT_AUTHORITY_SEED, LIQUIDITY_VAULT_SEED},
+    events::{
+        AccountEventHeader, LendingAccountBorrowFixedTermEvent,
+        LendingAccountConvertFixedTermEvent, LendingAccountRepayFixedTermEvent,
+    },
+    math_error,
+    prelude::{MarginfiError, MarginfiGroup, MarginfiResult},
+    state::{
+        marginfi_account::{
+            BalanceSide, BankAccountWrapper, FixedTermBorrow, MarginfiAccount, RiskEngine,
+            RiskRequirementType, DELEGATE_BORROW_PERMISSION, DELEGATE_REPAY_PERMISSION,
+            DISABLED_FLAG, IN_FLASHLOAN_FLAG,
+        },
+        marginfi_group::{Bank, BankVaultType},
+    },
+    utils,
+};
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{TokenAccount, TokenInterface};
+use fixed::types::I80F48;
+use solana_program::{clock::Clock, sysvar::Sysvar};
+
+/// 1. Accrue interest, and record the bank's price for its price guard
+/// 2. Lock the rate: the bank's borrowing rate after the borrow, plus the bank's fixed-term premium
+/// 3. Record the fixed-term borrow, including the bank's origination fee, creating the user's
+///    bank account for it if needed
+/// 4. Record the outflow against the bank's outflow limit, then transfer funds from the bank's
+///    liquidity vault to the signer's token account
+/// 5. Credit the origination fee to the program and group fees
+/// 6. Verify that the user account is in a healthy state, without the deposits in banks whose
+///    price guard tripped
+///
+/// Will error if the bank doesn't allow fixed-term borrows of `duration` seconds, if its price
+/// guard tripped, or if the user already has assets or a fixed-term borrow in the bank.
+pub fn lending_account_borrow_fixed_term<'info>(
+    mut ctx: Context<'_, '_, 'info, 'info, LendingAccountBorrowFixedTerm<'info>>,
+    amount: u64,
+    duration: u64,
+) -> MarginfiResult {
+    let LendingAccountBorrowFixedTerm {
+        marginfi_account: marginfi_account_loader,
+        destination_token_account,
+        bank_liquidity_vault,
+        token_program,
+        bank_liquidity_vault_authority,
+        bank: bank_loader,
+        marginfi_group: marginfi_group_loader,
+        ..
+    } = ctx.accounts;
+    let clock = Clock::get()?;
+    let maybe_bank_mint = utils::maybe_take_bank_mint(
+        &mut ctx.remaining_accounts,
+        &*bank_loader.load()?,
+        token_program.key,
+    )?;
+
+    let (mut marginfi_account, mut extended_balances) =
+        MarginfiAccount::load_mut_extended(marginfi_account_loader)?;
+    let group = &marginfi_group_loader.load()?;
+    let program_fee_rate: I80F48 = group.fee_state_cache.program_fee_rate.into();
+
+    check!(
+        !marginfi_account.get_flag(DISABLED_FLAG),
+        MarginfiError::AccountDisabled
+    );
+
+    marginfi_account.check_bank_allowed(&bank_loader.key())?;
+    let marginfi_account_in_flashloan = marginfi_account.get_flag(IN_FLASHLOAN_FLAG);
+
+    bank_loader.load_mut()?.accrue_interest(
+        clock.unix_timestamp,
+        group,
+        #[cfg(not(feature = "client"))]
+        bank_loader.key(),
+    )?;
+
+    {
+        let mut bank = bank_loader.load_mut()?;
+
+        check!(
+            duration > 0 && duration <= bank.fixed_term_max_duration,
+            MarginfiError::InvalidFixedTermBorrow,
+            "Fixed-term borrow duration not allowed by the bank"
+        );
+
+        let liquidity_vault_authority_bump = bank.liquidity_vault_authority_bump;
+        let origination_fee_rate: I80F48 = bank
+            .config
+            .interest_rate_config
+            .protocol_origination_fee
+            .into();
+
+        // User needs to borrow amount + fee to receive amount
+        let amount_pre_fee = maybe_bank_mint
+            .as_ref()
+            .map(|mint| {
+                utils::calculate_pre_fee_spl_deposit_amount(
+                    mint.to_account_info(),
+                    amount,
+                    clock.epoch,
+                )
+            })
+            .transpose()?
+            .unwrap_or(amount);
+        let origination_fee = I80F48::from_num(amount_pre_fee)
+            .checked_mul(origination_fee_rate)
+            .ok_or_else(math_error!())?;
+        let origination_fee_u64: u64 =
+            origination_fee.checked_to_num().ok_or_else(math_error!())?;
+        // Incurs a borrow that includes the origination fee (but withdraws just the amt)
+        let principal = I80F48::from_num(amount_pre_fee)
+            .checked_add(origination_fee)
+            .ok_or_else(math_error!())?;
+
+        let rate = bank.get_fixed_term_rate(group, principal)?;
+        let maturity = clock
+            .unix_timestamp
+            .checked_add(duration as i64)
+            .ok_or_else(math_error!())?;
+
+        marginfi_account.add_fixed_term_borrow(FixedTermBorrow {
+            bank_pk: bank_loader.key(),
+            principal: principal.into(),
+            rate: rate.into(),
+            start_time: clock.unix_timestamp,
+            maturity,
+        })?;
+
+        let mut bank_account = BankAccountWrapper::find_or_create(
+            &bank_loader.key(),
+            &mut bank,
+            &mut marginfi_account.lending_account,
+            &mut extended_balances,
+        )?;
+
+        check!(
+            bank_account.balance.is_empty(BalanceSide::Assets),
+            MarginfiError::InvalidFixedTermBorrow,
+            "Balance has existing assets"
+        );
+        bank_account.balance.fixed_term_borrow_active = true;
+
+        bank_account
+            .bank
+            .assert_operational_mode(group, Some(true))?;
+        // The bank's oracle accounts are passed for the health check, except in a flashloan
+        if bank_account.bank.is_price_guard_active() && !marginfi_account_in_flashloan {
+            let price = utils::get_bank_price(
+                &bank_loader.key(),
+                bank_account.bank,
+                ctx.remaining_accounts,
+                &clock,
+                None,
+            )?;
+            bank_account
+                .bank
+                .update_price_guard(price, clock.unix_timestamp)?;
+        }
+        check!(
+            !bank_account
+                .bank
+                .is_price_guard_tripped(clock.unix_timestamp),
+            MarginfiError::BankPriceGuardTripped
+        );
+        bank_account.bank.open_fixed_term_borrow(principal, rate)?;
+        bank_account.bank.check_utilization_ratio()?;
+
+        if bank_account.bank.is_borrow_limit_usd_active() {
+            let price = utils::get_bank_limit_price(
+                &bank_loader.key(),
+                bank_account.bank,
+                ctx.remaining_accounts,
+                &clock,
+            )?;
+            bank_account.bank.check_borrow_limit_usd(price)?;
+        }
+
+        bank_account
+            .bank
+            .record_outflow(amount_pre_fee, clock.unix_timestamp)?;
+
+        bank_account.withdraw_spl_transfer(
+            amount_pre_fee,
+            bank_liquidity_vault.to_account_info(),
+            destination_token_account.to_account_info(),
+            bank_liquidity_vault_authority.to_account_info(),
+            maybe_bank_mint.as_ref(),
+            token_program.to_account_info(),
+            bank_signer!(
+                BankVaultType::Liquidity,
+                bank_loader.key(),
+                liquidity_vault_authority_bump
+            ),
+            ctx.remaining_accounts,
+        )?;
+
+        bank_account
+            .bank
+            .collect_origination_fee(origination_fee, program_fee_rate)?;
+
+        emit!(LendingAccountBorrowFixedTermEvent {
+            header: AccountEventHeader {
+                signer: Some(ctx.accounts.signer.key()),
+                marginfi_account: marginfi_account_loader.key(),
+                marginfi_account_authority: marginfi_account.authority,
+                marginfi_group: marginfi_account.group,
+            },
+            bank: bank_loader.key(),
+            mint: bank.mint,
+            amount: amount_pre_fee + origination_fee_u64,
+            rate: rate.to_num::<f64>(),
+            maturity,
+        });
+    } // release mutable borrow of bank
+
+    // Check account health, if below threshold fail transaction
+    // Assuming `ctx.remaining_accounts` holds only oracle accounts
+    RiskEngine::check_account_borrow_health(
+        &marginfi_account,
+        &extended_balances,
+        ctx.remaining_accounts,
+    )?;
+    RiskEngine::check_account_liability_limit(
+        &marginfi_account,
+        &extended_balances,
+        ctx.remaining_accounts,
+    )?;
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct LendingAccountBorrowFixedTerm<'info> {
+    pub marginfi_group: AccountLoader<'info, MarginfiGroup>,
+
+    #[account(
+        mut,
+        constraint = marginfi_account.load() ?.group == marginfi_group.key(),
+    )]
+    pub marginfi_account: AccountLoader<'info, MarginfiAccount>,
+
+    #[account(
+        constraint = marginfi_account.load()?.can_sign_outflow(
+            signer.key,
+            DELEGATE_BORROW_PERMISSION,
+            &destination_token_account.owner,
+        ),
+    )]
+    pub signer: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = bank.load() ?.group == marginfi_group.key(),
+    )]
+    pub bank: AccountLoader<'info, Bank>,
+
+    #[account(mut)]
+    pub destination_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: Seed constraint check
+    #[account(
+        mut,
+        seeds = [
+            LIQUIDITY_VAULT_AUTHORITY_SEED.as_bytes(),
+            bank.key().as_ref(),
+        ],
+        bump = bank.load() ?.liquidity_vault_authority_bump,
+    )]
+    pub bank_liquidity_vault_authority: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [
+            LIQUIDITY_VAULT_SEED.as_bytes(),
+            bank.key().as_ref(),
+        ],
+        bump = bank.load() ?.liquidity_vault_bump,
+    )]
+    pub bank_liquidity_vault: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+/// 1. Accrue interest
+/// 2. Settle the user's fixed-term borrow in the bank, its interest was already credited to
+///    depositors as it accrued
+/// 3. Transfer the principal and the interest accrued at the locked rate from the signer's token
+///    account to the bank's liquidity vault
+///
+/// Fixed-term borrows are repaid in full, interest only accrues until repayment. The user's bank
+/// account is left open, it can be closed with `lending_account_close_balance` if empty.
+pub fn lending_account_repay_fixed_term<'info>(
+    mut ctx: Context<'_, '_, 'info, 'info, LendingAccountRepayFixedTerm<'info>>,
+) -> MarginfiResult {
+    let LendingAccountRepayFixedTerm {
+        marginfi_account: marginfi_account_loader,
+        signer,
+        signer_token_account,
+        bank_liquidity_vault,
+        token_program,
+        bank: bank_loader,
+        marginfi_group: marginfi_group_loader,
+        ..
+    } = ctx.accounts;
+    let clock = Clock::get()?;
+    let maybe_bank_mint = utils::maybe_take_bank_mint(
+        &mut ctx.remaining_accounts,
+        &*bank_loader.load()?,
+        token_program.key,
+    )?;
+
+    let mut bank = bank_loader.load_mut()?;
+    let (mut marginfi_account, mut extended_balances) =
+        MarginfiAccount::load_mut_extended(marginfi_account_loader)?;
+
+    check!(
+        !marginfi_account.get_flag(DISABLED_FLAG),
+        MarginfiError::AccountDisabled
+    );
+
+    bank.accrue_interest(
+        clock.unix_timestamp,
+        &*marginfi_group_loader.load()?,
+        #[cfg(not(feature = "client"))]
+        bank_loader.key(),
+    )?;
+    bank.assert_operational_mode(&*marginfi_group_loader.load()?, None)?;
+
+    let fixed_term_borrow = marginfi_account.get_fixed_term_borrow_mut(&bank_loader.key())?;
+    let principal: I80F48 = fixed_term_borrow.principal.into();
+    let rate: I80F48 = fixed_term_borrow.rate.into();
+    let amount_owed = fixed_term_borrow.get_amount_owed(clock.unix_timestamp)?;
+    *fixed_term_borrow = FixedTermBorrow::default();
+
+    bank.settle_fixed_term_borrow(principal, rate, amount_owed)?;
+
+    let spl_deposit_amount = amount_owed.checked_ceil().ok_or_else(math_error!())?;
+
+    bank.collected_insurance_fees_outstanding = {
+        spl_deposit_amount
+            .checked_sub(amount_owed)
+            .ok_or_else(math_error!())?
+            .checked_add(bank.collected_insurance_fees_outstanding.into())
+            .ok_or_else(math_error!())?
+            .into()
+    };
+
+    let repay_amount_post_fee: u64 = spl_deposit_amount
+        .checked_to_num()
+        .ok_or_else(math_error!())?;
+
+    bank.record_inflow(repay_amount_post_fee, clock.unix_timestamp);
+
+    let mut bank_account = BankAccountWrapper::find(
+        &bank_loader.key(),
+        &mut bank,
+        &mut marginfi_account.lending_account,
+        &mut extended_balances,
+    )?;
+    bank_account.balance.fixed_term_borrow_active = false;
+
+    let repay_amount_pre_fee = maybe_bank_mint
+        .as_ref()
+        .map(|mint| {
+            utils::calculate_pre_fee_spl_deposit_amount(
+                mint.to_account_info(),
+                repay_amount_post_fee,
+                clock.epoch,
+            )
+        })
+        .transpose()?
+        .unwrap_or(repay_amount_post_fee);
+
+    bank_account.deposit_spl_transfer(
+        repay_amount_pre_fee,
+        signer_token_account.to_account_info(),
+        bank_liquidity_vault.to_account_info(),
+        signer.to_account_info(),
+        maybe_bank_mint.as_ref(),
+        token_program.to_account_info(),
+        ctx.remaining_accounts,
+    )?;
+
+    emit!(LendingAccountRepayFixedTermEvent {
+        header: AccountEventHeader {
+            signer: Some(ctx.accounts.signer.key()),
+            marginfi_account: marginfi_account_loader.key(),
+            marginfi_account_authority: marginfi_account.authority,
+            marginfi_group: marginfi_account.group,
+        },
+        bank: bank_loader.key(),
+        mint: bank.mint,
+        amount: repay_amount_post_fee,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct LendingAccountRepayFixedTerm<'info> {
+    pub marginfi_group: AccountLoader<'info, MarginfiGroup>,
+
+    #[account(
+        mut,
+        constraint = marginfi_account.load()?.group == marginfi_group.key(),
+    )]
+    pub marginfi_account: AccountLoader<'info, MarginfiAccount>,
+
+    #[account(
+        constraint = marginfi_account.load()?.can_sign(signer.key, DELEGATE_REPAY_PERMISSION),
+    )]
+    pub signer: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = bank.load()?.group == marginfi_group.key(),
+    )]
+    pub bank: AccountLoader<'info, Bank>,
+
+    /// CHECK: Token mint/authority are checked at transfer
+    #[account(mut)]
+    pub signer_token_account: AccountInfo<'info>,
+
+    /// CHECK: Seed constraint check
+    #[account(
+        mut,
+        seeds = [
+            LIQUIDITY_VAULT_SEED.as_bytes(),
+            bank.key().as_ref(),
+        ],
+        bump = bank.load()?.liquidity_vault_bump,
+    )]
+    pub bank_liquidity_vault: AccountInfo<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+/// 1. Accrue interest
+/// 2. Check that the fixed-term borrow is matured, or that the account is below the maintenance
+///    requirement
+/// 3. Settle the fixed-term borrow, its interest was already credited to depositors as it accrued
+/// 4. Record the amount owed as a variable liability in the user's bank account
+///
+/// Permissionless, so matured borrows move from their locked rate to the variable rate, and
+/// fixed-term borrows of unhealthy accounts can be liquidated like any other liability. Remaining
+/// accounts are only needed for the health check when the borrow isn't matured yet.
+pub fn lending_account_convert_fixed_term<'info>(
+    ctx: Context<'_, '_, 'info, 'info, LendingAccountConvertFixedTerm<'info>>,
+) -> MarginfiResult {
+    let LendingAccountConvertFixedTerm {
+        marginfi_account: marginfi_account_loader,
+        bank: bank_loader,
+        marginfi_group: marginfi_group_loader,
+    } = ctx.accounts;
+    let clock = Clock::get()?;
+
+    {
+        let mut bank = bank_loader.load_mut()?;
+        bank.accrue_interest(
+            clock.unix_timestamp,
+            &*marginfi_group_loader.load()?,
+            #[cfg(not(feature = "client"))]
+            bank_loader.key(),
+        )?;
+        bank.assert_operational_mode(&*marginfi_group_loader.load()?, None)?;
+    }
+
+    let (mut marginfi_account, mut extended_balances) =
+        MarginfiAccount::load_mut_extended(marginfi_account_loader)?;
+    let fixed_term_borrow = *marginfi_account.get_fixed_term_borrow_mut(&bank_loader.key())?;
+
+    if !fixed_term_borrow.is_matured(clock.unix_timestamp) {
+        let (assets, liabs) = RiskEngine::new(
+            &marginfi_account,
+            &extended_balances,
+            ctx.remaining_accounts,
+        )?
+        .get_account_health_components(RiskRequirementType::Maintenance)?;
+
+        check!(
+            assets <= liabs,
+            MarginfiError::FixedTermBorrowNotConvertible
+        );
+    }
+
+    let principal: I80F48 = fixed_term_borrow.principal.into();
+    let rate: I80F48 = fixed_term_borrow.rate.into();
+    let amount_owed = fixed_term_borrow.get_amount_owed(clock.unix_timestamp)?;
+    *marginfi_account.get_fixed_term_borrow_mut(&bank_loader.key())? = FixedTermBorrow::default();
+
+    let mut bank = bank_loader.load_mut()?;
+    bank.settle_fixed_term_borrow(principal, rate, amount_owed)?;
+
+    BankAccountWrapper::find(
+        &bank_loader.key(),
+        &mut bank,
+        &mut marginfi_account.lending_account,
+        &mut extended_balances,
+    )?
+    .convert_fixed_term_borrow(amount_owed)?;
+
+    emit!(LendingAccountConvertFixedTermEvent {
+        header: AccountEventHeader {
+            signer: None,
+            marginfi_account: marginfi_account_loader.key(),
+            marginfi_account_authority: marginfi_account.authority,
+            marginfi_group: marginfi_account.group,
+        },
+        bank: bank_loader.key(),
+        mint: bank.mint,
+        amount: amount_owed.to_num::<f64>(),
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct LendingAccountConvertFixedTerm<'info> {
+    pub marginfi_group: AccountLoader<'info, MarginfiGroup>,
+
+    #[account(
+        mut,
+        constraint = marginfi_account.load()?.group == marginfi_group.key(),
+    )]
+    pub marginfi_account: AccountLoader<'info, MarginfiAccount>,
+
+    #[account(
+        mut,
+        constraint = bank.load()?.group == marginfi_group.key(),
+    )]
+    pub bank: AccountLoader<'info, Bank>,
+}
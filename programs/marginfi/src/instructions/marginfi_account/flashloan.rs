@@ -7,13 +7,21 @@ use solana_program::{
 use crate::{
     check,
     prelude::*,
-    state::marginfi_account::{MarginfiAccount, RiskEngine, DISABLED_FLAG, IN_FLASHLOAN_FLAG},
+    state::{
+        marginfi_account::{
+            BalanceSide, MarginfiAccount, RiskEngine, DELEGATE_FLASHLOAN_PERMISSION, DISABLED_FLAG,
+            IN_FLASHLOAN_FLAG,
+        },
+        marginfi_group::Bank,
+    },
 };
 
 pub fn lending_account_start_flashloan(
     ctx: Context<LendingAccountStartFlashloan>,
     end_index: u64,
 ) -> MarginfiResult<()> {
+    ctx.accounts.marginfi_group.load()?.assert_not_paused()?;
+
     check_flashloan_can_start(
         &ctx.accounts.marginfi_account,
         &ctx.accounts.ixs_sysvar,
@@ -30,11 +38,15 @@ pub fn lending_account_start_flashloan(
 pub struct LendingAccountStartFlashloan<'info> {
     #[account(mut)]
     pub marginfi_account: AccountLoader<'info, MarginfiAccount>,
-    #[account(address = marginfi_account.load()?.authority)]
+    #[account(
+        constraint = marginfi_account.load()?.can_sign(signer.key, DELEGATE_FLASHLOAN_PERMISSION),
+    )]
     pub signer: Signer<'info>,
     /// CHECK: Instructions sysvar
     #[account(address = sysvar::instructions::ID)]
     pub ixs_sysvar: AccountInfo<'info>,
+    #[account(address = marginfi_account.load()?.group)]
+    pub marginfi_group: AccountLoader<'info, MarginfiGroup>,
 }
 
 const END_FL_IX_MARGINFI_ACCOUNT_AI_IDX: usize = 0;
@@ -129,11 +141,42 @@ pub fn lending_account_end_flashloan<'info>(
         "End flashloan ix should not be in CPI"
     );
 
-    let mut marginfi_account = ctx.accounts.marginfi_account.load_mut()?;
+    ctx.accounts.marginfi_group.load()?.assert_not_paused()?;
+
+    let (mut marginfi_account, extended_balances) =
+        MarginfiAccount::load_mut_extended(&ctx.accounts.marginfi_account)?;
 
     marginfi_account.unset_flag(IN_FLASHLOAN_FLAG);
 
-    RiskEngine::check_account_init_health(&marginfi_account, ctx.remaining_accounts)?;
+    // The flashloan may have borrowed against the account's deposits
+    RiskEngine::check_account_borrow_health(
+        &marginfi_account,
+        &extended_balances,
+        ctx.remaining_accounts,
+    )?;
+    RiskEngine::check_account_liability_limit(
+        &marginfi_account,
+        &extended_balances,
+        ctx.remaining_accounts,
+    )?;
+
+    // Borrows made in the flashloan skipped the outflow limit, the net outflow of the banks the
+    // account still owes is checked instead
+    let clock = Clock::get()?;
+    for balance in marginfi_account
+        .get_balances(&extended_balances)
+        .filter(|b| b.active && !b.is_empty(BalanceSide::Liabilities))
+    {
+        let bank_ai = ctx
+            .remaining_accounts
+            .iter()
+            .find(|ai| ai.key.eq(&balance.bank_pk))
+            .ok_or(MarginfiError::InvalidBankAccount)?;
+
+        AccountLoader::<Bank>::try_from(bank_ai)?
+            .load()?
+            .check_flashloan_outflow_limit(clock.unix_timestamp)?;
+    }
 
     Ok(())
 }
@@ -142,6 +185,10 @@ pub fn lending_account_end_flashloan<'info>(
 pub struct LendingAccountEndFlashloan<'info> {
     #[account(mut)]
     pub marginfi_account: AccountLoader<'info, MarginfiAccount>,
-    #[account(address = marginfi_account.load()?.authority)]
+    #[account(
+        constraint = marginfi_account.load()?.can_sign(signer.key, DELEGATE_FLASHLOAN_PERMISSION),
+    )]
     pub signer: Signer<'info>,
+    #[account(address = marginfi_account.load()?.group)]
+    pub marginfi_group: AccountLoader<'info, MarginfiGroup>,
 }
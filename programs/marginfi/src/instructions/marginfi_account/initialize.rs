@@ -14,6 +14,8 @@ pub fn initialize_account(ctx: Context<MarginfiAccountInitialize>) -> MarginfiRe
         ..
     } = ctx.accounts;
 
+    marginfi_group.load()?.assert_not_paused()?;
+
     let mut marginfi_account = marginfi_account_loader.load_init()?;
 
     marginfi_account.initialize(marginfi_group.key(), authority.key());
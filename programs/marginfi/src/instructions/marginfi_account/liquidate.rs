@@ -1,14 +1,14 @@
-use crate::constants::{
-    INSURANCE_VAULT_SEED, LIQUIDATION_INSURANCE_FEE, LIQUIDATION_LIQUIDATOR_FEE,
-};
+use crate::constants::INSURANCE_VAULT_SEED;
 use crate::events::{AccountEventHeader, LendingAccountLiquidateEvent, LiquidationBalances};
-use crate::state::marginfi_account::{calc_amount, calc_value, RiskEngine};
+use crate::state::marginfi_account::{
+    calc_amount, calc_liquidation_shortfall, calc_value, RiskEngine,
+};
 use crate::state::marginfi_group::{Bank, BankVaultType};
 use crate::state::price::{OraclePriceFeedAdapter, OraclePriceType, PriceAdapter, PriceBias};
 use crate::{
     bank_signer,
     constants::{LIQUIDITY_VAULT_AUTHORITY_SEED, LIQUIDITY_VAULT_SEED},
-    state::marginfi_account::{BankAccountWrapper, MarginfiAccount},
+    state::marginfi_account::{BankAccountWrapper, MarginfiAccount, DELEGATE_LIQUIDATE_PERMISSION},
 };
 use crate::{check, debug, prelude::*, utils};
 use anchor_lang::prelude::*;
@@ -32,6 +32,24 @@ use solana_program::sysvar::Sysvar;
 /// - `f_l`: Liquidation fee
 /// - `f_i`: Insurance fee
 ///
+/// Both fees are configured on the asset bank (see `BankConfig::get_liquidation_liquidator_fee`
+/// and `BankConfig::get_liquidation_insurance_fee`), so riskier collateral can pay liquidators more.
+///
+/// The liquidator fee works as a dutch auction: if the asset bank sets
+/// `liquidation_max_liquidator_fee_bps`, `f_l` ramps from the flat liquidator fee up to that maximum
+/// as the liquidatee's maintenance shortfall grows (see `BankConfig::get_liquidator_fee`). The
+/// shortfall is measured before the liquidation as `-health / liabilities`, both maintenance-weighted.
+/// Slightly unhealthy accounts are liquidated cheaply, while deeply unhealthy accounts still attract
+/// liquidators.
+///
+/// A larger discount doesn't allow over-liquidating the account, the post liquidation health check
+/// still rejects liquidations that leave the liquidatee above the maintenance requirement.
+///
+/// The liability bank can also set a close factor (`BankConfig::liquidation_close_factor_bps`), the
+/// max share of the liquidatee's liability `q_lf` can repay in a single call, unless the
+/// liquidatee's shortfall is past `BankConfig::liquidation_close_factor_shortfall_bps`.
+/// `calc_max_liquidation_asset_amount` returns the largest `q_a` that passes all these checks.
+///
 /// The liquidator invokes this instruction with `q_a` as input (the total amount of collateral to be liquidated).
 /// This is done because `q_a` is the most bounded variable in this process, as if the `q_a` is larger than what the liquidatee has, the instruction will fail.
 /// The liquidator can observe how much collateral the liquidatee has, and ensures that the liquidatee will have enough collateral regardless of price action.
@@ -69,11 +87,14 @@ use solana_program::sysvar::Sysvar;
 /// Expected remaining account schema
 /// [
 ///    liab_mint_ai (if token2022 mint),
-///    asset_oracle_ai,
-///    liab_oracle_ai,
+///    asset_oracle_ais...,
+///    liab_oracle_ais...,
 ///    liquidator_observation_ais...,
 ///    liquidatee_observation_ais...,
 ///  ]
+///
+/// The number of oracle accounts of a bank depends on its oracle setup, see
+/// `BankConfig::get_oracle_ais_len`.
 
 pub fn lending_account_liquidate<'info>(
     mut ctx: Context<'_, '_, 'info, 'info, LendingAccountLiquidate<'info>>,
@@ -98,8 +119,10 @@ pub fn lending_account_liquidate<'info>(
         ..
     } = ctx.accounts;
 
-    let mut liquidator_marginfi_account = liquidator_marginfi_account_loader.load_mut()?;
-    let mut liquidatee_marginfi_account = liquidatee_marginfi_account_loader.load_mut()?;
+    let (mut liquidator_marginfi_account, mut liquidator_extended_balances) =
+        MarginfiAccount::load_mut_extended(liquidator_marginfi_account_loader)?;
+    let (mut liquidatee_marginfi_account, mut liquidatee_extended_balances) =
+        MarginfiAccount::load_mut_extended(liquidatee_marginfi_account_loader)?;
     let clock = Clock::get()?;
     let current_timestamp = clock.unix_timestamp;
 
@@ -108,30 +131,33 @@ pub fn lending_account_liquidate<'info>(
         &*ctx.accounts.liab_bank.load()?,
         ctx.accounts.token_program.key,
     )?;
-    {
-        let group = &*marginfi_group_loader.load()?;
-        ctx.accounts.asset_bank.load_mut()?.accrue_interest(
-            current_timestamp,
-            group,
-            #[cfg(not(feature = "client"))]
-            ctx.accounts.asset_bank.key(),
-        )?;
-        ctx.accounts.liab_bank.load_mut()?.accrue_interest(
-            current_timestamp,
-            group,
-            #[cfg(not(feature = "client"))]
-            ctx.accounts.liab_bank.key(),
-        )?;
-    }
-    let init_liquidatee_remaining_len = liquidatee_marginfi_account.get_remaining_accounts_len();
-    let pre_liquidation_health = {
+    let group = &*marginfi_group_loader.load()?;
+    ctx.accounts.asset_bank.load_mut()?.accrue_interest(
+        current_timestamp,
+        group,
+        #[cfg(not(feature = "client"))]
+        ctx.accounts.asset_bank.key(),
+    )?;
+    ctx.accounts.liab_bank.load_mut()?.accrue_interest(
+        current_timestamp,
+        group,
+        #[cfg(not(feature = "client"))]
+        ctx.accounts.liab_bank.key(),
+    )?;
+    let init_liquidatee_remaining_len = liquidatee_marginfi_account
+        .get_remaining_accounts_len(&liquidatee_extended_balances, ctx.remaining_accounts)?;
+    let (pre_liquidation_health, pre_liquidation_liabs) = {
         let liquidatee_accounts_starting_pos =
             ctx.remaining_accounts.len() - init_liquidatee_remaining_len;
         let liquidatee_remaining_accounts =
             &ctx.remaining_accounts[liquidatee_accounts_starting_pos..];
 
-        RiskEngine::new(&liquidatee_marginfi_account, liquidatee_remaining_accounts)?
-            .check_pre_liquidation_condition_and_get_account_health(&ctx.accounts.liab_bank.key())?
+        RiskEngine::new(
+            &liquidatee_marginfi_account,
+            &liquidatee_extended_balances,
+            liquidatee_remaining_accounts,
+        )?
+        .check_pre_liquidation_condition_and_get_account_health(&ctx.accounts.liab_bank.key())?
     };
 
     // ##Accounting changes##
@@ -140,29 +166,38 @@ pub fn lending_account_liquidate<'info>(
         let asset_amount = I80F48::from_num(asset_amount);
 
         let mut asset_bank = ctx.accounts.asset_bank.load_mut()?;
+        let mut liab_bank = ctx.accounts.liab_bank.load_mut()?;
+
+        let asset_oracle_ais_len = asset_bank.config.get_oracle_ais_len();
+        let liab_oracle_ais_len = liab_bank.config.get_oracle_ais_len();
+        check!(
+            asset_oracle_ais_len + liab_oracle_ais_len <= ctx.remaining_accounts.len(),
+            MarginfiError::MissingPythOrBankAccount
+        );
+
         let asset_price = {
-            let oracle_ais = &ctx.remaining_accounts[0..1];
-            let asset_pf = OraclePriceFeedAdapter::try_from_bank_config(
-                &asset_bank.config,
-                oracle_ais,
-                &clock,
-            )?;
+            let oracle_ais = &ctx.remaining_accounts[0..asset_oracle_ais_len];
+            let asset_pf = OraclePriceFeedAdapter::try_from_bank(&asset_bank, oracle_ais, &clock)?;
             asset_pf.get_price_of_type(OraclePriceType::RealTime, Some(PriceBias::Low))?
         };
 
-        let mut liab_bank = ctx.accounts.liab_bank.load_mut()?;
         let liab_price = {
-            let oracle_ais = &ctx.remaining_accounts[1..2];
-            let liab_pf = OraclePriceFeedAdapter::try_from_bank_config(
-                &liab_bank.config,
-                oracle_ais,
-                &clock,
-            )?;
+            let oracle_ais = &ctx.remaining_accounts
+                [asset_oracle_ais_len..asset_oracle_ais_len + liab_oracle_ais_len];
+            let liab_pf = OraclePriceFeedAdapter::try_from_bank(&liab_bank, oracle_ais, &clock)?;
             liab_pf.get_price_of_type(OraclePriceType::RealTime, Some(PriceBias::High))?
         };
 
-        let final_discount = I80F48::ONE - (LIQUIDATION_INSURANCE_FEE + LIQUIDATION_LIQUIDATOR_FEE);
-        let liquidator_discount = I80F48::ONE - LIQUIDATION_LIQUIDATOR_FEE;
+        let liquidation_shortfall =
+            calc_liquidation_shortfall(pre_liquidation_health, pre_liquidation_liabs)?;
+
+        let liquidator_fee = asset_bank
+            .config
+            .get_liquidator_fee(liquidation_shortfall)?;
+        let insurance_fee = asset_bank.config.get_liquidation_insurance_fee();
+
+        let final_discount = I80F48::ONE - (insurance_fee + liquidator_fee);
+        let liquidator_discount = I80F48::ONE - liquidator_fee;
 
         // Quantity of liability to be paid off by liquidator
         let liab_amount_liquidator = calc_amount(
@@ -197,8 +232,12 @@ pub fn lending_account_liquidate<'info>(
         );
 
         debug!(
-            "liab_quantity_liq: {}, liab_q_final: {}, asset_amount: {}, insurance_fund_fee: {}",
-            liab_amount_liquidator, liab_amount_final, asset_amount, insurance_fund_fee
+            "liab_quantity_liq: {}, liab_q_final: {}, asset_amount: {}, insurance_fund_fee: {}, liquidator_fee: {}",
+            liab_amount_liquidator,
+            liab_amount_final,
+            asset_amount,
+            insurance_fund_fee,
+            liquidator_fee
         );
 
         // Liquidator pays off liability
@@ -207,13 +246,14 @@ pub fn lending_account_liquidate<'info>(
                 &ctx.accounts.liab_bank.key(),
                 &mut liab_bank,
                 &mut liquidator_marginfi_account.lending_account,
+                &mut liquidator_extended_balances,
             )?;
 
             let pre_balance = bank_account
                 .bank
                 .get_liability_amount(bank_account.balance.liability_shares.into())?;
 
-            bank_account.decrease_balance_in_liquidation(liab_amount_liquidator)?;
+            bank_account.decrease_balance_in_liquidation(liab_amount_liquidator, group)?;
 
             let post_balance = bank_account
                 .bank
@@ -228,6 +268,7 @@ pub fn lending_account_liquidate<'info>(
                 &ctx.accounts.asset_bank.key(),
                 &mut asset_bank,
                 &mut liquidatee_marginfi_account.lending_account,
+                &mut liquidatee_extended_balances,
             )?;
 
             let pre_balance = bank_account
@@ -235,7 +276,7 @@ pub fn lending_account_liquidate<'info>(
                 .get_asset_amount(bank_account.balance.asset_shares.into())?;
 
             bank_account
-                .withdraw(asset_amount)
+                .withdraw(asset_amount, group)
                 .map_err(|_| MarginfiError::IllegalLiquidation)?;
 
             let post_balance = bank_account
@@ -251,13 +292,14 @@ pub fn lending_account_liquidate<'info>(
                 &ctx.accounts.asset_bank.key(),
                 &mut asset_bank,
                 &mut liquidator_marginfi_account.lending_account,
+                &mut liquidator_extended_balances,
             )?;
 
             let pre_balance = bank_account
                 .bank
                 .get_asset_amount(bank_account.balance.asset_shares.into())?;
 
-            bank_account.increase_balance_in_liquidation(asset_amount)?;
+            bank_account.increase_balance_in_liquidation(asset_amount, group)?;
 
             let post_balance = bank_account
                 .bank
@@ -281,6 +323,7 @@ pub fn lending_account_liquidate<'info>(
                 &ctx.accounts.liab_bank.key(),
                 &mut liab_bank,
                 &mut liquidatee_marginfi_account.lending_account,
+                &mut liquidatee_extended_balances,
             )?;
 
             let liquidatee_liability_pre_balance =
@@ -288,7 +331,22 @@ pub fn lending_account_liquidate<'info>(
                     liquidatee_liab_bank_account.balance.liability_shares.into(),
                 )?;
 
-            liquidatee_liab_bank_account.increase_balance(liab_amount_final)?;
+            if let Some(max_repay_amount) = liquidatee_liab_bank_account
+                .bank
+                .config
+                .get_max_liquidation_repay_amount(
+                    liquidatee_liability_pre_balance,
+                    liquidation_shortfall,
+                )?
+            {
+                check!(
+                    liab_amount_final <= max_repay_amount,
+                    MarginfiError::IllegalLiquidation,
+                    "Liquidation exceeds the liability bank close factor"
+                );
+            }
+
+            liquidatee_liab_bank_account.increase_balance(liab_amount_final, group)?;
 
             let liquidatee_liability_post_balance =
                 liquidatee_liab_bank_account.bank.get_liability_amount(
@@ -346,24 +404,29 @@ pub fn lending_account_liquidate<'info>(
 
     let liquidatee_accounts_starting_pos =
         ctx.remaining_accounts.len() - init_liquidatee_remaining_len;
-    let liquidator_accounts_starting_pos =
-        liquidatee_accounts_starting_pos - liquidator_marginfi_account.get_remaining_accounts_len();
+    let liquidator_accounts_starting_pos = liquidatee_accounts_starting_pos
+        - liquidator_marginfi_account
+            .get_remaining_accounts_len(&liquidator_extended_balances, ctx.remaining_accounts)?;
 
     let liquidatee_remaining_accounts = &ctx.remaining_accounts[liquidatee_accounts_starting_pos..];
     let liquidator_remaining_accounts =
         &ctx.remaining_accounts[liquidator_accounts_starting_pos..liquidatee_accounts_starting_pos];
 
     // Verify liquidatee liquidation post health
-    let post_liquidation_health =
-        RiskEngine::new(&liquidatee_marginfi_account, liquidatee_remaining_accounts)?
-            .check_post_liquidation_condition_and_get_account_health(
-                &ctx.accounts.liab_bank.key(),
-                pre_liquidation_health,
-            )?;
+    let post_liquidation_health = RiskEngine::new(
+        &liquidatee_marginfi_account,
+        &liquidatee_extended_balances,
+        liquidatee_remaining_accounts,
+    )?
+    .check_post_liquidation_condition_and_get_account_health(
+        &ctx.accounts.liab_bank.key(),
+        pre_liquidation_health,
+    )?;
 
     // Verify liquidator account health
     RiskEngine::check_account_init_health(
         &liquidator_marginfi_account,
+        &liquidator_extended_balances,
         liquidator_remaining_accounts,
     )?;
 
@@ -372,7 +435,7 @@ pub fn lending_account_liquidate<'info>(
             signer: Some(ctx.accounts.signer.key()),
             marginfi_account: liquidator_marginfi_account_loader.key(),
             marginfi_account_authority: liquidator_marginfi_account.authority,
-            marginfi_group: ctx.accounts.marginfi_group.key(),
+            marginfi_group: marginfi_group_loader.key(),
         },
         liquidatee_marginfi_account: liquidatee_marginfi_account_loader.key(),
         liquidatee_marginfi_account_authority: liquidatee_marginfi_account.authority,
@@ -412,7 +475,9 @@ pub struct LendingAccountLiquidate<'info> {
     pub liquidator_marginfi_account: AccountLoader<'info, MarginfiAccount>,
 
     #[account(
-        address = liquidator_marginfi_account.load()?.authority
+        constraint = liquidator_marginfi_account
+            .load()?
+            .can_sign(signer.key, DELEGATE_LIQUIDATE_PERMISSION)
     )]
     pub signer: Signer<'info>,
 
@@ -1,8 +1,11 @@
 mod borrow;
 mod close;
 mod close_balance;
+mod delegate;
 mod deposit;
 mod emissions;
+mod extend;
+mod fixed_term;
 mod flashloan;
 mod initialize;
 mod liquidate;
@@ -13,8 +16,11 @@ mod withdraw;
 pub use borrow::*;
 pub use close::*;
 pub use close_balance::*;
+pub use delegate::*;
 pub use deposit::*;
 pub use emissions::*;
+pub use extend::*;
+pub use fixed_term::*;
 pub use flashloan::*;
 pub use initialize::*;
 pub use liquidate::*;
@@ -4,7 +4,9 @@ use crate::{
     events::{AccountEventHeader, LendingAccountRepayEvent},
     prelude::{MarginfiError, MarginfiGroup, MarginfiResult},
     state::{
-        marginfi_account::{BankAccountWrapper, MarginfiAccount, DISABLED_FLAG},
+        marginfi_account::{
+            BankAccountWrapper, MarginfiAccount, DELEGATE_REPAY_PERMISSION, DISABLED_FLAG,
+        },
         marginfi_group::Bank,
     },
     utils,
@@ -44,7 +46,8 @@ pub fn lending_account_repay<'info>(
 
     let repay_all = repay_all.unwrap_or(false);
     let mut bank = bank_loader.load_mut()?;
-    let mut marginfi_account = marginfi_account_loader.load_mut()?;
+    let (mut marginfi_account, mut extended_balances) =
+        MarginfiAccount::load_mut_extended(marginfi_account_loader)?;
 
     check!(
         !marginfi_account.get_flag(DISABLED_FLAG),
@@ -62,16 +65,21 @@ pub fn lending_account_repay<'info>(
         &bank_loader.key(),
         &mut bank,
         &mut marginfi_account.lending_account,
+        &mut extended_balances,
     )?;
 
     let repay_amount_post_fee = if repay_all {
-        bank_account.repay_all()?
+        bank_account.repay_all(&*marginfi_group_loader.load()?)?
     } else {
-        bank_account.repay(I80F48::from_num(amount))?;
+        bank_account.repay(I80F48::from_num(amount), &*marginfi_group_loader.load()?)?;
 
         amount
     };
 
+    bank_account
+        .bank
+        .record_inflow(repay_amount_post_fee, clock.unix_timestamp);
+
     let repay_amount_pre_fee = maybe_bank_mint
         .as_ref()
         .map(|mint| {
@@ -121,7 +129,7 @@ pub struct LendingAccountRepay<'info> {
     pub marginfi_account: AccountLoader<'info, MarginfiAccount>,
 
     #[account(
-        address = marginfi_account.load()?.authority,
+        constraint = marginfi_account.load()?.can_sign(signer.key, DELEGATE_REPAY_PERMISSION),
     )]
     pub signer: Signer<'info>,
 
@@ -4,6 +4,8 @@ use anchor_lang::prelude::*;
 pub fn set_account_transfer_authority(
     ctx: Context<MarginfiAccountSetAccountAuthority>,
 ) -> MarginfiResult {
+    ctx.accounts.marginfi_group.load()?.assert_not_paused()?;
+
     // Ensure marginfi_account is dropped out of scope to not exceed stack frame limits
     {
         let mut marginfi_account = ctx.accounts.marginfi_account.load_mut()?;
@@ -21,11 +23,10 @@ pub struct MarginfiAccountSetAccountAuthority<'info> {
     #[account(mut)]
     pub marginfi_account: AccountLoader<'info, MarginfiAccount>,
 
-    /// CHECK: The group is confirmed by the address macro
     #[account(
         address = marginfi_account.load()?.group,
     )]
-    pub marginfi_group: AccountInfo<'info>,
+    pub marginfi_group: AccountLoader<'info, MarginfiGroup>,
 
     #[account(
         address = marginfi_account.load()?.authority,
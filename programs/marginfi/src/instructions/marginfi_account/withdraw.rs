@@ -4,7 +4,10 @@ use crate::{
     events::{AccountEventHeader, LendingAccountWithdrawEvent},
     prelude::*,
     state::{
-        marginfi_account::{BankAccountWrapper, MarginfiAccount, RiskEngine, DISABLED_FLAG},
+        marginfi_account::{
+            BankAccountWrapper, MarginfiAccount, RiskEngine, DELEGATE_WITHDRAW_PERMISSION,
+            DISABLED_FLAG,
+        },
         marginfi_group::{Bank, BankVaultType},
     },
     utils,
@@ -17,7 +20,8 @@ use solana_program::{clock::Clock, sysvar::Sysvar};
 /// 1. Accrue interest
 /// 2. Find the user's existing bank account for the asset withdrawn
 /// 3. Record asset decrease in the bank account
-/// 4. Transfer funds from the bank's liquidity vault to the signer's token account
+/// 4. Record the outflow against the bank's outflow limit, then transfer funds from the bank's
+///    liquidity vault to the signer's token account
 /// 5. Verify that the user account is in a healthy state
 ///
 /// Will error if there is no existing asset <=> borrowing is not allowed.
@@ -39,7 +43,8 @@ pub fn lending_account_withdraw<'info>(
     let clock = Clock::get()?;
 
     let withdraw_all = withdraw_all.unwrap_or(false);
-    let mut marginfi_account = marginfi_account_loader.load_mut()?;
+    let (mut marginfi_account, mut extended_balances) =
+        MarginfiAccount::load_mut_extended(marginfi_account_loader)?;
 
     check!(
         !marginfi_account.get_flag(DISABLED_FLAG),
@@ -68,10 +73,11 @@ pub fn lending_account_withdraw<'info>(
             &bank_loader.key(),
             &mut bank,
             &mut marginfi_account.lending_account,
+            &mut extended_balances,
         )?;
 
         let amount_pre_fee = if withdraw_all {
-            bank_account.withdraw_all()?
+            bank_account.withdraw_all(&*marginfi_group_loader.load()?)?
         } else {
             let amount_pre_fee = maybe_bank_mint
                 .as_ref()
@@ -85,11 +91,18 @@ pub fn lending_account_withdraw<'info>(
                 .transpose()?
                 .unwrap_or(amount);
 
-            bank_account.withdraw(I80F48::from_num(amount_pre_fee))?;
+            bank_account.withdraw(
+                I80F48::from_num(amount_pre_fee),
+                &*marginfi_group_loader.load()?,
+            )?;
 
             amount_pre_fee
         };
 
+        bank_account
+            .bank
+            .record_outflow(amount_pre_fee, clock.unix_timestamp)?;
+
         bank_account.withdraw_spl_transfer(
             amount_pre_fee,
             bank_liquidity_vault.to_account_info(),
@@ -121,7 +134,11 @@ pub fn lending_account_withdraw<'info>(
 
     // Check account health, if below threshold fail transaction
     // Assuming `ctx.remaining_accounts` holds only oracle accounts
-    RiskEngine::check_account_init_health(&marginfi_account, ctx.remaining_accounts)?;
+    RiskEngine::check_account_init_health(
+        &marginfi_account,
+        &extended_balances,
+        ctx.remaining_accounts,
+    )?;
 
     Ok(())
 }
@@ -137,7 +154,11 @@ pub struct LendingAccountWithdraw<'info> {
     pub marginfi_account: AccountLoader<'info, MarginfiAccount>,
 
     #[account(
-        address = marginfi_account.load()?.authority,
+        constraint = marginfi_account.load()?.can_sign_outflow(
+            signer.key,
+            DELEGATE_WITHDRAW_PERMISSION,
+            &destination_token_account.owner,
+        ),
     )]
     pub signer: Signer<'info>,
 
@@ -1,9 +1,16 @@
 use crate::{
-    state::marginfi_group::{Bank, MarginfiGroup},
+    state::{
+        marginfi_group::{Bank, MarginfiGroup},
+        price::{OraclePriceFeedAdapter, OraclePriceType, PriceAdapter},
+    },
     MarginfiResult,
 };
 use anchor_lang::prelude::*;
 
+/// Accrue the bank's interest.
+///
+/// If the bank has a `twap_window` or a price guard and its oracle accounts are passed as remaining
+/// accounts, the spot price is also recorded for the TWAP and checked by the price guard.
 pub fn lending_pool_accrue_bank_interest(
     ctx: Context<LendingPoolAccrueBankInterest>,
 ) -> MarginfiResult {
@@ -17,6 +24,15 @@ pub fn lending_pool_accrue_bank_interest(
         ctx.accounts.bank.key(),
     )?;
 
+    if (bank.twap_window != 0 || bank.is_price_guard_active()) && !ctx.remaining_accounts.is_empty()
+    {
+        let price = OraclePriceFeedAdapter::try_from_bank(&bank, ctx.remaining_accounts, &clock)?
+            .get_price_of_type(OraclePriceType::RealTime, None)?;
+
+        bank.record_price_observation(price, clock.unix_timestamp);
+        bank.update_price_guard(price, clock.unix_timestamp)?;
+    }
+
     Ok(())
 }
 
@@ -15,7 +15,7 @@ use anchor_spl::token_interface::*;
 
 /// Add a bank to the lending pool
 ///
-/// Admin only
+/// Risk admin only
 ///
 /// TODO: Allow for different oracle configurations
 pub fn lending_pool_add_bank(
@@ -68,7 +68,7 @@ pub fn lending_pool_add_bank(
     );
 
     bank.config.validate()?;
-    bank.config.validate_oracle_setup(ctx.remaining_accounts)?;
+    bank.validate_oracle_setup(ctx.remaining_accounts)?;
 
     emit!(LendingPoolBankCreateEvent {
         header: GroupEventHeader {
@@ -93,7 +93,7 @@ pub struct LendingPoolAddBank<'info> {
 
     #[account(
         mut,
-        address = marginfi_group.load()?.admin,
+        address = marginfi_group.load()?.get_risk_admin(),
     )]
     pub admin: Signer<'info>,
 
@@ -68,7 +68,7 @@ pub fn lending_pool_add_bank_with_seed(
     );
 
     bank.config.validate()?;
-    bank.config.validate_oracle_setup(ctx.remaining_accounts)?;
+    bank.validate_oracle_setup(ctx.remaining_accounts)?;
 
     emit!(LendingPoolBankCreateEvent {
         header: GroupEventHeader {
@@ -93,7 +93,7 @@ pub struct LendingPoolAddBankWithSeed<'info> {
 
     #[account(
         mut,
-        address = marginfi_group.load()?.admin,
+        address = marginfi_group.load()?.get_risk_admin(),
     )]
     pub admin: Signer<'info>,
 
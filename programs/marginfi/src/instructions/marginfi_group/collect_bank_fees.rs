@@ -291,7 +291,7 @@ pub struct LendingPoolWithdrawFees<'info> {
     pub bank: AccountLoader<'info, Bank>,
 
     #[account(
-        address = marginfi_group.load()?.admin,
+        address = marginfi_group.load()?.get_fee_collector(),
     )]
     pub admin: Signer<'info>,
 
@@ -368,7 +368,7 @@ pub struct LendingPoolWithdrawInsurance<'info> {
     pub bank: AccountLoader<'info, Bank>,
 
     #[account(
-        address = marginfi_group.load()?.admin,
+        address = marginfi_group.load()?.get_fee_collector(),
     )]
     pub admin: Signer<'info>,
 
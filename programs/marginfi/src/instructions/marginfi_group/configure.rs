@@ -1,8 +1,11 @@
 use crate::check;
-use crate::events::{GroupEventHeader, MarginfiGroupConfigureEvent};
+use crate::events::{
+    GroupEventHeader, MarginfiGroupConfigureEvent, MarginfiGroupSetPauseStateEvent,
+};
 use crate::prelude::MarginfiError;
 use crate::state::marginfi_account::{
-    MarginfiAccount, FLASHLOAN_ENABLED_FLAG, TRANSFER_AUTHORITY_ALLOWED_FLAG,
+    MarginfiAccount, FLASHLOAN_ENABLED_FLAG, MAX_ACCOUNT_ALLOWED_BANKS,
+    TRANSFER_AUTHORITY_ALLOWED_FLAG,
 };
 use crate::{
     state::marginfi_group::{GroupConfig, MarginfiGroup},
@@ -12,11 +15,11 @@ use anchor_lang::prelude::*;
 
 /// Configure margin group
 ///
-/// Admin only
+/// Admin only. Lowering the `config_delay` only takes effect once the current delay has passed.
 pub fn configure(ctx: Context<MarginfiGroupConfigure>, config: GroupConfig) -> MarginfiResult {
     let marginfi_group = &mut ctx.accounts.marginfi_group.load_mut()?;
 
-    marginfi_group.configure(&config)?;
+    marginfi_group.configure(&config, Clock::get()?.unix_timestamp)?;
 
     emit!(MarginfiGroupConfigureEvent {
         header: GroupEventHeader {
@@ -40,6 +43,43 @@ pub struct MarginfiGroupConfigure<'info> {
     pub admin: Signer<'info>,
 }
 
+/// Pause or unpause the group's banks or user instructions (see `MarginfiGroup::pause_state`)
+///
+/// Guardian or admin only
+pub fn set_pause_state(
+    ctx: Context<MarginfiGroupSetPauseState>,
+    pause_state: u8,
+) -> MarginfiResult {
+    let marginfi_group = &mut ctx.accounts.marginfi_group.load_mut()?;
+    let signer = ctx.accounts.signer.key();
+
+    check!(
+        signer == marginfi_group.guardian || signer == marginfi_group.admin,
+        MarginfiError::Unauthorized
+    );
+
+    marginfi_group.set_pause_state(pause_state)?;
+
+    emit!(MarginfiGroupSetPauseStateEvent {
+        header: GroupEventHeader {
+            marginfi_group: ctx.accounts.marginfi_group.key(),
+            signer: Some(*ctx.accounts.signer.key)
+        },
+        pause_state,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct MarginfiGroupSetPauseState<'info> {
+    #[account(mut)]
+    pub marginfi_group: AccountLoader<'info, MarginfiGroup>,
+
+    /// The group's guardian or admin
+    pub signer: Signer<'info>,
+}
+
 /// Only these flags can be configured
 ///
 /// Example:
@@ -104,6 +144,42 @@ pub struct UnsetAccountFlag<'info> {
     pub admin: Signer<'info>,
 }
 
+/// Set the account's limits: the max USD value of its liabilities, and the banks it may deposit
+/// into and borrow from. 0 and an empty list remove the limits.
+///
+/// Only the group admin can set them, the account authority can't bypass them.
+pub fn configure_account_limits(
+    ctx: Context<ConfigureAccountLimits>,
+    max_liability_value: u64,
+    allowed_banks: Vec<Pubkey>,
+) -> MarginfiResult {
+    check!(
+        allowed_banks.len() <= MAX_ACCOUNT_ALLOWED_BANKS,
+        MarginfiError::InvalidConfig
+    );
+
+    let mut marginfi_account = ctx.accounts.marginfi_account.load_mut()?;
+
+    marginfi_account.max_liability_value = max_liability_value;
+    marginfi_account.allowed_banks = [Pubkey::default(); MAX_ACCOUNT_ALLOWED_BANKS];
+    marginfi_account.allowed_banks[..allowed_banks.len()].copy_from_slice(&allowed_banks);
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct ConfigureAccountLimits<'info> {
+    #[account(address = marginfi_account.load()?.group)]
+    pub marginfi_group: AccountLoader<'info, MarginfiGroup>,
+
+    #[account(mut)]
+    pub marginfi_account: AccountLoader<'info, MarginfiAccount>,
+
+    /// Admin only
+    #[account(address = marginfi_group.load()?.admin)]
+    pub admin: Signer<'info>,
+}
+
 #[cfg(test)]
 mod tests {
     use crate::state::marginfi_account::{
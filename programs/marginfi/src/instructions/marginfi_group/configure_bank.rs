@@ -16,11 +16,23 @@ pub fn lending_pool_configure_bank(
     bank_config: BankConfigOpt,
 ) -> MarginfiResult {
     let mut bank = ctx.accounts.bank.load_mut()?;
+    let config_delay = ctx
+        .accounts
+        .marginfi_group
+        .load()?
+        .get_config_delay(Clock::get()?.unix_timestamp);
+
+    // With a config delay, configs go through `lending_pool_queue_bank_config`, except those
+    // only lowering the asset weights
+    check!(
+        config_delay == 0 || bank_config.only_lowers_asset_weights(&bank.config),
+        MarginfiError::BankConfigTimelocked
+    );
 
     bank.configure(&bank_config)?;
 
-    if bank_config.oracle.is_some() {
-        bank.config.validate_oracle_setup(ctx.remaining_accounts)?;
+    if bank_config.oracle.is_some() || bank_config.fixed_price.is_some() {
+        bank.validate_oracle_setup(ctx.remaining_accounts)?;
     }
 
     emit!(LendingPoolBankConfigureEvent {
@@ -41,7 +53,7 @@ pub struct LendingPoolConfigureBank<'info> {
     pub marginfi_group: AccountLoader<'info, MarginfiGroup>,
 
     #[account(
-        address = marginfi_group.load()?.admin,
+        address = marginfi_group.load()?.get_risk_admin(),
     )]
     pub admin: Signer<'info>,
 
@@ -101,7 +113,7 @@ pub struct LendingPoolSetupEmissions<'info> {
 
     #[account(
         mut,
-        address = marginfi_group.load()?.admin,
+        address = marginfi_group.load()?.get_emissions_admin(),
     )]
     pub admin: Signer<'info>,
 
@@ -216,7 +228,7 @@ pub struct LendingPoolUpdateEmissionsParameters<'info> {
 
     #[account(
         mut,
-        address = marginfi_group.load()?.admin,
+        address = marginfi_group.load()?.get_emissions_admin(),
     )]
     pub admin: Signer<'info>,
 
@@ -44,16 +44,22 @@ pub fn lending_pool_handle_bankruptcy<'info>(
 
     if !bank.get_flag(PERMISSIONLESS_BAD_DEBT_SETTLEMENT_FLAG) {
         check!(
-            ctx.accounts.signer.key() == marginfi_group_loader.load()?.admin,
+            ctx.accounts.signer.key() == marginfi_group_loader.load()?.get_bankruptcy_operator(),
             MarginfiError::Unauthorized
         );
     }
 
     drop(bank);
 
-    let mut marginfi_account = marginfi_account_loader.load_mut()?;
+    let (mut marginfi_account, mut extended_balances) =
+        MarginfiAccount::load_mut_extended(marginfi_account_loader)?;
 
-    RiskEngine::new(&marginfi_account, ctx.remaining_accounts)?.check_account_bankrupt()?;
+    RiskEngine::new(
+        &marginfi_account,
+        &extended_balances,
+        ctx.remaining_accounts,
+    )?
+    .check_account_bankrupt()?;
 
     let mut bank = bank_loader.load_mut()?;
 
@@ -68,6 +74,7 @@ pub fn lending_pool_handle_bankruptcy<'info>(
         .lending_account
         .balances
         .iter_mut()
+        .chain(extended_balances.iter_mut())
         .find(|balance| balance.active && balance.bank_pk == bank_loader.key());
 
     check!(
@@ -151,8 +158,9 @@ pub fn lending_pool_handle_bankruptcy<'info>(
         &bank_loader.key(),
         &mut bank,
         &mut marginfi_account.lending_account,
+        &mut extended_balances,
     )?
-    .repay(bad_debt)?;
+    .repay(bad_debt, &*marginfi_group_loader.load()?)?;
 
     marginfi_account.set_flag(DISABLED_FLAG);
 
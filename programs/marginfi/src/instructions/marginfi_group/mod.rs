@@ -10,6 +10,7 @@ mod handle_bankruptcy;
 mod init_global_fee_state;
 mod initialize;
 mod propagate_fee_state;
+mod queue_bank_config;
 
 pub use accrue_bank_interest::*;
 pub use add_pool::*;
@@ -23,3 +24,4 @@ pub use handle_bankruptcy::*;
 pub use init_global_fee_state::*;
 pub use initialize::*;
 pub use propagate_fee_state::*;
+pub use queue_bank_config::*;
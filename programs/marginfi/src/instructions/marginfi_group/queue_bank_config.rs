@@ -0,0 +1,181 @@
+use crate::constants::PENDING_BANK_CONFIG_SEED;
+use crate::events::{GroupEventHeader, LendingPoolBankConfigureEvent};
+use crate::prelude::MarginfiError;
+use crate::{check, math_error};
+use crate::{
+    state::marginfi_group::{Bank, BankConfigOpt, MarginfiGroup, PendingBankConfig},
+    MarginfiResult,
+};
+use anchor_lang::prelude::*;
+
+/// Queue a bank config, executable once the group's `config_delay` has passed.
+///
+/// Only one config can be queued per bank, a queued config must be executed or cancelled first.
+pub fn lending_pool_queue_bank_config(
+    ctx: Context<LendingPoolQueueBankConfig>,
+    bank_config: BankConfigOpt,
+) -> MarginfiResult {
+    let current_timestamp = Clock::get()?.unix_timestamp;
+    let config_delay = ctx
+        .accounts
+        .marginfi_group
+        .load()?
+        .get_config_delay(current_timestamp);
+    let eta = current_timestamp
+        .checked_add(config_delay as i64)
+        .ok_or_else(math_error!())?;
+
+    let pending_bank_config = &mut ctx.accounts.pending_bank_config;
+    pending_bank_config.bank = ctx.accounts.bank.key();
+    pending_bank_config.payer = ctx.accounts.fee_payer.key();
+    pending_bank_config.eta = eta;
+    pending_bank_config.config = bank_config;
+
+    msg!("Bank config queued, executable from {}", eta);
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(bank_config: BankConfigOpt)]
+pub struct LendingPoolQueueBankConfig<'info> {
+    pub marginfi_group: AccountLoader<'info, MarginfiGroup>,
+
+    #[account(
+        address = marginfi_group.load()?.get_risk_admin(),
+    )]
+    pub admin: Signer<'info>,
+
+    /// Pays to init the pending config, refunded when it's executed or cancelled
+    #[account(mut)]
+    pub fee_payer: Signer<'info>,
+
+    #[account(
+        constraint = bank.load()?.group == marginfi_group.key(),
+    )]
+    pub bank: AccountLoader<'info, Bank>,
+
+    #[account(
+        init,
+        payer = fee_payer,
+        space = PendingBankConfig::space(&bank_config)?,
+        seeds = [
+            PENDING_BANK_CONFIG_SEED.as_bytes(),
+            bank.key().as_ref(),
+        ],
+        bump,
+    )]
+    pub pending_bank_config: Account<'info, PendingBankConfig>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Apply a queued bank config once its eta has passed, closing the pending config.
+///
+/// As for `lending_pool_configure_bank`, the oracle accounts are passed as remaining accounts when
+/// the config changes the oracle setup or the fixed price.
+pub fn lending_pool_execute_bank_config(
+    ctx: Context<LendingPoolExecuteBankConfig>,
+) -> MarginfiResult {
+    let pending_bank_config = &ctx.accounts.pending_bank_config;
+
+    check!(
+        Clock::get()?.unix_timestamp >= pending_bank_config.eta,
+        MarginfiError::BankConfigNotReady
+    );
+
+    let bank_config = pending_bank_config.config.clone();
+    let mut bank = ctx.accounts.bank.load_mut()?;
+
+    bank.configure(&bank_config)?;
+
+    if bank_config.oracle.is_some() || bank_config.fixed_price.is_some() {
+        bank.validate_oracle_setup(ctx.remaining_accounts)?;
+    }
+
+    emit!(LendingPoolBankConfigureEvent {
+        header: GroupEventHeader {
+            marginfi_group: ctx.accounts.marginfi_group.key(),
+            signer: Some(*ctx.accounts.admin.key)
+        },
+        bank: ctx.accounts.bank.key(),
+        mint: bank.mint,
+        config: bank_config,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct LendingPoolExecuteBankConfig<'info> {
+    pub marginfi_group: AccountLoader<'info, MarginfiGroup>,
+
+    #[account(
+        address = marginfi_group.load()?.get_risk_admin(),
+    )]
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = bank.load()?.group == marginfi_group.key(),
+    )]
+    pub bank: AccountLoader<'info, Bank>,
+
+    #[account(
+        mut,
+        has_one = bank,
+        has_one = payer,
+        close = payer,
+        seeds = [
+            PENDING_BANK_CONFIG_SEED.as_bytes(),
+            bank.key().as_ref(),
+        ],
+        bump,
+    )]
+    pub pending_bank_config: Account<'info, PendingBankConfig>,
+
+    /// CHECK: Rent receiver, asserted against the pending config
+    #[account(mut)]
+    pub payer: AccountInfo<'info>,
+}
+
+/// Discard a queued bank config.
+pub fn lending_pool_cancel_bank_config(
+    _ctx: Context<LendingPoolCancelBankConfig>,
+) -> MarginfiResult {
+    msg!("Queued bank config cancelled");
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct LendingPoolCancelBankConfig<'info> {
+    pub marginfi_group: AccountLoader<'info, MarginfiGroup>,
+
+    #[account(
+        address = marginfi_group.load()?.get_risk_admin(),
+    )]
+    pub admin: Signer<'info>,
+
+    #[account(
+        constraint = bank.load()?.group == marginfi_group.key(),
+    )]
+    pub bank: AccountLoader<'info, Bank>,
+
+    #[account(
+        mut,
+        has_one = bank,
+        has_one = payer,
+        close = payer,
+        seeds = [
+            PENDING_BANK_CONFIG_SEED.as_bytes(),
+            bank.key().as_ref(),
+        ],
+        bump,
+    )]
+    pub pending_bank_config: Account<'info, PendingBankConfig>,
+
+    /// CHECK: Rent receiver, asserted against the pending config
+    #[account(mut)]
+    pub payer: AccountInfo<'info>,
+}
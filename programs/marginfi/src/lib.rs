@@ -40,6 +40,14 @@ pub mod marginfi {
         marginfi_group::configure(ctx, config)
     }
 
+    /// (guardian or admin only) Pause or unpause the group
+    pub fn marginfi_group_set_pause_state(
+        ctx: Context<MarginfiGroupSetPauseState>,
+        pause_state: u8,
+    ) -> MarginfiResult {
+        marginfi_group::set_pause_state(ctx, pause_state)
+    }
+
     pub fn lending_pool_add_bank(
         ctx: Context<LendingPoolAddBank>,
         bank_config: BankConfigCompact,
@@ -65,6 +73,27 @@ pub mod marginfi {
         marginfi_group::lending_pool_configure_bank(ctx, bank_config_opt)
     }
 
+    /// Queue a bank config, applied by `lending_pool_execute_bank_config` once the group's
+    /// `config_delay` has passed.
+    pub fn lending_pool_queue_bank_config(
+        ctx: Context<LendingPoolQueueBankConfig>,
+        bank_config_opt: BankConfigOpt,
+    ) -> MarginfiResult {
+        marginfi_group::lending_pool_queue_bank_config(ctx, bank_config_opt)
+    }
+
+    pub fn lending_pool_execute_bank_config(
+        ctx: Context<LendingPoolExecuteBankConfig>,
+    ) -> MarginfiResult {
+        marginfi_group::lending_pool_execute_bank_config(ctx)
+    }
+
+    pub fn lending_pool_cancel_bank_config(
+        ctx: Context<LendingPoolCancelBankConfig>,
+    ) -> MarginfiResult {
+        marginfi_group::lending_pool_cancel_bank_config(ctx)
+    }
+
     pub fn lending_pool_setup_emissions(
         ctx: Context<LendingPoolSetupEmissions>,
         flags: u64,
@@ -132,6 +161,29 @@ pub mod marginfi {
         marginfi_account::lending_account_borrow(ctx, amount)
     }
 
+    /// Borrow at a locked rate for `duration` seconds
+    pub fn lending_account_borrow_fixed_term<'info>(
+        ctx: Context<'_, '_, 'info, 'info, LendingAccountBorrowFixedTerm<'info>>,
+        amount: u64,
+        duration: u64,
+    ) -> MarginfiResult {
+        marginfi_account::lending_account_borrow_fixed_term(ctx, amount, duration)
+    }
+
+    pub fn lending_account_repay_fixed_term<'info>(
+        ctx: Context<'_, '_, 'info, 'info, LendingAccountRepayFixedTerm<'info>>,
+    ) -> MarginfiResult {
+        marginfi_account::lending_account_repay_fixed_term(ctx)
+    }
+
+    /// (Permissionless) Convert a matured fixed-term borrow, or one of an unhealthy account, to a
+    /// variable liability
+    pub fn lending_account_convert_fixed_term<'info>(
+        ctx: Context<'_, '_, 'info, 'info, LendingAccountConvertFixedTerm<'info>>,
+    ) -> MarginfiResult {
+        marginfi_account::lending_account_convert_fixed_term(ctx)
+    }
+
     pub fn lending_account_close_balance(
         ctx: Context<LendingAccountCloseBalance>,
     ) -> MarginfiResult {
@@ -206,12 +258,35 @@ pub mod marginfi {
         marginfi_group::unset_account_flag(ctx, flag)
     }
 
+    /// (group admin only) Limits the account's liabilities in USD and the banks it may use
+    pub fn configure_account_limits(
+        ctx: Context<ConfigureAccountLimits>,
+        max_liability_value: u64,
+        allowed_banks: Vec<Pubkey>,
+    ) -> MarginfiResult {
+        marginfi_group::configure_account_limits(ctx, max_liability_value, allowed_banks)
+    }
+
     pub fn set_new_account_authority(
         ctx: Context<MarginfiAccountSetAccountAuthority>,
     ) -> MarginfiResult {
         marginfi_account::set_account_transfer_authority(ctx)
     }
 
+    /// Register a delegate with scoped permissions, see `DELEGATE_*_PERMISSION`
+    pub fn marginfi_account_set_delegate(
+        ctx: Context<MarginfiAccountSetDelegate>,
+        delegate: Pubkey,
+        permissions: u64,
+    ) -> MarginfiResult {
+        marginfi_account::set_account_delegate(ctx, delegate, permissions)
+    }
+
+    /// Migrate the account to the extended layout, with room for more balances
+    pub fn marginfi_account_extend(ctx: Context<MarginfiAccountExtend>) -> MarginfiResult {
+        marginfi_account::extend_account(ctx)
+    }
+
     pub fn marginfi_account_close(ctx: Context<MarginfiAccountClose>) -> MarginfiResult {
         marginfi_account::close_account(ctx)
     }
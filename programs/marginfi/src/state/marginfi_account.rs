@@ -1,5 +1,5 @@
 use super::{
-    marginfi_group::{Bank, RiskTier, WrappedI80F48},
+    marginfi_group::{Bank, BankPairRules, MarginfiGroup, RiskTier, WrappedI80F48},
     price::{OraclePriceFeedAdapter, OraclePriceType, PriceAdapter, PriceBias},
 };
 use crate::{
@@ -9,7 +9,9 @@ use crate::{
         EMPTY_BALANCE_THRESHOLD, EXP_10_I80F48, MIN_EMISSIONS_START_TIME, SECONDS_PER_YEAR,
         ZERO_AMOUNT_THRESHOLD,
     },
-    debug, math_error,
+    debug,
+    events::LendingAccountOracleSourceEvent,
+    math_error,
     prelude::{MarginfiError, MarginfiResult},
     utils::NumTraitsWithTolerance,
 };
@@ -17,7 +19,9 @@ use anchor_lang::prelude::*;
 use anchor_spl::token_interface::Mint;
 use fixed::types::I80F48;
 use std::{
+    cell::{Ref, RefMut},
     cmp::{max, min},
+    mem::size_of,
     ops::Not,
 };
 #[cfg(any(feature = "test", feature = "client"))]
@@ -42,7 +46,19 @@ pub struct MarginfiAccount {
     /// - DISABLED_FLAG = 1 << 0 = 1 - This flag indicates that the account is disabled,
     /// and no further actions can be taken on it.
     pub account_flags: u64, // 8
-    pub _padding: [u64; 63],             // 504
+    /// Fixed-rate, fixed-maturity borrows, at most one per bank.
+    pub fixed_term_borrows: [FixedTermBorrow; MAX_FIXED_TERM_BORROWS], // 80 * 2 = 160
+    /// Max USD value of the account's liabilities, set by the group admin. 0 for no limit.
+    pub max_liability_value: u64, // 8
+    /// Banks the account may deposit into and borrow from, set by the group admin. Any bank if
+    /// empty.
+    pub allowed_banks: [Pubkey; MAX_ACCOUNT_ALLOWED_BANKS], // 32 * 4 = 128
+    /// Can sign the instructions allowed by `delegate_permissions` in place of the authority,
+    /// only sending tokens to token accounts owned by the authority. None if default.
+    pub delegate: Pubkey, // 32
+    /// Bitmask of the `DELEGATE_*_PERMISSION` instructions the delegate can sign.
+    pub delegate_permissions: u64, // 8
+    pub _padding: [u64; 21],             // 168
 }
 
 pub const DISABLED_FLAG: u64 = 1 << 0;
@@ -50,6 +66,37 @@ pub const IN_FLASHLOAN_FLAG: u64 = 1 << 1;
 pub const FLASHLOAN_ENABLED_FLAG: u64 = 1 << 2;
 pub const TRANSFER_AUTHORITY_ALLOWED_FLAG: u64 = 1 << 3;
 
+pub const DELEGATE_DEPOSIT_PERMISSION: u64 = 1 << 0;
+pub const DELEGATE_REPAY_PERMISSION: u64 = 1 << 1;
+pub const DELEGATE_WITHDRAW_PERMISSION: u64 = 1 << 2;
+pub const DELEGATE_BORROW_PERMISSION: u64 = 1 << 3;
+pub const DELEGATE_CLOSE_BALANCE_PERMISSION: u64 = 1 << 4;
+pub const DELEGATE_WITHDRAW_EMISSIONS_PERMISSION: u64 = 1 << 5;
+pub const DELEGATE_LIQUIDATE_PERMISSION: u64 = 1 << 6;
+pub const DELEGATE_FLASHLOAN_PERMISSION: u64 = 1 << 7;
+
+/// Permissions in use for delegates.
+pub const DELEGATE_ALLOWED_PERMISSIONS: u64 = DELEGATE_DEPOSIT_PERMISSION
+    | DELEGATE_REPAY_PERMISSION
+    | DELEGATE_WITHDRAW_PERMISSION
+    | DELEGATE_BORROW_PERMISSION
+    | DELEGATE_CLOSE_BALANCE_PERMISSION
+    | DELEGATE_WITHDRAW_EMISSIONS_PERMISSION
+    | DELEGATE_LIQUIDATE_PERMISSION
+    | DELEGATE_FLASHLOAN_PERMISSION;
+
+/// Balances past the `LendingAccount`'s, stored right after the `MarginfiAccount` in accounts
+/// extended by `marginfi_account_extend`.
+///
+/// Capped so that the account stays liquidatable: with a bank and an oracle per balance, the
+/// liquidatee's 24 balances take 48 accounts, leaving the liquidation's own accounts within the
+/// 64 account locks of a transaction.
+pub const MAX_EXTENDED_BALANCES: usize = 8;
+
+/// Size of an extended marginfi account, discriminator included.
+pub const EXTENDED_MARGINFI_ACCOUNT_LEN: usize =
+    8 + size_of::<MarginfiAccount>() + MAX_EXTENDED_BALANCES * size_of::<Balance>();
+
 impl MarginfiAccount {
     /// Set the initial data for the marginfi account.
     pub fn initialize(&mut self, group: Pubkey, authority: Pubkey) {
@@ -57,13 +104,119 @@ impl MarginfiAccount {
         self.group = group;
     }
 
-    pub fn get_remaining_accounts_len(&self) -> usize {
+    /// Loads the account along with its extended balances, empty unless the account was extended.
+    ///
+    /// Legacy accounts still load as a whole with `AccountLoader::load`, which only reads the
+    /// `MarginfiAccount` at the start of the account data.
+    pub fn load_extended<'a>(
+        loader: &'a AccountLoader<MarginfiAccount>,
+    ) -> MarginfiResult<(Ref<'a, MarginfiAccount>, Ref<'a, [Balance]>)> {
+        // Checks the account's owner and discriminator
+        drop(loader.load()?);
+
+        let data = loader.as_ref().try_borrow_data()?;
+
+        Ok(Ref::map_split(data, |data| {
+            let (account, extension) = data[8..].split_at(size_of::<MarginfiAccount>());
+            (
+                bytemuck::from_bytes(account),
+                bytemuck::cast_slice(extension),
+            )
+        }))
+    }
+
+    /// Mutable `load_extended`.
+    pub fn load_mut_extended<'a>(
+        loader: &'a AccountLoader<MarginfiAccount>,
+    ) -> MarginfiResult<(RefMut<'a, MarginfiAccount>, RefMut<'a, [Balance]>)> {
+        // Checks the account's owner, discriminator and that it's writable
+        drop(loader.load_mut()?);
+
+        let data = loader.as_ref().try_borrow_mut_data()?;
+
+        Ok(RefMut::map_split(data, |data| {
+            let (account, extension) = data[8..].split_at_mut(size_of::<MarginfiAccount>());
+            (
+                bytemuck::from_bytes_mut(account),
+                bytemuck::cast_slice_mut(extension),
+            )
+        }))
+    }
+
+    /// Balances of the `LendingAccount`, followed by the `extended_balances`.
+    pub fn get_balances<'a>(
+        &'a self,
+        extended_balances: &'a [Balance],
+    ) -> impl Iterator<Item = &'a Balance> {
         self.lending_account
             .balances
             .iter()
+            .chain(extended_balances.iter())
+    }
+
+    /// Number of remaining accounts the risk engine reads for this account: the bank of each
+    /// active balance, followed by its oracle accounts.
+    ///
+    /// The banks are looked up in `remaining_ais`, as the number of oracle accounts depends on
+    /// their oracle setup.
+    pub fn get_remaining_accounts_len<'info>(
+        &self,
+        extended_balances: &[Balance],
+        remaining_ais: &'info [AccountInfo<'info>],
+    ) -> MarginfiResult<usize> {
+        self.get_balances(extended_balances)
             .filter(|b| b.active)
-            .count()
-            * 2 // TODO: Make account count oracle setup specific
+            .map(|balance| {
+                let bank_ai = remaining_ais
+                    .iter()
+                    .rev()
+                    .find(|ai| ai.key.eq(&balance.bank_pk))
+                    .ok_or(MarginfiError::MissingPythOrBankAccount)?;
+                let bank_al = AccountLoader::<Bank>::try_from(bank_ai)?;
+                let oracle_ais_len = bank_al.load()?.config.get_oracle_ais_len();
+
+                Ok(1 + oracle_ais_len)
+            })
+            .sum()
+    }
+
+    /// Registers `delegate` with `permissions`, a bitmask of the `DELEGATE_*_PERMISSION`
+    /// constants. A default `delegate` removes the delegate.
+    pub fn set_delegate(&mut self, delegate: Pubkey, permissions: u64) -> MarginfiResult {
+        check!(
+            permissions & !DELEGATE_ALLOWED_PERMISSIONS == 0,
+            MarginfiError::IllegalFlag
+        );
+
+        self.delegate = delegate;
+        self.delegate_permissions = if delegate == Pubkey::default() {
+            0
+        } else {
+            permissions
+        };
+
+        Ok(())
+    }
+
+    /// True if `signer` is the authority, or the delegate holding `permission`.
+    pub fn can_sign(&self, signer: &Pubkey, permission: u64) -> bool {
+        let is_delegate = self.delegate.ne(&Pubkey::default())
+            && self.delegate.eq(signer)
+            && self.delegate_permissions & permission == permission;
+
+        self.authority.eq(signer) || is_delegate
+    }
+
+    /// `can_sign` for instructions sending tokens out of the group: the delegate can only send
+    /// them to a token account owned by the authority.
+    pub fn can_sign_outflow(
+        &self,
+        signer: &Pubkey,
+        permission: u64,
+        destination_owner: &Pubkey,
+    ) -> bool {
+        self.authority.eq(signer)
+            || (self.can_sign(signer, permission) && self.authority.eq(destination_owner))
     }
 
     pub fn set_flag(&mut self, flag: u64) {
@@ -80,15 +233,29 @@ impl MarginfiAccount {
         self.account_flags & flag != 0
     }
 
+    /// Checks the group admin allows the account to deposit into and borrow from the bank.
+    pub fn check_bank_allowed(&self, bank_pk: &Pubkey) -> MarginfiResult {
+        let any_bank_allowed = self.allowed_banks.iter().all(|b| *b == Pubkey::default());
+
+        check!(
+            any_bank_allowed || self.allowed_banks.contains(bank_pk),
+            MarginfiError::AccountBankNotAllowed
+        );
+
+        Ok(())
+    }
+
     pub fn set_new_account_authority_checked(&mut self, new_authority: Pubkey) -> MarginfiResult {
         // check if new account authority flag is set
         if !self.get_flag(TRANSFER_AUTHORITY_ALLOWED_FLAG) || self.get_flag(DISABLED_FLAG) {
             return Err(MarginfiError::IllegalAccountAuthorityTransfer.into());
         }
 
-        // update account authority
+        // update account authority, the delegate was registered by the old one
         let old_authority = self.authority;
         self.authority = new_authority;
+        self.delegate = Pubkey::default();
+        self.delegate_permissions = 0;
 
         // unset flag after updating the account authority
         self.unset_flag(TRANSFER_AUTHORITY_ALLOWED_FLAG);
@@ -102,15 +269,48 @@ impl MarginfiAccount {
         Ok(())
     }
 
-    pub fn can_be_closed(&self) -> bool {
+    pub fn can_be_closed(&self, extended_balances: &[Balance]) -> bool {
         let is_disabled = self.get_flag(DISABLED_FLAG);
         let only_has_empty_balances = self
-            .lending_account
-            .balances
-            .iter()
+            .get_balances(extended_balances)
             .all(|balance| balance.get_side().is_none());
+        let has_no_fixed_term_borrows = self
+            .fixed_term_borrows
+            .iter()
+            .all(|fixed_term_borrow| fixed_term_borrow.is_empty());
+
+        !is_disabled && only_has_empty_balances && has_no_fixed_term_borrows
+    }
 
-        !is_disabled && only_has_empty_balances
+    /// Find the fixed-term borrow in `bank_pk`.
+    pub fn get_fixed_term_borrow_mut(
+        &mut self,
+        bank_pk: &Pubkey,
+    ) -> MarginfiResult<&mut FixedTermBorrow> {
+        self.fixed_term_borrows
+            .iter_mut()
+            .find(|fixed_term_borrow| fixed_term_borrow.bank_pk.eq(bank_pk))
+            .ok_or_else(|| error!(MarginfiError::FixedTermBorrowNotFound))
+    }
+
+    /// Record a new fixed-term borrow, only one fixed-term borrow per bank is allowed.
+    pub fn add_fixed_term_borrow(&mut self, fixed_term_borrow: FixedTermBorrow) -> MarginfiResult {
+        check!(
+            self.fixed_term_borrows
+                .iter()
+                .all(|b| b.bank_pk != fixed_term_borrow.bank_pk),
+            MarginfiError::InvalidFixedTermBorrow,
+            "Bank already has a fixed-term borrow"
+        );
+
+        let slot = self
+            .fixed_term_borrows
+            .iter_mut()
+            .find(|b| b.is_empty())
+            .ok_or_else(|| error!(MarginfiError::FixedTermBorrowSlotsFull))?;
+        *slot = fixed_term_borrow;
+
+        Ok(())
     }
 }
 
@@ -154,6 +354,20 @@ pub struct BankAccountWithPriceFeed<'a, 'info> {
     bank: AccountInfo<'info>,
     price_feed: Box<MarginfiResult<OraclePriceFeedAdapter>>,
     balance: &'a Balance,
+    /// Amount owed by the account's fixed-term borrow in this bank, if any
+    fixed_term_liability: I80F48,
+    /// `Bank::emode_category` of the bank
+    emode_category: u16,
+    /// Collateral/borrow pair rules of the bank
+    pair_rules: BankPairRules,
+    /// `BankConfig::risk_tier` of the bank
+    risk_tier: RiskTier,
+    /// Max value of the deposit in initial health, see `Bank::get_isolated_collateral_cap`
+    isolated_collateral_cap: Option<I80F48>,
+    /// `Bank::is_price_guard_tripped` of the bank
+    price_guard_tripped: bool,
+    /// The oracle price moved past the bank's price guard, see `Bank::is_price_guard_move`
+    price_guard_move: bool,
 }
 
 pub enum BalanceSide {
@@ -163,55 +377,122 @@ pub enum BalanceSide {
 
 impl<'info> BankAccountWithPriceFeed<'_, 'info> {
     pub fn load<'a>(
-        lending_account: &'a LendingAccount,
+        marginfi_account: &'a MarginfiAccount,
+        extended_balances: &'a [Balance],
         remaining_ais: &'info [AccountInfo<'info>],
     ) -> MarginfiResult<Vec<BankAccountWithPriceFeed<'a, 'info>>> {
-        let active_balances = lending_account
-            .balances
-            .iter()
+        let active_balances = marginfi_account
+            .get_balances(extended_balances)
             .filter(|balance| balance.active)
             .collect::<Vec<_>>();
 
-        debug!("Expecting {} remaining accounts", active_balances.len() * 2);
         debug!("Got {} remaining accounts", remaining_ais.len());
 
-        check!(
-            active_balances.len() * 2 <= remaining_ais.len(),
-            MarginfiError::MissingPythOrBankAccount
-        );
-
         let clock = Clock::get()?;
+        // Each bank is followed by as many oracle accounts as its oracle setup requires (see
+        // `BankConfig::get_oracle_ais_len`), none for `OracleSetup::Fixed`. The accounts expected
+        // for each bank are checked once it's loaded.
+        let mut bank_index = 0;
 
         active_balances
             .iter()
-            .enumerate()
-            .map(|(i, balance)| {
-                let bank_index = i * 2;
-                let oracle_ai_idx = bank_index + 1;
-
-                let bank_ai = remaining_ais.get(bank_index).unwrap();
+            .map(|balance| {
+                let bank_ai = remaining_ais
+                    .get(bank_index)
+                    .ok_or(MarginfiError::MissingPythOrBankAccount)?;
 
                 check!(
                     balance.bank_pk.eq(bank_ai.key),
                     MarginfiError::InvalidBankAccount
                 );
 
-                let price_adapter = {
-                    let oracle_ais = &remaining_ais[oracle_ai_idx..oracle_ai_idx + 1];
+                let (
+                    price_adapter,
+                    emode_category,
+                    pair_rules,
+                    risk_tier,
+                    isolated_collateral_cap,
+                    price_guard_tripped,
+                    price_guard_move,
+                ) = {
                     let bank_al = AccountLoader::<Bank>::try_from(bank_ai)?;
                     let bank = bank_al.load()?;
 
-                    Box::new(OraclePriceFeedAdapter::try_from_bank_config(
-                        &bank.config,
+                    let oracle_ai_idx = bank_index + 1;
+                    let oracle_ais_end = oracle_ai_idx + bank.config.get_oracle_ais_len();
+
+                    check!(
+                        oracle_ais_end <= remaining_ais.len(),
+                        MarginfiError::MissingPythOrBankAccount
+                    );
+
+                    bank_index = oracle_ais_end;
+
+                    let oracle_ais = &remaining_ais[oracle_ai_idx..oracle_ais_end];
+
+                    let price_feed = OraclePriceFeedAdapter::try_from_bank_with_source(
+                        &bank,
                         oracle_ais,
                         &clock,
-                    ))
+                        bank.config.get_oracle_max_age(),
+                    );
+
+                    if bank.config.has_oracle_fallback() {
+                        if let Ok((_, oracle_source)) = &price_feed {
+                            emit!(LendingAccountOracleSourceEvent {
+                                marginfi_group: marginfi_account.group,
+                                marginfi_account_authority: marginfi_account.authority,
+                                bank: balance.bank_pk,
+                                oracle_source: *oracle_source,
+                            });
+                        }
+                    }
+
+                    let price_feed = price_feed.map(|(price_feed, _)| price_feed);
+
+                    // Catches a bad tick the guard didn't record yet
+                    let price_guard_move = match &price_feed {
+                        Ok(price_feed) if bank.is_price_guard_active() => bank
+                            .is_price_guard_move(
+                                price_feed.get_price_of_type(OraclePriceType::RealTime, None)?,
+                                clock.unix_timestamp,
+                            )?,
+                        _ => false,
+                    };
+
+                    (
+                        Box::new(price_feed),
+                        bank.emode_category,
+                        bank.get_pair_rules(),
+                        bank.config.risk_tier,
+                        bank.get_isolated_collateral_cap(balance.asset_shares.into())?,
+                        bank.is_price_guard_tripped(clock.unix_timestamp),
+                        price_guard_move,
+                    )
+                };
+
+                let fixed_term_liability = match marginfi_account
+                    .fixed_term_borrows
+                    .iter()
+                    .find(|b| b.bank_pk.eq(&balance.bank_pk))
+                {
+                    Some(fixed_term_borrow) => {
+                        fixed_term_borrow.get_amount_owed(clock.unix_timestamp)?
+                    }
+                    None => I80F48::ZERO,
                 };
 
                 Ok(BankAccountWithPriceFeed {
                     bank: bank_ai.clone(),
                     price_feed: price_adapter,
                     balance,
+                    fixed_term_liability,
+                    emode_category,
+                    pair_rules,
+                    risk_tier,
+                    isolated_collateral_cap,
+                    price_guard_tripped,
+                    price_guard_move,
                 })
             })
             .collect::<Result<Vec<_>>>()
@@ -226,31 +507,35 @@ impl<'info> BankAccountWithPriceFeed<'_, 'info> {
     /// 3. Initial requirement is discounted by the initial discount, if enabled and the usd limit is exceeded.
     /// 4. Assets are only calculated for collateral risk tier.
     /// 5. Oracle errors are ignored for deposits in isolated risk tier.
+    /// 6. Fixed-term borrows are liabilities, whether or not the balance has variable liabilities.
+    /// 7. E-mode weights are used if the account is in the bank's e-mode category.
     fn calc_weighted_assets_and_liabilities_values<'a>(
         &'a self,
         requirement_type: RequirementType,
+        emode_category: Option<u16>,
     ) -> MarginfiResult<(I80F48, I80F48)>
     where
         'info: 'a,
     {
-        match self.balance.get_side() {
-            Some(side) => {
-                // SAFETY: We are shortening 'info -> 'a
-                let shorter_bank: &'a AccountInfo<'a> = unsafe { core::mem::transmute(&self.bank) };
-                let bank_al = AccountLoader::<Bank>::try_from(shorter_bank)?;
-                let bank = bank_al.load()?;
-                match side {
-                    BalanceSide::Assets => Ok((
-                        self.calc_weighted_assets(requirement_type, &bank)?,
-                        I80F48::ZERO,
-                    )),
-                    BalanceSide::Liabilities => Ok((
-                        I80F48::ZERO,
-                        self.calc_weighted_liabs(requirement_type, &bank)?,
-                    )),
-                }
-            }
-            None => Ok((I80F48::ZERO, I80F48::ZERO)),
+        let side = match (self.balance.get_side(), self.fixed_term_liability.is_zero()) {
+            (Some(side), _) => side,
+            (None, false) => BalanceSide::Liabilities,
+            (None, true) => return Ok((I80F48::ZERO, I80F48::ZERO)),
+        };
+
+        // SAFETY: We are shortening 'info -> 'a
+        let shorter_bank: &'a AccountInfo<'a> = unsafe { core::mem::transmute(&self.bank) };
+        let bank_al = AccountLoader::<Bank>::try_from(shorter_bank)?;
+        let bank = bank_al.load()?;
+        match side {
+            BalanceSide::Assets => Ok((
+                self.calc_weighted_assets(requirement_type, &bank, emode_category)?,
+                I80F48::ZERO,
+            )),
+            BalanceSide::Liabilities => Ok((
+                I80F48::ZERO,
+                self.calc_weighted_liabs(requirement_type, &bank, emode_category)?,
+            )),
         }
     }
 
@@ -259,9 +544,10 @@ impl<'info> BankAccountWithPriceFeed<'_, 'info> {
         &'a self,
         requirement_type: RequirementType,
         bank: &'a Bank,
+        emode_category: Option<u16>,
     ) -> MarginfiResult<I80F48> {
         match bank.config.risk_tier {
-            RiskTier::Collateral => {
+            RiskTier::Collateral | RiskTier::IsolatedCollateral => {
                 let price_feed = self.try_get_price_feed();
 
                 if matches!(
@@ -274,9 +560,8 @@ impl<'info> BankAccountWithPriceFeed<'_, 'info> {
 
                 let price_feed = price_feed?;
 
-                let mut asset_weight = bank
-                    .config
-                    .get_weight(requirement_type, BalanceSide::Assets);
+                let mut asset_weight =
+                    bank.get_weight(requirement_type, BalanceSide::Assets, emode_category);
 
                 let lower_price = price_feed.get_price_of_type(
                     requirement_type.get_oracle_price_type(),
@@ -309,19 +594,24 @@ impl<'info> BankAccountWithPriceFeed<'_, 'info> {
         &self,
         requirement_type: RequirementType,
         bank: &Bank,
+        emode_category: Option<u16>,
     ) -> MarginfiResult<I80F48> {
         let price_feed = self.try_get_price_feed()?;
-        let liability_weight = bank
-            .config
-            .get_weight(requirement_type, BalanceSide::Liabilities);
+        let liability_weight =
+            bank.get_weight(requirement_type, BalanceSide::Liabilities, emode_category);
 
         let higher_price = price_feed.get_price_of_type(
             requirement_type.get_oracle_price_type(),
             Some(PriceBias::High),
         )?;
 
+        let liability_amount = bank
+            .get_liability_amount(self.balance.liability_shares.into())?
+            .checked_add(self.fixed_term_liability)
+            .ok_or_else(math_error!())?;
+
         calc_value(
-            bank.get_liability_amount(self.balance.liability_shares.into())?,
+            liability_amount,
             higher_price,
             bank.mint_decimals,
             Some(liability_weight),
@@ -341,7 +631,12 @@ impl<'info> BankAccountWithPriceFeed<'_, 'info> {
 
     #[inline]
     pub fn is_empty(&self, side: BalanceSide) -> bool {
-        self.balance.is_empty(side)
+        match side {
+            BalanceSide::Assets => self.balance.is_empty(side),
+            BalanceSide::Liabilities => {
+                self.balance.is_empty(side) && self.fixed_term_liability.is_zero()
+            }
+        }
     }
 }
 
@@ -405,6 +700,102 @@ pub fn calc_amount(value: I80F48, price: I80F48, mint_decimals: u8) -> MarginfiR
     Ok(qt)
 }
 
+/// Calculate how far an account is below the maintenance requirement, as a share of its
+/// maintenance-weighted liabilities, given its maintenance `health` and `liabs`.
+#[inline]
+pub fn calc_liquidation_shortfall(health: I80F48, liabs: I80F48) -> MarginfiResult<I80F48> {
+    if health >= I80F48::ZERO || liabs == I80F48::ZERO {
+        return Ok(I80F48::ZERO);
+    }
+
+    Ok(health
+        .checked_neg()
+        .ok_or_else(math_error!())?
+        .checked_div(liabs)
+        .ok_or_else(math_error!())?)
+}
+
+/// Calculate the max `asset_amount` a single `lending_account_liquidate` call can seize, so
+/// liquidators don't have to search for it by trial and error.
+///
+/// - `asset_price`, `liab_price`: real time prices, biased low and high respectively
+/// - `asset_amount`: liquidatee's deposit in `asset_bank`
+/// - `liab_amount`: liquidatee's liability in `liab_bank`
+/// - `health`, `liabs`: liquidatee's maintenance health and maintenance-weighted liabilities
+///
+/// The amount is capped by the liquidatee's deposit, the liability bank's close factor and the
+/// amount that brings the account back to the maintenance requirement. It's rounded down to native
+/// units, and is 0 if the account can't be liquidated with this pair of banks, or if it's not
+/// below the maintenance requirement (`health >= 0`).
+#[allow(clippy::too_many_arguments)]
+pub fn calc_max_liquidation_asset_amount(
+    asset_bank: &Bank,
+    liab_bank: &Bank,
+    asset_price: I80F48,
+    liab_price: I80F48,
+    asset_amount: I80F48,
+    liab_amount: I80F48,
+    health: I80F48,
+    liabs: I80F48,
+) -> MarginfiResult<I80F48> {
+    if health >= I80F48::ZERO || asset_amount <= I80F48::ZERO || liab_amount <= I80F48::ZERO {
+        return Ok(I80F48::ZERO);
+    }
+
+    let shortfall = calc_liquidation_shortfall(health, liabs)?;
+    let final_discount = I80F48::ONE
+        - (asset_bank.config.get_liquidator_fee(shortfall)?
+            + asset_bank.config.get_liquidation_insurance_fee());
+
+    // Collateral that repays the max repayable liability
+    let max_repay_amount = liab_bank
+        .config
+        .get_max_liquidation_repay_amount(liab_amount, shortfall)?
+        .unwrap_or(liab_amount);
+    let max_amount_by_liab = calc_amount(
+        calc_value(max_repay_amount, liab_price, liab_bank.mint_decimals, None)?
+            .checked_div(final_discount)
+            .ok_or_else(math_error!())?,
+        asset_price,
+        asset_bank.mint_decimals,
+    )?;
+
+    // Maintenance health gained per unit of collateral value seized
+    let asset_weight = match asset_bank.config.risk_tier {
+        RiskTier::Collateral | RiskTier::IsolatedCollateral => asset_bank
+            .config
+            .get_weight(RequirementType::Maintenance, BalanceSide::Assets),
+        RiskTier::Isolated => I80F48::ZERO,
+    };
+    let liab_weight = liab_bank
+        .config
+        .get_weight(RequirementType::Maintenance, BalanceSide::Liabilities);
+    let health_gain = final_discount
+        .checked_mul(liab_weight)
+        .ok_or_else(math_error!())?
+        .checked_sub(asset_weight)
+        .ok_or_else(math_error!())?;
+
+    if health_gain <= I80F48::ZERO {
+        return Ok(I80F48::ZERO);
+    }
+
+    let max_amount_by_health = calc_amount(
+        health
+            .checked_neg()
+            .ok_or_else(math_error!())?
+            .checked_div(health_gain)
+            .ok_or_else(math_error!())?,
+        asset_price,
+        asset_bank.mint_decimals,
+    )?;
+
+    Ok(asset_amount
+        .min(max_amount_by_liab)
+        .min(max_amount_by_health)
+        .floor())
+}
+
 pub enum RiskRequirementType {
     Initial,
     Maintenance,
@@ -424,11 +815,17 @@ impl RiskRequirementType {
 pub struct RiskEngine<'a, 'info> {
     marginfi_account: &'a MarginfiAccount,
     bank_accounts_with_price: Vec<BankAccountWithPriceFeed<'a, 'info>>,
+    /// Deposits in banks whose price guard tripped, or whose oracle price moved past the guard,
+    /// don't count toward initial health, see `check_account_borrow_health`.
+    ignore_price_guarded_assets: bool,
 }
 
 impl<'info> RiskEngine<'_, 'info> {
+    /// `extended_balances` are the balances past the lending account's, see
+    /// `MarginfiAccount::load_extended`, and are empty for accounts that weren't extended.
     pub fn new<'a>(
         marginfi_account: &'a MarginfiAccount,
+        extended_balances: &'a [Balance],
         remaining_ais: &'info [AccountInfo<'info>],
     ) -> MarginfiResult<RiskEngine<'a, 'info>> {
         check!(
@@ -436,21 +833,23 @@ impl<'info> RiskEngine<'_, 'info> {
             MarginfiError::AccountInFlashloan
         );
 
-        Self::new_no_flashloan_check(marginfi_account, remaining_ais)
+        Self::new_no_flashloan_check(marginfi_account, extended_balances, remaining_ais)
     }
 
     /// Internal constructor used either after manually checking account is not in a flashloan,
     /// or explicity checking health for flashloan enabled actions.
     fn new_no_flashloan_check<'a>(
         marginfi_account: &'a MarginfiAccount,
+        extended_balances: &'a [Balance],
         remaining_ais: &'info [AccountInfo<'info>],
     ) -> MarginfiResult<RiskEngine<'a, 'info>> {
         let bank_accounts_with_price =
-            BankAccountWithPriceFeed::load(&marginfi_account.lending_account, remaining_ais)?;
+            BankAccountWithPriceFeed::load(marginfi_account, extended_balances, remaining_ais)?;
 
         Ok(RiskEngine {
             marginfi_account,
             bank_accounts_with_price,
+            ignore_price_guarded_assets: false,
         })
     }
 
@@ -461,19 +860,76 @@ impl<'info> RiskEngine<'_, 'info> {
     /// - `remaining_ais` can be an empty vec.
     pub fn check_account_init_health<'a>(
         marginfi_account: &'a MarginfiAccount,
+        extended_balances: &'a [Balance],
         remaining_ais: &'info [AccountInfo<'info>],
     ) -> MarginfiResult<()> {
         if marginfi_account.get_flag(IN_FLASHLOAN_FLAG) {
             return Ok(());
         }
 
-        Self::new_no_flashloan_check(marginfi_account, remaining_ais)?
+        Self::new_no_flashloan_check(marginfi_account, extended_balances, remaining_ais)?
             .check_account_health(RiskRequirementType::Initial)?;
 
         Ok(())
     }
 
+    /// Checks account is healthy after opening new borrows, like `check_account_init_health`.
+    /// Deposits in banks whose price guard tripped, or whose oracle price moved past the guard,
+    /// don't back the borrows.
+    pub fn check_account_borrow_health<'a>(
+        marginfi_account: &'a MarginfiAccount,
+        extended_balances: &'a [Balance],
+        remaining_ais: &'info [AccountInfo<'info>],
+    ) -> MarginfiResult<()> {
+        if marginfi_account.get_flag(IN_FLASHLOAN_FLAG) {
+            return Ok(());
+        }
+
+        let mut risk_engine =
+            Self::new_no_flashloan_check(marginfi_account, extended_balances, remaining_ais)?;
+        risk_engine.ignore_price_guarded_assets = true;
+        risk_engine.check_account_health(RiskRequirementType::Initial)?;
+
+        Ok(())
+    }
+
+    /// Checks the account's liabilities are below the `max_liability_value` set by the group admin,
+    /// after actions that increase liabilities.
+    ///
+    /// Skipped while the account is in a flashloan, like the initial health check.
+    pub fn check_account_liability_limit(
+        marginfi_account: &MarginfiAccount,
+        extended_balances: &[Balance],
+        remaining_ais: &'info [AccountInfo<'info>],
+    ) -> MarginfiResult {
+        if marginfi_account.max_liability_value == 0 || marginfi_account.get_flag(IN_FLASHLOAN_FLAG)
+        {
+            return Ok(());
+        }
+
+        let (_, total_liabilities) =
+            Self::new_no_flashloan_check(marginfi_account, extended_balances, remaining_ais)?
+                .get_account_health_components(RiskRequirementType::Equity)?;
+
+        debug!(
+            "Liabilities: {}, limit: {}",
+            total_liabilities, marginfi_account.max_liability_value
+        );
+
+        check!(
+            total_liabilities <= I80F48::from_num(marginfi_account.max_liability_value),
+            MarginfiError::AccountLiabilityLimitExceeded
+        );
+
+        Ok(())
+    }
+
     /// Returns the total assets and liabilities of the account in the form of (assets, liabilities)
+    ///
+    /// For the initial requirement, deposits that may not back all of the account's liabilities
+    /// under the banks' pair rules are not counted as assets (see `BankPairRules`). Deposits in the
+    /// `IsolatedCollateral` risk tier count for at most their share of the bank's
+    /// `isolated_debt_ceiling`.
     pub fn get_account_health_components(
         &self,
         requirement_type: RiskRequirementType,
@@ -481,9 +937,52 @@ impl<'info> RiskEngine<'_, 'info> {
         let mut total_assets = I80F48::ZERO;
         let mut total_liabilities = I80F48::ZERO;
 
+        let emode_category = self.get_emode_category();
+
+        // Pair rules only restrict new risk, existing positions are not liquidated over them
+        let liab_pair_rules = match requirement_type {
+            RiskRequirementType::Initial => self
+                .bank_accounts_with_price
+                .iter()
+                .filter(|a| !a.is_empty(BalanceSide::Liabilities))
+                .map(|a| a.pair_rules)
+                .collect::<Vec<_>>(),
+            RiskRequirementType::Maintenance | RiskRequirementType::Equity => vec![],
+        };
+
         for a in &self.bank_accounts_with_price {
-            let (assets, liabilities) =
-                a.calc_weighted_assets_and_liabilities_values(requirement_type.to_weight_type())?;
+            let (mut assets, liabilities) = a.calc_weighted_assets_and_liabilities_values(
+                requirement_type.to_weight_type(),
+                emode_category,
+            )?;
+
+            if !liab_pair_rules.iter().all(|l| a.pair_rules.can_back(l)) {
+                debug!(
+                    "Balance {} may not back all liabilities, ignoring assets",
+                    a.balance.bank_pk
+                );
+                assets = I80F48::ZERO;
+            }
+
+            if let Some(isolated_collateral_cap) = a.isolated_collateral_cap {
+                if matches!(requirement_type, RiskRequirementType::Initial)
+                    && assets > isolated_collateral_cap
+                {
+                    debug!(
+                        "Balance {} capped by the isolated debt ceiling at {}",
+                        a.balance.bank_pk, isolated_collateral_cap
+                    );
+                    assets = isolated_collateral_cap;
+                }
+            }
+
+            if self.ignore_price_guarded_assets && (a.price_guard_tripped || a.price_guard_move) {
+                debug!(
+                    "Balance {} price guard tripped, ignoring assets",
+                    a.balance.bank_pk
+                );
+                assets = I80F48::ZERO;
+            }
 
             debug!(
                 "Balance {}, assets: {}, liabilities: {}",
@@ -499,6 +998,21 @@ impl<'info> RiskEngine<'_, 'info> {
         Ok((total_assets, total_liabilities))
     }
 
+    /// E-mode category the account is in: the category shared by all of its liabilities, if any.
+    /// Deposits and liabilities in the category's banks are then weighted with the e-mode weights.
+    pub fn get_emode_category(&self) -> Option<u16> {
+        let mut liab_categories = self
+            .bank_accounts_with_price
+            .iter()
+            .filter(|a| !a.is_empty(BalanceSide::Liabilities))
+            .map(|a| a.emode_category);
+
+        let emode_category = liab_categories.next()?;
+
+        (emode_category != 0 && liab_categories.all(|c| c == emode_category))
+            .then_some(emode_category)
+    }
+
     pub fn get_account_health(
         &'info self,
         requirement_type: RiskRequirementType,
@@ -533,10 +1047,13 @@ impl<'info> RiskEngine<'_, 'info> {
     /// Checks
     /// 1. Account is liquidatable
     /// 2. Account has an outstanding liability for the provided liability bank
+    ///
+    /// Returns the maintenance health and the maintenance-weighted liabilities of the account,
+    /// the latter is used to size the liquidation fee ramp.
     pub fn check_pre_liquidation_condition_and_get_account_health(
         &self,
         bank_pk: &Pubkey,
-    ) -> MarginfiResult<I80F48> {
+    ) -> MarginfiResult<(I80F48, I80F48)> {
         check!(
             !self.marginfi_account.get_flag(IN_FLASHLOAN_FLAG),
             MarginfiError::AccountInFlashloan
@@ -576,7 +1093,7 @@ impl<'info> RiskEngine<'_, 'info> {
             "Account not unhealthy"
         );
 
-        Ok(account_health)
+        Ok((account_health, liabs))
     }
 
     /// Check that the account is at most at the maintenance requirement level post liquidation.
@@ -680,7 +1197,7 @@ impl<'info> RiskEngine<'_, 'info> {
         let balances_with_liablities = self
             .bank_accounts_with_price
             .iter()
-            .filter(|a| a.balance.is_empty(BalanceSide::Liabilities).not());
+            .filter(|a| a.is_empty(BalanceSide::Liabilities).not());
 
         let n_balances_with_liablities = balances_with_liablities.clone().count();
 
@@ -701,6 +1218,20 @@ impl<'info> RiskEngine<'_, 'info> {
             MarginfiError::IsolatedAccountIllegalState
         );
 
+        // An account with liabilities can hold only one isolated collateral deposit
+        let n_isolated_collateral_deposits = self
+            .bank_accounts_with_price
+            .iter()
+            .filter(|a| {
+                a.risk_tier == RiskTier::IsolatedCollateral && !a.is_empty(BalanceSide::Assets)
+            })
+            .count();
+
+        check!(
+            n_balances_with_liablities == 0 || n_isolated_collateral_deposits <= 1,
+            MarginfiError::IsolatedAccountIllegalState
+        );
+
         Ok(())
     }
 }
@@ -739,6 +1270,82 @@ impl LendingAccount {
     }
 }
 
+#[cfg(any(feature = "test", feature = "client"))]
+impl MarginfiAccount {
+    /// Decodes the extended balances from the raw data of a marginfi account, see
+    /// `MarginfiAccount::load_extended`.
+    pub fn decode_extended_balances(data: &[u8]) -> Vec<Balance> {
+        data.get(8 + size_of::<MarginfiAccount>()..)
+            .unwrap_or_default()
+            .chunks_exact(size_of::<Balance>())
+            .map(bytemuck::pod_read_unaligned)
+            .collect()
+    }
+}
+
+pub const MAX_FIXED_TERM_BORROWS: usize = 2;
+
+pub const MAX_ACCOUNT_ALLOWED_BANKS: usize = 4;
+
+assert_struct_size!(FixedTermBorrow, 80);
+assert_struct_align!(FixedTermBorrow, 8);
+/// A borrow at a rate locked when it was opened, accruing simple interest until it's repaid, or
+/// converted to a variable liability once matured.
+///
+/// The principal is lent out of the bank's liquidity vault like a variable borrow, but is tracked
+/// in `Bank::fixed_term_liability_amount` instead of liability shares. The interest is credited to
+/// the bank's depositors as it accrues (see `Bank::fixed_term_annual_interest`).
+#[zero_copy(unsafe)]
+#[repr(C)]
+#[cfg_attr(
+    any(feature = "test", feature = "client"),
+    derive(Debug, PartialEq, Eq, TypeLayout)
+)]
+#[derive(Default)]
+pub struct FixedTermBorrow {
+    /// `Pubkey::default()` for an empty slot
+    pub bank_pk: Pubkey,
+    /// Amount borrowed, in native tokens
+    pub principal: WrappedI80F48,
+    /// Locked APR
+    pub rate: WrappedI80F48,
+    pub start_time: i64,
+    pub maturity: i64,
+}
+
+impl FixedTermBorrow {
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.bank_pk == Pubkey::default()
+    }
+
+    #[inline]
+    pub fn is_matured(&self, current_timestamp: i64) -> bool {
+        current_timestamp >= self.maturity
+    }
+
+    /// Principal plus interest at the locked rate, growing linearly from `start_time`. Interest
+    /// keeps accruing past `maturity` until the borrow is repaid or converted, as it does for the
+    /// bank.
+    pub fn get_amount_owed(&self, current_timestamp: i64) -> MarginfiResult<I80F48> {
+        let principal: I80F48 = self.principal.into();
+        let elapsed = current_timestamp
+            .checked_sub(self.start_time)
+            .ok_or_else(math_error!())?
+            .max(0);
+
+        let interest = principal
+            .checked_mul(self.rate.into())
+            .ok_or_else(math_error!())?
+            .checked_mul(I80F48::from_num(elapsed))
+            .ok_or_else(math_error!())?
+            .checked_div(SECONDS_PER_YEAR)
+            .ok_or_else(math_error!())?;
+
+        Ok(principal.checked_add(interest).ok_or_else(math_error!())?)
+    }
+}
+
 assert_struct_size!(Balance, 104);
 assert_struct_align!(Balance, 8);
 #[zero_copy(unsafe)]
@@ -755,7 +1362,10 @@ pub struct Balance {
     pub liability_shares: WrappedI80F48,
     pub emissions_outstanding: WrappedI80F48,
     pub last_update: u64,
-    pub _padding: [u64; 1],
+    /// Set while the account has a fixed-term borrow in this bank, the balance stays open so the
+    /// risk engine accounts for it and can't hold assets.
+    pub fixed_term_borrow_active: bool,
+    pub _padding: [u8; 7],
 }
 
 impl Balance {
@@ -795,6 +1405,11 @@ impl Balance {
             I80F48::from(self.emissions_outstanding) < I80F48::ONE,
             MarginfiError::CannotCloseOutstandingEmissions
         );
+        check!(
+            !self.fixed_term_borrow_active,
+            MarginfiError::IllegalBalanceState,
+            "Balance has an outstanding fixed-term borrow"
+        );
 
         *self = Self::empty_deactivated();
 
@@ -827,7 +1442,8 @@ impl Balance {
             liability_shares: WrappedI80F48::from(I80F48::ZERO),
             emissions_outstanding: WrappedI80F48::from(I80F48::ZERO),
             last_update: 0,
-            _padding: [0; 1],
+            fixed_term_borrow_active: false,
+            _padding: [0; 7],
         }
     }
 }
@@ -843,10 +1459,12 @@ impl<'a> BankAccountWrapper<'a> {
         bank_pk: &Pubkey,
         bank: &'a mut Bank,
         lending_account: &'a mut LendingAccount,
+        extended_balances: &'a mut [Balance],
     ) -> MarginfiResult<BankAccountWrapper<'a>> {
         let balance = lending_account
             .balances
             .iter_mut()
+            .chain(extended_balances.iter_mut())
             .find(|balance| balance.active && balance.bank_pk.eq(bank_pk))
             .ok_or_else(|| error!(MarginfiError::BankAccountNotFound))?;
 
@@ -854,32 +1472,39 @@ impl<'a> BankAccountWrapper<'a> {
     }
 
     // Find existing user lending account balance by bank address.
-    // Create it if not found.
+    // Create it if not found, in the first empty slot of the lending account, then of the
+    // extended balances.
     pub fn find_or_create(
         bank_pk: &Pubkey,
         bank: &'a mut Bank,
         lending_account: &'a mut LendingAccount,
+        extended_balances: &'a mut [Balance],
     ) -> MarginfiResult<BankAccountWrapper<'a>> {
         let balance_index = lending_account
             .balances
             .iter()
+            .chain(extended_balances.iter())
             .position(|balance| balance.active && balance.bank_pk.eq(bank_pk));
 
+        let mut balances = lending_account
+            .balances
+            .iter_mut()
+            .chain(extended_balances.iter_mut());
+
         match balance_index {
             Some(balance_index) => {
-                let balance = lending_account
-                    .balances
-                    .get_mut(balance_index)
+                let balance = balances
+                    .nth(balance_index)
                     .ok_or_else(|| error!(MarginfiError::BankAccountNotFound))?;
 
                 Ok(Self { balance, bank })
             }
             None => {
-                let empty_index = lending_account
-                    .get_first_empty_balance()
+                let balance = balances
+                    .find(|balance| !balance.active)
                     .ok_or_else(|| error!(MarginfiError::LendingAccountBalanceSlotsFull))?;
 
-                lending_account.balances[empty_index] = Balance {
+                *balance = Balance {
                     active: true,
                     bank_pk: *bank_pk,
                     _pad0: [0; 7],
@@ -887,13 +1512,11 @@ impl<'a> BankAccountWrapper<'a> {
                     liability_shares: I80F48::ZERO.into(),
                     emissions_outstanding: I80F48::ZERO.into(),
                     last_update: Clock::get()?.unix_timestamp as u64,
-                    _padding: [0; 1],
+                    fixed_term_borrow_active: false,
+                    _padding: [0; 7],
                 };
 
-                Ok(Self {
-                    balance: lending_account.balances.get_mut(empty_index).unwrap(),
-                    bank,
-                })
+                Ok(Self { balance, bank })
             }
         }
     }
@@ -901,41 +1524,45 @@ impl<'a> BankAccountWrapper<'a> {
     // ------------ Borrow / Lend primitives
 
     /// Deposit an asset, will repay any outstanding liabilities.
-    pub fn deposit(&mut self, amount: I80F48) -> MarginfiResult {
-        self.increase_balance_internal(amount, BalanceIncreaseType::Any)
+    pub fn deposit(&mut self, amount: I80F48, group: &MarginfiGroup) -> MarginfiResult {
+        self.increase_balance_internal(amount, BalanceIncreaseType::Any, group)
     }
 
     /// Repay a liability, will error if there is not enough liability - depositing is not allowed.
-    pub fn repay(&mut self, amount: I80F48) -> MarginfiResult {
-        self.increase_balance_internal(amount, BalanceIncreaseType::RepayOnly)
+    pub fn repay(&mut self, amount: I80F48, group: &MarginfiGroup) -> MarginfiResult {
+        self.increase_balance_internal(amount, BalanceIncreaseType::RepayOnly, group)
     }
 
     /// Withdraw an asset, will error if there is not enough asset - borrowing is not allowed.
-    pub fn withdraw(&mut self, amount: I80F48) -> MarginfiResult {
-        self.decrease_balance_internal(amount, BalanceDecreaseType::WithdrawOnly)
+    pub fn withdraw(&mut self, amount: I80F48, group: &MarginfiGroup) -> MarginfiResult {
+        self.decrease_balance_internal(amount, BalanceDecreaseType::WithdrawOnly, group)
     }
 
     /// Incur a borrow, will withdraw any existing assets.
-    pub fn borrow(&mut self, amount: I80F48) -> MarginfiResult {
-        self.decrease_balance_internal(amount, BalanceDecreaseType::Any)
+    pub fn borrow(&mut self, amount: I80F48, group: &MarginfiGroup) -> MarginfiResult {
+        self.decrease_balance_internal(amount, BalanceDecreaseType::Any, group)
     }
 
     // ------------ Hybrid operations for seamless repay + deposit / withdraw + borrow
 
     /// Repay liability and deposit/increase asset depending on
     /// the specified deposit amount and the existing balance.
-    pub fn increase_balance(&mut self, amount: I80F48) -> MarginfiResult {
-        self.increase_balance_internal(amount, BalanceIncreaseType::Any)
+    pub fn increase_balance(&mut self, amount: I80F48, group: &MarginfiGroup) -> MarginfiResult {
+        self.increase_balance_internal(amount, BalanceIncreaseType::Any, group)
     }
 
-    pub fn increase_balance_in_liquidation(&mut self, amount: I80F48) -> MarginfiResult {
-        self.increase_balance_internal(amount, BalanceIncreaseType::BypassDepositLimit)
+    pub fn increase_balance_in_liquidation(
+        &mut self,
+        amount: I80F48,
+        group: &MarginfiGroup,
+    ) -> MarginfiResult {
+        self.increase_balance_internal(amount, BalanceIncreaseType::BypassDepositLimit, group)
     }
 
     /// Withdraw asset and create/increase liability depending on
     /// the specified deposit amount and the existing balance.
-    pub fn decrease_balance(&mut self, amount: I80F48) -> MarginfiResult {
-        self.decrease_balance_internal(amount, BalanceDecreaseType::Any)
+    pub fn decrease_balance(&mut self, amount: I80F48, group: &MarginfiGroup) -> MarginfiResult {
+        self.decrease_balance_internal(amount, BalanceDecreaseType::Any, group)
     }
 
     /// Withdraw asset and create/increase liability depending on
@@ -943,18 +1570,22 @@ impl<'a> BankAccountWrapper<'a> {
     ///
     /// This function will also bypass borrow limits
     /// so liquidations can happen in banks with maxed out borrows.
-    pub fn decrease_balance_in_liquidation(&mut self, amount: I80F48) -> MarginfiResult {
-        self.decrease_balance_internal(amount, BalanceDecreaseType::BypassBorrowLimit)
+    pub fn decrease_balance_in_liquidation(
+        &mut self,
+        amount: I80F48,
+        group: &MarginfiGroup,
+    ) -> MarginfiResult {
+        self.decrease_balance_internal(amount, BalanceDecreaseType::BypassBorrowLimit, group)
     }
 
     /// Withdraw existing asset in full - will error if there is no asset.
-    pub fn withdraw_all(&mut self) -> MarginfiResult<u64> {
+    pub fn withdraw_all(&mut self, group: &MarginfiGroup) -> MarginfiResult<u64> {
         self.claim_emissions(Clock::get()?.unix_timestamp as u64)?;
 
         let balance = &mut self.balance;
         let bank = &mut self.bank;
 
-        bank.assert_operational_mode(None)?;
+        bank.assert_operational_mode(group, None)?;
 
         let total_asset_shares: I80F48 = balance.asset_shares.into();
         let current_asset_amount = bank.get_asset_amount(total_asset_shares)?;
@@ -997,13 +1628,13 @@ impl<'a> BankAccountWrapper<'a> {
     }
 
     /// Repay existing liability in full - will error if there is no liability.
-    pub fn repay_all(&mut self) -> MarginfiResult<u64> {
+    pub fn repay_all(&mut self, group: &MarginfiGroup) -> MarginfiResult<u64> {
         self.claim_emissions(Clock::get()?.unix_timestamp as u64)?;
 
         let balance = &mut self.balance;
         let bank = &mut self.bank;
 
-        bank.assert_operational_mode(None)?;
+        bank.assert_operational_mode(group, None)?;
 
         let total_liability_shares: I80F48 = balance.liability_shares.into();
         let current_liability_amount = bank.get_liability_amount(total_liability_shares)?;
@@ -1042,6 +1673,20 @@ impl<'a> BankAccountWrapper<'a> {
             .ok_or_else(math_error!())?)
     }
 
+    /// Record the amount owed by a settled fixed-term borrow as a variable liability. Borrow limits
+    /// and the operational state don't apply, the funds were already lent out.
+    pub fn convert_fixed_term_borrow(&mut self, amount_owed: I80F48) -> MarginfiResult {
+        self.claim_emissions(Clock::get()?.unix_timestamp as u64)?;
+
+        self.balance.fixed_term_borrow_active = false;
+
+        let liability_shares = self.bank.get_liability_shares(amount_owed)?;
+        self.balance.change_liability_shares(liability_shares)?;
+        self.bank.change_liability_shares(liability_shares, true)?;
+
+        Ok(())
+    }
+
     pub fn close_balance(&mut self) -> MarginfiResult<()> {
         self.claim_emissions(Clock::get()?.unix_timestamp as u64)?;
 
@@ -1075,6 +1720,7 @@ impl<'a> BankAccountWrapper<'a> {
         &mut self,
         balance_delta: I80F48,
         operation_type: BalanceIncreaseType,
+        group: &MarginfiGroup,
     ) -> MarginfiResult {
         debug!(
             "Balance increase: {} (type: {:?})",
@@ -1115,10 +1761,17 @@ impl<'a> BankAccountWrapper<'a> {
             BalanceIncreaseType::Any | BalanceIncreaseType::BypassDepositLimit => {}
         }
 
+        check!(
+            !balance.fixed_term_borrow_active
+                || asset_amount_increase.is_zero_with_tolerance(ZERO_AMOUNT_THRESHOLD),
+            MarginfiError::IllegalBalanceState,
+            "Can't deposit into a balance with an outstanding fixed-term borrow"
+        );
+
         {
             let is_asset_amount_increasing =
                 asset_amount_increase.is_positive_with_tolerance(ZERO_AMOUNT_THRESHOLD);
-            bank.assert_operational_mode(Some(is_asset_amount_increasing))?;
+            bank.assert_operational_mode(group, Some(is_asset_amount_increasing))?;
         }
 
         let asset_shares_increase = bank.get_asset_shares(asset_amount_increase)?;
@@ -1140,6 +1793,7 @@ impl<'a> BankAccountWrapper<'a> {
         &mut self,
         balance_delta: I80F48,
         operation_type: BalanceDecreaseType,
+        group: &MarginfiGroup,
     ) -> MarginfiResult {
         debug!(
             "Balance decrease: {} of (type: {:?})",
@@ -1183,7 +1837,7 @@ impl<'a> BankAccountWrapper<'a> {
         {
             let is_liability_amount_increasing =
                 liability_amount_increase.is_positive_with_tolerance(ZERO_AMOUNT_THRESHOLD);
-            bank.assert_operational_mode(Some(is_liability_amount_increasing))?;
+            bank.assert_operational_mode(group, Some(is_liability_amount_increasing))?;
         }
 
         let asset_shares_decrease = bank.get_asset_shares(asset_amount_decrease)?;
@@ -1378,6 +2032,7 @@ fn calc_emissions(
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::state::marginfi_group::BankConfig;
     use fixed_macro::types::I80F48;
 
     #[test]
@@ -1398,6 +2053,120 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_calc_max_liquidation_asset_amount() {
+        let mut asset_bank = Bank {
+            mint_decimals: 9,
+            config: BankConfig {
+                asset_weight_maint: I80F48!(0.5).into(),
+                risk_tier: RiskTier::Collateral,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut liab_bank = Bank {
+            mint_decimals: 6,
+            config: BankConfig {
+                liability_weight_maint: I80F48!(1).into(),
+                liquidation_close_factor_bps: 5_000,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let tolerance = I80F48::ONE;
+
+        // 100 SOL ($1000, $500 weighted) against 999 USDC of debt, the close factor binds:
+        // 999 * 0.5 / (1 - 0.05) / $10 = 52.578947368 SOL
+        let max_amount = calc_max_liquidation_asset_amount(
+            &asset_bank,
+            &liab_bank,
+            I80F48!(10),
+            I80F48!(1),
+            I80F48!(100_000_000_000),
+            I80F48!(999_000_000),
+            I80F48!(-499),
+            I80F48!(999),
+        )
+        .unwrap();
+        assert!((max_amount - I80F48!(52_578_947_368)).abs() <= tolerance);
+
+        // Past the close factor shortfall the whole liability can be repaid, the deposit binds
+        liab_bank.config.liquidation_close_factor_shortfall_bps = 2_000;
+        let max_amount = calc_max_liquidation_asset_amount(
+            &asset_bank,
+            &liab_bank,
+            I80F48!(10),
+            I80F48!(1),
+            I80F48!(100_000_000_000),
+            I80F48!(999_000_000),
+            I80F48!(-499),
+            I80F48!(999),
+        )
+        .unwrap();
+        assert_eq!(max_amount, I80F48!(100_000_000_000));
+
+        // 200 SOL ($2000, $900 weighted) against 999 USDC of debt, the health check binds:
+        // $99 / (1 - 0.05 - 0.45) / $10 = 19.8 SOL
+        asset_bank.config.asset_weight_maint = I80F48!(0.45).into();
+        liab_bank.config.liquidation_close_factor_bps = 0;
+        let max_amount = calc_max_liquidation_asset_amount(
+            &asset_bank,
+            &liab_bank,
+            I80F48!(10),
+            I80F48!(1),
+            I80F48!(200_000_000_000),
+            I80F48!(999_000_000),
+            I80F48!(-99),
+            I80F48!(999),
+        )
+        .unwrap();
+        assert!((max_amount - I80F48!(19_800_000_000)).abs() <= tolerance);
+
+        // Healthy accounts can't be liquidated
+        let max_amount = calc_max_liquidation_asset_amount(
+            &asset_bank,
+            &liab_bank,
+            I80F48!(10),
+            I80F48!(1),
+            I80F48!(200_000_000_000),
+            I80F48!(999_000_000),
+            I80F48!(1),
+            I80F48!(999),
+        )
+        .unwrap();
+        assert_eq!(max_amount, I80F48::ZERO);
+
+        // Nothing to seize from an account exactly at the maintenance requirement, even with the
+        // close factor and a health gain that would otherwise allow it
+        liab_bank.config.liquidation_close_factor_bps = 5_000;
+        let max_amount = calc_max_liquidation_asset_amount(
+            &asset_bank,
+            &liab_bank,
+            I80F48!(10),
+            I80F48!(1),
+            I80F48!(200_000_000_000),
+            I80F48!(999_000_000),
+            I80F48::ZERO,
+            I80F48!(999),
+        )
+        .unwrap();
+        assert_eq!(max_amount, I80F48::ZERO);
+
+        // Just below it, the health check binds: $0.5 / 0.5 / $10 = 0.1 SOL
+        let max_amount = calc_max_liquidation_asset_amount(
+            &asset_bank,
+            &liab_bank,
+            I80F48!(10),
+            I80F48!(1),
+            I80F48!(200_000_000_000),
+            I80F48!(999_000_000),
+            I80F48!(-0.5),
+            I80F48!(999),
+        )
+        .unwrap();
+        assert!((max_amount - I80F48!(100_000_000)).abs() <= tolerance);
+    }
+
     #[test]
     fn test_account_authority_transfer() {
         let group: [u8; 32] = [0; 32];
@@ -1417,20 +2186,75 @@ mod test {
                     liability_shares: WrappedI80F48::default(),
                     emissions_outstanding: WrappedI80F48::default(),
                     last_update: 0,
-                    _padding: [0_u64],
+                    fixed_term_borrow_active: false,
+                    _padding: [0; 7],
                 }; 16],
                 _padding: [0; 8],
             },
             account_flags: TRANSFER_AUTHORITY_ALLOWED_FLAG,
-            _padding: [0; 63],
+            fixed_term_borrows: [FixedTermBorrow::default(); MAX_FIXED_TERM_BORROWS],
+            max_liability_value: 0,
+            allowed_banks: [Pubkey::default(); MAX_ACCOUNT_ALLOWED_BANKS],
+            delegate: Pubkey::default(),
+            delegate_permissions: 0,
+            _padding: [0; 21],
         };
 
         assert!(acc.get_flag(TRANSFER_AUTHORITY_ALLOWED_FLAG));
 
+        let authority: Pubkey = authority.into();
+        let delegate: Pubkey = [4; 32].into();
+        let (repay, withdraw) = (DELEGATE_REPAY_PERMISSION, DELEGATE_WITHDRAW_PERMISSION);
+
+        acc.set_delegate(delegate, repay | withdraw).unwrap();
+        assert!(acc.set_delegate(delegate, 1 << 63).is_err());
+
+        assert!(acc.can_sign(&authority, DELEGATE_DEPOSIT_PERMISSION));
+        assert!(acc.can_sign(&delegate, repay));
+        assert!(!acc.can_sign(&delegate, DELEGATE_DEPOSIT_PERMISSION));
+        assert!(!acc.can_sign(&new_authority.into(), repay));
+
+        // The delegate only withdraws to the authority's token accounts
+        assert!(acc.can_sign_outflow(&delegate, withdraw, &authority));
+        assert!(!acc.can_sign_outflow(&delegate, withdraw, &delegate));
+        assert!(acc.can_sign_outflow(&authority, withdraw, &delegate));
+
         match acc.set_new_account_authority_checked(new_authority.into()) {
             Ok(_) => (),
             Err(_) => panic!("transerring account authority failed"),
         }
+
+        // The delegate doesn't carry over to the new authority
+        assert!(!acc.can_sign(&delegate, repay));
+    }
+
+    #[test]
+    fn test_fixed_term_borrow_amount_owed() {
+        let borrow = FixedTermBorrow {
+            bank_pk: Pubkey::new_unique(),
+            principal: I80F48!(1_000_000).into(),
+            rate: I80F48!(0.1).into(),
+            start_time: 0,
+            maturity: 31_536_000,
+        };
+
+        assert_eq!(borrow.get_amount_owed(0).unwrap(), I80F48!(1_000_000));
+        assert_eq!(borrow.get_amount_owed(-100).unwrap(), I80F48!(1_000_000));
+
+        let half = borrow.get_amount_owed(15_768_000).unwrap();
+        assert!((half - I80F48!(1_050_000)).abs() < I80F48!(0.001));
+
+        let at_maturity = borrow.get_amount_owed(31_536_000).unwrap();
+        assert!((at_maturity - I80F48!(1_100_000)).abs() < I80F48!(0.001));
+
+        // Interest keeps accruing at the locked rate until the borrow is settled
+        let past_maturity = borrow.get_amount_owed(63_072_000).unwrap();
+        assert!((past_maturity - I80F48!(1_200_000)).abs() < I80F48!(0.001));
+
+        assert!(!borrow.is_matured(31_535_999));
+        assert!(borrow.is_matured(31_536_000));
+        assert!(!borrow.is_empty());
+        assert!(FixedTermBorrow::default().is_empty());
     }
 
     #[test]
@@ -8,11 +8,17 @@ use crate::events::{GroupEventHeader, LendingPoolBankAccrueInterestEvent};
 use crate::{
     assert_struct_align, assert_struct_size, check,
     constants::{
-        EMISSION_FLAGS, FEE_VAULT_AUTHORITY_SEED, FEE_VAULT_SEED, GROUP_FLAGS,
-        INSURANCE_VAULT_AUTHORITY_SEED, INSURANCE_VAULT_SEED, LIQUIDITY_VAULT_AUTHORITY_SEED,
-        LIQUIDITY_VAULT_SEED, MAX_ORACLE_KEYS, MAX_PYTH_ORACLE_AGE, MAX_SWB_ORACLE_AGE,
-        PERMISSIONLESS_BAD_DEBT_SETTLEMENT_FLAG, PYTH_ID, SECONDS_PER_YEAR,
-        TOTAL_ASSET_VALUE_INIT_LIMIT_INACTIVE,
+        CONF_INTERVAL_MODE_CAP, CONF_INTERVAL_MODE_REJECT, CURVE_POINTS, EMISSION_FLAGS,
+        FEE_VAULT_AUTHORITY_SEED, FEE_VAULT_SEED, GROUP_FLAGS, GROUP_PAUSE_STATE_ALL,
+        GROUP_PAUSE_STATE_NONE, INSURANCE_VAULT_AUTHORITY_SEED, INSURANCE_VAULT_SEED,
+        INTEREST_CURVE_ADAPTIVE, INTEREST_CURVE_LEGACY, INTEREST_CURVE_MULTI_POINT,
+        LIQUIDATION_FEE_RAMP_SHORTFALL, LIQUIDATION_FEE_RAMP_SHORTFALL_SET_FLAG,
+        LIQUIDATION_INSURANCE_FEE, LIQUIDATION_INSURANCE_FEE_SET_FLAG, LIQUIDATION_LIQUIDATOR_FEE,
+        LIQUIDATION_LIQUIDATOR_FEE_SET_FLAG, LIQUIDATION_MAX_LIQUIDATOR_FEE_SET_FLAG,
+        LIQUIDITY_VAULT_AUTHORITY_SEED, LIQUIDITY_VAULT_SEED, MAX_CURVE_RATE, MAX_ORACLE_KEYS,
+        MAX_PYTH_ORACLE_AGE, MAX_SWB_ORACLE_AGE, PERMISSIONLESS_BAD_DEBT_SETTLEMENT_FLAG, PYTH_ID,
+        SECONDS_PER_DAY, SECONDS_PER_YEAR, TOTAL_ASSET_VALUE_INIT_LIMIT_INACTIVE,
+        USD_LIMIT_INACTIVE,
     },
     debug, math_error,
     prelude::MarginfiError,
@@ -52,7 +58,30 @@ pub struct MarginfiGroup {
     pub group_flags: u64,
     /// Caches information from the global `FeeState` so the FeeState can be omitted on certain ixes
     pub fee_state_cache: FeeStateCache,
-    pub _padding_0: [[u64; 2]; 27],
+    /// Can pause and unpause the group (see `pause_state`), with no other admin power.
+    pub guardian: Pubkey,
+    /// One of the `GROUP_PAUSE_STATE_*` constants, set by the guardian or the admin.
+    pub pause_state: u8,
+    pub _pad0: [u8; 15],
+    /// Adds and configures banks. The admin holds the role while it's unset, as for the roles
+    /// below.
+    pub risk_admin: Pubkey,
+    /// Sets up and updates the banks' emissions.
+    pub emissions_admin: Pubkey,
+    /// Withdraws the banks' fees and insurance funds.
+    pub fee_collector: Pubkey,
+    /// Handles bankruptcies in banks without permissionless bad debt settlement.
+    pub bankruptcy_operator: Pubkey,
+    /// Seconds a bank config queued by the risk admin waits before it can be executed. When set,
+    /// `lending_pool_configure_bank` only accepts configs lowering the asset weights. Increases
+    /// apply immediately, decreases wait for the current delay (see `pending_config_delay`).
+    pub config_delay: u64,
+    pub _pad1: [u8; 8],
+    /// Lower `config_delay` requested by the admin, in effect from `pending_config_delay_eta`.
+    pub pending_config_delay: u64,
+    /// When `pending_config_delay` takes effect, 0 if no decrease is pending.
+    pub pending_config_delay_eta: i64,
+    pub _padding_0: [[u64; 2]; 14],
     pub _padding_1: [[u64; 2]; 32],
     pub _padding_3: u64,
 }
@@ -77,8 +106,100 @@ impl MarginfiGroup {
     /// Configure the group parameters.
     /// This function validates config values so the group remains in a valid state.
     /// Any modification of group config should happen through this function.
-    pub fn configure(&mut self, config: &GroupConfig) -> MarginfiResult {
+    pub fn configure(&mut self, config: &GroupConfig, current_timestamp: i64) -> MarginfiResult {
         set_if_some!(self.admin, config.admin);
+        set_if_some!(self.guardian, config.guardian);
+        set_if_some!(self.risk_admin, config.risk_admin);
+        set_if_some!(self.emissions_admin, config.emissions_admin);
+        set_if_some!(self.fee_collector, config.fee_collector);
+        set_if_some!(self.bankruptcy_operator, config.bankruptcy_operator);
+
+        if let Some(config_delay) = config.config_delay {
+            self.set_config_delay(config_delay, current_timestamp)?;
+        }
+
+        Ok(())
+    }
+
+    /// Raises the config delay immediately. A lower delay is queued and only takes effect once
+    /// the current delay has passed, so the timelock can't be lifted in the same breath as a
+    /// bank config is applied.
+    fn set_config_delay(&mut self, config_delay: u64, current_timestamp: i64) -> MarginfiResult {
+        self.config_delay = self.get_config_delay(current_timestamp);
+
+        if config_delay >= self.config_delay {
+            self.config_delay = config_delay;
+            self.pending_config_delay = 0;
+            self.pending_config_delay_eta = 0;
+        } else {
+            let eta = current_timestamp
+                .checked_add(self.config_delay as i64)
+                .ok_or_else(math_error!())?;
+
+            msg!("Config delay of {} queued until {}", config_delay, eta);
+
+            self.pending_config_delay = config_delay;
+            self.pending_config_delay_eta = eta;
+        }
+
+        Ok(())
+    }
+
+    /// The config delay in effect at `current_timestamp`, taking a pending decrease into account.
+    pub fn get_config_delay(&self, current_timestamp: i64) -> u64 {
+        if self.pending_config_delay_eta != 0 && current_timestamp >= self.pending_config_delay_eta
+        {
+            self.pending_config_delay
+        } else {
+            self.config_delay
+        }
+    }
+
+    /// Key holding `role`: the role's key if set, the admin otherwise.
+    fn get_role_holder(&self, role: Pubkey) -> Pubkey {
+        if role == Pubkey::default() {
+            self.admin
+        } else {
+            role
+        }
+    }
+
+    pub fn get_risk_admin(&self) -> Pubkey {
+        self.get_role_holder(self.risk_admin)
+    }
+
+    pub fn get_emissions_admin(&self) -> Pubkey {
+        self.get_role_holder(self.emissions_admin)
+    }
+
+    pub fn get_fee_collector(&self) -> Pubkey {
+        self.get_role_holder(self.fee_collector)
+    }
+
+    pub fn get_bankruptcy_operator(&self) -> Pubkey {
+        self.get_role_holder(self.bankruptcy_operator)
+    }
+
+    /// Sets the pause state, to one of the `GROUP_PAUSE_STATE_*` constants.
+    pub fn set_pause_state(&mut self, pause_state: u8) -> MarginfiResult {
+        check!(
+            pause_state <= GROUP_PAUSE_STATE_ALL,
+            MarginfiError::InvalidConfig
+        );
+        self.pause_state = pause_state;
+
+        Ok(())
+    }
+
+    /// Errors if every user instruction of the group is paused (`GROUP_PAUSE_STATE_ALL`), for the
+    /// user instructions that don't move bank balances.
+    ///
+    /// Instructions moving bank balances are paused by `Bank::assert_operational_mode`.
+    pub fn assert_not_paused(&self) -> MarginfiResult {
+        check!(
+            self.pause_state != GROUP_PAUSE_STATE_ALL,
+            MarginfiError::GroupPaused
+        );
 
         Ok(())
     }
@@ -124,6 +245,13 @@ impl MarginfiGroup {
 #[derive(AnchorSerialize, AnchorDeserialize, Default, Debug, Clone)]
 pub struct GroupConfig {
     pub admin: Option<Pubkey>,
+    pub guardian: Option<Pubkey>,
+    /// `Pubkey::default()` hands the role back to the admin, as for the roles below.
+    pub risk_admin: Option<Pubkey>,
+    pub emissions_admin: Option<Pubkey>,
+    pub fee_collector: Option<Pubkey>,
+    pub bankruptcy_operator: Option<Pubkey>,
+    pub config_delay: Option<u64>,
 }
 
 /// Load and validate a pyth price feed account.
@@ -165,8 +293,7 @@ impl From<InterestRateConfigCompact> for InterestRateConfig {
             protocol_fixed_fee_apr: ir_config.protocol_fixed_fee_apr,
             protocol_ir_fee: ir_config.protocol_ir_fee,
             protocol_origination_fee: ir_config.protocol_origination_fee,
-            _padding0: [0; 16],
-            _padding1: [[0; 32]; 3],
+            ..Default::default()
         }
     }
 }
@@ -211,8 +338,85 @@ pub struct InterestRateConfig {
     pub protocol_ir_fee: WrappedI80F48,
     pub protocol_origination_fee: WrappedI80F48,
 
-    pub _padding0: [u8; 16],
-    pub _padding1: [[u8; 32]; 3],
+    /// `INTEREST_CURVE_LEGACY` uses the curve params above, `INTEREST_CURVE_MULTI_POINT` uses
+    /// `zero_util_rate`, `points` and `hundred_util_rate`, `INTEREST_CURVE_ADAPTIVE` uses the curve
+    /// params above with a plateau rate that moves within the `adaptive_*` bounds
+    pub curve_type: u8,
+    pub _pad0: [u8; 3], // 1x u8 + 3 = 4
+    /// Multi-point curve: rate at 0% utilization, as a fraction of `u32::MAX` = `MAX_CURVE_RATE`
+    pub zero_util_rate: u32,
+    /// Multi-point curve: rate at 100% utilization, as a fraction of `u32::MAX` = `MAX_CURVE_RATE`
+    pub hundred_util_rate: u32,
+    /// Multi-point curve: breakpoints sorted by utilization, unused points are zeroed and must come last
+    pub points: [RatePoint; CURVE_POINTS],
+
+    /// Adaptive curve: lowest plateau rate, as a fraction of `u32::MAX` = `MAX_CURVE_RATE`
+    pub adaptive_min_plateau_rate: u32,
+    /// Adaptive curve: highest plateau rate, as a fraction of `u32::MAX` = `MAX_CURVE_RATE`
+    pub adaptive_max_plateau_rate: u32,
+    /// Adaptive curve: max change of the plateau rate per day, reached while utilization sits at
+    /// 0% or 100%, as a fraction of `u32::MAX` = `MAX_CURVE_RATE`
+    pub adaptive_speed: u32,
+
+    /// Cap on `lending_rate_apr`, as a fraction of `u32::MAX` = `MAX_CURVE_RATE`, 0 = no cap
+    pub max_lending_rate: u32,
+    /// Cap on `borrowing_rate_apr` (fees included), as a fraction of `u32::MAX` = `MAX_CURVE_RATE`,
+    /// 0 = no cap
+    pub max_borrowing_rate: u32,
+
+    pub _padding0: [u8; 8],
+    pub _padding1: [u8; 32],
+}
+
+/// A (utilization, rate) breakpoint of a multi-point interest rate curve.
+#[zero_copy]
+#[repr(C)]
+#[cfg_attr(
+    any(feature = "test", feature = "client"),
+    derive(PartialEq, Eq, TypeLayout)
+)]
+#[derive(Default, Debug, AnchorDeserialize, AnchorSerialize)]
+pub struct RatePoint {
+    /// Utilization, as a fraction of `u32::MAX` = 100%
+    pub util: u32,
+    /// Rate, as a fraction of `u32::MAX` = `MAX_CURVE_RATE`
+    pub rate: u32,
+}
+
+impl RatePoint {
+    pub fn new(util: I80F48, rate: I80F48) -> Self {
+        Self {
+            util: utilization_to_u32(util),
+            rate: rate_to_u32(rate),
+        }
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.util == 0 && self.rate == 0
+    }
+}
+
+/// Converts a utilization ratio (0-1) to its `u32` representation, saturating outside that range.
+pub fn utilization_to_u32(ratio: I80F48) -> u32 {
+    ratio
+        .saturating_mul(I80F48::from_num(u32::MAX))
+        .saturating_to_num::<u32>()
+}
+
+/// Converts an APR (0-`MAX_CURVE_RATE`) to its `u32` representation, saturating outside that range.
+pub fn rate_to_u32(rate: I80F48) -> u32 {
+    utilization_to_u32(rate / MAX_CURVE_RATE)
+}
+
+#[inline]
+pub fn u32_to_utilization(value: u32) -> I80F48 {
+    I80F48::from_num(value) / I80F48::from_num(u32::MAX)
+}
+
+#[inline]
+pub fn u32_to_rate(value: u32) -> I80F48 {
+    u32_to_utilization(value) * MAX_CURVE_RATE
 }
 
 impl InterestRateConfig {
@@ -230,6 +434,12 @@ impl InterestRateConfig {
             insurance_rate_fee: self.insurance_ir_fee.into(),
             protocol_fixed_fee: self.protocol_fixed_fee_apr.into(),
             protocol_rate_fee: self.protocol_ir_fee.into(),
+            curve_type: self.curve_type,
+            zero_util_rate: self.zero_util_rate,
+            hundred_util_rate: self.hundred_util_rate,
+            points: self.points,
+            max_lending_rate: self.max_lending_rate,
+            max_borrowing_rate: self.max_borrowing_rate,
             add_program_fees: group_bank_config.program_fees,
             program_fee_fixed: group.fee_state_cache.program_fee_fixed.into(),
             program_fee_rate: group.fee_state_cache.program_fee_rate.into(),
@@ -237,17 +447,80 @@ impl InterestRateConfig {
     }
 
     pub fn validate(&self) -> MarginfiResult {
-        let optimal_ur: I80F48 = self.optimal_utilization_rate.into();
+        match self.curve_type {
+            INTEREST_CURVE_LEGACY | INTEREST_CURVE_ADAPTIVE => {
+                let optimal_ur: I80F48 = self.optimal_utilization_rate.into();
+                let plateau_ir: I80F48 = self.plateau_interest_rate.into();
+                let max_ir: I80F48 = self.max_interest_rate.into();
+
+                check!(
+                    optimal_ur > I80F48::ZERO && optimal_ur < I80F48::ONE,
+                    MarginfiError::InvalidConfig
+                );
+                check!(plateau_ir > I80F48::ZERO, MarginfiError::InvalidConfig);
+                check!(max_ir > I80F48::ZERO, MarginfiError::InvalidConfig);
+                check!(plateau_ir < max_ir, MarginfiError::InvalidConfig);
+
+                if self.curve_type == INTEREST_CURVE_ADAPTIVE {
+                    self.validate_adaptive_curve()?;
+                }
+            }
+            INTEREST_CURVE_MULTI_POINT => self.validate_multi_point_curve()?,
+            _ => return err!(MarginfiError::InvalidConfig),
+        }
+
+        Ok(())
+    }
+
+    /// The plateau rate bounds must contain the initial plateau rate and stay below the max rate,
+    /// and the plateau rate must be allowed to move.
+    fn validate_adaptive_curve(&self) -> MarginfiResult {
+        let min_plateau_ir = u32_to_rate(self.adaptive_min_plateau_rate);
+        let max_plateau_ir = u32_to_rate(self.adaptive_max_plateau_rate);
         let plateau_ir: I80F48 = self.plateau_interest_rate.into();
         let max_ir: I80F48 = self.max_interest_rate.into();
 
+        check!(min_plateau_ir > I80F48::ZERO, MarginfiError::InvalidConfig);
+        check!(
+            min_plateau_ir <= plateau_ir && plateau_ir <= max_plateau_ir,
+            MarginfiError::InvalidConfig
+        );
+        check!(max_plateau_ir < max_ir, MarginfiError::InvalidConfig);
+        check!(self.adaptive_speed > 0, MarginfiError::InvalidConfig);
+
+        Ok(())
+    }
+
+    /// Breakpoints must be strictly increasing in utilization (and below 100%), rates must never
+    /// decrease from `zero_util_rate` to `hundred_util_rate`, and unused points must come last.
+    fn validate_multi_point_curve(&self) -> MarginfiResult {
+        check!(self.hundred_util_rate > 0, MarginfiError::InvalidConfig);
+
+        let mut prev_util = 0;
+        let mut prev_rate = self.zero_util_rate;
+        let mut exhausted = false;
+
+        for point in self.points.iter() {
+            if point.is_empty() {
+                exhausted = true;
+                continue;
+            }
+
+            check!(!exhausted, MarginfiError::InvalidConfig);
+            check!(
+                point.util > prev_util && point.util < u32::MAX,
+                MarginfiError::InvalidConfig
+            );
+            check!(point.rate >= prev_rate, MarginfiError::InvalidConfig);
+
+            prev_util = point.util;
+            prev_rate = point.rate;
+        }
+
         check!(
-            optimal_ur > I80F48::ZERO && optimal_ur < I80F48::ONE,
+            self.hundred_util_rate >= prev_rate,
             MarginfiError::InvalidConfig
         );
-        check!(plateau_ir > I80F48::ZERO, MarginfiError::InvalidConfig);
-        check!(max_ir > I80F48::ZERO, MarginfiError::InvalidConfig);
-        check!(plateau_ir < max_ir, MarginfiError::InvalidConfig);
 
         Ok(())
     }
@@ -269,6 +542,21 @@ impl InterestRateConfig {
             ir_config.protocol_fixed_fee_apr
         );
         set_if_some!(self.protocol_ir_fee, ir_config.protocol_ir_fee);
+        set_if_some!(self.curve_type, ir_config.curve_type);
+        set_if_some!(self.zero_util_rate, ir_config.zero_util_rate);
+        set_if_some!(self.hundred_util_rate, ir_config.hundred_util_rate);
+        set_if_some!(self.points, ir_config.points);
+        set_if_some!(
+            self.adaptive_min_plateau_rate,
+            ir_config.adaptive_min_plateau_rate
+        );
+        set_if_some!(
+            self.adaptive_max_plateau_rate,
+            ir_config.adaptive_max_plateau_rate
+        );
+        set_if_some!(self.adaptive_speed, ir_config.adaptive_speed);
+        set_if_some!(self.max_lending_rate, ir_config.max_lending_rate);
+        set_if_some!(self.max_borrowing_rate, ir_config.max_borrowing_rate);
     }
 }
 
@@ -287,6 +575,14 @@ pub struct InterestRateCalc {
     /// AKA group rate fee
     protocol_rate_fee: I80F48,
 
+    curve_type: u8,
+    zero_util_rate: u32,
+    hundred_util_rate: u32,
+    points: [RatePoint; CURVE_POINTS],
+
+    max_lending_rate: u32,
+    max_borrowing_rate: u32,
+
     program_fee_fixed: I80F48,
     program_fee_rate: I80F48,
 
@@ -326,6 +622,15 @@ impl InterestRateCalc {
         let insurance_fee_apr = calc_fee_rate(base_rate, insurance_fee_rate, insurance_fee_fixed)?;
         let protocol_fee_apr = calc_fee_rate(base_rate, protocol_fee_rate, protocol_fee_fixed)?;
 
+        // Capped rates scale everything down together, so the fee split between insurance, group
+        // and protocol keeps its proportions and borrowers still pay for depositors and fees.
+        let cap_scale = self.rate_cap_scale(lending_rate_apr, borrowing_rate_apr)?;
+        let lending_rate_apr = lending_rate_apr.checked_mul(cap_scale)?;
+        let borrowing_rate_apr = borrowing_rate_apr.checked_mul(cap_scale)?;
+        let group_fee_apr = group_fee_apr.checked_mul(cap_scale)?;
+        let insurance_fee_apr = insurance_fee_apr.checked_mul(cap_scale)?;
+        let protocol_fee_apr = protocol_fee_apr.checked_mul(cap_scale)?;
+
         assert!(lending_rate_apr >= I80F48::ZERO);
         assert!(borrowing_rate_apr >= I80F48::ZERO);
         assert!(group_fee_apr >= I80F48::ZERO);
@@ -342,13 +647,87 @@ impl InterestRateCalc {
         })
     }
 
+    /// Factor (0-1) that brings both rates under their caps, 1 if no cap is hit.
+    fn rate_cap_scale(
+        &self,
+        lending_rate_apr: I80F48,
+        borrowing_rate_apr: I80F48,
+    ) -> Option<I80F48> {
+        let mut scale = I80F48::ONE;
+
+        for (rate, max_rate) in [
+            (lending_rate_apr, self.max_lending_rate),
+            (borrowing_rate_apr, self.max_borrowing_rate),
+        ] {
+            let max_rate = u32_to_rate(max_rate);
+            if max_rate > I80F48::ZERO && rate > max_rate {
+                scale = scale.min(max_rate.checked_div(rate)?);
+            }
+        }
+
+        Some(scale)
+    }
+
+    /// Overrides the plateau rate, used by adaptive curves (see `Bank::adaptive_plateau_rate`).
+    pub fn set_plateau_interest_rate(&mut self, plateau_interest_rate: I80F48) {
+        self.plateau_interest_rate = plateau_interest_rate;
+    }
+
+    #[inline]
+    fn interest_rate_curve(&self, ur: I80F48) -> Option<I80F48> {
+        match self.curve_type {
+            INTEREST_CURVE_MULTI_POINT => self.multi_point_interest_rate_curve(ur),
+            // Adaptive curves have the legacy shape, with the plateau rate set by the bank
+            _ => self.legacy_interest_rate_curve(ur),
+        }
+    }
+
+    /// Piecewise linear interest rate function, interpolated between
+    /// `(0, zero_util_rate)`, each used breakpoint and `(1, hundred_util_rate)`.
+    /// Utilization above 100% is charged `hundred_util_rate`.
+    ///
+    /// A single breakpoint at `(optimal_utilization_rate, plateau_interest_rate)`, with a 0 `zero_util_rate`
+    /// and `max_interest_rate` as `hundred_util_rate`, gives the same curve as `legacy_interest_rate_curve`.
+    #[inline]
+    fn multi_point_interest_rate_curve(&self, ur: I80F48) -> Option<I80F48> {
+        let mut prev_util = I80F48::ZERO;
+        let mut prev_rate = u32_to_rate(self.zero_util_rate);
+
+        let end = RatePoint {
+            util: u32::MAX,
+            rate: self.hundred_util_rate,
+        };
+
+        for point in self
+            .points
+            .iter()
+            .filter(|p| !p.is_empty())
+            .chain(std::iter::once(&end))
+        {
+            let util = u32_to_utilization(point.util);
+            let rate = u32_to_rate(point.rate);
+
+            if ur <= util {
+                return (ur - prev_util)
+                    .checked_div(util - prev_util)?
+                    .checked_mul(rate - prev_rate)?
+                    .checked_add(prev_rate);
+            }
+
+            prev_util = util;
+            prev_rate = rate;
+        }
+
+        Some(prev_rate)
+    }
+
     /// Piecewise linear interest rate function.
     /// The curves approaches the `plateau_interest_rate` as the utilization ratio approaches the `optimal_utilization_rate`,
     /// once the utilization ratio exceeds the `optimal_utilization_rate`, the curve approaches the `max_interest_rate`.
     ///
     /// To be clear we don't particularly appreciate the piecewise linear nature of this "curve", but it is what it is.
     #[inline]
-    fn interest_rate_curve(&self, ur: I80F48) -> Option<I80F48> {
+    fn legacy_interest_rate_curve(&self, ur: I80F48) -> Option<I80F48> {
         let optimal_ur: I80F48 = self.optimal_utilization_rate;
         let plateau_ir: I80F48 = self.plateau_interest_rate;
         let max_ir: I80F48 = self.max_interest_rate;
@@ -415,6 +794,18 @@ pub struct InterestRateConfigOpt {
     pub protocol_fixed_fee_apr: Option<WrappedI80F48>,
     pub protocol_ir_fee: Option<WrappedI80F48>,
     pub protocol_origination_fee: Option<WrappedI80F48>,
+
+    pub curve_type: Option<u8>,
+    pub zero_util_rate: Option<u32>,
+    pub hundred_util_rate: Option<u32>,
+    pub points: Option<[RatePoint; CURVE_POINTS]>,
+
+    pub adaptive_min_plateau_rate: Option<u32>,
+    pub adaptive_max_plateau_rate: Option<u32>,
+    pub adaptive_speed: Option<u32>,
+
+    pub max_lending_rate: Option<u32>,
+    pub max_borrowing_rate: Option<u32>,
 }
 
 /// Group level configuration to be used in bank accounts.
@@ -423,6 +814,45 @@ pub struct GroupBankConfig {
     pub program_fees: bool,
 }
 
+/// Collateral/borrow pair rules of a bank, see `Bank::pair_class`.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct BankPairRules {
+    pub pair_class: u8,
+    pub collateral_for_classes: u64,
+    pub accepted_collateral_classes: u64,
+}
+
+impl BankPairRules {
+    /// Whether deposits in a bank with these rules may back liabilities in a bank with
+    /// `liab_rules`. Both the collateral and the liability bank must allow the pair.
+    pub fn can_back(&self, liab_rules: &BankPairRules) -> bool {
+        let collateral_allows = self.collateral_for_classes == 0
+            || self.collateral_for_classes & (1 << liab_rules.pair_class) != 0;
+        let liability_allows = liab_rules.accepted_collateral_classes == 0
+            || liab_rules.accepted_collateral_classes & (1 << self.pair_class) != 0;
+
+        collateral_allows && liability_allows
+    }
+}
+
+pub const TWAP_OBSERVATIONS: usize = 8;
+
+assert_struct_size!(PriceObservation, 24);
+assert_struct_align!(PriceObservation, 8);
+/// A spot price recorded in `Bank::price_observations`.
+#[zero_copy(unsafe)]
+#[repr(C)]
+#[cfg_attr(
+    any(feature = "test", feature = "client"),
+    derive(Debug, PartialEq, Eq, TypeLayout)
+)]
+#[derive(Default)]
+pub struct PriceObservation {
+    pub price: WrappedI80F48,
+    /// 0 for an empty slot
+    pub timestamp: i64,
+}
+
 assert_struct_size!(Bank, 1856);
 assert_struct_align!(Bank, 8);
 #[account(zero_copy(unsafe))]
@@ -490,8 +920,129 @@ pub struct Bank {
     /// Fees collected and pending withdraw for the `FeeState.global_fee_wallet`'s cannonical ATA for `mint`
     pub collected_program_fees_outstanding: WrappedI80F48,
 
-    pub _padding_0: [[u64; 2]; 27],
-    pub _padding_1: [[u64; 2]; 32], // 16 * 2 * 32 = 1024B
+    /// Adaptive curve: current plateau rate, moved on each `accrue_interest` toward the rate that
+    /// keeps utilization at `optimal_utilization_rate` (see `Bank::update_adaptive_plateau_rate`).
+    ///
+    /// 0 until the first accrual with an adaptive curve, `plateau_interest_rate` is used until then.
+    /// Reset whenever the admin updates the curve type or the plateau rate.
+    pub adaptive_plateau_rate: WrappedI80F48,
+
+    /// APR added on top of the current `borrowing_rate_apr` to lock the rate of a fixed-term borrow.
+    pub fixed_term_premium: WrappedI80F48,
+    /// Principal and interest owed by fixed-term borrows not yet settled, in native tokens.
+    /// Counted as liabilities for utilization, but doesn't accrue variable interest.
+    pub fixed_term_liability_amount: WrappedI80F48,
+    /// Interest owed per year by fixed-term borrows not yet settled, i.e. the sum of their
+    /// principal times their locked rate, in native tokens. Accrued on each `accrue_interest`.
+    pub fixed_term_annual_interest: WrappedI80F48,
+    /// Longest fixed-term borrow allowed, in seconds.
+    ///
+    /// 0 disables fixed-term borrows.
+    pub fixed_term_max_duration: u64,
+    pub _pad3: [u8; 8],
+
+    /// Price of `OracleSetup::Fixed` and `OracleSetup::Peg` banks, in USD per whole token.
+    pub fixed_price: WrappedI80F48,
+
+    /// Window of the program maintained TWAP, in seconds. The TWAP is used in place of the oracle's
+    /// own time weighted price for initial and equity requirements (see `TwapPriceFeed`).
+    ///
+    /// 0 disables the TWAP, no observations are recorded.
+    pub twap_window: u64,
+    /// Index of the latest entry in `price_observations`
+    pub price_observation_index: u8,
+    pub _pad4: [u8; 7],
+    /// Ring buffer of spot prices, recorded on `accrue_interest` at most once every
+    /// `twap_window / TWAP_OBSERVATIONS` seconds.
+    pub price_observations: [PriceObservation; TWAP_OBSERVATIONS],
+
+    /// Multiple of the oracle confidence (Pyth) or standard deviation (Switchboard) used as the
+    /// confidence interval of biased prices.
+    ///
+    /// 0 means the feed's default `CONF_INTERVAL_MULTIPLE` or `STD_DEV_MULTIPLE` is used.
+    pub oracle_conf_interval_multiple: WrappedI80F48,
+    /// Max confidence interval, as a share of the price, e.g. 0.05 = 5%.
+    ///
+    /// 0 means the default `MAX_CONF_INTERVAL` is used.
+    pub oracle_max_conf_interval: WrappedI80F48,
+    /// `CONF_INTERVAL_MODE_CAP` caps wider confidence intervals at `oracle_max_conf_interval`,
+    /// `CONF_INTERVAL_MODE_REJECT` rejects the price instead.
+    pub oracle_conf_interval_mode: u8,
+    pub _pad5: [u8; 15],
+
+    /// E-mode category of correlated banks, e.g. SOL and LSTs, 0 if the bank isn't in any.
+    ///
+    /// The `emode_*` weights replace the config weights of the category's banks in accounts whose
+    /// liabilities are all in the category (see `RiskEngine::get_emode_category`).
+    pub emode_category: u16,
+    pub _pad6: [u8; 14],
+    pub emode_asset_weight_init: WrappedI80F48,
+    pub emode_asset_weight_maint: WrappedI80F48,
+    pub emode_liability_weight_init: WrappedI80F48,
+    pub emode_liability_weight_maint: WrappedI80F48,
+
+    /// Pair rules: bitmask of the `pair_class`es of the banks whose liabilities this bank's
+    /// deposits may back.
+    ///
+    /// 0 means the deposits may back any liability.
+    pub collateral_for_classes: u64,
+    /// Pair rules: bitmask of the `pair_class`es of the banks whose deposits may back this bank's
+    /// liabilities.
+    ///
+    /// 0 means any deposit may back the liabilities.
+    pub accepted_collateral_classes: u64,
+    /// Class of the bank in pair rules, from 0 to 63, e.g. all stablecoin banks in one class.
+    /// Deposits that may not back one of the account's liabilities don't count toward the
+    /// account's initial health (see `BankPairRules`).
+    pub pair_class: u8,
+    pub _pad7: [u8; 15],
+
+    /// Max USD value of the debt the bank's deposits may back across the group, for banks in the
+    /// `IsolatedCollateral` risk tier. Each deposit counts toward initial health for at most its
+    /// share of the bank's deposits times the ceiling.
+    pub isolated_debt_ceiling: u64,
+    pub _pad8: [u8; 8],
+
+    /// Max USD value of the bank's total deposits, checked at deposit time with the bank's
+    /// oracle. `USD_LIMIT_INACTIVE` disables it.
+    pub deposit_limit_usd: u64,
+    /// Max USD value of the bank's total liabilities, checked at borrow time with the bank's
+    /// oracle. `USD_LIMIT_INACTIVE` disables it.
+    pub borrow_limit_usd: u64,
+
+    /// Length in seconds of the sliding window over which outflows from the liquidity vault are
+    /// limited. 0 disables the outflow limit.
+    pub outflow_window: u64,
+    /// Max amount of native tokens that can leave the liquidity vault in the window. 0 means no
+    /// absolute limit.
+    pub outflow_limit: u64,
+    /// Max outflow in the window as basis points of the bank's total deposits. 0 means no
+    /// relative limit.
+    pub outflow_limit_bps: u16,
+    pub _pad9: [u8; 6],
+    /// Start of the current outflow window.
+    pub outflow_window_start: i64,
+    /// Native tokens that left the liquidity vault in the current window, net of the deposits and
+    /// repays made in it.
+    pub outflow_current_window: u64,
+    /// Native tokens that left the liquidity vault in the previous window.
+    pub outflow_previous_window: u64,
+
+    /// Max move of the oracle price between two prices recorded by the price guard, in basis
+    /// points of the previous price. 0 disables the price guard.
+    pub price_guard_max_move_bps: u16,
+    pub _pad10: [u8; 6],
+    /// Moves are only compared between prices recorded at most this many seconds apart, and a trip
+    /// clears this many seconds after it tripped. 0 means any two consecutive prices are compared.
+    pub price_guard_interval: u64,
+    /// Latest price recorded by the price guard, on `accrue_interest` and on borrows.
+    pub price_guard_last_price: WrappedI80F48,
+    pub price_guard_last_timestamp: i64,
+    /// When the price guard last tripped, 0 if it didn't. The group admin can reset it before the
+    /// trip clears.
+    pub price_guard_tripped_at: i64,
+
+    pub _padding_1: [[u64; 2]; 22], // 16 * 22 = 352B
 }
 
 impl Bank {
@@ -589,6 +1140,34 @@ impl Bank {
         Ok(())
     }
 
+    /// Max USD value a deposit of `asset_shares` counts for in initial health, for banks in the
+    /// `IsolatedCollateral` risk tier: the deposit's share of the bank's deposits times the
+    /// `isolated_debt_ceiling`. The bank's deposits can then back at most the ceiling in debt
+    /// across the group, whatever they are worth.
+    pub fn get_isolated_collateral_cap(
+        &self,
+        asset_shares: I80F48,
+    ) -> MarginfiResult<Option<I80F48>> {
+        if self.config.risk_tier != RiskTier::IsolatedCollateral {
+            return Ok(None);
+        }
+
+        let total_asset_shares: I80F48 = self.total_asset_shares.into();
+        if total_asset_shares.is_zero() {
+            return Ok(Some(I80F48::ZERO));
+        }
+
+        let deposit_share = asset_shares
+            .checked_div(total_asset_shares)
+            .ok_or_else(math_error!())?;
+
+        Ok(Some(
+            I80F48::from_num(self.isolated_debt_ceiling)
+                .checked_mul(deposit_share)
+                .ok_or_else(math_error!())?,
+        ))
+    }
+
     pub fn maybe_get_asset_weight_init_discount(
         &self,
         price: I80F48,
@@ -643,1102 +1222,3116 @@ impl Bank {
 
         if bypass_borrow_limit.not() && shares.is_positive() && self.config.is_borrow_limit_active()
         {
-            let total_liability_amount =
-                self.get_liability_amount(self.total_liability_shares.into())?;
-            let borrow_limit = I80F48::from_num(self.config.borrow_limit);
+            self.check_borrow_limit()?;
+        }
 
-            check!(
-                total_liability_amount < borrow_limit,
-                crate::prelude::MarginfiError::BankLiabilityCapacityExceeded
-            )
+        Ok(())
+    }
+
+    /// Change the fixed-term amount lent out by `amount`. Fixed-term borrows count toward the
+    /// borrow limit.
+    pub fn change_fixed_term_liability_amount(
+        &mut self,
+        amount: I80F48,
+        bypass_borrow_limit: bool,
+    ) -> MarginfiResult {
+        let fixed_term_liability_amount: I80F48 = self.fixed_term_liability_amount.into();
+        self.fixed_term_liability_amount = fixed_term_liability_amount
+            .checked_add(amount)
+            .ok_or_else(math_error!())?
+            .into();
+
+        if bypass_borrow_limit.not() && amount.is_positive() && self.config.is_borrow_limit_active()
+        {
+            self.check_borrow_limit()?;
         }
 
         Ok(())
     }
 
-    pub fn check_utilization_ratio(&self) -> MarginfiResult {
-        let total_assets = self.get_asset_amount(self.total_asset_shares.into())?;
-        let total_liabilities = self.get_liability_amount(self.total_liability_shares.into())?;
+    fn check_borrow_limit(&self) -> MarginfiResult {
+        let total_liability_amount = self.get_total_liability_amount()?;
+        let borrow_limit = I80F48::from_num(self.config.borrow_limit);
 
         check!(
-            total_assets >= total_liabilities,
-            crate::prelude::MarginfiError::IllegalUtilizationRatio
+            total_liability_amount < borrow_limit,
+            crate::prelude::MarginfiError::BankLiabilityCapacityExceeded
         );
 
         Ok(())
     }
 
-    pub fn configure(&mut self, config: &BankConfigOpt) -> MarginfiResult {
-        set_if_some!(self.config.asset_weight_init, config.asset_weight_init);
-        set_if_some!(self.config.asset_weight_maint, config.asset_weight_maint);
-        set_if_some!(
-            self.config.liability_weight_init,
-            config.liability_weight_init
-        );
-        set_if_some!(
-            self.config.liability_weight_maint,
-            config.liability_weight_maint
+    #[inline]
+    pub fn is_deposit_limit_usd_active(&self) -> bool {
+        self.deposit_limit_usd != USD_LIMIT_INACTIVE
+    }
+
+    #[inline]
+    pub fn is_borrow_limit_usd_active(&self) -> bool {
+        self.borrow_limit_usd != USD_LIMIT_INACTIVE
+    }
+
+    /// Checks the bank's total deposits, valued at `price`, are below `deposit_limit_usd`.
+    pub fn check_deposit_limit_usd(&self, price: I80F48) -> MarginfiResult {
+        let total_deposits_value = calc_value(
+            self.get_asset_amount(self.total_asset_shares.into())?,
+            price,
+            self.mint_decimals,
+            None,
+        )?;
+
+        check!(
+            total_deposits_value < I80F48::from_num(self.deposit_limit_usd),
+            MarginfiError::BankAssetCapacityExceeded
         );
-        set_if_some!(self.config.deposit_limit, config.deposit_limit);
 
-        set_if_some!(self.config.borrow_limit, config.borrow_limit);
+        Ok(())
+    }
 
-        set_if_some!(self.config.operational_state, config.operational_state);
+    /// Checks the bank's total liabilities, valued at `price`, are below `borrow_limit_usd`.
+    pub fn check_borrow_limit_usd(&self, price: I80F48) -> MarginfiResult {
+        let total_liabilities_value = calc_value(
+            self.get_total_liability_amount()?,
+            price,
+            self.mint_decimals,
+            None,
+        )?;
 
-        set_if_some!(self.config.oracle_setup, config.oracle.map(|o| o.setup));
+        check!(
+            total_liabilities_value < I80F48::from_num(self.borrow_limit_usd),
+            MarginfiError::BankLiabilityCapacityExceeded
+        );
 
-        set_if_some!(self.config.oracle_keys, config.oracle.map(|o| o.keys));
+        Ok(())
+    }
 
-        if let Some(ir_config) = &config.interest_rate_config {
-            self.config.interest_rate_config.update(ir_config);
+    #[inline]
+    pub fn is_outflow_limit_active(&self) -> bool {
+        self.outflow_window != 0 && (self.outflow_limit != 0 || self.outflow_limit_bps != 0)
+    }
+
+    /// Max outflow in the window: the lowest of the absolute and the relative limits that are set.
+    pub fn get_outflow_limit(&self) -> MarginfiResult<u64> {
+        let mut limit = u64::MAX;
+
+        if self.outflow_limit != 0 {
+            limit = self.outflow_limit;
         }
 
-        set_if_some!(self.config.risk_tier, config.risk_tier);
+        if self.outflow_limit_bps != 0 {
+            let total_deposits = self.get_asset_amount(self.total_asset_shares.into())?;
+            let relative_limit: u64 = total_deposits
+                .checked_mul(I80F48::from_num(self.outflow_limit_bps))
+                .ok_or_else(math_error!())?
+                .checked_div(I80F48::from_num(10_000))
+                .ok_or_else(math_error!())?
+                .checked_to_num()
+                .ok_or_else(math_error!())?;
+            limit = limit.min(relative_limit);
+        }
 
-        set_if_some!(
-            self.config.total_asset_value_init_limit,
-            config.total_asset_value_init_limit
-        );
+        Ok(limit)
+    }
 
-        set_if_some!(self.config.oracle_max_age, config.oracle_max_age);
+    /// Moves the outflow window forward so that it contains `current_timestamp`.
+    fn roll_outflow_window(&mut self, current_timestamp: i64) {
+        let window = self.outflow_window as i64;
+        let elapsed = current_timestamp.saturating_sub(self.outflow_window_start);
+
+        if elapsed >= 2 * window {
+            self.outflow_previous_window = 0;
+            self.outflow_current_window = 0;
+            self.outflow_window_start = current_timestamp - elapsed % window;
+        } else if elapsed >= window {
+            self.outflow_previous_window = self.outflow_current_window;
+            self.outflow_current_window = 0;
+            self.outflow_window_start += window;
+        }
+    }
 
-        if let Some(flag) = config.permissionless_bad_debt_settlement {
-            self.update_flag(flag, PERMISSIONLESS_BAD_DEBT_SETTLEMENT_FLAG);
+    /// Outflow over the last `outflow_window` seconds, counting the previous window pro rata to
+    /// its overlap with the sliding window.
+    pub fn get_windowed_outflow(&self, current_timestamp: i64) -> MarginfiResult<u64> {
+        if self.outflow_window == 0 {
+            return Ok(self.outflow_current_window);
         }
 
-        self.config.validate()?;
+        let window = self.outflow_window as u128;
+        let elapsed = current_timestamp.saturating_sub(self.outflow_window_start) as u128;
+        let previous_weighted =
+            self.outflow_previous_window as u128 * window.saturating_sub(elapsed) / window;
 
-        Ok(())
+        (previous_weighted as u64)
+            .checked_add(self.outflow_current_window)
+            .ok_or_else(math_error!())
     }
 
-    /// Calculate the interest rate accrual state changes for a given time period
+    /// Records `amount` leaving the liquidity vault in the bank's outflow window.
     ///
-    /// Collected protocol and insurance fees are stored in state.
-    /// A separate instruction is required to withdraw these fees.
-    pub fn accrue_interest(
-        &mut self,
-        current_timestamp: i64,
-        group: &MarginfiGroup,
-        #[cfg(not(feature = "client"))] bank: Pubkey,
-    ) -> MarginfiResult<()> {
-        #[cfg(all(not(feature = "client"), feature = "debug"))]
-        solana_program::log::sol_log_compute_units();
+    /// The outflow that first crosses the limit goes through and puts the bank in `ReduceOnly`,
+    /// later outflows error until the windowed outflow is back under the limit.
+    pub fn record_outflow(&mut self, amount: u64, current_timestamp: i64) -> MarginfiResult {
+        if !self.is_outflow_limit_active() {
+            return Ok(());
+        }
 
-        let time_delta: u64 = (current_timestamp - self.last_update).try_into().unwrap();
+        self.add_outflow(amount, current_timestamp)?;
 
-        if time_delta == 0 {
+        if self.get_windowed_outflow(current_timestamp)? <= self.get_outflow_limit()? {
             return Ok(());
         }
 
-        let total_assets = self.get_asset_amount(self.total_asset_shares.into())?;
-        let total_liabilities = self.get_liability_amount(self.total_liability_shares.into())?;
+        check!(
+            matches!(
+                self.config.operational_state,
+                BankOperationalState::Operational
+            ),
+            MarginfiError::BankOutflowLimitExceeded
+        );
 
-        self.last_update = current_timestamp;
+        msg!("Bank outflow limit reached, switching to reduce only");
+        self.config.operational_state = BankOperationalState::ReduceOnly;
 
-        if (total_assets == I80F48::ZERO) || (total_liabilities == I80F48::ZERO) {
-            #[cfg(not(feature = "client"))]
-            emit!(LendingPoolBankAccrueInterestEvent {
-                header: GroupEventHeader {
-                    marginfi_group: self.group,
-                    signer: None
-                },
-                bank,
-                mint: self.mint,
-                delta: time_delta,
-                fees_collected: 0.,
-                insurance_collected: 0.,
-            });
+        Ok(())
+    }
 
+    /// Records `amount` leaving the liquidity vault during a flashloan, without checking the limit.
+    ///
+    /// Flashloan borrows are usually repaid in the same transaction, the net outflow is checked
+    /// once the flashloan ends (see `Bank::check_flashloan_outflow_limit`).
+    pub fn record_flashloan_outflow(
+        &mut self,
+        amount: u64,
+        current_timestamp: i64,
+    ) -> MarginfiResult {
+        if !self.is_outflow_limit_active() {
             return Ok(());
         }
-        let ir_calc = self
-            .config
-            .interest_rate_config
-            .create_interest_rate_calculator(group);
-
-        let InterestRateStateChanges {
-            new_asset_share_value: asset_share_value,
-            new_liability_share_value: liability_share_value,
-            insurance_fees_collected,
-            group_fees_collected,
-            protocol_fees_collected,
-        } = calc_interest_rate_accrual_state_changes(
-            time_delta,
-            total_assets,
-            total_liabilities,
-            &ir_calc,
-            self.asset_share_value.into(),
-            self.liability_share_value.into(),
-        )
-        .ok_or_else(math_error!())?;
 
-        debug!("deposit share value: {}\nliability share value: {}\nfees collected: {}\ninsurance collected: {}",
-            asset_share_value, liability_share_value, group_fees_collected, insurance_fees_collected);
-
-        self.asset_share_value = asset_share_value.into();
-        self.liability_share_value = liability_share_value.into();
+        self.add_outflow(amount, current_timestamp)
+    }
 
-        if group_fees_collected > I80F48::ZERO {
-            self.collected_group_fees_outstanding = {
-                group_fees_collected
-                    .checked_add(self.collected_group_fees_outstanding.into())
-                    .ok_or_else(math_error!())?
-                    .into()
-            };
+    /// Records `amount` entering the liquidity vault (deposits and repays), netting it against the
+    /// outflow of the current window.
+    ///
+    /// Inflows only offset outflows already recorded, they never raise the limit of later outflows.
+    pub fn record_inflow(&mut self, amount: u64, current_timestamp: i64) {
+        if !self.is_outflow_limit_active() {
+            return;
         }
 
-        if insurance_fees_collected > I80F48::ZERO {
-            self.collected_insurance_fees_outstanding = {
-                insurance_fees_collected
-                    .checked_add(self.collected_insurance_fees_outstanding.into())
-                    .ok_or_else(math_error!())?
-                    .into()
-            };
-        }
-        if protocol_fees_collected > I80F48::ZERO {
-            self.collected_program_fees_outstanding = {
-                protocol_fees_collected
-                    .checked_add(self.collected_program_fees_outstanding.into())
-                    .ok_or_else(math_error!())?
-                    .into()
-            };
-        }
+        self.roll_outflow_window(current_timestamp);
+        self.outflow_current_window = self.outflow_current_window.saturating_sub(amount);
+    }
 
-        #[cfg(not(feature = "client"))]
+    /// Errors if the windowed outflow is over the limit of an `Operational` bank, i.e. the limit
+    /// was crossed by flashloan outflows, which don't trip the bank.
+    ///
+    /// Banks already tripped to `ReduceOnly` don't allow flashloan borrows, so they are skipped.
+    pub fn check_flashloan_outflow_limit(&self, current_timestamp: i64) -> MarginfiResult {
+        if !self.is_outflow_limit_active()
+            || !matches!(
+                self.config.operational_state,
+                BankOperationalState::Operational
+            )
         {
-            #[cfg(feature = "debug")]
-            solana_program::log::sol_log_compute_units();
-
-            emit!(LendingPoolBankAccrueInterestEvent {
-                header: GroupEventHeader {
-                    marginfi_group: self.group,
-                    signer: None
-                },
-                bank,
-                mint: self.mint,
-                delta: time_delta,
-                fees_collected: group_fees_collected.to_num::<f64>(),
-                insurance_collected: insurance_fees_collected.to_num::<f64>(),
-            });
+            return Ok(());
         }
 
-        Ok(())
-    }
-
-    pub fn deposit_spl_transfer<'info>(
-        &self,
-        amount: u64,
-        from: AccountInfo<'info>,
-        to: AccountInfo<'info>,
-        authority: AccountInfo<'info>,
-        maybe_mint: Option<&InterfaceAccount<'info, Mint>>,
-        program: AccountInfo<'info>,
-        remaining_accounts: &[AccountInfo<'info>],
-    ) -> MarginfiResult {
         check!(
-            to.key.eq(&self.liquidity_vault),
-            MarginfiError::InvalidTransfer
+            self.get_windowed_outflow(current_timestamp)? <= self.get_outflow_limit()?,
+            MarginfiError::BankOutflowLimitExceeded
         );
 
-        debug!(
-            "deposit_spl_transfer: amount: {} from {} to {}, auth {}",
-            amount, from.key, to.key, authority.key
-        );
+        Ok(())
+    }
 
-        if let Some(mint) = maybe_mint {
-            spl_token_2022::onchain::invoke_transfer_checked(
-                program.key,
-                from,
-                mint.to_account_info(),
-                to,
-                authority,
-                remaining_accounts,
-                amount,
-                mint.decimals,
-                &[],
-            )?;
-        } else {
-            #[allow(deprecated)]
-            transfer(
-                CpiContext::new_with_signer(
-                    program,
-                    Transfer {
-                        from,
-                        to,
-                        authority,
-                    },
-                    &[],
-                ),
-                amount,
-            )?;
-        }
+    fn add_outflow(&mut self, amount: u64, current_timestamp: i64) -> MarginfiResult {
+        self.roll_outflow_window(current_timestamp);
+        self.outflow_current_window = self
+            .outflow_current_window
+            .checked_add(amount)
+            .ok_or_else(math_error!())?;
 
         Ok(())
     }
 
-    pub fn withdraw_spl_transfer<'info>(
-        &self,
-        amount: u64,
-        from: AccountInfo<'info>,
-        to: AccountInfo<'info>,
-        authority: AccountInfo<'info>,
-        maybe_mint: Option<&InterfaceAccount<'info, Mint>>,
-        program: AccountInfo<'info>,
-        signer_seeds: &[&[&[u8]]],
-        remaining_accounts: &[AccountInfo<'info>],
-    ) -> MarginfiResult {
-        debug!(
-            "withdraw_spl_transfer: amount: {} from {} to {}, auth {}",
-            amount, from.key, to.key, authority.key
-        );
-
-        if let Some(mint) = maybe_mint {
-            spl_token_2022::onchain::invoke_transfer_checked(
-                program.key,
-                from,
-                mint.to_account_info(),
-                to,
-                authority,
-                remaining_accounts,
-                amount,
-                mint.decimals,
-                signer_seeds,
-            )?;
-        } else {
-            // `transfer_checked` and `transfer` does the same thing, the additional `_checked` logic
-            // is only to assert the expected attributes by the user (mint, decimal scaling),
-            //
-            // Security of `transfer` is equal to `transfer_checked`.
-            #[allow(deprecated)]
-            transfer(
-                CpiContext::new_with_signer(
-                    program,
-                    Transfer {
-                        from,
-                        to,
-                        authority,
-                    },
-                    signer_seeds,
-                ),
-                amount,
-            )?;
-        }
+    #[inline]
+    pub fn is_price_guard_active(&self) -> bool {
+        self.price_guard_max_move_bps != 0
+    }
 
-        Ok(())
+    /// While tripped, the bank's deposits can't back new borrows and the bank can't be borrowed
+    /// from, see `RiskEngine::check_account_borrow_health`. Other instructions are not affected.
+    ///
+    /// The trip clears `price_guard_interval` seconds after it tripped.
+    #[inline]
+    pub fn is_price_guard_tripped(&self, current_timestamp: i64) -> bool {
+        self.price_guard_tripped_at != 0
+            && current_timestamp.saturating_sub(self.price_guard_tripped_at)
+                <= self.price_guard_interval as i64
     }
 
-    /// Socialize a loss `loss_amount` among depositors,
-    /// the `total_deposit_shares` stays the same, but total value of deposits is
-    /// reduced by `loss_amount`;
-    pub fn socialize_loss(&mut self, loss_amount: I80F48) -> MarginfiResult {
-        let total_asset_shares: I80F48 = self.total_asset_shares.into();
-        let old_asset_share_value: I80F48 = self.asset_share_value.into();
+    /// Records `price` as the price guard's latest price, tripping the guard if it moved more than
+    /// `price_guard_max_move_bps` from the previous price recorded within `price_guard_interval`.
+    pub fn update_price_guard(&mut self, price: I80F48, current_timestamp: i64) -> MarginfiResult {
+        if !self.is_price_guard_active() {
+            return Ok(());
+        }
 
-        let new_share_value = total_asset_shares
-            .checked_mul(old_asset_share_value)
-            .ok_or_else(math_error!())?
-            .checked_sub(loss_amount)
-            .ok_or_else(math_error!())?
-            .checked_div(total_asset_shares)
-            .ok_or_else(math_error!())?;
+        if !self.is_price_guard_tripped(current_timestamp)
+            && self.is_price_guard_move(price, current_timestamp)?
+        {
+            msg!(
+                "Price moved from {} to {}, tripping the price guard",
+                I80F48::from(self.price_guard_last_price),
+                price
+            );
+            self.price_guard_tripped_at = current_timestamp;
+        }
 
-        self.asset_share_value = new_share_value.into();
+        self.price_guard_last_price = price.into();
+        self.price_guard_last_timestamp = current_timestamp;
 
         Ok(())
     }
 
-    pub fn assert_operational_mode(
+    /// Whether `price` moved more than `price_guard_max_move_bps` from the price guard's latest
+    /// price, if that price was recorded within `price_guard_interval`.
+    ///
+    /// The risk engine checks the oracle prices of new borrows' collateral with this, so a bad tick
+    /// is caught even if it was never recorded by the guard.
+    pub fn is_price_guard_move(
         &self,
-        is_asset_or_liability_amount_increasing: Option<bool>,
-    ) -> Result<()> {
-        match self.config.operational_state {
-            BankOperationalState::Paused => Err(MarginfiError::BankPaused.into()),
-            BankOperationalState::Operational => Ok(()),
-            BankOperationalState::ReduceOnly => {
-                if let Some(is_asset_or_liability_amount_increasing) =
-                    is_asset_or_liability_amount_increasing
-                {
-                    check!(
-                        !is_asset_or_liability_amount_increasing,
-                        MarginfiError::BankReduceOnly
-                    );
-                }
+        price: I80F48,
+        current_timestamp: i64,
+    ) -> MarginfiResult<bool> {
+        let last_price: I80F48 = self.price_guard_last_price.into();
+        let elapsed = current_timestamp.saturating_sub(self.price_guard_last_timestamp);
 
-                Ok(())
-            }
+        if !self.is_price_guard_active()
+            || last_price.is_zero()
+            || (self.price_guard_interval != 0 && elapsed > self.price_guard_interval as i64)
+        {
+            return Ok(false);
         }
-    }
 
-    pub fn get_flag(&self, flag: u64) -> bool {
-        (self.flags & flag) == flag
+        let max_move = last_price
+            .checked_mul(I80F48::from_num(self.price_guard_max_move_bps))
+            .ok_or_else(math_error!())?
+            .checked_div(I80F48::from_num(10_000))
+            .ok_or_else(math_error!())?;
+        let price_move = price
+            .checked_sub(last_price)
+            .ok_or_else(math_error!())?
+            .abs();
+
+        Ok(price_move > max_move)
     }
 
-    pub(crate) fn override_emissions_flag(&mut self, flag: u64) {
-        assert!(Self::verify_emissions_flags(flag));
-        self.flags = flag;
+    /// Variable liabilities plus the fixed-term principal lent out.
+    pub fn get_total_liability_amount(&self) -> MarginfiResult<I80F48> {
+        self.get_liability_amount(self.total_liability_shares.into())?
+            .checked_add(self.fixed_term_liability_amount.into())
+            .ok_or_else(math_error!())
     }
 
-    pub(crate) fn update_flag(&mut self, value: bool, flag: u64) {
-        assert!(Self::verify_group_flags(flag));
+    pub fn check_utilization_ratio(&self) -> MarginfiResult {
+        let total_assets = self.get_asset_amount(self.total_asset_shares.into())?;
+        let total_liabilities = self.get_total_liability_amount()?;
 
-        if value {
-            self.flags |= flag;
-        } else {
-            self.flags &= !flag;
-        }
-    }
+        check!(
+            total_assets >= total_liabilities,
+            crate::prelude::MarginfiError::IllegalUtilizationRatio
+        );
 
-    const fn verify_emissions_flags(flags: u64) -> bool {
-        flags & EMISSION_FLAGS == flags
+        Ok(())
     }
 
-    const fn verify_group_flags(flags: u64) -> bool {
-        flags & GROUP_FLAGS == flags
-    }
-}
+    pub fn configure(&mut self, config: &BankConfigOpt) -> MarginfiResult {
+        set_if_some!(self.config.asset_weight_init, config.asset_weight_init);
+        set_if_some!(self.config.asset_weight_maint, config.asset_weight_maint);
+        set_if_some!(
+            self.config.liability_weight_init,
+            config.liability_weight_init
+        );
+        set_if_some!(
+            self.config.liability_weight_maint,
+            config.liability_weight_maint
+        );
+        set_if_some!(self.config.deposit_limit, config.deposit_limit);
 
-/// We use a simple interest rate model that auto settles the accrued interest into the lending account balances.
-/// The plan is to move to a compound interest model in the future.
-///
-/// Simple interest rate model:
-/// - `P` - principal
-/// - `i` - interest rate (per second)
-/// - `t` - time (in seconds)
-///
-/// `P_t = P_0 * (1 + i) * t`
-///
-/// We use two interest rates, one for lending and one for borrowing.
-///
-/// Lending interest rate:
-/// - `i_l` - lending interest rate
-/// - `i` - base interest rate
-/// - `ur` - utilization rate
-///
-/// `i_l` = `i` * `ur`
-///
-/// Borrowing interest rate:
-/// - `i_b` - borrowing interest rate
-/// - `i` - base interest rate
-/// - `f_i` - interest rate fee
-/// - `f_f` - fixed fee
-///
-/// `i_b = i * (1 + f_i) + f_f`
-///
-fn calc_interest_rate_accrual_state_changes(
-    time_delta: u64,
-    total_assets_amount: I80F48,
-    total_liabilities_amount: I80F48,
-    interest_rate_calc: &InterestRateCalc,
-    asset_share_value: I80F48,
-    liability_share_value: I80F48,
-) -> Option<InterestRateStateChanges> {
-    let utilization_rate = total_liabilities_amount.checked_div(total_assets_amount)?;
-    let computed_rates = interest_rate_calc.calc_interest_rate(utilization_rate)?;
+        set_if_some!(self.config.borrow_limit, config.borrow_limit);
 
-    debug!(
-        "Utilization rate: {}, time delta {}s",
-        utilization_rate, time_delta
-    );
-    debug!("{:#?}", computed_rates);
+        set_if_some!(self.config.operational_state, config.operational_state);
 
-    let ComputedInterestRates {
-        lending_rate_apr,
-        borrowing_rate_apr,
-        group_fee_apr,
-        insurance_fee_apr,
-        protocol_fee_apr,
-    } = computed_rates;
+        set_if_some!(self.config.oracle_setup, config.oracle.map(|o| o.setup));
 
-    Some(InterestRateStateChanges {
-        new_asset_share_value: calc_accrued_interest_payment_per_period(
-            lending_rate_apr,
-            time_delta,
-            asset_share_value,
-        )?,
-        new_liability_share_value: calc_accrued_interest_payment_per_period(
-            borrowing_rate_apr,
-            time_delta,
-            liability_share_value,
-        )?,
-        insurance_fees_collected: calc_interest_payment_for_period(
-            insurance_fee_apr,
-            time_delta,
-            total_liabilities_amount,
-        )?,
-        group_fees_collected: calc_interest_payment_for_period(
-            group_fee_apr,
-            time_delta,
-            total_liabilities_amount,
-        )?,
-        protocol_fees_collected: calc_interest_payment_for_period(
-            protocol_fee_apr,
-            time_delta,
-            total_liabilities_amount,
-        )?,
-    })
-}
+        set_if_some!(self.config.oracle_keys, config.oracle.map(|o| o.keys));
 
-struct InterestRateStateChanges {
-    new_asset_share_value: I80F48,
-    new_liability_share_value: I80F48,
-    insurance_fees_collected: I80F48,
-    group_fees_collected: I80F48,
-    protocol_fees_collected: I80F48,
-}
+        set_if_some!(
+            self.config.oracle_source_setups,
+            config.oracle.map(|o| o.source_setups)
+        );
 
-/// Calculates the fee rate for a given base rate and fees specified.
-/// The returned rate is only the fee rate without the base rate.
-///
-/// Used for calculating the fees charged to the borrowers.
-fn calc_fee_rate(base_rate: I80F48, rate_fees: I80F48, fixed_fees: I80F48) -> Option<I80F48> {
-    if rate_fees.is_zero() {
-        return Some(fixed_fees);
-    }
+        set_if_some!(
+            self.config.oracle_fallback_setup,
+            config.oracle.map(|o| o.fallback_setup)
+        );
 
-    base_rate.checked_mul(rate_fees)?.checked_add(fixed_fees)
-}
+        set_if_some!(
+            self.config.oracle_max_deviation_bps,
+            config.oracle_max_deviation_bps
+        );
 
-/// Calculates the accrued interest payment per period `time_delta` in a principal value `value` for interest rate (in APR) `arp`.
-/// Result is the new principal value.
-fn calc_accrued_interest_payment_per_period(
-    apr: I80F48,
-    time_delta: u64,
-    value: I80F48,
-) -> Option<I80F48> {
-    let ir_per_period = apr
-        .checked_mul(time_delta.into())?
-        .checked_div(SECONDS_PER_YEAR)?;
+        if let Some(ir_config) = &config.interest_rate_config {
+            self.config.interest_rate_config.update(ir_config);
 
-    let new_value = value.checked_mul(I80F48::ONE.checked_add(ir_per_period)?)?;
+            if ir_config.curve_type.is_some() || ir_config.plateau_interest_rate.is_some() {
+                self.adaptive_plateau_rate = I80F48::ZERO.into();
+            }
+        }
 
-    Some(new_value)
-}
+        set_if_some!(self.config.risk_tier, config.risk_tier);
 
-/// Calculates the interest payment for a given period `time_delta` in a principal value `value` for interest rate (in APR) `arp`.
-/// Result is the interest payment.
-fn calc_interest_payment_for_period(apr: I80F48, time_delta: u64, value: I80F48) -> Option<I80F48> {
-    if apr.is_zero() {
-        return Some(I80F48::ZERO);
-    }
+        set_if_some!(
+            self.config.total_asset_value_init_limit,
+            config.total_asset_value_init_limit
+        );
 
-    let interest_payment = value
-        .checked_mul(apr)?
-        .checked_mul(time_delta.into())?
-        .checked_div(SECONDS_PER_YEAR)?;
+        set_if_some!(self.config.oracle_max_age, config.oracle_max_age);
 
-    Some(interest_payment)
-}
+        if let Some(fee_bps) = config.liquidation_liquidator_fee_bps {
+            self.config.liquidation_liquidator_fee_bps = fee_bps;
+            self.config.liquidation_fee_flags |= LIQUIDATION_LIQUIDATOR_FEE_SET_FLAG;
+        }
 
-#[repr(u8)]
-#[cfg_attr(any(feature = "test", feature = "client"), derive(PartialEq, Eq))]
-#[derive(Copy, Clone, Debug, AnchorSerialize, AnchorDeserialize)]
-pub enum BankOperationalState {
-    Paused,
-    Operational,
-    ReduceOnly,
-}
+        if let Some(fee_bps) = config.liquidation_max_liquidator_fee_bps {
+            self.config.liquidation_max_liquidator_fee_bps = fee_bps;
+            self.config.liquidation_fee_flags |= LIQUIDATION_MAX_LIQUIDATOR_FEE_SET_FLAG;
+        }
 
-#[cfg(feature = "client")]
-impl Display for BankOperationalState {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        match self {
-            BankOperationalState::Paused => write!(f, "Paused"),
-            BankOperationalState::Operational => write!(f, "Operational"),
-            BankOperationalState::ReduceOnly => write!(f, "ReduceOnly"),
+        if let Some(shortfall_bps) = config.liquidation_fee_ramp_shortfall_bps {
+            self.config.liquidation_fee_ramp_shortfall_bps = shortfall_bps;
+            self.config.liquidation_fee_flags |= LIQUIDATION_FEE_RAMP_SHORTFALL_SET_FLAG;
         }
-    }
-}
 
-#[repr(u8)]
-#[derive(Copy, Clone, Debug, AnchorSerialize, AnchorDeserialize, PartialEq, Eq)]
-pub enum RiskTier {
-    Collateral,
-    /// ## Isolated Risk
-    /// Assets in this trance can be borrowed only in isolation.
-    /// They can't be borrowed together with other assets.
-    ///
-    /// For example, if users has USDC, and wants to borrow XYZ which is isolated,
-    /// they can't borrow XYZ together with SOL, only XYZ alone.
-    Isolated,
-}
+        if let Some(fee_bps) = config.liquidation_insurance_fee_bps {
+            self.config.liquidation_insurance_fee_bps = fee_bps;
+            self.config.liquidation_fee_flags |= LIQUIDATION_INSURANCE_FEE_SET_FLAG;
+        }
 
-#[repr(C)]
-#[cfg_attr(
-    any(feature = "test", feature = "client"),
-    derive(PartialEq, Eq, TypeLayout)
-)]
-#[derive(AnchorDeserialize, AnchorSerialize, Debug)]
-/// TODO: Convert weights to (u64, u64) to avoid precision loss (maybe?)
-pub struct BankConfigCompact {
-    pub asset_weight_init: WrappedI80F48,
-    pub asset_weight_maint: WrappedI80F48,
+        set_if_some!(
+            self.config.liquidation_close_factor_bps,
+            config.liquidation_close_factor_bps
+        );
 
-    pub liability_weight_init: WrappedI80F48,
-    pub liability_weight_maint: WrappedI80F48,
+        set_if_some!(
+            self.config.liquidation_close_factor_shortfall_bps,
+            config.liquidation_close_factor_shortfall_bps
+        );
 
-    pub deposit_limit: u64,
+        set_if_some!(self.fixed_term_premium, config.fixed_term_premium);
 
-    pub interest_rate_config: InterestRateConfigCompact,
-    pub operational_state: BankOperationalState,
+        set_if_some!(self.fixed_term_max_duration, config.fixed_term_max_duration);
 
-    pub oracle_setup: OracleSetup,
-    pub oracle_key: Pubkey,
+        set_if_some!(self.fixed_price, config.fixed_price);
 
-    pub borrow_limit: u64,
+        set_if_some!(self.twap_window, config.twap_window);
 
-    pub risk_tier: RiskTier,
+        set_if_some!(
+            self.oracle_conf_interval_multiple,
+            config.oracle_conf_interval_multiple
+        );
 
-    pub _pad0: [u8; 7],
+        set_if_some!(
+            self.oracle_max_conf_interval,
+            config.oracle_max_conf_interval
+        );
 
-    /// USD denominated limit for calculating asset value for initialization margin requirements.
-    /// Example, if total SOL deposits are equal to $1M and the limit it set to $500K,
-    /// then SOL assets will be discounted by 50%.
-    ///
-    /// In other words the max value of liabilities that can be backed by the asset is $500K.
-    /// This is useful for limiting the damage of orcale attacks.
-    ///
-    /// Value is UI USD value, for example value 100 -> $100
-    pub total_asset_value_init_limit: u64,
+        set_if_some!(
+            self.oracle_conf_interval_mode,
+            config.oracle_conf_interval_mode
+        );
 
-    /// Time window in seconds for the oracle price feed to be considered live.
-    pub oracle_max_age: u16,
-}
+        set_if_some!(self.emode_category, config.emode_category);
 
-impl From<BankConfigCompact> for BankConfig {
-    fn from(config: BankConfigCompact) -> Self {
-        let keys = [
-            config.oracle_key,
-            Pubkey::default(),
-            Pubkey::default(),
-            Pubkey::default(),
-            Pubkey::default(),
-        ];
-        Self {
-            asset_weight_init: config.asset_weight_init,
-            asset_weight_maint: config.asset_weight_maint,
-            liability_weight_init: config.liability_weight_init,
-            liability_weight_maint: config.liability_weight_maint,
-            deposit_limit: config.deposit_limit,
-            interest_rate_config: config.interest_rate_config.into(),
-            operational_state: config.operational_state,
-            oracle_setup: config.oracle_setup,
-            oracle_keys: keys,
-            _pad0: [0; 6],
-            borrow_limit: config.borrow_limit,
-            risk_tier: config.risk_tier,
-            _pad1: [0; 7],
-            total_asset_value_init_limit: config.total_asset_value_init_limit,
-            oracle_max_age: config.oracle_max_age,
-            _padding: [0; 38],
-        }
-    }
-}
+        set_if_some!(self.emode_asset_weight_init, config.emode_asset_weight_init);
 
-impl From<BankConfig> for BankConfigCompact {
-    fn from(config: BankConfig) -> Self {
-        Self {
-            asset_weight_init: config.asset_weight_init,
-            asset_weight_maint: config.asset_weight_maint,
-            liability_weight_init: config.liability_weight_init,
-            liability_weight_maint: config.liability_weight_maint,
-            deposit_limit: config.deposit_limit,
-            interest_rate_config: config.interest_rate_config.into(),
-            operational_state: config.operational_state,
-            oracle_setup: config.oracle_setup,
-            oracle_key: config.oracle_keys[0],
-            borrow_limit: config.borrow_limit,
-            risk_tier: config.risk_tier,
-            _pad0: [0; 7],
-            total_asset_value_init_limit: config.total_asset_value_init_limit,
-            oracle_max_age: config.oracle_max_age,
+        set_if_some!(
+            self.emode_asset_weight_maint,
+            config.emode_asset_weight_maint
+        );
+
+        set_if_some!(
+            self.emode_liability_weight_init,
+            config.emode_liability_weight_init
+        );
+
+        set_if_some!(
+            self.emode_liability_weight_maint,
+            config.emode_liability_weight_maint
+        );
+
+        set_if_some!(self.pair_class, config.pair_class);
+
+        set_if_some!(self.collateral_for_classes, config.collateral_for_classes);
+
+        set_if_some!(
+            self.accepted_collateral_classes,
+            config.accepted_collateral_classes
+        );
+
+        set_if_some!(self.isolated_debt_ceiling, config.isolated_debt_ceiling);
+
+        set_if_some!(self.deposit_limit_usd, config.deposit_limit_usd);
+
+        set_if_some!(self.borrow_limit_usd, config.borrow_limit_usd);
+
+        set_if_some!(self.outflow_window, config.outflow_window);
+
+        set_if_some!(self.outflow_limit, config.outflow_limit);
+
+        set_if_some!(self.outflow_limit_bps, config.outflow_limit_bps);
+
+        set_if_some!(
+            self.price_guard_max_move_bps,
+            config.price_guard_max_move_bps
+        );
+
+        set_if_some!(self.price_guard_interval, config.price_guard_interval);
+
+        if let Some(true) = config.price_guard_reset {
+            self.price_guard_tripped_at = 0;
+            self.price_guard_last_price = I80F48::ZERO.into();
+            self.price_guard_last_timestamp = 0;
         }
-    }
-}
 
-assert_struct_size!(BankConfig, 544);
-assert_struct_align!(BankConfig, 8);
-#[zero_copy(unsafe)]
-#[repr(C)]
-#[cfg_attr(
-    any(feature = "test", feature = "client"),
-    derive(PartialEq, Eq, TypeLayout)
-)]
-#[derive(Debug)]
-/// TODO: Convert weights to (u64, u64) to avoid precision loss (maybe?)
-pub struct BankConfig {
-    pub asset_weight_init: WrappedI80F48,
-    pub asset_weight_maint: WrappedI80F48,
+        if let Some(flag) = config.permissionless_bad_debt_settlement {
+            self.update_flag(flag, PERMISSIONLESS_BAD_DEBT_SETTLEMENT_FLAG);
+        }
 
-    pub liability_weight_init: WrappedI80F48,
-    pub liability_weight_maint: WrappedI80F48,
+        self.config.validate()?;
 
-    pub deposit_limit: u64,
+        check!(
+            I80F48::from(self.fixed_term_premium) >= I80F48::ZERO,
+            MarginfiError::InvalidConfig
+        );
 
-    pub interest_rate_config: InterestRateConfig,
-    pub operational_state: BankOperationalState,
+        self.validate_oracle_conf_interval_config()?;
+        self.validate_emode_config()?;
 
-    pub oracle_setup: OracleSetup,
-    pub oracle_keys: [Pubkey; MAX_ORACLE_KEYS],
+        check!(
+            (self.pair_class as u32) < u64::BITS,
+            MarginfiError::InvalidConfig
+        );
 
-    // Note: Pubkey is aligned 1, so borrow_limit is the first aligned-8 value after deposit_limit
-    pub _pad0: [u8; 6], // Bank state (1) + Oracle Setup (1) + 6 = 8
+        check!(
+            self.outflow_limit_bps <= 10_000,
+            MarginfiError::InvalidConfig
+        );
 
-    pub borrow_limit: u64,
+        Ok(())
+    }
 
-    pub risk_tier: RiskTier,
+    pub fn get_pair_rules(&self) -> BankPairRules {
+        BankPairRules {
+            pair_class: self.pair_class,
+            collateral_for_classes: self.collateral_for_classes,
+            accepted_collateral_classes: self.accepted_collateral_classes,
+        }
+    }
 
-    pub _pad1: [u8; 7],
+    pub fn validate_oracle_setup(&self, ais: &[AccountInfo]) -> MarginfiResult {
+        OraclePriceFeedAdapter::validate_bank(self, ais)
+    }
 
-    /// USD denominated limit for calculating asset value for initialization margin requirements.
-    /// Example, if total SOL deposits are equal to $1M and the limit it set to $500K,
-    /// then SOL assets will be discounted by 50%.
-    ///
-    /// In other words the max value of liabilities that can be backed by the asset is $500K.
-    /// This is useful for limiting the damage of orcale attacks.
-    ///
-    /// Value is UI USD value, for example value 100 -> $100
-    pub total_asset_value_init_limit: u64,
+    /// Price of `OracleSetup::Fixed` and `OracleSetup::Peg` banks, errors if it was never set.
+    pub fn get_fixed_price(&self) -> MarginfiResult<I80F48> {
+        let fixed_price = I80F48::from(self.fixed_price);
+        check!(
+            fixed_price > I80F48::ZERO,
+            MarginfiError::InvalidOracleSetup,
+            "Fixed price is not set"
+        );
 
-    /// Time window in seconds for the oracle price feed to be considered live.
-    pub oracle_max_age: u16,
+        Ok(fixed_price)
+    }
 
-    pub _padding: [u8; 38],
-}
+    /// Records a spot price for the TWAP. Skipped if the TWAP is disabled, or if the latest
+    /// observation is too recent, so that the ring buffer spans the whole window.
+    pub fn record_price_observation(&mut self, price: I80F48, current_timestamp: i64) {
+        if self.twap_window == 0 {
+            return;
+        }
 
-impl Default for BankConfig {
-    fn default() -> Self {
-        Self {
-            asset_weight_init: I80F48::ZERO.into(),
-            asset_weight_maint: I80F48::ZERO.into(),
-            liability_weight_init: I80F48::ONE.into(),
-            liability_weight_maint: I80F48::ONE.into(),
-            deposit_limit: 0,
-            borrow_limit: 0,
-            interest_rate_config: Default::default(),
-            operational_state: BankOperationalState::Paused,
-            oracle_setup: OracleSetup::None,
-            oracle_keys: [Pubkey::default(); MAX_ORACLE_KEYS],
-            _pad0: [0; 6],
-            risk_tier: RiskTier::Isolated,
-            _pad1: [0; 7],
-            total_asset_value_init_limit: TOTAL_ASSET_VALUE_INIT_LIMIT_INACTIVE,
-            oracle_max_age: 0,
-            _padding: [0; 38],
+        let latest = &self.price_observations[self.price_observation_index as usize];
+        let min_interval = (self.twap_window / TWAP_OBSERVATIONS as u64) as i64;
+
+        if latest.timestamp != 0
+            && current_timestamp.saturating_sub(latest.timestamp) < min_interval.max(1)
+        {
+            return;
         }
+
+        let index = (self.price_observation_index as usize + 1) % TWAP_OBSERVATIONS;
+        self.price_observation_index = index as u8;
+        self.price_observations[index] = PriceObservation {
+            price: price.into(),
+            timestamp: current_timestamp,
+        };
     }
-}
 
-impl BankConfig {
-    #[inline]
-    pub fn get_weights(&self, req_type: RequirementType) -> (I80F48, I80F48) {
-        match req_type {
-            RequirementType::Initial => (
-                self.asset_weight_init.into(),
-                self.liability_weight_init.into(),
-            ),
-            RequirementType::Maintenance => (
-                self.asset_weight_maint.into(),
-                self.liability_weight_maint.into(),
-            ),
-            RequirementType::Equity => (I80F48::ONE, I80F48::ONE),
+    /// Time weighted average of the recorded prices over the last `twap_window` seconds, each
+    /// price is weighted by how long it stayed the latest observation.
+    ///
+    /// None if the TWAP is disabled or no price was recorded within the window.
+    pub fn get_twap_price(&self, current_timestamp: i64) -> MarginfiResult<Option<I80F48>> {
+        if self.twap_window == 0 {
+            return Ok(None);
+        }
+
+        let window_start = current_timestamp.saturating_sub(self.twap_window as i64);
+        let latest = &self.price_observations[self.price_observation_index as usize];
+
+        if latest.timestamp == 0 || latest.timestamp < window_start {
+            return Ok(None);
+        }
+
+        let mut weighted_sum = I80F48::ZERO;
+        let mut total_duration: i64 = 0;
+        let mut end = current_timestamp;
+
+        for i in 0..TWAP_OBSERVATIONS {
+            let index =
+                (self.price_observation_index as usize + TWAP_OBSERVATIONS - i) % TWAP_OBSERVATIONS;
+            let observation = &self.price_observations[index];
+
+            if observation.timestamp == 0 {
+                break;
+            }
+
+            let duration = end.saturating_sub(observation.timestamp.max(window_start));
+            if duration > 0 {
+                weighted_sum = I80F48::from(observation.price)
+                    .checked_mul(I80F48::from_num(duration))
+                    .and_then(|v| v.checked_add(weighted_sum))
+                    .ok_or_else(math_error!())?;
+                total_duration += duration;
+            }
+
+            if observation.timestamp <= window_start {
+                break;
+            }
+
+            end = observation.timestamp;
         }
+
+        if total_duration == 0 {
+            return Ok(Some(latest.price.into()));
+        }
+
+        Ok(Some(
+            weighted_sum
+                .checked_div(I80F48::from_num(total_duration))
+                .ok_or_else(math_error!())?,
+        ))
     }
 
+    /// Risk weight of the bank. The e-mode weights are used if the account is in the bank's e-mode
+    /// category, see `RiskEngine::get_emode_category`.
     #[inline]
     pub fn get_weight(
         &self,
         requirement_type: RequirementType,
         balance_side: BalanceSide,
+        emode_category: Option<u16>,
     ) -> I80F48 {
+        if self.emode_category == 0 || emode_category != Some(self.emode_category) {
+            return self.config.get_weight(requirement_type, balance_side);
+        }
+
         match (requirement_type, balance_side) {
-            (RequirementType::Initial, BalanceSide::Assets) => self.asset_weight_init.into(),
+            (RequirementType::Initial, BalanceSide::Assets) => self.emode_asset_weight_init.into(),
             (RequirementType::Initial, BalanceSide::Liabilities) => {
-                self.liability_weight_init.into()
+                self.emode_liability_weight_init.into()
+            }
+            (RequirementType::Maintenance, BalanceSide::Assets) => {
+                self.emode_asset_weight_maint.into()
             }
-            (RequirementType::Maintenance, BalanceSide::Assets) => self.asset_weight_maint.into(),
             (RequirementType::Maintenance, BalanceSide::Liabilities) => {
-                self.liability_weight_maint.into()
+                self.emode_liability_weight_maint.into()
             }
             (RequirementType::Equity, _) => I80F48::ONE,
         }
     }
 
-    pub fn validate(&self) -> MarginfiResult {
-        let asset_init_w = I80F48::from(self.asset_weight_init);
-        let asset_maint_w = I80F48::from(self.asset_weight_maint);
+    /// E-mode weights follow the same rules as the config weights, and can only be more
+    /// favorable than them, so entering e-mode never lowers an account's health.
+    fn validate_emode_config(&self) -> MarginfiResult {
+        if self.emode_category == 0 {
+            return Ok(());
+        }
+
+        let asset_init_w = I80F48::from(self.emode_asset_weight_init);
+        let asset_maint_w = I80F48::from(self.emode_asset_weight_maint);
+        let liab_init_w = I80F48::from(self.emode_liability_weight_init);
+        let liab_maint_w = I80F48::from(self.emode_liability_weight_maint);
 
         check!(
             asset_init_w >= I80F48::ZERO && asset_init_w <= I80F48::ONE,
             MarginfiError::InvalidConfig
         );
         check!(asset_maint_w >= asset_init_w, MarginfiError::InvalidConfig);
-
-        let liab_init_w = I80F48::from(self.liability_weight_init);
-        let liab_maint_w = I80F48::from(self.liability_weight_maint);
-
         check!(liab_init_w >= I80F48::ONE, MarginfiError::InvalidConfig);
         check!(
             liab_maint_w <= liab_init_w && liab_maint_w >= I80F48::ONE,
             MarginfiError::InvalidConfig
         );
 
-        self.interest_rate_config.validate()?;
+        check!(
+            asset_init_w >= I80F48::from(self.config.asset_weight_init)
+                && asset_maint_w >= I80F48::from(self.config.asset_weight_maint)
+                && liab_init_w <= I80F48::from(self.config.liability_weight_init)
+                && liab_maint_w <= I80F48::from(self.config.liability_weight_maint),
+            MarginfiError::InvalidConfig,
+            "E-mode weights must be at least as favorable as the bank's weights"
+        );
 
-        if self.risk_tier == RiskTier::Isolated {
-            check!(asset_init_w == I80F48::ZERO, MarginfiError::InvalidConfig);
-            check!(asset_maint_w == I80F48::ZERO, MarginfiError::InvalidConfig);
+        Ok(())
+    }
+
+    /// The confidence interval multiple can't be negative, and the max confidence interval is a
+    /// share of the price, between 0 and 1.
+    fn validate_oracle_conf_interval_config(&self) -> MarginfiResult {
+        let conf_interval_multiple: I80F48 = self.oracle_conf_interval_multiple.into();
+        let max_conf_interval: I80F48 = self.oracle_max_conf_interval.into();
+
+        check!(
+            conf_interval_multiple >= I80F48::ZERO,
+            MarginfiError::InvalidConfig
+        );
+        check!(
+            max_conf_interval >= I80F48::ZERO && max_conf_interval <= I80F48::ONE,
+            MarginfiError::InvalidConfig
+        );
+        check!(
+            matches!(
+                self.oracle_conf_interval_mode,
+                CONF_INTERVAL_MODE_CAP | CONF_INTERVAL_MODE_REJECT
+            ),
+            MarginfiError::InvalidConfig
+        );
+
+        Ok(())
+    }
+
+    /// Interest rate calculator for this bank, with the current plateau rate for adaptive curves.
+    pub fn create_interest_rate_calculator(&self, group: &MarginfiGroup) -> InterestRateCalc {
+        let mut ir_calc = self
+            .config
+            .interest_rate_config
+            .create_interest_rate_calculator(group);
+
+        if self.config.interest_rate_config.curve_type == INTEREST_CURVE_ADAPTIVE {
+            ir_calc.set_plateau_interest_rate(self.get_adaptive_plateau_rate());
+        }
+
+        ir_calc
+    }
+
+    /// Current plateau rate of an adaptive curve, `plateau_interest_rate` until the first accrual.
+    #[inline]
+    pub fn get_adaptive_plateau_rate(&self) -> I80F48 {
+        let adaptive_plateau_rate: I80F48 = self.adaptive_plateau_rate.into();
+        if adaptive_plateau_rate == I80F48::ZERO {
+            self.config
+                .interest_rate_config
+                .plateau_interest_rate
+                .into()
+        } else {
+            adaptive_plateau_rate
+        }
+    }
+
+    /// Moves the plateau rate of an adaptive curve after `time_delta` seconds spent at
+    /// `utilization_rate`.
+    ///
+    /// The plateau rate rises while utilization sits above `optimal_utilization_rate` and falls while
+    /// it sits below, proportionally to the distance from the optimal rate (normalized to 0-1 on
+    /// each side) and to the time spent there. It moves by at most `adaptive_speed` per day, and
+    /// stays within `adaptive_min_plateau_rate` and `adaptive_max_plateau_rate`.
+    ///
+    /// Returns the plateau rate in effect after the update, which is the configured plateau rate for
+    /// other curve types.
+    fn update_adaptive_plateau_rate(
+        &mut self,
+        utilization_rate: I80F48,
+        time_delta: u64,
+    ) -> MarginfiResult<I80F48> {
+        let ir_config = &self.config.interest_rate_config;
+
+        if ir_config.curve_type != INTEREST_CURVE_ADAPTIVE {
+            return Ok(ir_config.plateau_interest_rate.into());
         }
 
+        let optimal_ur: I80F48 = ir_config.optimal_utilization_rate.into();
+        let ur_error = if utilization_rate > optimal_ur {
+            (utilization_rate - optimal_ur)
+                .checked_div(I80F48::ONE - optimal_ur)
+                .ok_or_else(math_error!())?
+                .min(I80F48::ONE)
+        } else {
+            (utilization_rate - optimal_ur)
+                .checked_div(optimal_ur)
+                .ok_or_else(math_error!())?
+        };
+
+        let plateau_change = u32_to_rate(ir_config.adaptive_speed)
+            .checked_mul(ur_error)
+            .ok_or_else(math_error!())?
+            .checked_mul(I80F48::from_num(time_delta))
+            .ok_or_else(math_error!())?
+            .checked_div(SECONDS_PER_DAY)
+            .ok_or_else(math_error!())?;
+
+        let plateau_interest_rate = self
+            .get_adaptive_plateau_rate()
+            .checked_add(plateau_change)
+            .ok_or_else(math_error!())?
+            .max(u32_to_rate(ir_config.adaptive_min_plateau_rate))
+            .min(u32_to_rate(ir_config.adaptive_max_plateau_rate));
+
+        self.adaptive_plateau_rate = plateau_interest_rate.into();
+
+        Ok(plateau_interest_rate)
+    }
+
+    /// Rate locked by a new fixed-term borrow of `amount`: the borrowing rate at the utilization
+    /// after the borrow, plus `fixed_term_premium`.
+    pub fn get_fixed_term_rate(
+        &self,
+        group: &MarginfiGroup,
+        amount: I80F48,
+    ) -> MarginfiResult<I80F48> {
+        let total_assets = self.get_asset_amount(self.total_asset_shares.into())?;
+        let utilization_rate = self
+            .get_total_liability_amount()?
+            .checked_add(amount)
+            .ok_or_else(math_error!())?
+            .checked_div(total_assets)
+            .ok_or_else(math_error!())?;
+
+        let ComputedInterestRates {
+            borrowing_rate_apr, ..
+        } = self
+            .create_interest_rate_calculator(group)
+            .calc_interest_rate(utilization_rate)
+            .ok_or_else(math_error!())?;
+
+        Ok(borrowing_rate_apr
+            .checked_add(self.fixed_term_premium.into())
+            .ok_or_else(math_error!())?)
+    }
+
+    /// Open a fixed-term borrow of `principal` at the locked `rate`. From now on its interest
+    /// accrues on each `accrue_interest`, like the interest of variable liabilities.
+    pub fn open_fixed_term_borrow(&mut self, principal: I80F48, rate: I80F48) -> MarginfiResult {
+        self.change_fixed_term_liability_amount(principal, false)?;
+
+        let fixed_term_annual_interest: I80F48 = self.fixed_term_annual_interest.into();
+        self.fixed_term_annual_interest = principal
+            .checked_mul(rate)
+            .ok_or_else(math_error!())?
+            .checked_add(fixed_term_annual_interest)
+            .ok_or_else(math_error!())?
+            .into();
+
+        Ok(())
+    }
+
+    /// Settle a fixed-term borrow of `principal` at the locked `rate` that owes `amount_owed`, when
+    /// it's repaid or converted to a variable liability. Its interest was already accrued, the
+    /// amount owed is only no longer counted as lent out.
+    pub fn settle_fixed_term_borrow(
+        &mut self,
+        principal: I80F48,
+        rate: I80F48,
+        amount_owed: I80F48,
+    ) -> MarginfiResult {
+        // Interest is accrued for all fixed-term borrows at once, the totals can be a rounding
+        // error below what the last borrows owe
+        let fixed_term_liability_amount: I80F48 = self.fixed_term_liability_amount.into();
+        self.fixed_term_liability_amount = fixed_term_liability_amount
+            .checked_sub(amount_owed)
+            .ok_or_else(math_error!())?
+            .max(I80F48::ZERO)
+            .into();
+
+        let fixed_term_annual_interest: I80F48 = self.fixed_term_annual_interest.into();
+        self.fixed_term_annual_interest = fixed_term_annual_interest
+            .checked_sub(principal.checked_mul(rate).ok_or_else(math_error!())?)
+            .ok_or_else(math_error!())?
+            .max(I80F48::ZERO)
+            .into();
+
         Ok(())
     }
 
-    #[inline]
-    pub fn is_deposit_limit_active(&self) -> bool {
-        self.deposit_limit != u64::MAX
+    /// Credit an origination fee charged on top of a borrow to the program and group fees.
+    pub fn collect_origination_fee(
+        &mut self,
+        origination_fee: I80F48,
+        program_fee_rate: I80F48,
+    ) -> MarginfiResult {
+        if origination_fee.is_zero() {
+            return Ok(());
+        }
+
+        let mut bank_fees_after: I80F48 = self.collected_group_fees_outstanding.into();
+
+        if !program_fee_rate.is_zero() {
+            // Some portion of the origination fee to goes to program fees
+            let program_fee_amount: I80F48 = origination_fee
+                .checked_mul(program_fee_rate)
+                .ok_or_else(math_error!())?;
+            // The remainder of the origination fee goes to group fees
+            bank_fees_after =
+                bank_fees_after.saturating_add(origination_fee.saturating_sub(program_fee_amount));
+
+            // Update the bank's program fees
+            let program_fees_before: I80F48 = self.collected_program_fees_outstanding.into();
+            self.collected_program_fees_outstanding = program_fees_before
+                .saturating_add(program_fee_amount)
+                .into();
+        } else {
+            // If program fee rate is zero, add the full origination fee to group fees
+            bank_fees_after = bank_fees_after.saturating_add(origination_fee);
+        }
+
+        // Update the bank's group fees
+        self.collected_group_fees_outstanding = bank_fees_after.into();
+
+        Ok(())
+    }
+
+    /// Calculate the interest rate accrual state changes for a given time period
+    ///
+    /// Collected protocol and insurance fees are stored in state.
+    /// A separate instruction is required to withdraw these fees.
+    pub fn accrue_interest(
+        &mut self,
+        current_timestamp: i64,
+        group: &MarginfiGroup,
+        #[cfg(not(feature = "client"))] bank: Pubkey,
+    ) -> MarginfiResult<()> {
+        #[cfg(all(not(feature = "client"), feature = "debug"))]
+        solana_program::log::sol_log_compute_units();
+
+        let time_delta: u64 = (current_timestamp - self.last_update).try_into().unwrap();
+
+        if time_delta == 0 {
+            return Ok(());
+        }
+
+        let total_assets = self.get_asset_amount(self.total_asset_shares.into())?;
+        let total_liabilities = self.get_liability_amount(self.total_liability_shares.into())?;
+        let fixed_term_liabilities: I80F48 = self.fixed_term_liability_amount.into();
+
+        self.last_update = current_timestamp;
+
+        if (total_assets == I80F48::ZERO)
+            || (total_liabilities == I80F48::ZERO && fixed_term_liabilities == I80F48::ZERO)
+        {
+            let plateau_interest_rate =
+                self.update_adaptive_plateau_rate(I80F48::ZERO, time_delta)?;
+
+            #[cfg(not(feature = "client"))]
+            emit!(LendingPoolBankAccrueInterestEvent {
+                header: GroupEventHeader {
+                    marginfi_group: self.group,
+                    signer: None
+                },
+                bank,
+                mint: self.mint,
+                delta: time_delta,
+                fees_collected: 0.,
+                insurance_collected: 0.,
+                plateau_interest_rate: plateau_interest_rate.to_num::<f64>(),
+            });
+
+            return Ok(());
+        }
+        let ir_calc = self.create_interest_rate_calculator(group);
+
+        let InterestRateStateChanges {
+            new_asset_share_value: asset_share_value,
+            new_liability_share_value: liability_share_value,
+            insurance_fees_collected,
+            group_fees_collected,
+            protocol_fees_collected,
+            fixed_term_interest_accrued,
+        } = calc_interest_rate_accrual_state_changes(
+            time_delta,
+            total_assets,
+            total_liabilities,
+            fixed_term_liabilities,
+            self.fixed_term_annual_interest.into(),
+            &ir_calc,
+            self.asset_share_value.into(),
+            self.liability_share_value.into(),
+        )
+        .ok_or_else(math_error!())?;
+
+        debug!("deposit share value: {}\nliability share value: {}\nfees collected: {}\ninsurance collected: {}",
+            asset_share_value, liability_share_value, group_fees_collected, insurance_fees_collected);
+
+        self.asset_share_value = asset_share_value.into();
+        self.liability_share_value = liability_share_value.into();
+        self.fixed_term_liability_amount = fixed_term_liabilities
+            .checked_add(fixed_term_interest_accrued)
+            .ok_or_else(math_error!())?
+            .into();
+
+        // The plateau rate charged above applied over the whole period, it only moves afterwards
+        let utilization_rate = total_liabilities
+            .checked_add(fixed_term_liabilities)
+            .ok_or_else(math_error!())?
+            .checked_div(total_assets)
+            .ok_or_else(math_error!())?;
+        let plateau_interest_rate =
+            self.update_adaptive_plateau_rate(utilization_rate, time_delta)?;
+
+        if group_fees_collected > I80F48::ZERO {
+            self.collected_group_fees_outstanding = {
+                group_fees_collected
+                    .checked_add(self.collected_group_fees_outstanding.into())
+                    .ok_or_else(math_error!())?
+                    .into()
+            };
+        }
+
+        if insurance_fees_collected > I80F48::ZERO {
+            self.collected_insurance_fees_outstanding = {
+                insurance_fees_collected
+                    .checked_add(self.collected_insurance_fees_outstanding.into())
+                    .ok_or_else(math_error!())?
+                    .into()
+            };
+        }
+        if protocol_fees_collected > I80F48::ZERO {
+            self.collected_program_fees_outstanding = {
+                protocol_fees_collected
+                    .checked_add(self.collected_program_fees_outstanding.into())
+                    .ok_or_else(math_error!())?
+                    .into()
+            };
+        }
+
+        #[cfg(not(feature = "client"))]
+        {
+            #[cfg(feature = "debug")]
+            solana_program::log::sol_log_compute_units();
+
+            emit!(LendingPoolBankAccrueInterestEvent {
+                header: GroupEventHeader {
+                    marginfi_group: self.group,
+                    signer: None
+                },
+                bank,
+                mint: self.mint,
+                delta: time_delta,
+                fees_collected: group_fees_collected.to_num::<f64>(),
+                insurance_collected: insurance_fees_collected.to_num::<f64>(),
+                plateau_interest_rate: plateau_interest_rate.to_num::<f64>(),
+            });
+        }
+
+        Ok(())
+    }
+
+    pub fn deposit_spl_transfer<'info>(
+        &self,
+        amount: u64,
+        from: AccountInfo<'info>,
+        to: AccountInfo<'info>,
+        authority: AccountInfo<'info>,
+        maybe_mint: Option<&InterfaceAccount<'info, Mint>>,
+        program: AccountInfo<'info>,
+        remaining_accounts: &[AccountInfo<'info>],
+    ) -> MarginfiResult {
+        check!(
+            to.key.eq(&self.liquidity_vault),
+            MarginfiError::InvalidTransfer
+        );
+
+        debug!(
+            "deposit_spl_transfer: amount: {} from {} to {}, auth {}",
+            amount, from.key, to.key, authority.key
+        );
+
+        if let Some(mint) = maybe_mint {
+            spl_token_2022::onchain::invoke_transfer_checked(
+                program.key,
+                from,
+                mint.to_account_info(),
+                to,
+                authority,
+                remaining_accounts,
+                amount,
+                mint.decimals,
+                &[],
+            )?;
+        } else {
+            #[allow(deprecated)]
+            transfer(
+                CpiContext::new_with_signer(
+                    program,
+                    Transfer {
+                        from,
+                        to,
+                        authority,
+                    },
+                    &[],
+                ),
+                amount,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    pub fn withdraw_spl_transfer<'info>(
+        &self,
+        amount: u64,
+        from: AccountInfo<'info>,
+        to: AccountInfo<'info>,
+        authority: AccountInfo<'info>,
+        maybe_mint: Option<&InterfaceAccount<'info, Mint>>,
+        program: AccountInfo<'info>,
+        signer_seeds: &[&[&[u8]]],
+        remaining_accounts: &[AccountInfo<'info>],
+    ) -> MarginfiResult {
+        debug!(
+            "withdraw_spl_transfer: amount: {} from {} to {}, auth {}",
+            amount, from.key, to.key, authority.key
+        );
+
+        if let Some(mint) = maybe_mint {
+            spl_token_2022::onchain::invoke_transfer_checked(
+                program.key,
+                from,
+                mint.to_account_info(),
+                to,
+                authority,
+                remaining_accounts,
+                amount,
+                mint.decimals,
+                signer_seeds,
+            )?;
+        } else {
+            // `transfer_checked` and `transfer` does the same thing, the additional `_checked` logic
+            // is only to assert the expected attributes by the user (mint, decimal scaling),
+            //
+            // Security of `transfer` is equal to `transfer_checked`.
+            #[allow(deprecated)]
+            transfer(
+                CpiContext::new_with_signer(
+                    program,
+                    Transfer {
+                        from,
+                        to,
+                        authority,
+                    },
+                    signer_seeds,
+                ),
+                amount,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Socialize a loss `loss_amount` among depositors,
+    /// the `total_deposit_shares` stays the same, but total value of deposits is
+    /// reduced by `loss_amount`;
+    pub fn socialize_loss(&mut self, loss_amount: I80F48) -> MarginfiResult {
+        let total_asset_shares: I80F48 = self.total_asset_shares.into();
+        let old_asset_share_value: I80F48 = self.asset_share_value.into();
+
+        let new_share_value = total_asset_shares
+            .checked_mul(old_asset_share_value)
+            .ok_or_else(math_error!())?
+            .checked_sub(loss_amount)
+            .ok_or_else(math_error!())?
+            .checked_div(total_asset_shares)
+            .ok_or_else(math_error!())?;
+
+        self.asset_share_value = new_share_value.into();
+
+        Ok(())
+    }
+
+    /// Errors if the bank's operational state doesn't allow the balance change. A paused group
+    /// (any `GROUP_PAUSE_STATE_*` but `GROUP_PAUSE_STATE_NONE`) pauses all of its banks.
+    pub fn assert_operational_mode(
+        &self,
+        group: &MarginfiGroup,
+        is_asset_or_liability_amount_increasing: Option<bool>,
+    ) -> Result<()> {
+        check!(
+            group.pause_state == GROUP_PAUSE_STATE_NONE,
+            MarginfiError::GroupPaused
+        );
+
+        match self.config.operational_state {
+            BankOperationalState::Paused => Err(MarginfiError::BankPaused.into()),
+            BankOperationalState::Operational => Ok(()),
+            BankOperationalState::ReduceOnly => {
+                if let Some(is_asset_or_liability_amount_increasing) =
+                    is_asset_or_liability_amount_increasing
+                {
+                    check!(
+                        !is_asset_or_liability_amount_increasing,
+                        MarginfiError::BankReduceOnly
+                    );
+                }
+
+                Ok(())
+            }
+        }
+    }
+
+    pub fn get_flag(&self, flag: u64) -> bool {
+        (self.flags & flag) == flag
+    }
+
+    pub(crate) fn override_emissions_flag(&mut self, flag: u64) {
+        assert!(Self::verify_emissions_flags(flag));
+        self.flags = flag;
+    }
+
+    pub(crate) fn update_flag(&mut self, value: bool, flag: u64) {
+        assert!(Self::verify_group_flags(flag));
+
+        if value {
+            self.flags |= flag;
+        } else {
+            self.flags &= !flag;
+        }
+    }
+
+    const fn verify_emissions_flags(flags: u64) -> bool {
+        flags & EMISSION_FLAGS == flags
+    }
+
+    const fn verify_group_flags(flags: u64) -> bool {
+        flags & GROUP_FLAGS == flags
+    }
+}
+
+/// We use a simple interest rate model that auto settles the accrued interest into the lending account balances.
+/// The plan is to move to a compound interest model in the future.
+///
+/// Simple interest rate model:
+/// - `P` - principal
+/// - `i` - interest rate (per second)
+/// - `t` - time (in seconds)
+///
+/// `P_t = P_0 * (1 + i) * t`
+///
+/// We use two interest rates, one for lending and one for borrowing.
+///
+/// Lending interest rate:
+/// - `i_l` - lending interest rate
+/// - `i` - base interest rate
+/// - `ur` - utilization rate
+///
+/// `i_l` = `i` * `ur`
+///
+/// Borrowing interest rate:
+/// - `i_b` - borrowing interest rate
+/// - `i` - base interest rate
+/// - `f_i` - interest rate fee
+/// - `f_f` - fixed fee
+///
+/// `i_b = i * (1 + f_i) + f_f`
+///
+/// Fixed-term liabilities count toward utilization and accrue `fixed_term_annual_interest` at
+/// their locked rates. That interest is split between depositors and fees in the same proportions
+/// as `i_b`.
+#[allow(clippy::too_many_arguments)]
+fn calc_interest_rate_accrual_state_changes(
+    time_delta: u64,
+    total_assets_amount: I80F48,
+    total_liabilities_amount: I80F48,
+    fixed_term_liabilities_amount: I80F48,
+    fixed_term_annual_interest: I80F48,
+    interest_rate_calc: &InterestRateCalc,
+    asset_share_value: I80F48,
+    liability_share_value: I80F48,
+) -> Option<InterestRateStateChanges> {
+    let total_borrowed_amount =
+        total_liabilities_amount.checked_add(fixed_term_liabilities_amount)?;
+    let utilization_rate = total_borrowed_amount.checked_div(total_assets_amount)?;
+    let computed_rates = interest_rate_calc.calc_interest_rate(utilization_rate)?;
+
+    debug!(
+        "Utilization rate: {}, time delta {}s",
+        utilization_rate, time_delta
+    );
+    debug!("{:#?}", computed_rates);
+
+    let ComputedInterestRates {
+        lending_rate_apr,
+        borrowing_rate_apr,
+        group_fee_apr,
+        insurance_fee_apr,
+        protocol_fee_apr,
+    } = computed_rates;
+
+    let lending_rate_apr = lending_rate_apr
+        .checked_mul(total_liabilities_amount)?
+        .checked_div(total_borrowed_amount)?;
+
+    let fixed_term_interest =
+        calc_interest_payment_for_period(I80F48::ONE, time_delta, fixed_term_annual_interest)?;
+    let fixed_term_fee = |fee_apr: I80F48| -> Option<I80F48> {
+        if borrowing_rate_apr.is_zero() {
+            return Some(I80F48::ZERO);
+        }
+
+        fixed_term_interest
+            .checked_mul(fee_apr)?
+            .checked_div(borrowing_rate_apr)
+    };
+    let fixed_term_insurance_fees = fixed_term_fee(insurance_fee_apr)?;
+    let fixed_term_group_fees = fixed_term_fee(group_fee_apr)?;
+    let fixed_term_protocol_fees = fixed_term_fee(protocol_fee_apr)?;
+    let fixed_term_deposit_interest = fixed_term_interest
+        .checked_sub(fixed_term_insurance_fees)?
+        .checked_sub(fixed_term_group_fees)?
+        .checked_sub(fixed_term_protocol_fees)?
+        .max(I80F48::ZERO);
+
+    Some(InterestRateStateChanges {
+        new_asset_share_value: calc_accrued_interest_payment_per_period(
+            lending_rate_apr,
+            time_delta,
+            asset_share_value,
+        )?
+        .checked_add(
+            fixed_term_deposit_interest
+                .checked_mul(asset_share_value)?
+                .checked_div(total_assets_amount)?,
+        )?,
+        new_liability_share_value: calc_accrued_interest_payment_per_period(
+            borrowing_rate_apr,
+            time_delta,
+            liability_share_value,
+        )?,
+        insurance_fees_collected: calc_interest_payment_for_period(
+            insurance_fee_apr,
+            time_delta,
+            total_liabilities_amount,
+        )?
+        .checked_add(fixed_term_insurance_fees)?,
+        group_fees_collected: calc_interest_payment_for_period(
+            group_fee_apr,
+            time_delta,
+            total_liabilities_amount,
+        )?
+        .checked_add(fixed_term_group_fees)?,
+        protocol_fees_collected: calc_interest_payment_for_period(
+            protocol_fee_apr,
+            time_delta,
+            total_liabilities_amount,
+        )?
+        .checked_add(fixed_term_protocol_fees)?,
+        fixed_term_interest_accrued: fixed_term_interest,
+    })
+}
+
+struct InterestRateStateChanges {
+    new_asset_share_value: I80F48,
+    new_liability_share_value: I80F48,
+    insurance_fees_collected: I80F48,
+    group_fees_collected: I80F48,
+    protocol_fees_collected: I80F48,
+    fixed_term_interest_accrued: I80F48,
+}
+
+/// Calculates the fee rate for a given base rate and fees specified.
+/// The returned rate is only the fee rate without the base rate.
+///
+/// Used for calculating the fees charged to the borrowers.
+fn calc_fee_rate(base_rate: I80F48, rate_fees: I80F48, fixed_fees: I80F48) -> Option<I80F48> {
+    if rate_fees.is_zero() {
+        return Some(fixed_fees);
+    }
+
+    base_rate.checked_mul(rate_fees)?.checked_add(fixed_fees)
+}
+
+/// Calculates the accrued interest payment per period `time_delta` in a principal value `value` for interest rate (in APR) `arp`.
+/// Result is the new principal value.
+fn calc_accrued_interest_payment_per_period(
+    apr: I80F48,
+    time_delta: u64,
+    value: I80F48,
+) -> Option<I80F48> {
+    let ir_per_period = apr
+        .checked_mul(time_delta.into())?
+        .checked_div(SECONDS_PER_YEAR)?;
+
+    let new_value = value.checked_mul(I80F48::ONE.checked_add(ir_per_period)?)?;
+
+    Some(new_value)
+}
+
+/// Calculates the interest payment for a given period `time_delta` in a principal value `value` for interest rate (in APR) `arp`.
+/// Result is the interest payment.
+fn calc_interest_payment_for_period(apr: I80F48, time_delta: u64, value: I80F48) -> Option<I80F48> {
+    if apr.is_zero() {
+        return Some(I80F48::ZERO);
+    }
+
+    let interest_payment = value
+        .checked_mul(apr)?
+        .checked_mul(time_delta.into())?
+        .checked_div(SECONDS_PER_YEAR)?;
+
+    Some(interest_payment)
+}
+
+#[repr(u8)]
+#[cfg_attr(any(feature = "test", feature = "client"), derive(PartialEq, Eq))]
+#[derive(Copy, Clone, Debug, AnchorSerialize, AnchorDeserialize)]
+pub enum BankOperationalState {
+    Paused,
+    Operational,
+    ReduceOnly,
+}
+
+#[cfg(feature = "client")]
+impl Display for BankOperationalState {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BankOperationalState::Paused => write!(f, "Paused"),
+            BankOperationalState::Operational => write!(f, "Operational"),
+            BankOperationalState::ReduceOnly => write!(f, "ReduceOnly"),
+        }
+    }
+}
+
+#[repr(u8)]
+#[derive(Copy, Clone, Debug, AnchorSerialize, AnchorDeserialize, PartialEq, Eq)]
+pub enum RiskTier {
+    Collateral,
+    /// ## Isolated Risk
+    /// Assets in this trance can be borrowed only in isolation.
+    /// They can't be borrowed together with other assets.
+    ///
+    /// For example, if users has USDC, and wants to borrow XYZ which is isolated,
+    /// they can't borrow XYZ together with SOL, only XYZ alone.
+    Isolated,
+    /// ## Isolated Collateral
+    /// Assets in this tranche can back debt only up to the bank's `isolated_debt_ceiling`,
+    /// counted across all accounts of the group.
+    ///
+    /// For example, if XYZ has a debt ceiling of $1M, an account holding 10% of the XYZ deposits
+    /// can count them for at most $100k toward its initial health, whatever they are worth. An
+    /// account can hold only one such deposit while it has liabilities.
+    IsolatedCollateral,
+}
+
+#[repr(C)]
+#[cfg_attr(
+    any(feature = "test", feature = "client"),
+    derive(PartialEq, Eq, TypeLayout)
+)]
+#[derive(AnchorDeserialize, AnchorSerialize, Debug)]
+/// TODO: Convert weights to (u64, u64) to avoid precision loss (maybe?)
+pub struct BankConfigCompact {
+    pub asset_weight_init: WrappedI80F48,
+    pub asset_weight_maint: WrappedI80F48,
+
+    pub liability_weight_init: WrappedI80F48,
+    pub liability_weight_maint: WrappedI80F48,
+
+    pub deposit_limit: u64,
+
+    pub interest_rate_config: InterestRateConfigCompact,
+    pub operational_state: BankOperationalState,
+
+    pub oracle_setup: OracleSetup,
+    pub oracle_key: Pubkey,
+
+    pub borrow_limit: u64,
+
+    pub risk_tier: RiskTier,
+
+    pub _pad0: [u8; 7],
+
+    /// USD denominated limit for calculating asset value for initialization margin requirements.
+    /// Example, if total SOL deposits are equal to $1M and the limit it set to $500K,
+    /// then SOL assets will be discounted by 50%.
+    ///
+    /// In other words the max value of liabilities that can be backed by the asset is $500K.
+    /// This is useful for limiting the damage of orcale attacks.
+    ///
+    /// Value is UI USD value, for example value 100 -> $100
+    pub total_asset_value_init_limit: u64,
+
+    /// Time window in seconds for the oracle price feed to be considered live.
+    pub oracle_max_age: u16,
+}
+
+impl From<BankConfigCompact> for BankConfig {
+    fn from(config: BankConfigCompact) -> Self {
+        let keys = [
+            config.oracle_key,
+            Pubkey::default(),
+            Pubkey::default(),
+            Pubkey::default(),
+            Pubkey::default(),
+        ];
+        Self {
+            asset_weight_init: config.asset_weight_init,
+            asset_weight_maint: config.asset_weight_maint,
+            liability_weight_init: config.liability_weight_init,
+            liability_weight_maint: config.liability_weight_maint,
+            deposit_limit: config.deposit_limit,
+            interest_rate_config: config.interest_rate_config.into(),
+            operational_state: config.operational_state,
+            oracle_setup: config.oracle_setup,
+            oracle_keys: keys,
+            oracle_source_setups: [OracleSetup::None; MAX_ORACLE_KEYS],
+            oracle_fallback_setup: OracleSetup::None,
+            borrow_limit: config.borrow_limit,
+            risk_tier: config.risk_tier,
+            _pad1: [0; 7],
+            total_asset_value_init_limit: config.total_asset_value_init_limit,
+            oracle_max_age: config.oracle_max_age,
+            oracle_max_deviation_bps: 0,
+            liquidation_liquidator_fee_bps: 0,
+            liquidation_max_liquidator_fee_bps: 0,
+            liquidation_fee_ramp_shortfall_bps: 0,
+            liquidation_insurance_fee_bps: 0,
+            liquidation_close_factor_bps: 0,
+            liquidation_close_factor_shortfall_bps: 0,
+            liquidation_fee_flags: 0,
+            _padding: [0; 23],
+        }
+    }
+}
+
+impl From<BankConfig> for BankConfigCompact {
+    fn from(config: BankConfig) -> Self {
+        Self {
+            asset_weight_init: config.asset_weight_init,
+            asset_weight_maint: config.asset_weight_maint,
+            liability_weight_init: config.liability_weight_init,
+            liability_weight_maint: config.liability_weight_maint,
+            deposit_limit: config.deposit_limit,
+            interest_rate_config: config.interest_rate_config.into(),
+            operational_state: config.operational_state,
+            oracle_setup: config.oracle_setup,
+            oracle_key: config.oracle_keys[0],
+            borrow_limit: config.borrow_limit,
+            risk_tier: config.risk_tier,
+            _pad0: [0; 7],
+            total_asset_value_init_limit: config.total_asset_value_init_limit,
+            oracle_max_age: config.oracle_max_age,
+        }
+    }
+}
+
+assert_struct_size!(BankConfig, 544);
+assert_struct_align!(BankConfig, 8);
+#[zero_copy(unsafe)]
+#[repr(C)]
+#[cfg_attr(
+    any(feature = "test", feature = "client"),
+    derive(PartialEq, Eq, TypeLayout)
+)]
+#[derive(Debug)]
+/// TODO: Convert weights to (u64, u64) to avoid precision loss (maybe?)
+pub struct BankConfig {
+    pub asset_weight_init: WrappedI80F48,
+    pub asset_weight_maint: WrappedI80F48,
+
+    pub liability_weight_init: WrappedI80F48,
+    pub liability_weight_maint: WrappedI80F48,
+
+    pub deposit_limit: u64,
+
+    pub interest_rate_config: InterestRateConfig,
+    pub operational_state: BankOperationalState,
+
+    pub oracle_setup: OracleSetup,
+    pub oracle_keys: [Pubkey; MAX_ORACLE_KEYS],
+
+    /// Setup of each `oracle_keys` entry, for oracle setups that combine several feeds
+    /// (`OracleSetup::Median`). Unused slots are `OracleSetup::None`.
+    pub oracle_source_setups: [OracleSetup; MAX_ORACLE_KEYS],
+
+    /// Setup of the fallback feed at `oracle_keys[1]`, used only while the primary feed is stale.
+    /// `OracleSetup::None` disables the fallback. Not supported for multi-feed setups.
+    pub oracle_fallback_setup: OracleSetup,
+
+    // Note: Pubkey is aligned 1, so borrow_limit is the first aligned-8 value after deposit_limit
+    // Bank state (1) + Oracle Setup (1) + Source setups (5) + Fallback setup (1) = 8
+    pub borrow_limit: u64,
+
+    pub risk_tier: RiskTier,
+
+    pub _pad1: [u8; 7],
+
+    /// USD denominated limit for calculating asset value for initialization margin requirements.
+    /// Example, if total SOL deposits are equal to $1M and the limit it set to $500K,
+    /// then SOL assets will be discounted by 50%.
+    ///
+    /// In other words the max value of liabilities that can be backed by the asset is $500K.
+    /// This is useful for limiting the damage of orcale attacks.
+    ///
+    /// Value is UI USD value, for example value 100 -> $100
+    pub total_asset_value_init_limit: u64,
+
+    /// Time window in seconds for the oracle price feed to be considered live.
+    pub oracle_max_age: u16,
+
+    /// Max spread between the prices of a multi-feed oracle setup, relative to their median, in
+    /// basis points. Beyond it, the bank is priced conservatively, see `MedianPriceFeed`.
+    /// For `OracleSetup::Peg`, the band around the fixed price in which the peg holds.
+    ///
+    /// 0 disables the check.
+    pub oracle_max_deviation_bps: u16,
+
+    /// Share of the liquidated collateral value that goes to the liquidator, in basis points.
+    /// Charged when this bank's deposits are seized in a liquidation.
+    ///
+    /// The default `LIQUIDATION_LIQUIDATOR_FEE` is used until the bank configures its own.
+    pub liquidation_liquidator_fee_bps: u16,
+    /// Liquidator fee charged when this bank's deposits are seized from a deeply unhealthy account,
+    /// in basis points. The fee ramps linearly from the liquidator fee up to this value as the
+    /// liquidatee's maintenance health falls below zero (see `BankConfig::get_liquidator_fee`).
+    ///
+    /// The ramp is disabled, the liquidator fee always charged, until the bank configures it.
+    pub liquidation_max_liquidator_fee_bps: u16,
+    /// Shortfall, as a share of the liquidatee's maintenance-weighted liabilities in basis points,
+    /// at which the liquidator fee reaches `liquidation_max_liquidator_fee_bps`.
+    ///
+    /// The default `LIQUIDATION_FEE_RAMP_SHORTFALL` is used until the bank configures its own.
+    pub liquidation_fee_ramp_shortfall_bps: u16,
+    /// Share of the liquidated collateral value that goes to the liability bank's insurance fund,
+    /// in basis points. Charged when this bank's deposits are seized in a liquidation.
+    ///
+    /// The default `LIQUIDATION_INSURANCE_FEE` is used until the bank configures its own.
+    pub liquidation_insurance_fee_bps: u16,
+    /// Max share of the liquidatee's liability in this bank that a single liquidation can repay,
+    /// in basis points, e.g. 5000 = 50%.
+    ///
+    /// 0 disables the close factor, liquidations are only limited by the post liquidation health
+    /// check.
+    pub liquidation_close_factor_bps: u16,
+    /// Shortfall, as a share of the liquidatee's maintenance-weighted liabilities in basis points,
+    /// at or above which the close factor no longer applies and the liability can be repaid in a
+    /// single liquidation.
+    ///
+    /// 0 means the close factor always applies.
+    pub liquidation_close_factor_shortfall_bps: u16,
+
+    /// `LIQUIDATION_*_SET_FLAG`s, set once the bank configures the corresponding fee or fee ramp
+    /// parameter.
+    pub liquidation_fee_flags: u8,
+
+    pub _padding: [u8; 23],
+}
+
+impl Default for BankConfig {
+    fn default() -> Self {
+        Self {
+            asset_weight_init: I80F48::ZERO.into(),
+            asset_weight_maint: I80F48::ZERO.into(),
+            liability_weight_init: I80F48::ONE.into(),
+            liability_weight_maint: I80F48::ONE.into(),
+            deposit_limit: 0,
+            borrow_limit: 0,
+            interest_rate_config: Default::default(),
+            operational_state: BankOperationalState::Paused,
+            oracle_setup: OracleSetup::None,
+            oracle_keys: [Pubkey::default(); MAX_ORACLE_KEYS],
+            oracle_source_setups: [OracleSetup::None; MAX_ORACLE_KEYS],
+            oracle_fallback_setup: OracleSetup::None,
+            risk_tier: RiskTier::Isolated,
+            _pad1: [0; 7],
+            total_asset_value_init_limit: TOTAL_ASSET_VALUE_INIT_LIMIT_INACTIVE,
+            oracle_max_age: 0,
+            oracle_max_deviation_bps: 0,
+            liquidation_liquidator_fee_bps: 0,
+            liquidation_max_liquidator_fee_bps: 0,
+            liquidation_fee_ramp_shortfall_bps: 0,
+            liquidation_insurance_fee_bps: 0,
+            liquidation_close_factor_bps: 0,
+            liquidation_close_factor_shortfall_bps: 0,
+            liquidation_fee_flags: 0,
+            _padding: [0; 23],
+        }
+    }
+}
+
+#[inline]
+fn bps_to_ratio(bps: u16) -> I80F48 {
+    I80F48::from_num(bps) / I80F48::from_num(10_000)
+}
+
+impl BankConfig {
+    #[inline]
+    pub fn get_weights(&self, req_type: RequirementType) -> (I80F48, I80F48) {
+        match req_type {
+            RequirementType::Initial => (
+                self.asset_weight_init.into(),
+                self.liability_weight_init.into(),
+            ),
+            RequirementType::Maintenance => (
+                self.asset_weight_maint.into(),
+                self.liability_weight_maint.into(),
+            ),
+            RequirementType::Equity => (I80F48::ONE, I80F48::ONE),
+        }
+    }
+
+    #[inline]
+    pub fn get_weight(
+        &self,
+        requirement_type: RequirementType,
+        balance_side: BalanceSide,
+    ) -> I80F48 {
+        match (requirement_type, balance_side) {
+            (RequirementType::Initial, BalanceSide::Assets) => self.asset_weight_init.into(),
+            (RequirementType::Initial, BalanceSide::Liabilities) => {
+                self.liability_weight_init.into()
+            }
+            (RequirementType::Maintenance, BalanceSide::Assets) => self.asset_weight_maint.into(),
+            (RequirementType::Maintenance, BalanceSide::Liabilities) => {
+                self.liability_weight_maint.into()
+            }
+            (RequirementType::Equity, _) => I80F48::ONE,
+        }
+    }
+
+    pub fn validate(&self) -> MarginfiResult {
+        let asset_init_w = I80F48::from(self.asset_weight_init);
+        let asset_maint_w = I80F48::from(self.asset_weight_maint);
+
+        check!(
+            asset_init_w >= I80F48::ZERO && asset_init_w <= I80F48::ONE,
+            MarginfiError::InvalidConfig
+        );
+        check!(asset_maint_w >= asset_init_w, MarginfiError::InvalidConfig);
+
+        let liab_init_w = I80F48::from(self.liability_weight_init);
+        let liab_maint_w = I80F48::from(self.liability_weight_maint);
+
+        check!(liab_init_w >= I80F48::ONE, MarginfiError::InvalidConfig);
+        check!(
+            liab_maint_w <= liab_init_w && liab_maint_w >= I80F48::ONE,
+            MarginfiError::InvalidConfig
+        );
+
+        self.interest_rate_config.validate()?;
+
+        self.validate_liquidation_fees()?;
+
+        if self.risk_tier == RiskTier::Isolated {
+            check!(asset_init_w == I80F48::ZERO, MarginfiError::InvalidConfig);
+            check!(asset_maint_w == I80F48::ZERO, MarginfiError::InvalidConfig);
+        }
+
+        Ok(())
+    }
+
+    #[inline]
+    pub fn is_deposit_limit_active(&self) -> bool {
+        self.deposit_limit != u64::MAX
+    }
+
+    #[inline]
+    pub fn is_borrow_limit_active(&self) -> bool {
+        self.borrow_limit != u64::MAX
+    }
+
+    pub fn usd_init_limit_active(&self) -> bool {
+        self.total_asset_value_init_limit != TOTAL_ASSET_VALUE_INIT_LIMIT_INACTIVE
+    }
+
+    /// Oracle feeds the bank is priced from, in the order their accounts are expected.
+    pub fn get_oracle_sources(&self) -> Vec<(OracleSetup, Pubkey)> {
+        match self.oracle_setup {
+            OracleSetup::Median => self
+                .oracle_source_setups
+                .iter()
+                .zip(self.oracle_keys.iter())
+                .take_while(|(oracle_setup, _)| !matches!(oracle_setup, OracleSetup::None))
+                .map(|(oracle_setup, key)| (*oracle_setup, *key))
+                .collect(),
+            OracleSetup::Fixed => vec![],
+            OracleSetup::Peg => vec![(self.oracle_source_setups[0], self.oracle_keys[0])],
+            OracleSetup::StakePool => vec![
+                (OracleSetup::PythPushOracle, self.oracle_keys[0]),
+                (OracleSetup::StakePool, self.oracle_keys[1]),
+            ],
+            oracle_setup => {
+                let mut sources = vec![(oracle_setup, self.oracle_keys[0])];
+
+                if self.has_oracle_fallback() {
+                    sources.push((self.oracle_fallback_setup, self.oracle_keys[1]));
+                }
+
+                sources
+            }
+        }
+    }
+
+    #[inline]
+    pub fn has_oracle_fallback(&self) -> bool {
+        !matches!(self.oracle_fallback_setup, OracleSetup::None)
+    }
+
+    /// Number of oracle accounts following the bank in remaining accounts.
+    #[inline]
+    pub fn get_oracle_ais_len(&self) -> usize {
+        match self.oracle_setup {
+            OracleSetup::Median => self
+                .oracle_source_setups
+                .iter()
+                .take_while(|oracle_setup| !matches!(oracle_setup, OracleSetup::None))
+                .count(),
+            OracleSetup::Fixed => 0,
+            OracleSetup::Peg => 1,
+            OracleSetup::StakePool => 2,
+            _ if self.has_oracle_fallback() => 2,
+            _ => 1,
+        }
+    }
+
+    #[inline]
+    pub fn get_oracle_max_deviation(&self) -> I80F48 {
+        I80F48::from_num(self.oracle_max_deviation_bps) / I80F48::from_num(10_000)
+    }
+
+    /// Median banks default to the strictest default max age of their sources, so a Pyth source
+    /// is held to the same freshness as in a Pyth bank. A peg only reads its feed as a guard, so
+    /// it defaults to the most lenient max age.
+    #[inline]
+    pub fn get_oracle_max_age(&self) -> u64 {
+        match (self.oracle_max_age, self.oracle_setup) {
+            (0, OracleSetup::Median) => self
+                .oracle_source_setups
+                .iter()
+                .take_while(|oracle_setup| !matches!(oracle_setup, OracleSetup::None))
+                .map(|oracle_setup| match oracle_setup {
+                    OracleSetup::PythLegacy | OracleSetup::PythPushOracle => MAX_PYTH_ORACLE_AGE,
+                    _ => MAX_SWB_ORACLE_AGE,
+                })
+                .min()
+                .unwrap_or(MAX_SWB_ORACLE_AGE),
+            (0, OracleSetup::SwitchboardV2 | OracleSetup::Peg) => MAX_SWB_ORACLE_AGE,
+            (0, OracleSetup::PythLegacy | OracleSetup::PythPushOracle | OracleSetup::StakePool) => {
+                MAX_PYTH_ORACLE_AGE
+            }
+            (n, _) => n as u64,
+        }
+    }
+
+    #[inline]
+    pub fn get_liquidation_liquidator_fee(&self) -> I80F48 {
+        if self.liquidation_fee_flags & LIQUIDATION_LIQUIDATOR_FEE_SET_FLAG == 0 {
+            LIQUIDATION_LIQUIDATOR_FEE
+        } else {
+            bps_to_ratio(self.liquidation_liquidator_fee_bps)
+        }
+    }
+
+    #[inline]
+    pub fn get_liquidation_insurance_fee(&self) -> I80F48 {
+        if self.liquidation_fee_flags & LIQUIDATION_INSURANCE_FEE_SET_FLAG == 0 {
+            LIQUIDATION_INSURANCE_FEE
+        } else {
+            bps_to_ratio(self.liquidation_insurance_fee_bps)
+        }
+    }
+
+    /// `None` until the bank configures a max liquidator fee, the fee ramp is disabled then.
+    #[inline]
+    pub fn get_liquidation_max_liquidator_fee(&self) -> Option<I80F48> {
+        if self.liquidation_fee_flags & LIQUIDATION_MAX_LIQUIDATOR_FEE_SET_FLAG == 0 {
+            None
+        } else {
+            Some(bps_to_ratio(self.liquidation_max_liquidator_fee_bps))
+        }
+    }
+
+    #[inline]
+    pub fn get_liquidation_fee_ramp_shortfall(&self) -> I80F48 {
+        if self.liquidation_fee_flags & LIQUIDATION_FEE_RAMP_SHORTFALL_SET_FLAG == 0 {
+            LIQUIDATION_FEE_RAMP_SHORTFALL
+        } else {
+            bps_to_ratio(self.liquidation_fee_ramp_shortfall_bps)
+        }
+    }
+
+    /// Liquidator fee charged for seizing this bank's deposits from an account with the given
+    /// maintenance `shortfall`, expressed as a share of the account's maintenance-weighted
+    /// liabilities (0 = just at the maintenance requirement).
+    ///
+    /// Ramps linearly from the liquidator fee to `liquidation_max_liquidator_fee_bps`, which is
+    /// reached once `shortfall` hits `get_liquidation_fee_ramp_shortfall()`.
+    pub fn get_liquidator_fee(&self, shortfall: I80F48) -> MarginfiResult<I80F48> {
+        let min_fee = self.get_liquidation_liquidator_fee();
+        let max_fee = match self.get_liquidation_max_liquidator_fee() {
+            Some(max_fee) if max_fee > min_fee => max_fee,
+            _ => return Ok(min_fee),
+        };
+
+        if shortfall <= I80F48::ZERO {
+            return Ok(min_fee);
+        }
+
+        let ramp_shortfall = self.get_liquidation_fee_ramp_shortfall();
+        let ramp = if ramp_shortfall.is_zero() {
+            I80F48::ONE
+        } else {
+            shortfall
+                .checked_div(ramp_shortfall)
+                .ok_or_else(math_error!())?
+                .min(I80F48::ONE)
+        };
+
+        Ok(min_fee
+            .checked_add(
+                max_fee
+                    .checked_sub(min_fee)
+                    .ok_or_else(math_error!())?
+                    .checked_mul(ramp)
+                    .ok_or_else(math_error!())?,
+            )
+            .ok_or_else(math_error!())?)
+    }
+
+    /// Max amount of liability in this bank that a single liquidation can repay, given the
+    /// liquidatee's liability `liab_amount` and its maintenance `shortfall`
+    /// (see `BankConfig::get_liquidator_fee`).
+    ///
+    /// `None` if the close factor is disabled or the shortfall is past
+    /// `liquidation_close_factor_shortfall_bps`.
+    pub fn get_max_liquidation_repay_amount(
+        &self,
+        liab_amount: I80F48,
+        shortfall: I80F48,
+    ) -> MarginfiResult<Option<I80F48>> {
+        if self.liquidation_close_factor_bps == 0
+            || (self.liquidation_close_factor_shortfall_bps != 0
+                && shortfall >= bps_to_ratio(self.liquidation_close_factor_shortfall_bps))
+        {
+            return Ok(None);
+        }
+
+        Ok(Some(
+            liab_amount
+                .checked_mul(bps_to_ratio(self.liquidation_close_factor_bps))
+                .ok_or_else(math_error!())?,
+        ))
+    }
+
+    /// The liquidator and insurance fees must leave part of the seized collateral value to the
+    /// liquidatee. The liquidator fee ramp must end at or above the liquidator fee and must also
+    /// leave room for the insurance fee. The close factor is at most the whole liability.
+    fn validate_liquidation_fees(&self) -> MarginfiResult {
+        let liquidator_fee = self.get_liquidation_liquidator_fee();
+        let insurance_fee = self.get_liquidation_insurance_fee();
+
+        check!(
+            liquidator_fee + insurance_fee < I80F48::ONE,
+            MarginfiError::InvalidConfig
+        );
+
+        if let Some(max_liquidator_fee) = self.get_liquidation_max_liquidator_fee() {
+            check!(
+                max_liquidator_fee >= liquidator_fee,
+                MarginfiError::InvalidConfig
+            );
+            check!(
+                max_liquidator_fee + insurance_fee < I80F48::ONE,
+                MarginfiError::InvalidConfig
+            );
+        }
+
+        check!(
+            self.liquidation_close_factor_bps <= 10_000,
+            MarginfiError::InvalidConfig
+        );
+
+        Ok(())
+    }
+
+    pub fn get_pyth_push_oracle_feed_id(&self) -> Option<&FeedId> {
+        if matches!(self.oracle_setup, OracleSetup::PythPushOracle) {
+            let bytes: &[u8; 32] = self.oracle_keys[0].as_ref().try_into().unwrap();
+            Some(bytes)
+        } else {
+            None
+        }
+    }
+}
+
+#[zero_copy]
+#[repr(C, align(8))]
+#[cfg_attr(any(feature = "test", feature = "client"), derive(TypeLayout))]
+#[derive(Default, BorshDeserialize, BorshSerialize)]
+pub struct WrappedI80F48 {
+    pub value: [u8; 16],
+}
+
+impl Debug for WrappedI80F48 {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", I80F48::from_le_bytes(self.value))
+    }
+}
+
+impl From<I80F48> for WrappedI80F48 {
+    fn from(i: I80F48) -> Self {
+        Self {
+            value: i.to_le_bytes(),
+        }
+    }
+}
+
+impl From<WrappedI80F48> for I80F48 {
+    fn from(w: WrappedI80F48) -> Self {
+        Self::from_le_bytes(w.value)
+    }
+}
+
+impl PartialEq for WrappedI80F48 {
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value
+    }
+}
+
+impl Eq for WrappedI80F48 {}
+
+#[cfg_attr(
+    any(feature = "test", feature = "client"),
+    derive(PartialEq, Eq, TypeLayout)
+)]
+#[derive(AnchorDeserialize, AnchorSerialize, Default, Clone)]
+pub struct BankConfigOpt {
+    pub asset_weight_init: Option<WrappedI80F48>,
+    pub asset_weight_maint: Option<WrappedI80F48>,
+
+    pub liability_weight_init: Option<WrappedI80F48>,
+    pub liability_weight_maint: Option<WrappedI80F48>,
+
+    pub deposit_limit: Option<u64>,
+    pub borrow_limit: Option<u64>,
+
+    pub operational_state: Option<BankOperationalState>,
+
+    pub oracle: Option<OracleConfig>,
+
+    pub interest_rate_config: Option<InterestRateConfigOpt>,
+
+    pub risk_tier: Option<RiskTier>,
+
+    pub total_asset_value_init_limit: Option<u64>,
+
+    pub oracle_max_age: Option<u16>,
+
+    pub permissionless_bad_debt_settlement: Option<bool>,
+
+    pub liquidation_liquidator_fee_bps: Option<u16>,
+    pub liquidation_max_liquidator_fee_bps: Option<u16>,
+    pub liquidation_fee_ramp_shortfall_bps: Option<u16>,
+    pub liquidation_insurance_fee_bps: Option<u16>,
+
+    pub liquidation_close_factor_bps: Option<u16>,
+    pub liquidation_close_factor_shortfall_bps: Option<u16>,
+
+    pub fixed_term_premium: Option<WrappedI80F48>,
+    pub fixed_term_max_duration: Option<u64>,
+
+    pub oracle_max_deviation_bps: Option<u16>,
+
+    pub fixed_price: Option<WrappedI80F48>,
+
+    pub twap_window: Option<u64>,
+
+    pub oracle_conf_interval_multiple: Option<WrappedI80F48>,
+    pub oracle_max_conf_interval: Option<WrappedI80F48>,
+    pub oracle_conf_interval_mode: Option<u8>,
+
+    pub emode_category: Option<u16>,
+    pub emode_asset_weight_init: Option<WrappedI80F48>,
+    pub emode_asset_weight_maint: Option<WrappedI80F48>,
+    pub emode_liability_weight_init: Option<WrappedI80F48>,
+    pub emode_liability_weight_maint: Option<WrappedI80F48>,
+
+    pub pair_class: Option<u8>,
+    pub collateral_for_classes: Option<u64>,
+    pub accepted_collateral_classes: Option<u64>,
+
+    pub isolated_debt_ceiling: Option<u64>,
+
+    pub deposit_limit_usd: Option<u64>,
+    pub borrow_limit_usd: Option<u64>,
+
+    pub outflow_window: Option<u64>,
+    pub outflow_limit: Option<u64>,
+    pub outflow_limit_bps: Option<u16>,
+
+    pub price_guard_max_move_bps: Option<u16>,
+    pub price_guard_interval: Option<u64>,
+    /// Clears a tripped price guard and its latest recorded price.
+    pub price_guard_reset: Option<bool>,
+}
+
+impl BankConfigOpt {
+    /// True if the config sets nothing but the asset weights, neither above the bank's current
+    /// weight. Such configs skip the group's `config_delay`.
+    pub fn only_lowers_asset_weights(&self, bank_config: &BankConfig) -> bool {
+        // Destructured without `..`, so that new fields have to be added to the check
+        let BankConfigOpt {
+            asset_weight_init,
+            asset_weight_maint,
+            liability_weight_init,
+            liability_weight_maint,
+            deposit_limit,
+            borrow_limit,
+            operational_state,
+            oracle,
+            interest_rate_config,
+            risk_tier,
+            total_asset_value_init_limit,
+            oracle_max_age,
+            permissionless_bad_debt_settlement,
+            liquidation_liquidator_fee_bps,
+            liquidation_max_liquidator_fee_bps,
+            liquidation_fee_ramp_shortfall_bps,
+            liquidation_insurance_fee_bps,
+            liquidation_close_factor_bps,
+            liquidation_close_factor_shortfall_bps,
+            fixed_term_premium,
+            fixed_term_max_duration,
+            oracle_max_deviation_bps,
+            fixed_price,
+            twap_window,
+            oracle_conf_interval_multiple,
+            oracle_max_conf_interval,
+            oracle_conf_interval_mode,
+            emode_category,
+            emode_asset_weight_init,
+            emode_asset_weight_maint,
+            emode_liability_weight_init,
+            emode_liability_weight_maint,
+            pair_class,
+            collateral_for_classes,
+            accepted_collateral_classes,
+            isolated_debt_ceiling,
+            deposit_limit_usd,
+            borrow_limit_usd,
+            outflow_window,
+            outflow_limit,
+            outflow_limit_bps,
+            price_guard_max_move_bps,
+            price_guard_interval,
+            price_guard_reset,
+        } = self;
+
+        let rest_unset = liability_weight_init.is_none()
+            && liability_weight_maint.is_none()
+            && deposit_limit.is_none()
+            && borrow_limit.is_none()
+            && operational_state.is_none()
+            && oracle.is_none()
+            && interest_rate_config.is_none()
+            && risk_tier.is_none()
+            && total_asset_value_init_limit.is_none()
+            && oracle_max_age.is_none()
+            && permissionless_bad_debt_settlement.is_none()
+            && liquidation_liquidator_fee_bps.is_none()
+            && liquidation_max_liquidator_fee_bps.is_none()
+            && liquidation_fee_ramp_shortfall_bps.is_none()
+            && liquidation_insurance_fee_bps.is_none()
+            && liquidation_close_factor_bps.is_none()
+            && liquidation_close_factor_shortfall_bps.is_none()
+            && fixed_term_premium.is_none()
+            && fixed_term_max_duration.is_none()
+            && oracle_max_deviation_bps.is_none()
+            && fixed_price.is_none()
+            && twap_window.is_none()
+            && oracle_conf_interval_multiple.is_none()
+            && oracle_max_conf_interval.is_none()
+            && oracle_conf_interval_mode.is_none()
+            && emode_category.is_none()
+            && emode_asset_weight_init.is_none()
+            && emode_asset_weight_maint.is_none()
+            && emode_liability_weight_init.is_none()
+            && emode_liability_weight_maint.is_none()
+            && pair_class.is_none()
+            && collateral_for_classes.is_none()
+            && accepted_collateral_classes.is_none()
+            && isolated_debt_ceiling.is_none()
+            && deposit_limit_usd.is_none()
+            && borrow_limit_usd.is_none()
+            && outflow_window.is_none()
+            && outflow_limit.is_none()
+            && outflow_limit_bps.is_none()
+            && price_guard_max_move_bps.is_none()
+            && price_guard_interval.is_none()
+            && price_guard_reset.is_none();
+
+        let lowers = |weight: &Option<WrappedI80F48>, current: WrappedI80F48| {
+            weight.map_or(true, |w| I80F48::from(w) <= I80F48::from(current))
+        };
+
+        rest_unset
+            && lowers(asset_weight_init, bank_config.asset_weight_init)
+            && lowers(asset_weight_maint, bank_config.asset_weight_maint)
+    }
+}
+
+/// A `BankConfigOpt` queued by the risk admin, applied to `bank` once `eta` has passed.
+#[account]
+pub struct PendingBankConfig {
+    pub bank: Pubkey,
+    /// Receives the rent when the config is executed or cancelled.
+    pub payer: Pubkey,
+    pub eta: i64,
+    pub config: BankConfigOpt,
+}
+
+impl PendingBankConfig {
+    /// Account space needed to queue `config`, discriminator included.
+    pub fn space(config: &BankConfigOpt) -> MarginfiResult<usize> {
+        let config_len = config
+            .try_to_vec()
+            .map_err(|_| MarginfiError::InvalidConfig)?
+            .len();
+
+        Ok(8 + 32 + 32 + 8 + config_len)
+    }
+}
+
+#[cfg_attr(
+    any(feature = "test", feature = "client"),
+    derive(PartialEq, Eq, TypeLayout)
+)]
+#[derive(Clone, Copy, AnchorDeserialize, AnchorSerialize, Debug)]
+pub struct OracleConfig {
+    pub setup: OracleSetup,
+    pub keys: [Pubkey; MAX_ORACLE_KEYS],
+    /// Setup of each key, only used by multi-feed setups.
+    pub source_setups: [OracleSetup; MAX_ORACLE_KEYS],
+    /// Setup of the fallback feed at `keys[1]`, `OracleSetup::None` disables the fallback.
+    pub fallback_setup: OracleSetup,
+}
+
+#[derive(Debug, Clone)]
+pub enum BankVaultType {
+    Liquidity,
+    Insurance,
+    Fee,
+}
+
+impl BankVaultType {
+    pub fn get_seed(self) -> &'static [u8] {
+        match self {
+            BankVaultType::Liquidity => LIQUIDITY_VAULT_SEED.as_bytes(),
+            BankVaultType::Insurance => INSURANCE_VAULT_SEED.as_bytes(),
+            BankVaultType::Fee => FEE_VAULT_SEED.as_bytes(),
+        }
+    }
+
+    pub fn get_authority_seed(self) -> &'static [u8] {
+        match self {
+            BankVaultType::Liquidity => LIQUIDITY_VAULT_AUTHORITY_SEED.as_bytes(),
+            BankVaultType::Insurance => INSURANCE_VAULT_AUTHORITY_SEED.as_bytes(),
+            BankVaultType::Fee => FEE_VAULT_AUTHORITY_SEED.as_bytes(),
+        }
+    }
+}
+
+#[macro_export]
+macro_rules! assert_eq_with_tolerance {
+    ($test_val:expr, $val:expr, $tolerance:expr) => {
+        assert!(
+            ($test_val - $val).abs() <= $tolerance,
+            "assertion failed: `({} - {}) <= {}`",
+            $test_val,
+            $val,
+            $tolerance
+        );
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    use crate::constants::{PROTOCOL_FEE_FIXED_DEFAULT, PROTOCOL_FEE_RATE_DEFAULT};
+
+    use super::*;
+    use fixed_macro::types::I80F48;
+
+    #[test]
+    /// Tests that the interest payment for a 1 year period with 100% APR is 1.
+    fn interest_payment_100apr_1year() {
+        let apr = I80F48::ONE;
+        let time_delta = 31_536_000; // 1 year
+        let value = I80F48::ONE;
+
+        assert_eq_with_tolerance!(
+            calc_interest_payment_for_period(apr, time_delta, value).unwrap(),
+            I80F48::ONE,
+            I80F48!(0.001)
+        );
+    }
+
+    /// Tests that the interest payment for a 1 year period with 50% APR is 0.5.
+    #[test]
+    fn interest_payment_50apr_1year() {
+        let apr = I80F48::from_num(0.5);
+        let time_delta = 31_536_000; // 1 year
+        let value = I80F48::ONE;
+
+        assert_eq_with_tolerance!(
+            calc_interest_payment_for_period(apr, time_delta, value).unwrap(),
+            I80F48::from_num(0.5),
+            I80F48!(0.001)
+        );
+    }
+    /// P: 1_000_000
+    /// Apr: 12%
+    /// Time: 1 second
+    #[test]
+    fn interest_payment_12apr_1second() {
+        let apr = I80F48!(0.12);
+        let time_delta = 1;
+        let value = I80F48!(1_000_000);
+
+        assert_eq_with_tolerance!(
+            calc_interest_payment_for_period(apr, time_delta, value).unwrap(),
+            I80F48!(0.0038),
+            I80F48!(0.001)
+        );
+    }
+
+    #[test]
+    /// apr: 100%
+    /// time: 1 year
+    /// principal: 2
+    /// expected: 4
+    fn accrued_interest_apr100_year1() {
+        assert_eq_with_tolerance!(
+            calc_accrued_interest_payment_per_period(I80F48!(1), 31_536_000, I80F48!(2)).unwrap(),
+            I80F48!(4),
+            I80F48!(0.001)
+        );
+    }
+
+    #[test]
+    /// apr: 50%
+    /// time: 1 year
+    /// principal: 2
+    /// expected: 3
+    fn accrued_interest_apr50_year1() {
+        assert_eq_with_tolerance!(
+            calc_accrued_interest_payment_per_period(I80F48!(0.5), 31_536_000, I80F48!(2)).unwrap(),
+            I80F48!(3),
+            I80F48!(0.001)
+        );
+    }
+
+    #[test]
+    /// apr: 12%
+    /// time: 1 second
+    /// principal: 1_000_000
+    /// expected: 1_038
+    fn accrued_interest_apr12_year1() {
+        assert_eq_with_tolerance!(
+            calc_accrued_interest_payment_per_period(I80F48!(0.12), 1, I80F48!(1_000_000)).unwrap(),
+            I80F48!(1_000_000.0038),
+            I80F48!(0.001)
+        );
+    }
+
+    #[test]
+    /// ur: 0
+    /// protocol_fixed_fee: 0.01
+    fn ir_config_calc_interest_rate_pff_01() {
+        let config = InterestRateConfig {
+            optimal_utilization_rate: I80F48!(0.6).into(),
+            plateau_interest_rate: I80F48!(0.40).into(),
+            protocol_fixed_fee_apr: I80F48!(0.01).into(),
+            ..Default::default()
+        };
+
+        let ComputedInterestRates {
+            lending_rate_apr: lending_apr,
+            borrowing_rate_apr: borrow_apr,
+            group_fee_apr: group_fees_apr,
+            insurance_fee_apr: insurance_apr,
+            protocol_fee_apr,
+        } = config
+            .create_interest_rate_calculator(&MarginfiGroup::default())
+            .calc_interest_rate(I80F48!(0.6))
+            .unwrap();
+
+        assert_eq_with_tolerance!(lending_apr, I80F48!(0.24), I80F48!(0.001));
+        assert_eq_with_tolerance!(borrow_apr, I80F48!(0.41), I80F48!(0.001));
+        assert_eq_with_tolerance!(group_fees_apr, I80F48!(0.01), I80F48!(0.001));
+        assert_eq_with_tolerance!(insurance_apr, I80F48!(0), I80F48!(0.001));
+        assert_eq_with_tolerance!(protocol_fee_apr, I80F48!(0), I80F48!(0.001));
+    }
+
+    #[test]
+    /// ur: 0.5
+    /// protocol_fixed_fee: 0.01
+    /// optimal_utilization_rate: 0.5
+    /// plateau_interest_rate: 0.4
+    fn ir_config_calc_interest_rate_pff_01_ur_05() {
+        let config = InterestRateConfig {
+            optimal_utilization_rate: I80F48!(0.5).into(),
+            plateau_interest_rate: I80F48!(0.4).into(),
+            protocol_fixed_fee_apr: I80F48!(0.01).into(),
+            insurance_ir_fee: I80F48!(0.1).into(),
+            ..Default::default()
+        };
+
+        let ComputedInterestRates {
+            lending_rate_apr: lending_apr,
+            borrowing_rate_apr: borrow_apr,
+            group_fee_apr: group_fees_apr,
+            insurance_fee_apr: insurance_apr,
+            protocol_fee_apr: _,
+        } = config
+            .create_interest_rate_calculator(&MarginfiGroup::default())
+            .calc_interest_rate(I80F48!(0.5))
+            .unwrap();
+
+        assert_eq_with_tolerance!(lending_apr, I80F48!(0.2), I80F48!(0.001));
+        assert_eq_with_tolerance!(borrow_apr, I80F48!(0.45), I80F48!(0.001));
+        assert_eq_with_tolerance!(group_fees_apr, I80F48!(0.01), I80F48!(0.001));
+        assert_eq_with_tolerance!(insurance_apr, I80F48!(0.04), I80F48!(0.001));
     }
 
-    #[inline]
-    pub fn is_borrow_limit_active(&self) -> bool {
-        self.borrow_limit != u64::MAX
-    }
+    #[test]
+    fn calc_fee_rate_1() {
+        let rate = I80F48!(0.4);
+        let fee_ir = I80F48!(0.05);
+        let fee_fixed = I80F48!(0.01);
 
-    pub fn validate_oracle_setup(&self, ais: &[AccountInfo]) -> MarginfiResult {
-        OraclePriceFeedAdapter::validate_bank_config(self, ais)?;
-        Ok(())
+        assert_eq!(
+            calc_fee_rate(rate, fee_ir, fee_fixed).unwrap(),
+            I80F48!(0.03)
+        );
     }
 
-    pub fn usd_init_limit_active(&self) -> bool {
-        self.total_asset_value_init_limit != TOTAL_ASSET_VALUE_INIT_LIMIT_INACTIVE
-    }
+    /// ur: 0.8
+    /// protocol_fixed_fee: 0.01
+    /// optimal_utilization_rate: 0.5
+    /// plateau_interest_rate: 0.4
+    /// max_interest_rate: 3
+    /// insurance_ir_fee: 0.1
+    #[test]
+    fn ir_config_calc_interest_rate_pff_01_ur_08() {
+        let config = InterestRateConfig {
+            optimal_utilization_rate: I80F48!(0.4).into(),
+            plateau_interest_rate: I80F48!(0.4).into(),
+            protocol_fixed_fee_apr: I80F48!(0.01).into(),
+            max_interest_rate: I80F48!(3).into(),
+            insurance_ir_fee: I80F48!(0.1).into(),
+            ..Default::default()
+        };
 
-    #[inline]
-    pub fn get_oracle_max_age(&self) -> u64 {
-        match (self.oracle_max_age, self.oracle_setup) {
-            (0, OracleSetup::SwitchboardV2) => MAX_SWB_ORACLE_AGE,
-            (0, OracleSetup::PythLegacy | OracleSetup::PythPushOracle) => MAX_PYTH_ORACLE_AGE,
-            (n, _) => n as u64,
-        }
+        let ComputedInterestRates {
+            lending_rate_apr: lending_apr,
+            borrowing_rate_apr: borrow_apr,
+            group_fee_apr: group_fees_apr,
+            insurance_fee_apr: insurance_apr,
+            protocol_fee_apr: _,
+        } = config
+            .create_interest_rate_calculator(&MarginfiGroup::default())
+            .calc_interest_rate(I80F48!(0.7))
+            .unwrap();
+
+        assert_eq_with_tolerance!(lending_apr, I80F48!(1.19), I80F48!(0.001));
+        assert_eq_with_tolerance!(borrow_apr, I80F48!(1.88), I80F48!(0.001));
+        assert_eq_with_tolerance!(group_fees_apr, I80F48!(0.01), I80F48!(0.001));
+        assert_eq_with_tolerance!(insurance_apr, I80F48!(0.17), I80F48!(0.001));
     }
 
-    pub fn get_pyth_push_oracle_feed_id(&self) -> Option<&FeedId> {
-        if matches!(self.oracle_setup, OracleSetup::PythPushOracle) {
-            let bytes: &[u8; 32] = self.oracle_keys[0].as_ref().try_into().unwrap();
-            Some(bytes)
-        } else {
-            None
+    #[test]
+    fn ir_config_multi_point_curve_matches_legacy() {
+        let legacy = InterestRateConfig {
+            optimal_utilization_rate: I80F48!(0.4).into(),
+            plateau_interest_rate: I80F48!(0.4).into(),
+            max_interest_rate: I80F48!(3).into(),
+            ..Default::default()
+        };
+        let multi_point = InterestRateConfig {
+            curve_type: INTEREST_CURVE_MULTI_POINT,
+            zero_util_rate: 0,
+            hundred_util_rate: rate_to_u32(I80F48!(3)),
+            points: [
+                RatePoint::new(I80F48!(0.4), I80F48!(0.4)),
+                RatePoint::default(),
+                RatePoint::default(),
+                RatePoint::default(),
+                RatePoint::default(),
+            ],
+            ..Default::default()
+        };
+        assert!(legacy.validate().is_ok());
+        assert!(multi_point.validate().is_ok());
+
+        let legacy_calc = legacy.create_interest_rate_calculator(&MarginfiGroup::default());
+        let multi_point_calc =
+            multi_point.create_interest_rate_calculator(&MarginfiGroup::default());
+
+        for ur in [
+            I80F48!(0),
+            I80F48!(0.2),
+            I80F48!(0.4),
+            I80F48!(0.7),
+            I80F48!(1),
+        ] {
+            assert_eq_with_tolerance!(
+                multi_point_calc.interest_rate_curve(ur).unwrap(),
+                legacy_calc.interest_rate_curve(ur).unwrap(),
+                I80F48!(0.000001)
+            );
         }
     }
-}
 
-#[zero_copy]
-#[repr(C, align(8))]
-#[cfg_attr(any(feature = "test", feature = "client"), derive(TypeLayout))]
-#[derive(Default, BorshDeserialize, BorshSerialize)]
-pub struct WrappedI80F48 {
-    pub value: [u8; 16],
-}
+    #[test]
+    fn ir_config_multi_point_curve() {
+        let mut config = InterestRateConfig {
+            curve_type: INTEREST_CURVE_MULTI_POINT,
+            zero_util_rate: rate_to_u32(I80F48!(0.01)),
+            hundred_util_rate: rate_to_u32(I80F48!(5)),
+            points: [
+                RatePoint::new(I80F48!(0.5), I80F48!(0.05)),
+                RatePoint::new(I80F48!(0.8), I80F48!(0.1)),
+                RatePoint::new(I80F48!(0.9), I80F48!(1)),
+                RatePoint::default(),
+                RatePoint::default(),
+            ],
+            ..Default::default()
+        };
+        assert!(config.validate().is_ok());
 
-impl Debug for WrappedI80F48 {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", I80F48::from_le_bytes(self.value))
-    }
-}
+        let calc = config.create_interest_rate_calculator(&MarginfiGroup::default());
+        let tolerance = I80F48!(0.000001);
 
-impl From<I80F48> for WrappedI80F48 {
-    fn from(i: I80F48) -> Self {
-        Self {
-            value: i.to_le_bytes(),
-        }
-    }
-}
+        assert_eq_with_tolerance!(
+            calc.interest_rate_curve(I80F48!(0)).unwrap(),
+            I80F48!(0.01),
+            tolerance
+        );
+        assert_eq_with_tolerance!(
+            calc.interest_rate_curve(I80F48!(0.25)).unwrap(),
+            I80F48!(0.03),
+            tolerance
+        );
+        assert_eq_with_tolerance!(
+            calc.interest_rate_curve(I80F48!(0.65)).unwrap(),
+            I80F48!(0.075),
+            tolerance
+        );
+        assert_eq_with_tolerance!(
+            calc.interest_rate_curve(I80F48!(0.85)).unwrap(),
+            I80F48!(0.55),
+            tolerance
+        );
+        assert_eq_with_tolerance!(
+            calc.interest_rate_curve(I80F48!(0.95)).unwrap(),
+            I80F48!(3),
+            tolerance
+        );
+        assert_eq_with_tolerance!(
+            calc.interest_rate_curve(I80F48!(1)).unwrap(),
+            I80F48!(5),
+            tolerance
+        );
 
-impl From<WrappedI80F48> for I80F48 {
-    fn from(w: WrappedI80F48) -> Self {
-        Self::from_le_bytes(w.value)
-    }
-}
+        // Utilization must increase between breakpoints
+        config.points[1] = RatePoint::new(I80F48!(0.4), I80F48!(0.1));
+        assert!(config.validate().is_err());
 
-impl PartialEq for WrappedI80F48 {
-    fn eq(&self, other: &Self) -> bool {
-        self.value == other.value
-    }
-}
+        // Rates can't decrease
+        config.points[1] = RatePoint::new(I80F48!(0.8), I80F48!(0.04));
+        assert!(config.validate().is_err());
 
-impl Eq for WrappedI80F48 {}
+        // Unused breakpoints must come last
+        config.points[1] = RatePoint::default();
+        assert!(config.validate().is_err());
 
-#[cfg_attr(
-    any(feature = "test", feature = "client"),
-    derive(Clone, PartialEq, Eq, TypeLayout)
-)]
-#[derive(AnchorDeserialize, AnchorSerialize, Default)]
-pub struct BankConfigOpt {
-    pub asset_weight_init: Option<WrappedI80F48>,
-    pub asset_weight_maint: Option<WrappedI80F48>,
+        // Unknown curve types are rejected
+        config.points[1] = RatePoint::new(I80F48!(0.8), I80F48!(0.1));
+        config.curve_type = 2;
+        assert!(config.validate().is_err());
+    }
 
-    pub liability_weight_init: Option<WrappedI80F48>,
-    pub liability_weight_maint: Option<WrappedI80F48>,
+    #[test]
+    fn ir_config_rate_caps() {
+        let mut config = InterestRateConfig {
+            optimal_utilization_rate: I80F48!(0.5).into(),
+            plateau_interest_rate: I80F48!(0.4).into(),
+            max_interest_rate: I80F48!(3).into(),
+            insurance_fee_fixed_apr: I80F48!(0.01).into(),
+            insurance_ir_fee: I80F48!(0.1).into(),
+            protocol_fixed_fee_apr: I80F48!(0.01).into(),
+            protocol_ir_fee: I80F48!(0.1).into(),
+            max_borrowing_rate: rate_to_u32(I80F48!(1.81)),
+            ..Default::default()
+        };
+        let tolerance = I80F48!(0.000001);
 
-    pub deposit_limit: Option<u64>,
-    pub borrow_limit: Option<u64>,
+        // Below the caps, rates are untouched
+        let rates = config
+            .create_interest_rate_calculator(&MarginfiGroup::default())
+            .calc_interest_rate(I80F48!(0.5))
+            .unwrap();
+        assert_eq_with_tolerance!(rates.lending_rate_apr, I80F48!(0.2), tolerance);
+        assert_eq_with_tolerance!(rates.borrowing_rate_apr, I80F48!(0.5), tolerance);
 
-    pub operational_state: Option<BankOperationalState>,
+        // Uncapped borrowing rate would be 3.62, everything is halved
+        let rates = config
+            .create_interest_rate_calculator(&MarginfiGroup::default())
+            .calc_interest_rate(I80F48::ONE)
+            .unwrap();
+        assert_eq_with_tolerance!(rates.lending_rate_apr, I80F48!(1.5), tolerance);
+        assert_eq_with_tolerance!(rates.borrowing_rate_apr, I80F48!(1.81), tolerance);
+        assert_eq_with_tolerance!(rates.group_fee_apr, I80F48!(0.155), tolerance);
+        assert_eq_with_tolerance!(rates.insurance_fee_apr, I80F48!(0.155), tolerance);
+
+        // Uncapped lending rate would be 3, the tighter cap wins
+        config.max_lending_rate = rate_to_u32(I80F48!(1));
+        let rates = config
+            .create_interest_rate_calculator(&MarginfiGroup::default())
+            .calc_interest_rate(I80F48::ONE)
+            .unwrap();
+        assert_eq_with_tolerance!(rates.lending_rate_apr, I80F48!(1), tolerance);
+        assert_eq_with_tolerance!(rates.borrowing_rate_apr, I80F48!(3.62) / 3, tolerance);
+        assert_eq_with_tolerance!(rates.group_fee_apr, I80F48!(0.31) / 3, tolerance);
+        assert_eq_with_tolerance!(rates.insurance_fee_apr, I80F48!(0.31) / 3, tolerance);
+    }
 
-    pub oracle: Option<OracleConfig>,
+    #[test]
+    fn liquidation_fees_default_and_validation() {
+        let mut config = BankConfig {
+            interest_rate_config: InterestRateConfig {
+                optimal_utilization_rate: I80F48!(0.5).into(),
+                plateau_interest_rate: I80F48!(0.6).into(),
+                max_interest_rate: I80F48!(3).into(),
+                ..Default::default()
+            },
+            risk_tier: RiskTier::Collateral,
+            ..Default::default()
+        };
+        let tolerance = I80F48!(0.000001);
 
-    pub interest_rate_config: Option<InterestRateConfigOpt>,
+        // Unset fees fall back to the global defaults
+        assert_eq!(
+            config.get_liquidation_liquidator_fee(),
+            LIQUIDATION_LIQUIDATOR_FEE
+        );
+        assert_eq!(
+            config.get_liquidation_insurance_fee(),
+            LIQUIDATION_INSURANCE_FEE
+        );
+        assert!(config.validate().is_ok());
 
-    pub risk_tier: Option<RiskTier>,
+        // Values are only used once flagged as set
+        config.liquidation_liquidator_fee_bps = 1_000;
+        config.liquidation_insurance_fee_bps = 100;
+        assert_eq!(
+            config.get_liquidation_liquidator_fee(),
+            LIQUIDATION_LIQUIDATOR_FEE
+        );
 
-    pub total_asset_value_init_limit: Option<u64>,
+        config.liquidation_fee_flags =
+            LIQUIDATION_LIQUIDATOR_FEE_SET_FLAG | LIQUIDATION_INSURANCE_FEE_SET_FLAG;
+        assert_eq_with_tolerance!(
+            config.get_liquidation_liquidator_fee(),
+            I80F48!(0.1),
+            tolerance
+        );
+        assert_eq_with_tolerance!(
+            config.get_liquidation_insurance_fee(),
+            I80F48!(0.01),
+            tolerance
+        );
+        assert!(config.validate().is_ok());
 
-    pub oracle_max_age: Option<u16>,
+        // A fee can be set to 0
+        config.liquidation_insurance_fee_bps = 0;
+        assert_eq!(config.get_liquidation_insurance_fee(), I80F48::ZERO);
+        assert!(config.validate().is_ok());
 
-    pub permissionless_bad_debt_settlement: Option<bool>,
-}
+        // Fees can't consume the whole collateral value
+        config.liquidation_liquidator_fee_bps = 6_000;
+        config.liquidation_insurance_fee_bps = 4_000;
+        assert!(config.validate().is_err());
+    }
 
-#[cfg_attr(
-    any(feature = "test", feature = "client"),
-    derive(PartialEq, Eq, TypeLayout)
-)]
-#[derive(Clone, Copy, AnchorDeserialize, AnchorSerialize, Debug)]
-pub struct OracleConfig {
-    pub setup: OracleSetup,
-    pub keys: [Pubkey; MAX_ORACLE_KEYS],
-}
+    #[test]
+    fn liquidation_fee_ramp() {
+        let mut bank = Bank {
+            config: BankConfig {
+                interest_rate_config: InterestRateConfig {
+                    optimal_utilization_rate: I80F48!(0.5).into(),
+                    plateau_interest_rate: I80F48!(0.6).into(),
+                    max_interest_rate: I80F48!(3).into(),
+                    ..Default::default()
+                },
+                risk_tier: RiskTier::Collateral,
+                liquidation_liquidator_fee_bps: 200,
+                liquidation_fee_flags: LIQUIDATION_LIQUIDATOR_FEE_SET_FLAG,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let tolerance = I80F48!(0.000001);
 
-#[derive(Debug, Clone)]
-pub enum BankVaultType {
-    Liquidity,
-    Insurance,
-    Fee,
-}
+        // Ramp disabled, flat fee regardless of the shortfall
+        assert_eq_with_tolerance!(
+            bank.config.get_liquidator_fee(I80F48!(0.5)).unwrap(),
+            I80F48!(0.02),
+            tolerance
+        );
 
-impl BankVaultType {
-    pub fn get_seed(self) -> &'static [u8] {
-        match self {
-            BankVaultType::Liquidity => LIQUIDITY_VAULT_SEED.as_bytes(),
-            BankVaultType::Insurance => INSURANCE_VAULT_SEED.as_bytes(),
-            BankVaultType::Fee => FEE_VAULT_SEED.as_bytes(),
-        }
-    }
+        // The max fee is only used once flagged as set
+        bank.config.liquidation_max_liquidator_fee_bps = 1_000;
+        assert_eq_with_tolerance!(
+            bank.config.get_liquidator_fee(I80F48!(0.5)).unwrap(),
+            I80F48!(0.02),
+            tolerance
+        );
 
-    pub fn get_authority_seed(self) -> &'static [u8] {
-        match self {
-            BankVaultType::Liquidity => LIQUIDITY_VAULT_AUTHORITY_SEED.as_bytes(),
-            BankVaultType::Insurance => INSURANCE_VAULT_AUTHORITY_SEED.as_bytes(),
-            BankVaultType::Fee => FEE_VAULT_AUTHORITY_SEED.as_bytes(),
-        }
-    }
-}
+        bank.configure(&BankConfigOpt {
+            liquidation_max_liquidator_fee_bps: Some(1_000),
+            ..Default::default()
+        })
+        .unwrap();
 
-#[macro_export]
-macro_rules! assert_eq_with_tolerance {
-    ($test_val:expr, $val:expr, $tolerance:expr) => {
-        assert!(
-            ($test_val - $val).abs() <= $tolerance,
-            "assertion failed: `({} - {}) <= {}`",
-            $test_val,
-            $val,
-            $tolerance
+        assert_eq!(
+            bank.config.get_liquidation_fee_ramp_shortfall(),
+            LIQUIDATION_FEE_RAMP_SHORTFALL
+        );
+        assert_eq_with_tolerance!(
+            bank.config.get_liquidator_fee(I80F48::ZERO).unwrap(),
+            I80F48!(0.02),
+            tolerance
+        );
+        assert_eq_with_tolerance!(
+            bank.config.get_liquidator_fee(I80F48!(0.05)).unwrap(),
+            I80F48!(0.06),
+            tolerance
+        );
+        assert_eq_with_tolerance!(
+            bank.config.get_liquidator_fee(I80F48!(0.1)).unwrap(),
+            I80F48!(0.1),
+            tolerance
+        );
+        // Capped at the max fee
+        assert_eq_with_tolerance!(
+            bank.config.get_liquidator_fee(I80F48!(0.8)).unwrap(),
+            I80F48!(0.1),
+            tolerance
         );
-    };
-}
 
-#[cfg(test)]
-mod tests {
-    use std::time::{SystemTime, UNIX_EPOCH};
+        bank.configure(&BankConfigOpt {
+            liquidation_fee_ramp_shortfall_bps: Some(2_000),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq_with_tolerance!(
+            bank.config.get_liquidator_fee(I80F48!(0.05)).unwrap(),
+            I80F48!(0.04),
+            tolerance
+        );
+
+        // A 0 shortfall charges the max fee as soon as the account is unhealthy
+        bank.configure(&BankConfigOpt {
+            liquidation_fee_ramp_shortfall_bps: Some(0),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq_with_tolerance!(
+            bank.config.get_liquidator_fee(I80F48::ZERO).unwrap(),
+            I80F48!(0.02),
+            tolerance
+        );
+        assert_eq_with_tolerance!(
+            bank.config.get_liquidator_fee(I80F48!(0.01)).unwrap(),
+            I80F48!(0.1),
+            tolerance
+        );
 
-    use crate::constants::{PROTOCOL_FEE_FIXED_DEFAULT, PROTOCOL_FEE_RATE_DEFAULT};
+        // Max fee below the liquidator fee is rejected
+        assert!(bank
+            .configure(&BankConfigOpt {
+                liquidation_max_liquidator_fee_bps: Some(100),
+                ..Default::default()
+            })
+            .is_err());
 
-    use super::*;
-    use fixed_macro::types::I80F48;
+        // Raising the liquidator fee past the max fee is rejected as well
+        assert!(bank
+            .configure(&BankConfigOpt {
+                liquidation_liquidator_fee_bps: Some(1_500),
+                ..Default::default()
+            })
+            .is_err());
+
+        // Max fee plus insurance fee can't consume the whole collateral value
+        assert!(bank
+            .configure(&BankConfigOpt {
+                liquidation_max_liquidator_fee_bps: Some(9_800),
+                ..Default::default()
+            })
+            .is_err());
+    }
 
     #[test]
-    /// Tests that the interest payment for a 1 year period with 100% APR is 1.
-    fn interest_payment_100apr_1year() {
-        let apr = I80F48::ONE;
-        let time_delta = 31_536_000; // 1 year
-        let value = I80F48::ONE;
+    fn median_oracle_max_age() {
+        let mut config = BankConfig {
+            oracle_setup: OracleSetup::Median,
+            oracle_source_setups: [
+                OracleSetup::SwitchboardPull,
+                OracleSetup::SwitchboardV2,
+                OracleSetup::None,
+                OracleSetup::None,
+                OracleSetup::None,
+            ],
+            ..Default::default()
+        };
+        assert_eq!(config.get_oracle_max_age(), MAX_SWB_ORACLE_AGE);
 
-        assert_eq_with_tolerance!(
-            calc_interest_payment_for_period(apr, time_delta, value).unwrap(),
-            I80F48::ONE,
-            I80F48!(0.001)
-        );
+        // A Pyth source holds the median to the Pyth default
+        config.oracle_source_setups[2] = OracleSetup::PythPushOracle;
+        assert_eq!(config.get_oracle_max_age(), MAX_PYTH_ORACLE_AGE);
+
+        // Sources past the first unused slot are ignored
+        config.oracle_source_setups[2] = OracleSetup::None;
+        config.oracle_source_setups[3] = OracleSetup::PythLegacy;
+        assert_eq!(config.get_oracle_max_age(), MAX_SWB_ORACLE_AGE);
+
+        // A configured max age takes precedence
+        config.oracle_max_age = 30;
+        assert_eq!(config.get_oracle_max_age(), 30);
     }
 
-    /// Tests that the interest payment for a 1 year period with 50% APR is 0.5.
     #[test]
-    fn interest_payment_50apr_1year() {
-        let apr = I80F48::from_num(0.5);
-        let time_delta = 31_536_000; // 1 year
-        let value = I80F48::ONE;
+    fn adaptive_plateau_rate() {
+        let mut bank = Bank {
+            config: BankConfig {
+                interest_rate_config: InterestRateConfig {
+                    optimal_utilization_rate: I80F48!(0.5).into(),
+                    plateau_interest_rate: I80F48!(0.1).into(),
+                    max_interest_rate: I80F48!(3).into(),
+                    ..Default::default()
+                },
+                risk_tier: RiskTier::Collateral,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let day = 24 * 60 * 60;
+        let tolerance = I80F48!(0.000001);
 
-        assert_eq_with_tolerance!(
-            calc_interest_payment_for_period(apr, time_delta, value).unwrap(),
-            I80F48::from_num(0.5),
-            I80F48!(0.001)
+        // Not adaptive, the plateau rate doesn't move
+        assert_eq!(
+            bank.update_adaptive_plateau_rate(I80F48::ONE, day).unwrap(),
+            I80F48!(0.1)
         );
-    }
-    /// P: 1_000_000
-    /// Apr: 12%
-    /// Time: 1 second
-    #[test]
-    fn interest_payment_12apr_1second() {
-        let apr = I80F48!(0.12);
-        let time_delta = 1;
-        let value = I80F48!(1_000_000);
+        assert_eq!(I80F48::from(bank.adaptive_plateau_rate), I80F48::ZERO);
+
+        bank.configure(&BankConfigOpt {
+            interest_rate_config: Some(InterestRateConfigOpt {
+                curve_type: Some(INTEREST_CURVE_ADAPTIVE),
+                adaptive_min_plateau_rate: Some(rate_to_u32(I80F48!(0.05))),
+                adaptive_max_plateau_rate: Some(rate_to_u32(I80F48!(1))),
+                adaptive_speed: Some(rate_to_u32(I80F48!(0.1))),
+                ..Default::default()
+            }),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(bank.get_adaptive_plateau_rate(), I80F48!(0.1));
 
+        // Halfway between optimal and 100% utilization for a day, half the max speed
         assert_eq_with_tolerance!(
-            calc_interest_payment_for_period(apr, time_delta, value).unwrap(),
-            I80F48!(0.0038),
-            I80F48!(0.001)
+            bank.update_adaptive_plateau_rate(I80F48!(0.75), day)
+                .unwrap(),
+            I80F48!(0.15),
+            tolerance
         );
-    }
-
-    #[test]
-    /// apr: 100%
-    /// time: 1 year
-    /// principal: 2
-    /// expected: 4
-    fn accrued_interest_apr100_year1() {
+        // Halfway between 0% and optimal utilization for a day
         assert_eq_with_tolerance!(
-            calc_accrued_interest_payment_per_period(I80F48!(1), 31_536_000, I80F48!(2)).unwrap(),
-            I80F48!(4),
-            I80F48!(0.001)
+            bank.update_adaptive_plateau_rate(I80F48!(0.25), day)
+                .unwrap(),
+            I80F48!(0.1),
+            tolerance
         );
-    }
-
-    #[test]
-    /// apr: 50%
-    /// time: 1 year
-    /// principal: 2
-    /// expected: 3
-    fn accrued_interest_apr50_year1() {
+        // At optimal utilization the plateau rate holds
         assert_eq_with_tolerance!(
-            calc_accrued_interest_payment_per_period(I80F48!(0.5), 31_536_000, I80F48!(2)).unwrap(),
-            I80F48!(3),
-            I80F48!(0.001)
+            bank.update_adaptive_plateau_rate(I80F48!(0.5), day)
+                .unwrap(),
+            I80F48!(0.1),
+            tolerance
         );
-    }
 
-    #[test]
-    /// apr: 12%
-    /// time: 1 second
-    /// principal: 1_000_000
-    /// expected: 1_038
-    fn accrued_interest_apr12_year1() {
+        // Capped at the admin bounds
         assert_eq_with_tolerance!(
-            calc_accrued_interest_payment_per_period(I80F48!(0.12), 1, I80F48!(1_000_000)).unwrap(),
-            I80F48!(1_000_000.0038),
-            I80F48!(0.001)
+            bank.update_adaptive_plateau_rate(I80F48::ONE, 30 * day)
+                .unwrap(),
+            I80F48!(1),
+            tolerance
+        );
+        assert_eq_with_tolerance!(
+            bank.update_adaptive_plateau_rate(I80F48::ZERO, 30 * day)
+                .unwrap(),
+            I80F48!(0.05),
+            tolerance
         );
+
+        // The calculator charges the adapted plateau rate at optimal utilization
+        let rates = bank
+            .create_interest_rate_calculator(&MarginfiGroup::default())
+            .calc_interest_rate(I80F48!(0.5))
+            .unwrap();
+        assert_eq_with_tolerance!(rates.borrowing_rate_apr, I80F48!(0.05), tolerance);
+
+        // Updating the plateau rate resets the adapted rate
+        bank.configure(&BankConfigOpt {
+            interest_rate_config: Some(InterestRateConfigOpt {
+                plateau_interest_rate: Some(I80F48!(0.2).into()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(bank.get_adaptive_plateau_rate(), I80F48!(0.2));
+
+        // Initial plateau rate outside of the adaptive bounds is rejected
+        assert!(bank
+            .configure(&BankConfigOpt {
+                interest_rate_config: Some(InterestRateConfigOpt {
+                    plateau_interest_rate: Some(I80F48!(2).into()),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            })
+            .is_err());
     }
 
     #[test]
-    /// ur: 0
-    /// protocol_fixed_fee: 0.01
-    fn ir_config_calc_interest_rate_pff_01() {
-        let config = InterestRateConfig {
-            optimal_utilization_rate: I80F48!(0.6).into(),
-            plateau_interest_rate: I80F48!(0.40).into(),
-            protocol_fixed_fee_apr: I80F48!(0.01).into(),
+    fn twap_price() {
+        let mut bank = Bank {
+            twap_window: 800,
             ..Default::default()
         };
 
-        let ComputedInterestRates {
-            lending_rate_apr: lending_apr,
-            borrowing_rate_apr: borrow_apr,
-            group_fee_apr: group_fees_apr,
-            insurance_fee_apr: insurance_apr,
-            protocol_fee_apr,
-        } = config
-            .create_interest_rate_calculator(&MarginfiGroup::default())
-            .calc_interest_rate(I80F48!(0.6))
-            .unwrap();
+        assert_eq!(bank.get_twap_price(1_000).unwrap(), None);
 
-        assert_eq_with_tolerance!(lending_apr, I80F48!(0.24), I80F48!(0.001));
-        assert_eq_with_tolerance!(borrow_apr, I80F48!(0.41), I80F48!(0.001));
-        assert_eq_with_tolerance!(group_fees_apr, I80F48!(0.01), I80F48!(0.001));
-        assert_eq_with_tolerance!(insurance_apr, I80F48!(0), I80F48!(0.001));
-        assert_eq_with_tolerance!(protocol_fee_apr, I80F48!(0), I80F48!(0.001));
+        bank.record_price_observation(I80F48!(10), 1_000);
+        assert_eq!(bank.get_twap_price(1_000).unwrap(), Some(I80F48!(10)));
+        assert_eq!(bank.get_twap_price(1_050).unwrap(), Some(I80F48!(10)));
+
+        // Within `twap_window / TWAP_OBSERVATIONS` of the latest observation, skipped
+        bank.record_price_observation(I80F48!(20), 1_050);
+        assert_eq!(bank.price_observation_index, 1);
+
+        bank.record_price_observation(I80F48!(20), 1_100);
+        assert_eq!(bank.price_observation_index, 2);
+        assert_eq!(bank.get_twap_price(1_200).unwrap(), Some(I80F48!(15)));
+
+        // The first observation is out of the window
+        assert_eq!(bank.get_twap_price(1_900).unwrap(), Some(I80F48!(20)));
+
+        // No observation within the window
+        assert_eq!(bank.get_twap_price(2_000).unwrap(), None);
+
+        // The ring buffer wraps around
+        for i in 0..TWAP_OBSERVATIONS as i64 {
+            bank.record_price_observation(I80F48!(30), 2_000 + i * 100);
+        }
+        assert_eq!(bank.price_observation_index, 2);
+        assert_eq!(bank.get_twap_price(2_800).unwrap(), Some(I80F48!(30)));
+
+        bank.twap_window = 0;
+        assert_eq!(bank.get_twap_price(2_800).unwrap(), None);
     }
 
     #[test]
-    /// ur: 0.5
-    /// protocol_fixed_fee: 0.01
-    /// optimal_utilization_rate: 0.5
-    /// plateau_interest_rate: 0.4
-    fn ir_config_calc_interest_rate_pff_01_ur_05() {
-        let config = InterestRateConfig {
-            optimal_utilization_rate: I80F48!(0.5).into(),
-            plateau_interest_rate: I80F48!(0.4).into(),
-            protocol_fixed_fee_apr: I80F48!(0.01).into(),
-            insurance_ir_fee: I80F48!(0.1).into(),
+    fn emode_weights() {
+        let mut bank = Bank {
+            config: BankConfig {
+                asset_weight_init: I80F48!(0.5).into(),
+                asset_weight_maint: I80F48!(0.6).into(),
+                liability_weight_init: I80F48!(1.2).into(),
+                liability_weight_maint: I80F48!(1.1).into(),
+                ..Default::default()
+            },
+            emode_category: 1,
+            emode_asset_weight_init: I80F48!(0.9).into(),
+            emode_asset_weight_maint: I80F48!(0.95).into(),
+            emode_liability_weight_init: I80F48!(1.05).into(),
+            emode_liability_weight_maint: I80F48!(1).into(),
             ..Default::default()
         };
+        assert!(bank.validate_emode_config().is_ok());
+
+        // Account not in e-mode, or in another category
+        for emode_category in [None, Some(2)] {
+            assert_eq!(
+                bank.get_weight(
+                    RequirementType::Initial,
+                    BalanceSide::Assets,
+                    emode_category
+                ),
+                I80F48!(0.5)
+            );
+            assert_eq!(
+                bank.get_weight(
+                    RequirementType::Maintenance,
+                    BalanceSide::Liabilities,
+                    emode_category
+                ),
+                I80F48!(1.1)
+            );
+        }
 
-        let ComputedInterestRates {
-            lending_rate_apr: lending_apr,
-            borrowing_rate_apr: borrow_apr,
-            group_fee_apr: group_fees_apr,
-            insurance_fee_apr: insurance_apr,
-            protocol_fee_apr: _,
-        } = config
-            .create_interest_rate_calculator(&MarginfiGroup::default())
-            .calc_interest_rate(I80F48!(0.5))
-            .unwrap();
+        // Account in the bank's category
+        assert_eq!(
+            bank.get_weight(RequirementType::Initial, BalanceSide::Assets, Some(1)),
+            I80F48!(0.9)
+        );
+        assert_eq!(
+            bank.get_weight(RequirementType::Maintenance, BalanceSide::Assets, Some(1)),
+            I80F48!(0.95)
+        );
+        assert_eq!(
+            bank.get_weight(RequirementType::Initial, BalanceSide::Liabilities, Some(1)),
+            I80F48!(1.05)
+        );
+        assert_eq!(
+            bank.get_weight(RequirementType::Equity, BalanceSide::Assets, Some(1)),
+            I80F48::ONE
+        );
 
-        assert_eq_with_tolerance!(lending_apr, I80F48!(0.2), I80F48!(0.001));
-        assert_eq_with_tolerance!(borrow_apr, I80F48!(0.45), I80F48!(0.001));
-        assert_eq_with_tolerance!(group_fees_apr, I80F48!(0.01), I80F48!(0.001));
-        assert_eq_with_tolerance!(insurance_apr, I80F48!(0.04), I80F48!(0.001));
+        // E-mode liability weight above the generic one
+        bank.emode_liability_weight_init = I80F48!(1.3).into();
+        assert!(bank.validate_emode_config().is_err());
     }
 
     #[test]
-    fn calc_fee_rate_1() {
-        let rate = I80F48!(0.4);
-        let fee_ir = I80F48!(0.05);
-        let fee_fixed = I80F48!(0.01);
+    fn pair_rules() {
+        let stable = BankPairRules {
+            pair_class: 1,
+            ..Default::default()
+        };
+        // Only backs stablecoin liabilities
+        let lst = BankPairRules {
+            pair_class: 2,
+            collateral_for_classes: 1 << 1,
+            ..Default::default()
+        };
+        // Only accepts stablecoin collateral
+        let volatile = BankPairRules {
+            pair_class: 3,
+            accepted_collateral_classes: 1 << 1,
+            ..Default::default()
+        };
 
-        assert_eq!(
-            calc_fee_rate(rate, fee_ir, fee_fixed).unwrap(),
-            I80F48!(0.03)
-        );
+        assert!(lst.can_back(&stable));
+        assert!(!lst.can_back(&volatile));
+        assert!(stable.can_back(&volatile));
+        assert!(!lst.can_back(&BankPairRules::default()));
+        assert!(BankPairRules::default().can_back(&stable));
+        assert!(!BankPairRules::default().can_back(&volatile));
     }
 
-    /// ur: 0.8
-    /// protocol_fixed_fee: 0.01
-    /// optimal_utilization_rate: 0.5
-    /// plateau_interest_rate: 0.4
-    /// max_interest_rate: 3
-    /// insurance_ir_fee: 0.1
     #[test]
-    fn ir_config_calc_interest_rate_pff_01_ur_08() {
-        let config = InterestRateConfig {
-            optimal_utilization_rate: I80F48!(0.4).into(),
-            plateau_interest_rate: I80F48!(0.4).into(),
-            protocol_fixed_fee_apr: I80F48!(0.01).into(),
-            max_interest_rate: I80F48!(3).into(),
-            insurance_ir_fee: I80F48!(0.1).into(),
+    fn outflow_window() {
+        let mut bank = Bank {
+            outflow_window: 100,
+            outflow_limit: 1_000,
             ..Default::default()
         };
+        bank.config.operational_state = BankOperationalState::Operational;
+
+        bank.record_outflow(600, 1_000).unwrap();
+        bank.record_outflow(300, 1_050).unwrap();
+        assert_eq!(bank.outflow_window_start, 1_000);
+        assert_eq!(bank.outflow_current_window, 900);
+
+        // Half of the previous window still counts
+        bank.record_outflow(500, 1_150).unwrap();
+        assert_eq!(bank.outflow_previous_window, 900);
+        assert_eq!(bank.get_windowed_outflow(1_150).unwrap(), 950);
+        assert!(matches!(
+            bank.config.operational_state,
+            BankOperationalState::Operational
+        ));
+
+        bank.record_outflow(100, 1_150).unwrap();
+        assert!(matches!(
+            bank.config.operational_state,
+            BankOperationalState::ReduceOnly
+        ));
+        assert!(bank.record_outflow(1, 1_150).is_err());
+
+        bank.record_outflow(1, 1_300).unwrap();
+        assert_eq!(bank.outflow_window_start, 1_300);
+        assert_eq!(bank.outflow_previous_window, 0);
+    }
 
-        let ComputedInterestRates {
-            lending_rate_apr: lending_apr,
-            borrowing_rate_apr: borrow_apr,
-            group_fee_apr: group_fees_apr,
-            insurance_fee_apr: insurance_apr,
-            protocol_fee_apr: _,
-        } = config
-            .create_interest_rate_calculator(&MarginfiGroup::default())
-            .calc_interest_rate(I80F48!(0.7))
-            .unwrap();
+    #[test]
+    fn price_guard() {
+        let mut bank = Bank {
+            price_guard_max_move_bps: 1_000,
+            price_guard_interval: 600,
+            ..Default::default()
+        };
 
-        assert_eq_with_tolerance!(lending_apr, I80F48!(1.19), I80F48!(0.001));
-        assert_eq_with_tolerance!(borrow_apr, I80F48!(1.88), I80F48!(0.001));
-        assert_eq_with_tolerance!(group_fees_apr, I80F48!(0.01), I80F48!(0.001));
-        assert_eq_with_tolerance!(insurance_apr, I80F48!(0.17), I80F48!(0.001));
+        bank.update_price_guard(I80F48!(10), 1_000).unwrap();
+        bank.update_price_guard(I80F48!(10.9), 1_100).unwrap();
+        assert!(!bank.is_price_guard_tripped(1_100));
+
+        // Large moves over a long interval are fine
+        bank.update_price_guard(I80F48!(15), 2_000).unwrap();
+        assert!(!bank.is_price_guard_tripped(2_000));
+
+        bank.update_price_guard(I80F48!(13), 2_100).unwrap();
+        assert_eq!(bank.price_guard_tripped_at, 2_100);
+        assert!(bank.is_price_guard_tripped(2_100));
+
+        // Stays tripped for the interval
+        bank.update_price_guard(I80F48!(15), 2_200).unwrap();
+        assert_eq!(bank.price_guard_tripped_at, 2_100);
+        assert_eq!(I80F48::from(bank.price_guard_last_price), I80F48!(15));
+        assert!(bank.is_price_guard_tripped(2_700));
+
+        // Then clears, and may trip again
+        assert!(!bank.is_price_guard_tripped(2_701));
+        bank.update_price_guard(I80F48!(10), 2_750).unwrap();
+        assert_eq!(bank.price_guard_tripped_at, 2_750);
     }
 
     #[test]
@@ -1918,10 +4511,13 @@ mod tests {
             insurance_fees_collected: insurance_collected,
             group_fees_collected,
             protocol_fees_collected,
+            ..
         } = calc_interest_rate_accrual_state_changes(
             3600,
             total_asset_shares,
             total_liability_shares,
+            I80F48::ZERO,
+            I80F48::ZERO,
             &ir_config.create_interest_rate_calculator(&group),
             asset_share_value,
             liab_share_value,
@@ -1962,4 +4558,84 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn fixed_term_interest_accrual() -> anyhow::Result<()> {
+        let mut bank = Bank {
+            asset_share_value: I80F48::ONE.into(),
+            liability_share_value: I80F48::ONE.into(),
+            total_asset_shares: I80F48!(1_000_000).into(),
+            config: BankConfig {
+                borrow_limit: u64::MAX,
+                deposit_limit: u64::MAX,
+                interest_rate_config: InterestRateConfig {
+                    optimal_utilization_rate: I80F48!(0.4).into(),
+                    plateau_interest_rate: I80F48!(0.4).into(),
+                    max_interest_rate: I80F48!(3).into(),
+                    insurance_ir_fee: I80F48!(0.1).into(),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let group = MarginfiGroup::default();
+        let tolerance = I80F48!(0.01);
+
+        // 100_000 lent out at 10% for a year, no variable liabilities
+        bank.open_fixed_term_borrow(I80F48!(100_000), I80F48!(0.1))?;
+
+        let accrue = |bank: &mut Bank, timestamp: i64| {
+            bank.accrue_interest(
+                timestamp,
+                &group,
+                #[cfg(not(feature = "client"))]
+                Pubkey::default(),
+            )
+        };
+
+        // Half a year in, depositors already earned their share of the interest: at 10%
+        // utilization the borrowing rate is 0.1 * 1.1, so the insurance fee takes 1/11th
+        accrue(&mut bank, 15_768_000)?;
+
+        let total_assets = bank.get_asset_amount(bank.total_asset_shares.into())?;
+        let insurance_fees = I80F48::from(bank.collected_insurance_fees_outstanding);
+        assert_eq_with_tolerance!(
+            I80F48::from(bank.fixed_term_liability_amount),
+            I80F48!(105_000),
+            tolerance
+        );
+        assert_eq_with_tolerance!(insurance_fees, I80F48!(5_000) / 11, tolerance);
+        assert_eq_with_tolerance!(
+            total_assets,
+            I80F48!(1_000_000) + I80F48!(50_000) / 11,
+            tolerance
+        );
+
+        // At maturity the whole interest went to depositors and fees
+        accrue(&mut bank, 31_536_000)?;
+
+        let total_assets = bank.get_asset_amount(bank.total_asset_shares.into())?;
+        let insurance_fees = I80F48::from(bank.collected_insurance_fees_outstanding);
+        assert_eq_with_tolerance!(
+            I80F48::from(bank.fixed_term_liability_amount),
+            I80F48!(110_000),
+            tolerance
+        );
+        assert_eq_with_tolerance!(
+            total_assets - I80F48!(1_000_000) + insurance_fees,
+            I80F48!(10_000),
+            tolerance
+        );
+
+        // Settling doesn't credit the interest again
+        let asset_share_value = I80F48::from(bank.asset_share_value);
+        bank.settle_fixed_term_borrow(I80F48!(100_000), I80F48!(0.1), I80F48!(110_000))?;
+
+        assert_eq!(I80F48::from(bank.asset_share_value), asset_share_value);
+        assert_eq!(I80F48::from(bank.fixed_term_liability_amount), I80F48::ZERO);
+        assert_eq!(I80F48::from(bank.fixed_term_annual_interest), I80F48::ZERO);
+
+        Ok(())
+    }
 }
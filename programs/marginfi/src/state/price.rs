@@ -1,4 +1,7 @@
-use std::{cell::Ref, cmp::min};
+use std::{
+    cell::Ref,
+    cmp::{max, min},
+};
 
 use anchor_lang::prelude::*;
 use enum_dispatch::enum_dispatch;
@@ -15,14 +18,15 @@ pub use pyth_sdk_solana;
 use crate::{
     check,
     constants::{
-        CONF_INTERVAL_MULTIPLE, EXP_10, EXP_10_I80F48, MAX_CONF_INTERVAL,
-        MIN_PYTH_PUSH_VERIFICATION_LEVEL, PYTH_ID, STD_DEV_MULTIPLE, SWITCHBOARD_PULL_ID,
+        CONF_INTERVAL_MODE_REJECT, CONF_INTERVAL_MULTIPLE, EXP_10, EXP_10_I80F48,
+        MAX_CONF_INTERVAL, MAX_STAKE_POOL_EPOCH_LAG, MIN_PYTH_PUSH_VERIFICATION_LEVEL, PYTH_ID,
+        SPL_STAKE_POOL_ID, STD_DEV_MULTIPLE, SWITCHBOARD_PULL_ID,
     },
     debug, math_error,
     prelude::*,
 };
 
-use super::marginfi_group::BankConfig;
+use super::marginfi_group::Bank;
 use anchor_lang::prelude::borsh;
 use pyth_solana_receiver_sdk::PYTH_PUSH_ORACLE_ID;
 
@@ -35,6 +39,27 @@ pub enum OracleSetup {
     SwitchboardV2,
     PythPushOracle,
     SwitchboardPull,
+    /// Median of the feeds in `BankConfig::oracle_keys`, each priced with the matching
+    /// `BankConfig::oracle_source_setups` entry.
+    Median,
+    /// `Bank::fixed_price`, set by the admin. No oracle account required.
+    Fixed,
+    /// `Bank::fixed_price`, as long as the feed at `BankConfig::oracle_keys[0]` (priced with
+    /// `BankConfig::oracle_source_setups[0]`) stays within `BankConfig::oracle_max_deviation_bps`
+    /// of it. Outside of that band, the feed price is used.
+    Peg,
+    /// SPL stake pool token, priced as the SOL Pyth push feed at `BankConfig::oracle_keys[0]`
+    /// times the exchange rate of the stake pool at `BankConfig::oracle_keys[1]`.
+    StakePool,
+}
+
+/// Which of a bank's configured feeds priced it, see `BankConfig::oracle_fallback_setup`.
+#[repr(u8)]
+#[cfg_attr(any(feature = "test", feature = "client"), derive(PartialEq, Eq))]
+#[derive(Copy, Clone, Debug, AnchorSerialize, AnchorDeserialize)]
+pub enum OracleSource {
+    Primary,
+    Fallback,
 }
 
 #[derive(Copy, Clone, Debug)]
@@ -61,6 +86,78 @@ pub trait PriceAdapter {
     ) -> MarginfiResult<I80F48>;
 }
 
+/// Confidence interval settings of a bank, see `Bank::oracle_conf_interval_multiple`.
+#[derive(Copy, Clone, Debug)]
+pub struct ConfIntervalConfig {
+    /// None means the feed's default multiple is used
+    multiple: Option<I80F48>,
+    max_conf_interval: I80F48,
+    reject_wide_conf_interval: bool,
+}
+
+impl Default for ConfIntervalConfig {
+    fn default() -> Self {
+        Self {
+            multiple: None,
+            max_conf_interval: MAX_CONF_INTERVAL,
+            reject_wide_conf_interval: false,
+        }
+    }
+}
+
+impl ConfIntervalConfig {
+    pub fn from_bank(bank: &Bank) -> Self {
+        let multiple: I80F48 = bank.oracle_conf_interval_multiple.into();
+        let max_conf_interval: I80F48 = bank.oracle_max_conf_interval.into();
+
+        Self {
+            multiple: (multiple != I80F48::ZERO).then_some(multiple),
+            max_conf_interval: if max_conf_interval == I80F48::ZERO {
+                MAX_CONF_INTERVAL
+            } else {
+                max_conf_interval
+            },
+            reject_wide_conf_interval: bank.oracle_conf_interval_mode == CONF_INTERVAL_MODE_REJECT,
+        }
+    }
+
+    /// Confidence interval of `price`, `conf` times the multiple, capped at the max confidence
+    /// interval, or rejected if wider and the bank is in `CONF_INTERVAL_MODE_REJECT`.
+    fn calc_conf_interval(
+        &self,
+        conf: I80F48,
+        price: I80F48,
+        default_multiple: I80F48,
+    ) -> MarginfiResult<I80F48> {
+        let conf_interval = conf
+            .checked_mul(self.multiple.unwrap_or(default_multiple))
+            .ok_or_else(math_error!())?;
+
+        let max_conf_interval = price
+            .checked_mul(self.max_conf_interval)
+            .ok_or_else(math_error!())?;
+
+        assert!(
+            max_conf_interval >= I80F48::ZERO,
+            "Negative max confidence interval"
+        );
+
+        assert!(
+            conf_interval >= I80F48::ZERO,
+            "Negative confidence interval"
+        );
+
+        if self.reject_wide_conf_interval {
+            check!(
+                conf_interval <= max_conf_interval,
+                MarginfiError::OracleConfidenceTooWide
+            );
+        }
+
+        Ok(min(conf_interval, max_conf_interval))
+    }
+}
+
 #[enum_dispatch(PriceAdapter)]
 #[cfg_attr(feature = "client", derive(Clone))]
 pub enum OraclePriceFeedAdapter {
@@ -68,146 +165,684 @@ pub enum OraclePriceFeedAdapter {
     SwitchboardV2(SwitchboardV2PriceFeed),
     PythPushOracle(PythPushOraclePriceFeed),
     SwitchboardPull(SwitchboardPullPriceFeed),
+    Median(MedianPriceFeed),
+    Fixed(FixedPriceFeed),
+    Peg(PegPriceFeed),
+    StakePool(StakePoolPriceFeed),
+    Twap(TwapPriceFeed),
 }
 
 impl OraclePriceFeedAdapter {
-    pub fn try_from_bank_config(
-        bank_config: &BankConfig,
+    pub fn try_from_bank(bank: &Bank, ais: &[AccountInfo], clock: &Clock) -> MarginfiResult<Self> {
+        Self::try_from_bank_with_max_age(bank, ais, clock, bank.config.get_oracle_max_age())
+    }
+
+    pub fn try_from_bank_with_max_age(
+        bank: &Bank,
         ais: &[AccountInfo],
         clock: &Clock,
+        max_age: u64,
     ) -> MarginfiResult<Self> {
-        Self::try_from_bank_config_with_max_age(
-            bank_config,
-            ais,
-            clock,
-            bank_config.get_oracle_max_age(),
-        )
+        Self::try_from_bank_with_source(bank, ais, clock, max_age).map(|(price_feed, _)| price_feed)
     }
 
-    pub fn try_from_bank_config_with_max_age(
-        bank_config: &BankConfig,
+    /// Load the bank's price feed, falling back to the secondary feed if the primary is stale.
+    /// Also returns which of the two priced the bank.
+    ///
+    /// Banks with a `twap_window` get their time weighted price from the program maintained TWAP.
+    pub fn try_from_bank_with_source(
+        bank: &Bank,
         ais: &[AccountInfo],
         clock: &Clock,
         max_age: u64,
-    ) -> MarginfiResult<Self> {
+    ) -> MarginfiResult<(Self, OracleSource)> {
+        let (price_feed, oracle_source) = Self::load_with_source(bank, ais, clock, max_age)?;
+
+        if bank.twap_window == 0 {
+            return Ok((price_feed, oracle_source));
+        }
+
+        let twap = bank.get_twap_price(clock.unix_timestamp)?;
+
+        Ok((
+            OraclePriceFeedAdapter::Twap(TwapPriceFeed::new(price_feed, twap)),
+            oracle_source,
+        ))
+    }
+
+    fn load_with_source(
+        bank: &Bank,
+        ais: &[AccountInfo],
+        clock: &Clock,
+        max_age: u64,
+    ) -> MarginfiResult<(Self, OracleSource)> {
+        let bank_config = &bank.config;
+        let conf_interval_config = ConfIntervalConfig::from_bank(bank);
+
         match bank_config.oracle_setup {
             OracleSetup::None => Err(MarginfiError::OracleNotSetup.into()),
-            OracleSetup::PythLegacy => {
+            OracleSetup::Fixed => {
+                check!(ais.is_empty(), MarginfiError::InvalidOracleAccount);
+
+                Ok((
+                    OraclePriceFeedAdapter::Fixed(FixedPriceFeed::new(bank.get_fixed_price()?)),
+                    OracleSource::Primary,
+                ))
+            }
+            OracleSetup::Peg => {
                 check!(ais.len() == 1, MarginfiError::InvalidOracleAccount);
-                check!(
-                    ais[0].key == &bank_config.oracle_keys[0],
-                    MarginfiError::InvalidOracleAccount
-                );
 
-                let account_info = &ais[0];
+                let feed = Self::load_source_checked(
+                    bank_config.oracle_source_setups[0],
+                    &bank_config.oracle_keys[0],
+                    &ais[0],
+                    clock,
+                    max_age,
+                    conf_interval_config,
+                )?;
 
-                Ok(OraclePriceFeedAdapter::PythLegacy(
-                    PythLegacyPriceFeed::load_checked(account_info, clock.unix_timestamp, max_age)?,
+                let price_feed = PegPriceFeed::new(
+                    bank.get_fixed_price()?,
+                    feed,
+                    bank_config.get_oracle_max_deviation(),
+                );
+
+                Ok((
+                    OraclePriceFeedAdapter::Peg(price_feed),
+                    OracleSource::Primary,
                 ))
             }
-            OracleSetup::SwitchboardV2 => {
-                check!(ais.len() == 1, MarginfiError::InvalidOracleAccount);
+            OracleSetup::StakePool => {
+                check!(ais.len() == 2, MarginfiError::InvalidOracleAccount);
                 check!(
-                    ais[0].key == &bank_config.oracle_keys[0],
+                    ais[1].key == &bank_config.oracle_keys[1],
                     MarginfiError::InvalidOracleAccount
                 );
 
-                Ok(OraclePriceFeedAdapter::SwitchboardV2(
-                    SwitchboardV2PriceFeed::load_checked(&ais[0], clock.unix_timestamp, max_age)?,
-                ))
-            }
-            OracleSetup::PythPushOracle => {
-                check!(ais.len() == 1, MarginfiError::InvalidOracleAccount);
+                let sol_feed = Self::load_source_checked(
+                    OracleSetup::PythPushOracle,
+                    &bank_config.oracle_keys[0],
+                    &ais[0],
+                    clock,
+                    max_age,
+                    conf_interval_config,
+                )?;
 
-                let account_info = &ais[0];
+                let price_feed = StakePoolPriceFeed::load_checked(sol_feed, &ais[1], clock)?;
 
+                Ok((
+                    OraclePriceFeedAdapter::StakePool(price_feed),
+                    OracleSource::Primary,
+                ))
+            }
+            OracleSetup::Median => {
+                let sources = bank_config.get_oracle_sources();
                 check!(
-                    account_info.owner == &pyth_solana_receiver_sdk::id(),
+                    ais.len() == sources.len(),
                     MarginfiError::InvalidOracleAccount
                 );
 
-                let price_feed_id = bank_config.get_pyth_push_oracle_feed_id().unwrap();
+                let price_feed = MedianPriceFeed::load_checked(
+                    &sources,
+                    ais,
+                    clock,
+                    max_age,
+                    bank_config.get_oracle_max_deviation(),
+                    conf_interval_config,
+                )?;
 
-                Ok(OraclePriceFeedAdapter::PythPushOracle(
-                    PythPushOraclePriceFeed::load_checked(
-                        account_info,
-                        price_feed_id,
-                        clock,
-                        max_age,
-                    )?,
+                Ok((
+                    OraclePriceFeedAdapter::Median(price_feed),
+                    OracleSource::Primary,
                 ))
             }
-            OracleSetup::SwitchboardPull => {
-                check!(ais.len() == 1, MarginfiError::InvalidOracleAccount);
+            oracle_setup => {
                 check!(
-                    ais[0].key == &bank_config.oracle_keys[0],
+                    ais.len() == bank_config.get_oracle_ais_len(),
                     MarginfiError::InvalidOracleAccount
                 );
 
-                Ok(OraclePriceFeedAdapter::SwitchboardPull(
-                    SwitchboardPullPriceFeed::load_checked(&ais[0], clock.unix_timestamp, max_age)?,
-                ))
+                let primary = Self::load_source_checked(
+                    oracle_setup,
+                    &bank_config.oracle_keys[0],
+                    &ais[0],
+                    clock,
+                    max_age,
+                    conf_interval_config,
+                );
+
+                match primary {
+                    Err(e) if e == MarginfiError::StaleOracle.into() => {
+                        if !bank_config.has_oracle_fallback() {
+                            return Err(e);
+                        }
+
+                        debug!("Primary oracle is stale, using fallback {}", ais[1].key);
+
+                        let fallback = Self::load_source_checked(
+                            bank_config.oracle_fallback_setup,
+                            &bank_config.oracle_keys[1],
+                            &ais[1],
+                            clock,
+                            max_age,
+                            conf_interval_config,
+                        )?;
+
+                        Ok((fallback, OracleSource::Fallback))
+                    }
+                    primary => Ok((primary?, OracleSource::Primary)),
+                }
             }
         }
     }
 
-    pub fn validate_bank_config(
-        bank_config: &BankConfig,
-        oracle_ais: &[AccountInfo],
-    ) -> MarginfiResult {
-        match bank_config.oracle_setup {
-            OracleSetup::None => Err(MarginfiError::OracleNotSetup.into()),
+    /// Load a single price feed of type `oracle_setup`. `key` is the oracle account address, or
+    /// the feed id for Pyth push oracles.
+    fn load_source_checked(
+        oracle_setup: OracleSetup,
+        key: &Pubkey,
+        ai: &AccountInfo,
+        clock: &Clock,
+        max_age: u64,
+        conf_interval_config: ConfIntervalConfig,
+    ) -> MarginfiResult<Self> {
+        match oracle_setup {
+            OracleSetup::None
+            | OracleSetup::Median
+            | OracleSetup::Fixed
+            | OracleSetup::Peg
+            | OracleSetup::StakePool => err!(MarginfiError::InvalidOracleSetup),
             OracleSetup::PythLegacy => {
-                check!(oracle_ais.len() == 1, MarginfiError::InvalidOracleAccount);
-                check!(
-                    oracle_ais[0].key == &bank_config.oracle_keys[0],
-                    MarginfiError::InvalidOracleAccount
-                );
+                check!(ai.key == key, MarginfiError::InvalidOracleAccount);
 
-                PythLegacyPriceFeed::check_ais(&oracle_ais[0])?;
+                let mut price_feed =
+                    PythLegacyPriceFeed::load_checked(ai, clock.unix_timestamp, max_age)?;
+                price_feed.conf_interval_config = conf_interval_config;
 
-                Ok(())
+                Ok(OraclePriceFeedAdapter::PythLegacy(price_feed))
             }
             OracleSetup::SwitchboardV2 => {
-                check!(oracle_ais.len() == 1, MarginfiError::InvalidOracleAccount);
+                check!(ai.key == key, MarginfiError::InvalidOracleAccount);
+
+                let mut price_feed =
+                    SwitchboardV2PriceFeed::load_checked(ai, clock.unix_timestamp, max_age)?;
+                price_feed.conf_interval_config = conf_interval_config;
+
+                Ok(OraclePriceFeedAdapter::SwitchboardV2(price_feed))
+            }
+            OracleSetup::PythPushOracle => {
                 check!(
-                    oracle_ais[0].key == &bank_config.oracle_keys[0],
+                    ai.owner == &pyth_solana_receiver_sdk::id(),
                     MarginfiError::InvalidOracleAccount
                 );
 
-                SwitchboardV2PriceFeed::check_ais(&oracle_ais[0])?;
+                let price_feed_id: &FeedId = key.as_ref().try_into().unwrap();
+
+                let mut price_feed =
+                    PythPushOraclePriceFeed::load_checked(ai, price_feed_id, clock, max_age)?;
+                price_feed.conf_interval_config = conf_interval_config;
 
-                Ok(())
+                Ok(OraclePriceFeedAdapter::PythPushOracle(price_feed))
             }
-            OracleSetup::PythPushOracle => {
-                check!(oracle_ais.len() == 1, MarginfiError::InvalidOracleAccount);
+            OracleSetup::SwitchboardPull => {
+                check!(ai.key == key, MarginfiError::InvalidOracleAccount);
 
-                PythPushOraclePriceFeed::check_ai_and_feed_id(
-                    &oracle_ais[0],
-                    bank_config.get_pyth_push_oracle_feed_id().unwrap(),
-                )?;
+                let mut price_feed =
+                    SwitchboardPullPriceFeed::load_checked(ai, clock.unix_timestamp, max_age)?;
+                price_feed.conf_interval_config = conf_interval_config;
 
-                Ok(())
+                Ok(OraclePriceFeedAdapter::SwitchboardPull(price_feed))
             }
-            OracleSetup::SwitchboardPull => {
-                check!(oracle_ais.len() == 1, MarginfiError::InvalidOracleAccount);
+        }
+    }
+
+    pub fn validate_bank(bank: &Bank, oracle_ais: &[AccountInfo]) -> MarginfiResult {
+        let bank_config = &bank.config;
+
+        if matches!(bank_config.oracle_setup, OracleSetup::None) {
+            return err!(MarginfiError::OracleNotSetup);
+        }
+
+        if bank_config.has_oracle_fallback() {
+            check!(
+                !matches!(
+                    bank_config.oracle_setup,
+                    OracleSetup::Median
+                        | OracleSetup::Fixed
+                        | OracleSetup::Peg
+                        | OracleSetup::StakePool
+                ),
+                MarginfiError::InvalidOracleSetup,
+                "Fallback oracles are only supported for single feed oracle setups"
+            );
+        }
+
+        let sources = bank_config.get_oracle_sources();
+        check!(
+            oracle_ais.len() == sources.len(),
+            MarginfiError::InvalidOracleAccount
+        );
+
+        match bank_config.oracle_setup {
+            OracleSetup::Median => {
+                check!(
+                    sources.len() >= 2,
+                    MarginfiError::InvalidOracleSetup,
+                    "Median oracle setup requires at least 2 sources"
+                );
+            }
+            OracleSetup::Fixed => {
+                bank.get_fixed_price()?;
+            }
+            OracleSetup::Peg => {
+                bank.get_fixed_price()?;
+                check!(
+                    bank_config.oracle_max_deviation_bps != 0,
+                    MarginfiError::InvalidOracleSetup,
+                    "Peg oracle setup requires a max deviation"
+                );
+            }
+            OracleSetup::StakePool => {
+                Self::validate_source(
+                    OracleSetup::PythPushOracle,
+                    &bank_config.oracle_keys[0],
+                    &oracle_ais[0],
+                )?;
                 check!(
-                    oracle_ais[0].key == &bank_config.oracle_keys[0],
+                    oracle_ais[1].key == &bank_config.oracle_keys[1],
                     MarginfiError::InvalidOracleAccount
                 );
 
-                SwitchboardPullPriceFeed::check_ais(&oracle_ais[0])?;
+                return StakePoolPriceFeed::check_ais(&oracle_ais[1], &bank.mint);
+            }
+            _ => {}
+        }
+
+        for ((oracle_setup, key), ai) in sources.iter().zip(oracle_ais) {
+            Self::validate_source(*oracle_setup, key, ai)?;
+        }
+
+        Ok(())
+    }
+
+    fn validate_source(
+        oracle_setup: OracleSetup,
+        key: &Pubkey,
+        ai: &AccountInfo,
+    ) -> MarginfiResult {
+        match oracle_setup {
+            OracleSetup::None
+            | OracleSetup::Median
+            | OracleSetup::Fixed
+            | OracleSetup::Peg
+            | OracleSetup::StakePool => err!(MarginfiError::InvalidOracleSetup),
+            OracleSetup::PythLegacy => {
+                check!(ai.key == key, MarginfiError::InvalidOracleAccount);
+
+                PythLegacyPriceFeed::check_ais(ai)
+            }
+            OracleSetup::SwitchboardV2 => {
+                check!(ai.key == key, MarginfiError::InvalidOracleAccount);
+
+                SwitchboardV2PriceFeed::check_ais(ai)
+            }
+            OracleSetup::PythPushOracle => {
+                let price_feed_id: &FeedId = key.as_ref().try_into().unwrap();
+
+                PythPushOraclePriceFeed::check_ai_and_feed_id(ai, price_feed_id)
+            }
+            OracleSetup::SwitchboardPull => {
+                check!(ai.key == key, MarginfiError::InvalidOracleAccount);
 
-                Ok(())
+                SwitchboardPullPriceFeed::check_ais(ai)
             }
         }
     }
 }
 
+/// Median of several price feeds, see `OracleSetup::Median`.
+///
+/// Stale sources are skipped, as long as more than half of the configured sources are fresh.
+/// If the fresh prices are further apart than the bank's `oracle_max_deviation_bps` (relative
+/// to the median), the feed falls back to the most conservative source price for the requested
+/// bias, and refuses to price unbiased requests.
+#[cfg_attr(feature = "client", derive(Clone))]
+pub struct MedianPriceFeed {
+    feeds: Vec<OraclePriceFeedAdapter>,
+    /// 0 disables the divergence guard.
+    max_deviation: I80F48,
+}
+
+impl MedianPriceFeed {
+    pub fn load_checked(
+        sources: &[(OracleSetup, Pubkey)],
+        ais: &[AccountInfo],
+        clock: &Clock,
+        max_age: u64,
+        max_deviation: I80F48,
+        conf_interval_config: ConfIntervalConfig,
+    ) -> MarginfiResult<Self> {
+        let mut feeds = Vec::with_capacity(sources.len());
+
+        for ((oracle_setup, key), ai) in sources.iter().zip(ais) {
+            let feed = OraclePriceFeedAdapter::load_source_checked(
+                *oracle_setup,
+                key,
+                ai,
+                clock,
+                max_age,
+                conf_interval_config,
+            );
+
+            match feed {
+                Ok(feed) => feeds.push(feed),
+                Err(e) if e == MarginfiError::StaleOracle.into() => {
+                    debug!("Skipping stale median source {}", ai.key);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        check!(
+            feeds.len() * 2 > sources.len(),
+            MarginfiError::StaleOracle,
+            "Not enough fresh median sources"
+        );
+
+        Ok(Self {
+            feeds,
+            max_deviation,
+        })
+    }
+
+    fn get_source_prices(
+        &self,
+        price_type: OraclePriceType,
+        bias: Option<PriceBias>,
+    ) -> MarginfiResult<Vec<I80F48>> {
+        self.feeds
+            .iter()
+            .map(|feed| feed.get_price_of_type(price_type, bias))
+            .collect()
+    }
+}
+
+impl PriceAdapter for MedianPriceFeed {
+    fn get_price_of_type(
+        &self,
+        price_type: OraclePriceType,
+        bias: Option<PriceBias>,
+    ) -> MarginfiResult<I80F48> {
+        let mut prices = self.get_source_prices(price_type, None)?;
+        prices.sort();
+
+        let diverged = self.max_deviation != I80F48::ZERO
+            && calc_price_deviation(&prices)? > self.max_deviation;
+
+        if diverged {
+            debug!("Median oracle sources diverge");
+        }
+
+        match (bias, diverged) {
+            (None, false) => calc_median(&prices),
+            (None, true) => err!(MarginfiError::OracleSourcesDiverged),
+            (Some(price_bias), diverged) => {
+                let mut prices = self.get_source_prices(price_type, Some(price_bias))?;
+                prices.sort();
+
+                match (price_bias, diverged) {
+                    (_, false) => calc_median(&prices),
+                    (PriceBias::Low, true) => Ok(prices[0]),
+                    (PriceBias::High, true) => Ok(prices[prices.len() - 1]),
+                }
+            }
+        }
+    }
+}
+
+/// Median of sorted `prices`, the mean of the two middle prices for an even count.
+fn calc_median(prices: &[I80F48]) -> MarginfiResult<I80F48> {
+    check!(!prices.is_empty(), MarginfiError::InvalidPrice);
+
+    let mid = prices.len() / 2;
+
+    if prices.len() % 2 == 1 {
+        Ok(prices[mid])
+    } else {
+        let sum = prices[mid - 1]
+            .checked_add(prices[mid])
+            .ok_or_else(math_error!())?;
+
+        Ok(sum
+            .checked_div(I80F48::from_num(2))
+            .ok_or_else(math_error!())?)
+    }
+}
+
+/// Spread of sorted `prices` relative to their median: `(max - min) / median`.
+fn calc_price_deviation(prices: &[I80F48]) -> MarginfiResult<I80F48> {
+    let median = calc_median(prices)?;
+    check!(median > I80F48::ZERO, MarginfiError::InvalidPrice);
+
+    let spread = prices[prices.len() - 1]
+        .checked_sub(prices[0])
+        .ok_or_else(math_error!())?;
+
+    Ok(spread.checked_div(median).ok_or_else(math_error!())?)
+}
+
+/// Admin set price, see `OracleSetup::Fixed`. It has no confidence interval or history, so
+/// every price type and bias returns the same price.
+#[cfg_attr(feature = "client", derive(Clone, Debug))]
+pub struct FixedPriceFeed {
+    price: I80F48,
+}
+
+impl FixedPriceFeed {
+    pub fn new(price: I80F48) -> Self {
+        Self { price }
+    }
+}
+
+impl PriceAdapter for FixedPriceFeed {
+    fn get_price_of_type(
+        &self,
+        _price_type: OraclePriceType,
+        _bias: Option<PriceBias>,
+    ) -> MarginfiResult<I80F48> {
+        Ok(self.price)
+    }
+}
+
+/// Fixed price guarded by a live feed, see `OracleSetup::Peg`.
+#[cfg_attr(feature = "client", derive(Clone))]
+pub struct PegPriceFeed {
+    fixed_price: I80F48,
+    feed: Box<OraclePriceFeedAdapter>,
+    max_deviation: I80F48,
+}
+
+impl PegPriceFeed {
+    pub fn new(fixed_price: I80F48, feed: OraclePriceFeedAdapter, max_deviation: I80F48) -> Self {
+        Self {
+            fixed_price,
+            feed: Box::new(feed),
+            max_deviation,
+        }
+    }
+}
+
+impl PriceAdapter for PegPriceFeed {
+    fn get_price_of_type(
+        &self,
+        price_type: OraclePriceType,
+        bias: Option<PriceBias>,
+    ) -> MarginfiResult<I80F48> {
+        let feed_price = self.feed.get_price_of_type(price_type, None)?;
+
+        let deviation = feed_price
+            .checked_sub(self.fixed_price)
+            .ok_or_else(math_error!())?
+            .abs()
+            .checked_div(self.fixed_price)
+            .ok_or_else(math_error!())?;
+
+        if deviation > self.max_deviation {
+            debug!("Peg broken, using the feed price");
+            return self.feed.get_price_of_type(price_type, bias);
+        }
+
+        Ok(self.fixed_price)
+    }
+}
+
+/// Spot feed whose time weighted price comes from the bank's own TWAP, see `Bank::twap_window`.
+///
+/// Biased time weighted prices take the more conservative of the TWAP and the biased spot price,
+/// so a manipulated spot price can't raise collateral or lower liability values. Falls back to
+/// the spot feed while no observation was recorded within the window.
+#[cfg_attr(feature = "client", derive(Clone))]
+pub struct TwapPriceFeed {
+    spot_feed: Box<OraclePriceFeedAdapter>,
+    twap: Option<I80F48>,
+}
+
+impl TwapPriceFeed {
+    pub fn new(spot_feed: OraclePriceFeedAdapter, twap: Option<I80F48>) -> Self {
+        Self {
+            spot_feed: Box::new(spot_feed),
+            twap,
+        }
+    }
+}
+
+impl PriceAdapter for TwapPriceFeed {
+    fn get_price_of_type(
+        &self,
+        price_type: OraclePriceType,
+        bias: Option<PriceBias>,
+    ) -> MarginfiResult<I80F48> {
+        let twap = match (price_type, self.twap) {
+            (OraclePriceType::TimeWeighted, Some(twap)) => twap,
+            _ => return self.spot_feed.get_price_of_type(price_type, bias),
+        };
+
+        let spot_price = self
+            .spot_feed
+            .get_price_of_type(OraclePriceType::RealTime, bias)?;
+
+        Ok(match bias {
+            Some(PriceBias::Low) => min(twap, spot_price),
+            Some(PriceBias::High) => max(twap, spot_price),
+            None => twap,
+        })
+    }
+}
+
+/// Offsets of the fields read from a borsh serialized SPL `StakePool` account.
+const STAKE_POOL_ACCOUNT_TYPE: u8 = 1;
+const STAKE_POOL_POOL_MINT_OFFSET: usize = 162;
+const STAKE_POOL_TOTAL_LAMPORTS_OFFSET: usize = 258;
+const STAKE_POOL_POOL_TOKEN_SUPPLY_OFFSET: usize = 266;
+const STAKE_POOL_LAST_UPDATE_EPOCH_OFFSET: usize = 274;
+
+/// SPL stake pool token priced off SOL, see `OracleSetup::StakePool`.
+///
+/// The exchange rate only moves when the pool is updated, once per epoch. A pool that hasn't
+/// been updated in the current epoch is treated as a stale oracle, as the pool itself refuses
+/// deposits and withdrawals until then.
+#[cfg_attr(feature = "client", derive(Clone))]
+pub struct StakePoolPriceFeed {
+    sol_feed: Box<OraclePriceFeedAdapter>,
+    /// Lamports per pool token, pool tokens and SOL both have 9 decimals.
+    exchange_rate: I80F48,
+}
+
+impl StakePoolPriceFeed {
+    pub fn load_checked(
+        sol_feed: OraclePriceFeedAdapter,
+        stake_pool_ai: &AccountInfo,
+        clock: &Clock,
+    ) -> MarginfiResult<Self> {
+        let data = Self::load_stake_pool_data(stake_pool_ai)?;
+
+        let last_update_epoch = read_u64_le(&data, STAKE_POOL_LAST_UPDATE_EPOCH_OFFSET);
+        check!(
+            last_update_epoch.saturating_add(MAX_STAKE_POOL_EPOCH_LAG) >= clock.epoch,
+            MarginfiError::StaleOracle,
+            "Stake pool not updated since the previous epoch"
+        );
+
+        let total_lamports = read_u64_le(&data, STAKE_POOL_TOTAL_LAMPORTS_OFFSET);
+        let pool_token_supply = read_u64_le(&data, STAKE_POOL_POOL_TOKEN_SUPPLY_OFFSET);
+        check!(pool_token_supply > 0, MarginfiError::InvalidPrice);
+
+        let exchange_rate = I80F48::from_num(total_lamports)
+            .checked_div(I80F48::from_num(pool_token_supply))
+            .ok_or_else(math_error!())?;
+
+        Ok(Self {
+            sol_feed: Box::new(sol_feed),
+            exchange_rate,
+        })
+    }
+
+    /// Checks that `stake_pool_ai` is a stake pool whose pool token is `mint`.
+    pub fn check_ais(stake_pool_ai: &AccountInfo, mint: &Pubkey) -> MarginfiResult {
+        let data = Self::load_stake_pool_data(stake_pool_ai)?;
+
+        let pool_mint = &data[STAKE_POOL_POOL_MINT_OFFSET..STAKE_POOL_POOL_MINT_OFFSET + 32];
+        check!(
+            pool_mint == mint.as_ref(),
+            MarginfiError::InvalidOracleAccount,
+            "Stake pool token is not the bank mint"
+        );
+
+        Ok(())
+    }
+
+    fn load_stake_pool_data<'a>(
+        stake_pool_ai: &'a AccountInfo,
+    ) -> MarginfiResult<Ref<'a, &'a mut [u8]>> {
+        check!(
+            stake_pool_ai.owner == &SPL_STAKE_POOL_ID,
+            MarginfiError::InvalidOracleAccount
+        );
+
+        let data = stake_pool_ai.try_borrow_data()?;
+        check!(
+            data.len() >= STAKE_POOL_LAST_UPDATE_EPOCH_OFFSET + 8
+                && data[0] == STAKE_POOL_ACCOUNT_TYPE,
+            MarginfiError::InvalidOracleAccount
+        );
+
+        Ok(data)
+    }
+}
+
+impl PriceAdapter for StakePoolPriceFeed {
+    fn get_price_of_type(
+        &self,
+        price_type: OraclePriceType,
+        bias: Option<PriceBias>,
+    ) -> MarginfiResult<I80F48> {
+        let sol_price = self.sol_feed.get_price_of_type(price_type, bias)?;
+
+        Ok(sol_price
+            .checked_mul(self.exchange_rate)
+            .ok_or_else(math_error!())?)
+    }
+}
+
+fn read_u64_le(data: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap())
+}
+
 #[cfg_attr(feature = "client", derive(Clone, Debug))]
 pub struct PythLegacyPriceFeed {
     ema_price: Box<Price>,
     price: Box<Price>,
+    conf_interval_config: ConfIntervalConfig,
 }
 
 impl PythLegacyPriceFeed {
@@ -225,6 +860,7 @@ impl PythLegacyPriceFeed {
         Ok(Self {
             ema_price: Box::new(ema_price),
             price: Box::new(price),
+            conf_interval_config: ConfIntervalConfig::default(),
         })
     }
 
@@ -240,29 +876,11 @@ impl PythLegacyPriceFeed {
             &self.price
         };
 
-        let conf_interval =
-            pyth_price_components_to_i80f48(I80F48::from_num(price.conf), price.expo)?
-                .checked_mul(CONF_INTERVAL_MULTIPLE)
-                .ok_or_else(math_error!())?;
-
-        // Cap confidence interval to 5% of price
+        let conf = pyth_price_components_to_i80f48(I80F48::from_num(price.conf), price.expo)?;
         let price = pyth_price_components_to_i80f48(I80F48::from_num(price.price), price.expo)?;
 
-        let max_conf_interval = price
-            .checked_mul(MAX_CONF_INTERVAL)
-            .ok_or_else(math_error!())?;
-
-        assert!(
-            max_conf_interval >= I80F48::ZERO,
-            "Negative max confidence interval"
-        );
-
-        assert!(
-            conf_interval >= I80F48::ZERO,
-            "Negative confidence interval"
-        );
-
-        Ok(min(conf_interval, max_conf_interval))
+        self.conf_interval_config
+            .calc_conf_interval(conf, price, CONF_INTERVAL_MULTIPLE)
     }
 
     #[inline(always)]
@@ -309,6 +927,7 @@ impl PriceAdapter for PythLegacyPriceFeed {
 #[cfg_attr(feature = "client", derive(Clone, Debug))]
 pub struct SwitchboardPullPriceFeed {
     pub feed: Box<LitePullFeedAccountData>,
+    pub conf_interval_config: ConfIntervalConfig,
 }
 
 impl SwitchboardPullPriceFeed {
@@ -335,6 +954,7 @@ impl SwitchboardPullPriceFeed {
 
         Ok(Self {
             feed: Box::new(feed.into()),
+            conf_interval_config: ConfIntervalConfig::default(),
         })
     }
 
@@ -369,27 +989,10 @@ impl SwitchboardPullPriceFeed {
     fn get_confidence_interval(&self) -> MarginfiResult<I80F48> {
         let std_div: I80F48 = I80F48::from_num(self.feed.result.std_dev);
 
-        let conf_interval = std_div
-            .checked_mul(STD_DEV_MULTIPLE)
-            .ok_or_else(math_error!())?;
-
         let price = self.get_price()?;
 
-        let max_conf_interval = price
-            .checked_mul(MAX_CONF_INTERVAL)
-            .ok_or_else(math_error!())?;
-
-        assert!(
-            max_conf_interval >= I80F48::ZERO,
-            "Negative max confidence interval"
-        );
-
-        assert!(
-            conf_interval >= I80F48::ZERO,
-            "Negative confidence interval"
-        );
-
-        Ok(min(conf_interval, max_conf_interval))
+        self.conf_interval_config
+            .calc_conf_interval(std_div, price, STD_DEV_MULTIPLE)
     }
 }
 
@@ -422,6 +1025,7 @@ impl PriceAdapter for SwitchboardPullPriceFeed {
 #[cfg_attr(feature = "client", derive(Clone, Debug))]
 pub struct SwitchboardV2PriceFeed {
     aggregator_account: Box<LiteAggregatorAccountData>,
+    conf_interval_config: ConfIntervalConfig,
 }
 
 impl SwitchboardV2PriceFeed {
@@ -446,6 +1050,7 @@ impl SwitchboardV2PriceFeed {
 
         Ok(Self {
             aggregator_account: Box::new(aggregator_account.into()),
+            conf_interval_config: ConfIntervalConfig::default(),
         })
     }
 
@@ -478,27 +1083,10 @@ impl SwitchboardV2PriceFeed {
         let std_div = switchboard_decimal_to_i80f48(std_div)
             .ok_or(MarginfiError::InvalidSwitchboardDecimalConversion)?;
 
-        let conf_interval = std_div
-            .checked_mul(STD_DEV_MULTIPLE)
-            .ok_or_else(math_error!())?;
-
         let price = self.get_price()?;
 
-        let max_conf_interval = price
-            .checked_mul(MAX_CONF_INTERVAL)
-            .ok_or_else(math_error!())?;
-
-        assert!(
-            max_conf_interval >= I80F48::ZERO,
-            "Negative max confidence interval"
-        );
-
-        assert!(
-            conf_interval >= I80F48::ZERO,
-            "Negative confidence interval"
-        );
-
-        Ok(min(conf_interval, max_conf_interval))
+        self.conf_interval_config
+            .calc_conf_interval(std_div, price, STD_DEV_MULTIPLE)
     }
 }
 
@@ -551,6 +1139,7 @@ pub fn load_price_update_v2_checked(ai: &AccountInfo) -> MarginfiResult<PriceUpd
 pub struct PythPushOraclePriceFeed {
     ema_price: Box<pyth_solana_receiver_sdk::price_update::Price>,
     price: Box<pyth_solana_receiver_sdk::price_update::Price>,
+    conf_interval_config: ConfIntervalConfig,
 }
 
 impl PythPushOraclePriceFeed {
@@ -616,6 +1205,7 @@ impl PythPushOraclePriceFeed {
         Ok(Self {
             price: Box::new(price),
             ema_price: Box::new(ema_price),
+            conf_interval_config: ConfIntervalConfig::default(),
         })
     }
 
@@ -656,6 +1246,7 @@ impl PythPushOraclePriceFeed {
         Ok(Self {
             price: Box::new(price),
             ema_price: Box::new(ema_price),
+            conf_interval_config: ConfIntervalConfig::default(),
         })
     }
 
@@ -684,29 +1275,11 @@ impl PythPushOraclePriceFeed {
             &self.price
         };
 
-        let conf_interval =
-            pyth_price_components_to_i80f48(I80F48::from_num(price.conf), price.exponent)?
-                .checked_mul(CONF_INTERVAL_MULTIPLE)
-                .ok_or_else(math_error!())?;
-
-        // Cap confidence interval to 5% of price
+        let conf = pyth_price_components_to_i80f48(I80F48::from_num(price.conf), price.exponent)?;
         let price = pyth_price_components_to_i80f48(I80F48::from_num(price.price), price.exponent)?;
 
-        let max_conf_interval = price
-            .checked_mul(MAX_CONF_INTERVAL)
-            .ok_or_else(math_error!())?;
-
-        assert!(
-            max_conf_interval >= I80F48::ZERO,
-            "Negative max confidence interval"
-        );
-
-        assert!(
-            conf_interval >= I80F48::ZERO,
-            "Negative confidence interval"
-        );
-
-        Ok(min(conf_interval, max_conf_interval))
+        self.conf_interval_config
+            .calc_conf_interval(conf, price, CONF_INTERVAL_MULTIPLE)
     }
 
     #[inline(always)]
@@ -965,6 +1538,7 @@ mod tests {
         let pyth_adapter = PythLegacyPriceFeed {
             ema_price: high_confidence_price,
             price: low_confidence_price,
+            conf_interval_config: ConfIntervalConfig::default(),
         };
 
         // Test confidence interval when using EMA price (high confidence)
@@ -990,6 +1564,7 @@ mod tests {
                 latest_confirmed_round_std_deviation: SwitchboardDecimal::from_f64(10.0),
                 min_oracle_results: 1,
             }),
+            conf_interval_config: ConfIntervalConfig::default(),
         };
 
         let swb_adapter_low_confidence = SwitchboardV2PriceFeed {
@@ -1000,6 +1575,7 @@ mod tests {
                 latest_confirmed_round_std_deviation: SwitchboardDecimal::from_f64(1.0),
                 min_oracle_results: 1,
             }),
+            conf_interval_config: ConfIntervalConfig::default(),
         };
 
         // Test confidence interval
@@ -1018,6 +1594,180 @@ mod tests {
         assert_eq!(low_conf_interval, I80F48!(1.96));
     }
 
+    #[test]
+    fn bank_conf_interval_config() {
+        // Defaults
+        let conf_interval_config = ConfIntervalConfig::from_bank(&Bank::default());
+        assert_eq!(
+            conf_interval_config
+                .calc_conf_interval(I80F48!(10), I80F48!(100), CONF_INTERVAL_MULTIPLE)
+                .unwrap(),
+            I80F48!(5.00000000000007)
+        );
+
+        // Custom multiple and cap
+        let bank = Bank {
+            oracle_conf_interval_multiple: I80F48!(1).into(),
+            oracle_max_conf_interval: I80F48!(0.5).into(),
+            ..Default::default()
+        };
+        let conf_interval_config = ConfIntervalConfig::from_bank(&bank);
+        assert_eq!(
+            conf_interval_config
+                .calc_conf_interval(I80F48!(10), I80F48!(100), CONF_INTERVAL_MULTIPLE)
+                .unwrap(),
+            I80F48!(10)
+        );
+
+        // Wide confidence intervals are rejected instead of capped
+        let bank = Bank {
+            oracle_conf_interval_mode: CONF_INTERVAL_MODE_REJECT,
+            ..Default::default()
+        };
+        let conf_interval_config = ConfIntervalConfig::from_bank(&bank);
+        assert_eq!(
+            conf_interval_config
+                .calc_conf_interval(I80F48!(1), I80F48!(100), STD_DEV_MULTIPLE)
+                .unwrap(),
+            I80F48!(1.96)
+        );
+        assert!(conf_interval_config
+            .calc_conf_interval(I80F48!(10), I80F48!(100), STD_DEV_MULTIPLE)
+            .is_err());
+    }
+
+    fn swb_v2_feed(price: f64) -> OraclePriceFeedAdapter {
+        OraclePriceFeedAdapter::SwitchboardV2(SwitchboardV2PriceFeed {
+            aggregator_account: Box::new(LiteAggregatorAccountData {
+                resolution_mode: AggregatorResolutionMode::ModeSlidingResolution,
+                latest_confirmed_round_result: SwitchboardDecimal::from_f64(price),
+                latest_confirmed_round_num_success: 1,
+                latest_confirmed_round_std_deviation: SwitchboardDecimal::from_f64(0.),
+                min_oracle_results: 1,
+            }),
+            conf_interval_config: ConfIntervalConfig::default(),
+        })
+    }
+
+    #[test]
+    fn median_price_feed() {
+        assert_eq!(
+            calc_median(&[I80F48!(100), I80F48!(102)]).unwrap(),
+            I80F48!(101)
+        );
+        assert_eq!(
+            calc_price_deviation(&[I80F48!(50), I80F48!(100), I80F48!(150)]).unwrap(),
+            I80F48!(1)
+        );
+
+        let median_feed = MedianPriceFeed {
+            feeds: vec![swb_v2_feed(101.), swb_v2_feed(100.), swb_v2_feed(130.)],
+            max_deviation: I80F48!(0.5),
+        };
+
+        for bias in [None, Some(PriceBias::Low), Some(PriceBias::High)] {
+            assert_eq!(
+                median_feed
+                    .get_price_of_type(OraclePriceType::RealTime, bias)
+                    .unwrap(),
+                I80F48!(101)
+            );
+        }
+
+        // Sources 30% apart exceed a 10% max deviation
+        let median_feed = MedianPriceFeed {
+            max_deviation: I80F48!(0.1),
+            ..median_feed
+        };
+
+        assert_eq!(
+            median_feed
+                .get_price_of_type(OraclePriceType::RealTime, Some(PriceBias::Low))
+                .unwrap(),
+            I80F48!(100)
+        );
+        assert_eq!(
+            median_feed
+                .get_price_of_type(OraclePriceType::RealTime, Some(PriceBias::High))
+                .unwrap(),
+            I80F48!(130)
+        );
+        assert!(median_feed
+            .get_price_of_type(OraclePriceType::RealTime, None)
+            .is_err());
+    }
+
+    #[test]
+    fn peg_price_feed() {
+        let fixed_feed = FixedPriceFeed::new(I80F48!(1));
+        assert_eq!(
+            fixed_feed
+                .get_price_of_type(OraclePriceType::TimeWeighted, Some(PriceBias::Low))
+                .unwrap(),
+            I80F48!(1)
+        );
+
+        // Feed within the band, the peg holds
+        let peg_feed = PegPriceFeed::new(I80F48!(1), swb_v2_feed(1.005), I80F48!(0.01));
+        assert_eq!(
+            peg_feed
+                .get_price_of_type(OraclePriceType::RealTime, Some(PriceBias::High))
+                .unwrap(),
+            I80F48!(1)
+        );
+
+        // Feed outside of the band, the feed price is used
+        let peg_feed = PegPriceFeed::new(I80F48!(1), swb_v2_feed(0.5), I80F48!(0.01));
+        assert_eq!(
+            peg_feed
+                .get_price_of_type(OraclePriceType::RealTime, Some(PriceBias::Low))
+                .unwrap(),
+            I80F48!(0.5)
+        );
+    }
+
+    #[test]
+    fn twap_price_feed() {
+        let twap_feed = TwapPriceFeed::new(swb_v2_feed(2.), Some(I80F48!(1)));
+
+        // Real time prices come from the spot feed
+        assert_eq!(
+            twap_feed
+                .get_price_of_type(OraclePriceType::RealTime, Some(PriceBias::Low))
+                .unwrap(),
+            I80F48!(2)
+        );
+
+        // Time weighted prices take the conservative side of the TWAP and the spot price
+        assert_eq!(
+            twap_feed
+                .get_price_of_type(OraclePriceType::TimeWeighted, Some(PriceBias::Low))
+                .unwrap(),
+            I80F48!(1)
+        );
+        assert_eq!(
+            twap_feed
+                .get_price_of_type(OraclePriceType::TimeWeighted, Some(PriceBias::High))
+                .unwrap(),
+            I80F48!(2)
+        );
+        assert_eq!(
+            twap_feed
+                .get_price_of_type(OraclePriceType::TimeWeighted, None)
+                .unwrap(),
+            I80F48!(1)
+        );
+
+        // No TWAP yet, the spot price is used
+        let twap_feed = TwapPriceFeed::new(swb_v2_feed(2.), None);
+        assert_eq!(
+            twap_feed
+                .get_price_of_type(OraclePriceType::TimeWeighted, Some(PriceBias::Low))
+                .unwrap(),
+            I80F48!(2)
+        );
+    }
+
     #[test]
     fn pyth_and_pyth_push_cmp() {
         fn get_prices(
@@ -1055,11 +1805,13 @@ mod tests {
         let pyth_legacy = PythLegacyPriceFeed {
             ema_price: Box::new(legacy_ema),
             price: Box::new(legacy_price),
+            conf_interval_config: ConfIntervalConfig::default(),
         };
 
         let pyth_push = PythPushOraclePriceFeed {
             ema_price: Box::new(push_price_ema),
             price: Box::new(push_price),
+            conf_interval_config: ConfIntervalConfig::default(),
         };
 
         assert_eq!(
@@ -1130,11 +1882,13 @@ mod tests {
         let pyth_legacy = PythLegacyPriceFeed {
             ema_price: Box::new(legacy_ema),
             price: Box::new(legacy_price),
+            conf_interval_config: ConfIntervalConfig::default(),
         };
 
         let pyth_push = PythPushOraclePriceFeed {
             ema_price: Box::new(push_price_ema),
             price: Box::new(push_price),
+            conf_interval_config: ConfIntervalConfig::default(),
         };
 
         // Test high bias ema
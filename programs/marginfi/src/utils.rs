@@ -1,6 +1,10 @@
 use crate::{
-    bank_authority_seed, bank_seed,
-    state::marginfi_group::{Bank, BankVaultType},
+    bank_authority_seed, bank_seed, check,
+    constants::PENDING_BANK_CONFIG_SEED,
+    state::{
+        marginfi_group::{Bank, BankVaultType},
+        price::{OraclePriceFeedAdapter, OraclePriceType, PriceAdapter, PriceBias},
+    },
     MarginfiError, MarginfiResult,
 };
 use anchor_lang::prelude::*;
@@ -25,6 +29,13 @@ pub fn find_bank_vault_authority_pda(bank_pk: &Pubkey, vault_type: BankVaultType
     Pubkey::find_program_address(bank_authority_seed!(vault_type, bank_pk), &crate::id())
 }
 
+pub fn find_pending_bank_config_pda(bank_pk: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[PENDING_BANK_CONFIG_SEED.as_bytes(), bank_pk.as_ref()],
+        &crate::id(),
+    )
+}
+
 pub trait NumTraitsWithTolerance<T> {
     fn is_zero_with_tolerance(&self, t: T) -> bool;
     fn is_positive_with_tolerance(&self, t: T) -> bool;
@@ -143,6 +154,47 @@ pub fn maybe_take_bank_mint<'info>(
     }
 }
 
+/// Loads the price used for the bank's USD limits, from the oracle accounts that follow the bank
+/// in `remaining_ais`, as laid out for the risk engine.
+///
+/// The price is biased high, so the limits are not exceeded by a low confidence price.
+pub fn get_bank_limit_price(
+    bank_pk: &Pubkey,
+    bank: &Bank,
+    remaining_ais: &[AccountInfo],
+    clock: &Clock,
+) -> MarginfiResult<I80F48> {
+    get_bank_price(bank_pk, bank, remaining_ais, clock, Some(PriceBias::High))
+}
+
+/// Loads the bank's real time price, from the oracle accounts that follow the bank in
+/// `remaining_ais`, as laid out for the risk engine.
+pub fn get_bank_price(
+    bank_pk: &Pubkey,
+    bank: &Bank,
+    remaining_ais: &[AccountInfo],
+    clock: &Clock,
+    bias: Option<PriceBias>,
+) -> MarginfiResult<I80F48> {
+    let bank_ai_idx = remaining_ais
+        .iter()
+        .position(|ai| ai.key.eq(bank_pk))
+        .ok_or(MarginfiError::MissingPythOrBankAccount)?;
+
+    let oracle_ai_idx = bank_ai_idx + 1;
+    let oracle_ais_end = oracle_ai_idx + bank.config.get_oracle_ais_len();
+
+    check!(
+        oracle_ais_end <= remaining_ais.len(),
+        MarginfiError::MissingPythOrBankAccount
+    );
+
+    let oracle_ais = &remaining_ais[oracle_ai_idx..oracle_ais_end];
+
+    OraclePriceFeedAdapter::try_from_bank(bank, oracle_ais, clock)?
+        .get_price_of_type(OraclePriceType::RealTime, bias)
+}
+
 const ONE_IN_BASIS_POINTS: u128 = 10_000;
 /// backported fix from
 /// https://github.com/solana-labs/solana-program-library/commit/20e6792179fc7f1251579c1c33a4a0feec48e15e
@@ -0,0 +1,75 @@
+use fixtures::{assert_custom_error, prelude::*};
+use marginfi::prelude::MarginfiError;
+use pretty_assertions::assert_eq;
+use solana_program_test::*;
+
+#[tokio::test]
+async fn account_limits_restrict_banks_and_liabilities() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+
+    let usdc_bank_f = test_f.get_bank(&BankMint::Usdc);
+    let sol_bank_f = test_f.get_bank(&BankMint::Sol);
+    let sol_eq_bank_f = test_f.get_bank(&BankMint::SolEquivalent);
+
+    // LP
+    let lp_mfi_account_f = test_f.create_marginfi_account().await;
+    let lp_token_account_usdc = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+    lp_mfi_account_f
+        .try_bank_deposit(lp_token_account_usdc.key, usdc_bank_f, 1_000)
+        .await?;
+
+    // Managed account, limited to SOL and USDC and $50 of liabilities
+    let managed_mfi_account_f = test_f.create_marginfi_account().await;
+    managed_mfi_account_f
+        .try_configure_limits(50, vec![sol_bank_f.key, usdc_bank_f.key])
+        .await?;
+
+    let managed_mfi_account = managed_mfi_account_f.load().await;
+    assert_eq!(managed_mfi_account.max_liability_value, 50);
+    assert_eq!(managed_mfi_account.allowed_banks[0], sol_bank_f.key);
+    assert_eq!(managed_mfi_account.allowed_banks[1], usdc_bank_f.key);
+
+    let token_account_sol_eq = test_f
+        .sol_equivalent_mint
+        .create_token_account_and_mint_to(10)
+        .await;
+    let res = managed_mfi_account_f
+        .try_bank_deposit(token_account_sol_eq.key, sol_eq_bank_f, 10)
+        .await;
+    assert!(res.is_err());
+    assert_custom_error!(res.unwrap_err(), MarginfiError::AccountBankNotAllowed);
+
+    let token_account_sol = test_f.sol_mint.create_token_account_and_mint_to(10).await;
+    managed_mfi_account_f
+        .try_bank_deposit(token_account_sol.key, sol_bank_f, 10)
+        .await?;
+
+    // $60 of liabilities are healthy, but over the limit
+    let token_account_usdc = test_f.usdc_mint.create_empty_token_account().await;
+    let res = managed_mfi_account_f
+        .try_bank_borrow(token_account_usdc.key, usdc_bank_f, 60)
+        .await;
+    assert!(res.is_err());
+    assert_custom_error!(
+        res.unwrap_err(),
+        MarginfiError::AccountLiabilityLimitExceeded
+    );
+
+    managed_mfi_account_f
+        .try_bank_borrow(token_account_usdc.key, usdc_bank_f, 40)
+        .await?;
+
+    // Removing the limits
+    managed_mfi_account_f
+        .try_configure_limits(0, vec![])
+        .await?;
+
+    managed_mfi_account_f
+        .try_bank_borrow(token_account_usdc.key, usdc_bank_f, 20)
+        .await?;
+
+    Ok(())
+}
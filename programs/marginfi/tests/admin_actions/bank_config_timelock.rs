@@ -0,0 +1,197 @@
+use fixed::types::I80F48;
+use fixed_macro::types::I80F48;
+use fixtures::{assert_custom_error, prelude::*};
+use marginfi::{
+    prelude::{GroupConfig, MarginfiError},
+    state::marginfi_group::BankConfigOpt,
+};
+use pretty_assertions::assert_eq;
+use solana_program_test::*;
+
+#[tokio::test]
+async fn bank_config_timelock() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+
+    let usdc_bank_f = test_f.get_bank(&BankMint::Usdc);
+
+    // One hour config delay
+    test_f
+        .marginfi_group
+        .try_update(GroupConfig {
+            admin: None,
+            config_delay: Some(3_600),
+            ..GroupConfig::default()
+        })
+        .await?;
+    assert_eq!(test_f.marginfi_group.load().await.config_delay, 3_600);
+
+    // Configs can't be applied right away anymore
+    let res = usdc_bank_f
+        .update_config(BankConfigOpt {
+            deposit_limit: Some(1_000),
+            ..BankConfigOpt::default()
+        })
+        .await;
+    assert!(res.is_err());
+
+    // Except those lowering the asset weights
+    let old_bank = usdc_bank_f.load().await;
+    let asset_weight_maint = I80F48::from(old_bank.config.asset_weight_maint);
+    usdc_bank_f
+        .update_config(BankConfigOpt {
+            asset_weight_init: Some(I80F48!(0.5).into()),
+            asset_weight_maint: Some(asset_weight_maint.into()),
+            ..BankConfigOpt::default()
+        })
+        .await?;
+    assert_eq!(
+        I80F48::from(usdc_bank_f.load().await.config.asset_weight_init),
+        I80F48!(0.5)
+    );
+
+    let res = usdc_bank_f
+        .update_config(BankConfigOpt {
+            asset_weight_init: Some(I80F48!(0.6).into()),
+            ..BankConfigOpt::default()
+        })
+        .await;
+    assert!(res.is_err());
+
+    // Not along with another field
+    let res = usdc_bank_f
+        .update_config(BankConfigOpt {
+            asset_weight_init: Some(I80F48!(0.4).into()),
+            deposit_limit: Some(1_500),
+            ..BankConfigOpt::default()
+        })
+        .await;
+    assert!(res.is_err());
+
+    // Queue, then execute once the delay has passed
+    usdc_bank_f
+        .try_queue_config(BankConfigOpt {
+            deposit_limit: Some(1_000),
+            ..BankConfigOpt::default()
+        })
+        .await?;
+
+    let pending_config = usdc_bank_f.load_pending_config().await;
+    let now = test_f.get_clock().await.unix_timestamp;
+    assert_eq!(pending_config.bank, usdc_bank_f.key);
+    assert_eq!(pending_config.payer, test_f.payer());
+    assert_eq!(pending_config.eta, now + 3_600);
+
+    let res = usdc_bank_f.try_execute_config().await;
+    assert!(res.is_err());
+    assert_custom_error!(res.unwrap_err(), MarginfiError::BankConfigNotReady);
+
+    test_f.advance_time(3_600).await;
+
+    usdc_bank_f.try_execute_config().await?;
+    assert_eq!(usdc_bank_f.load().await.config.deposit_limit, 1_000);
+    assert!(test_f
+        .context
+        .borrow_mut()
+        .banks_client
+        .get_account(usdc_bank_f.get_pending_config_address())
+        .await?
+        .is_none());
+
+    // A cancelled config is never applied
+    usdc_bank_f
+        .try_queue_config(BankConfigOpt {
+            deposit_limit: Some(2_000),
+            ..BankConfigOpt::default()
+        })
+        .await?;
+    usdc_bank_f.try_cancel_config().await?;
+
+    test_f.advance_time(3_600).await;
+
+    assert_eq!(usdc_bank_f.load().await.config.deposit_limit, 1_000);
+    assert!(test_f
+        .context
+        .borrow_mut()
+        .banks_client
+        .get_account(usdc_bank_f.get_pending_config_address())
+        .await?
+        .is_none());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn config_delay_decrease_timelock() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+
+    let usdc_bank_f = test_f.get_bank(&BankMint::Usdc);
+
+    let config_delay = |config_delay| GroupConfig {
+        admin: None,
+        config_delay: Some(config_delay),
+        ..GroupConfig::default()
+    };
+
+    // Increases apply right away
+    test_f
+        .marginfi_group
+        .try_update(config_delay(3_600))
+        .await?;
+    assert_eq!(test_f.marginfi_group.load().await.config_delay, 3_600);
+
+    // Decreases wait for the current delay
+    test_f.marginfi_group.try_update(config_delay(0)).await?;
+
+    let now = test_f.get_clock().await.unix_timestamp;
+    let marginfi_group = test_f.marginfi_group.load().await;
+    assert_eq!(marginfi_group.config_delay, 3_600);
+    assert_eq!(marginfi_group.pending_config_delay, 0);
+    assert_eq!(marginfi_group.pending_config_delay_eta, now + 3_600);
+
+    let res = usdc_bank_f
+        .update_config(BankConfigOpt {
+            deposit_limit: Some(1_000),
+            ..BankConfigOpt::default()
+        })
+        .await;
+    assert!(res.is_err());
+    assert_custom_error!(res.unwrap_err(), MarginfiError::BankConfigTimelocked);
+
+    test_f.advance_time(3_600).await;
+
+    usdc_bank_f
+        .update_config(BankConfigOpt {
+            deposit_limit: Some(2_000),
+            ..BankConfigOpt::default()
+        })
+        .await?;
+    assert_eq!(usdc_bank_f.load().await.config.deposit_limit, 2_000);
+
+    // An increase drops a pending decrease
+    test_f
+        .marginfi_group
+        .try_update(config_delay(7_200))
+        .await?;
+    test_f.marginfi_group.try_update(config_delay(60)).await?;
+    test_f
+        .marginfi_group
+        .try_update(config_delay(10_800))
+        .await?;
+
+    let marginfi_group = test_f.marginfi_group.load().await;
+    assert_eq!(marginfi_group.config_delay, 10_800);
+    assert_eq!(marginfi_group.pending_config_delay_eta, 0);
+
+    test_f.advance_time(7_200).await;
+
+    let res = usdc_bank_f
+        .update_config(BankConfigOpt {
+            deposit_limit: Some(3_000),
+            ..BankConfigOpt::default()
+        })
+        .await;
+    assert!(res.is_err());
+    assert_custom_error!(res.unwrap_err(), MarginfiError::BankConfigTimelocked);
+
+    Ok(())
+}
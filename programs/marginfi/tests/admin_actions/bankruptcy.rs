@@ -866,7 +866,10 @@ async fn marginfi_group_handle_bankruptcy_success_not_insured_3_depositors() ->
                 }),
             },
         ],
-        group_config: Some(GroupConfig { admin: None }),
+        group_config: Some(GroupConfig {
+            admin: None,
+            ..GroupConfig::default()
+        }),
         protocol_fees: false,
     }))
     .await;
@@ -16,7 +16,10 @@ use solana_sdk::pubkey::Pubkey;
 #[tokio::test]
 async fn marginfi_group_handle_bankruptcy_unauthorized() -> anyhow::Result<()> {
     let mut test_f = TestFixture::new(Some(TestSettings {
-        group_config: Some(GroupConfig { admin: None }),
+        group_config: Some(GroupConfig {
+            admin: None,
+            ..GroupConfig::default()
+        }),
         banks: vec![
             TestBankSetting {
                 mint: BankMint::Usdc,
@@ -92,6 +95,7 @@ async fn marginfi_group_handle_bankruptcy_unauthorized() -> anyhow::Result<()> {
         .marginfi_group
         .try_update(GroupConfig {
             admin: Some(Pubkey::new_unique()),
+            ..GroupConfig::default()
         })
         .await?;
 
@@ -111,7 +115,10 @@ async fn marginfi_group_handle_bankruptcy_unauthorized() -> anyhow::Result<()> {
 #[tokio::test]
 async fn marginfi_group_handle_bankruptcy_perimssionless() -> anyhow::Result<()> {
     let mut test_f = TestFixture::new(Some(TestSettings {
-        group_config: Some(GroupConfig { admin: None }),
+        group_config: Some(GroupConfig {
+            admin: None,
+            ..GroupConfig::default()
+        }),
         banks: vec![
             TestBankSetting {
                 mint: BankMint::Usdc,
@@ -195,6 +202,7 @@ async fn marginfi_group_handle_bankruptcy_perimssionless() -> anyhow::Result<()>
         .marginfi_group
         .try_update(GroupConfig {
             admin: Some(Pubkey::new_unique()),
+            ..GroupConfig::default()
         })
         .await?;
 
@@ -1,6 +1,9 @@
 use anchor_lang::{InstructionData, ToAccountMetas};
 use fixtures::prelude::*;
-use marginfi::{constants::FEE_STATE_SEED, prelude::MarginfiGroup};
+use marginfi::{
+    constants::{FEE_STATE_SEED, GROUP_PAUSE_STATE_NONE},
+    prelude::MarginfiGroup,
+};
 use pretty_assertions::assert_eq;
 use solana_program::{instruction::Instruction, system_program};
 use solana_program_test::*;
@@ -49,6 +52,9 @@ async fn marginfi_group_create_success() -> anyhow::Result<()> {
 
     // Check basic properties
     assert_eq!(marginfi_group.admin, test_f.payer());
+    assert_eq!(marginfi_group.guardian, Pubkey::default());
+    assert_eq!(marginfi_group.pause_state, GROUP_PAUSE_STATE_NONE);
+    assert_eq!(marginfi_group.config_delay, 0);
 
     Ok(())
 }
@@ -0,0 +1,105 @@
+use fixtures::{assert_custom_error, prelude::*};
+use marginfi::{
+    constants::{GROUP_PAUSE_STATE_ALL, GROUP_PAUSE_STATE_BANKS, GROUP_PAUSE_STATE_NONE},
+    prelude::{GroupConfig, MarginfiError},
+};
+use pretty_assertions::assert_eq;
+use solana_program_test::*;
+use solana_sdk::{signature::Keypair, signer::Signer};
+
+#[tokio::test]
+async fn guardian_pauses_group() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+
+    let usdc_bank_f = test_f.get_bank(&BankMint::Usdc);
+
+    let guardian = Keypair::new();
+    test_f
+        .marginfi_group
+        .try_update(GroupConfig {
+            admin: None,
+            guardian: Some(guardian.pubkey()),
+            ..GroupConfig::default()
+        })
+        .await?;
+    assert_eq!(
+        test_f.marginfi_group.load().await.guardian,
+        guardian.pubkey()
+    );
+
+    let lender_mfi_account_f = test_f.create_marginfi_account().await;
+    let lender_token_account_usdc = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+    lender_mfi_account_f
+        .try_bank_deposit(lender_token_account_usdc.key, usdc_bank_f, 500)
+        .await?;
+
+    // Only the guardian or the admin can pause
+    let res = test_f
+        .marginfi_group
+        .try_set_pause_state(&Keypair::new(), GROUP_PAUSE_STATE_BANKS)
+        .await;
+    assert!(res.is_err());
+    assert_custom_error!(res.unwrap_err(), MarginfiError::Unauthorized);
+
+    let res = test_f
+        .marginfi_group
+        .try_set_pause_state(&guardian, GROUP_PAUSE_STATE_ALL + 1)
+        .await;
+    assert!(res.is_err());
+    assert_custom_error!(res.unwrap_err(), MarginfiError::InvalidConfig);
+
+    // Banks paused: balances can't move, accounts can still be created
+    test_f
+        .marginfi_group
+        .try_set_pause_state(&guardian, GROUP_PAUSE_STATE_BANKS)
+        .await?;
+
+    let res = lender_mfi_account_f
+        .try_bank_deposit(lender_token_account_usdc.key, usdc_bank_f, 100)
+        .await;
+    assert!(res.is_err());
+    assert_custom_error!(res.unwrap_err(), MarginfiError::GroupPaused);
+
+    test_f.create_marginfi_account().await;
+    lender_mfi_account_f
+        .try_flashloan(vec![], vec![], vec![])
+        .await?;
+
+    // Every user instruction paused
+    test_f
+        .marginfi_group
+        .try_set_pause_state(&guardian, GROUP_PAUSE_STATE_ALL)
+        .await?;
+
+    let res = lender_mfi_account_f
+        .try_bank_withdraw(lender_token_account_usdc.key, usdc_bank_f, 100, None)
+        .await;
+    assert!(res.is_err());
+    assert_custom_error!(res.unwrap_err(), MarginfiError::GroupPaused);
+
+    let res = lender_mfi_account_f
+        .try_flashloan(vec![], vec![usdc_bank_f.key], vec![])
+        .await;
+    assert!(res.is_err());
+    assert_custom_error!(res.unwrap_err(), MarginfiError::GroupPaused);
+
+    let res = lender_mfi_account_f
+        .try_transfer_account_authority(Keypair::new().pubkey(), None)
+        .await;
+    assert!(res.is_err());
+    assert_custom_error!(res.unwrap_err(), MarginfiError::GroupPaused);
+
+    test_f
+        .marginfi_group
+        .try_set_pause_state(&guardian, GROUP_PAUSE_STATE_NONE)
+        .await?;
+
+    lender_mfi_account_f
+        .try_bank_withdraw(lender_token_account_usdc.key, usdc_bank_f, 100, None)
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,71 @@
+use anchor_lang::error::ErrorCode;
+use fixtures::{assert_anchor_error, prelude::*};
+use marginfi::{prelude::GroupConfig, state::marginfi_group::BankConfigOpt};
+use pretty_assertions::assert_eq;
+use solana_program_test::*;
+use solana_sdk::pubkey::Pubkey;
+
+#[tokio::test]
+async fn group_roles_replace_admin() -> anyhow::Result<()> {
+    let mut test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+
+    let fee_vault = test_f.get_bank(&BankMint::Usdc).load().await.fee_vault;
+    test_f.usdc_mint.mint_to(&fee_vault, 100).await;
+
+    let usdc_bank_f = test_f.get_bank(&BankMint::Usdc);
+
+    // Hand the risk and fee roles to other keys
+    let risk_admin = Pubkey::new_unique();
+    let fee_collector = Pubkey::new_unique();
+    test_f
+        .marginfi_group
+        .try_update(GroupConfig {
+            admin: None,
+            risk_admin: Some(risk_admin),
+            fee_collector: Some(fee_collector),
+            ..GroupConfig::default()
+        })
+        .await?;
+
+    let group = test_f.marginfi_group.load().await;
+    assert_eq!(group.get_risk_admin(), risk_admin);
+    assert_eq!(group.get_fee_collector(), fee_collector);
+    assert_eq!(group.get_emissions_admin(), test_f.payer());
+    assert_eq!(group.get_bankruptcy_operator(), test_f.payer());
+
+    // The admin no longer holds the roles
+    let res = usdc_bank_f
+        .update_config(BankConfigOpt {
+            deposit_limit: Some(1_000),
+            ..BankConfigOpt::default()
+        })
+        .await;
+    assert!(res.is_err());
+
+    let receiving_account = usdc_bank_f.mint.create_empty_token_account().await;
+    let res = usdc_bank_f.try_withdraw_fees(&receiving_account, 100).await;
+    assert_anchor_error!(res.unwrap_err(), ErrorCode::ConstraintAddress);
+
+    // Unsetting a role hands it back to the admin
+    test_f
+        .marginfi_group
+        .try_update(GroupConfig {
+            admin: None,
+            risk_admin: Some(Pubkey::default()),
+            fee_collector: Some(Pubkey::default()),
+            ..GroupConfig::default()
+        })
+        .await?;
+
+    usdc_bank_f
+        .update_config(BankConfigOpt {
+            deposit_limit: Some(1_000),
+            ..BankConfigOpt::default()
+        })
+        .await?;
+    usdc_bank_f
+        .try_withdraw_fees(&receiving_account, 100)
+        .await?;
+
+    Ok(())
+}
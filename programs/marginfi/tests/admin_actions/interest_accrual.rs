@@ -4,8 +4,12 @@ use fixed::types::I80F48;
 use fixed_macro::types::I80F48;
 use fixtures::{assert_eq_noise, native, prelude::*};
 use marginfi::{
+    constants::INTEREST_CURVE_MULTI_POINT,
     prelude::GroupConfig,
-    state::marginfi_group::{Bank, BankConfig, BankVaultType, InterestRateConfig},
+    state::marginfi_group::{
+        rate_to_u32, Bank, BankConfig, BankConfigOpt, BankVaultType, InterestRateConfig,
+        InterestRateConfigOpt, RatePoint,
+    },
 };
 use pretty_assertions::assert_eq;
 use solana_program_test::*;
@@ -13,7 +17,10 @@ use solana_program_test::*;
 #[tokio::test]
 async fn marginfi_group_accrue_interest_rates_success_1() -> anyhow::Result<()> {
     let test_f = TestFixture::new(Some(TestSettings {
-        group_config: Some(GroupConfig { admin: None }),
+        group_config: Some(GroupConfig {
+            admin: None,
+            ..GroupConfig::default()
+        }),
         banks: vec![
             TestBankSetting {
                 mint: BankMint::Usdc,
@@ -93,6 +100,105 @@ async fn marginfi_group_accrue_interest_rates_success_1() -> anyhow::Result<()>
     Ok(())
 }
 
+#[tokio::test]
+async fn marginfi_group_accrue_interest_rates_multi_point_curve() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings {
+        group_config: Some(GroupConfig {
+            admin: None,
+            ..GroupConfig::default()
+        }),
+        banks: vec![
+            TestBankSetting {
+                mint: BankMint::Usdc,
+                config: None,
+            },
+            TestBankSetting {
+                mint: BankMint::Sol,
+                config: Some(BankConfig {
+                    asset_weight_init: I80F48!(1).into(),
+                    ..*DEFAULT_SOL_TEST_BANK_CONFIG
+                }),
+            },
+        ],
+        protocol_fees: false,
+    }))
+    .await;
+
+    let usdc_bank_f = test_f.get_bank(&BankMint::Usdc);
+    let sol_bank_f = test_f.get_bank(&BankMint::Sol);
+
+    // 100% APR at 90% utilization, the curve is flat at 10% until 80% utilization
+    usdc_bank_f
+        .update_config(BankConfigOpt {
+            interest_rate_config: Some(InterestRateConfigOpt {
+                curve_type: Some(INTEREST_CURVE_MULTI_POINT),
+                zero_util_rate: Some(rate_to_u32(I80F48!(0.1))),
+                hundred_util_rate: Some(rate_to_u32(I80F48!(3))),
+                points: Some([
+                    RatePoint::new(I80F48!(0.8), I80F48!(0.1)),
+                    RatePoint::new(I80F48!(0.9), I80F48!(1)),
+                    RatePoint::default(),
+                    RatePoint::default(),
+                    RatePoint::default(),
+                ]),
+                ..Default::default()
+            }),
+            ..Default::default()
+        })
+        .await?;
+
+    let lender_mfi_account_f = test_f.create_marginfi_account().await;
+    let lender_token_account_usdc = test_f.usdc_mint.create_token_account_and_mint_to(100).await;
+    lender_mfi_account_f
+        .try_bank_deposit(lender_token_account_usdc.key, usdc_bank_f, 100)
+        .await?;
+
+    let borrower_mfi_account_f = test_f.create_marginfi_account().await;
+    let borrower_token_account_sol = test_f
+        .sol_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+    borrower_mfi_account_f
+        .try_bank_deposit(borrower_token_account_sol.key, sol_bank_f, 999)
+        .await?;
+    let borrower_token_account_usdc = test_f.usdc_mint.create_empty_token_account().await;
+    borrower_mfi_account_f
+        .try_bank_borrow(borrower_token_account_usdc.key, usdc_bank_f, 90)
+        .await?;
+
+    {
+        let mut ctx = test_f.context.borrow_mut();
+        let mut clock: Clock = ctx.banks_client.get_sysvar().await?;
+        // Advance clock by 1 year
+        clock.unix_timestamp += 365 * 24 * 60 * 60;
+        ctx.set_sysvar(&clock);
+    }
+
+    test_f
+        .marginfi_group
+        .try_accrue_interest(usdc_bank_f)
+        .await?;
+
+    let borrower_mfi_account = borrower_mfi_account_f.load().await;
+    let borrower_bank_account = borrower_mfi_account.lending_account.balances[1];
+    let usdc_bank: Bank = usdc_bank_f.load().await;
+    let liabilities =
+        usdc_bank.get_liability_amount(borrower_bank_account.liability_shares.into())?;
+
+    let lender_mfi_account = lender_mfi_account_f.load().await;
+    let lender_bank_account = lender_mfi_account.lending_account.balances[0];
+    let assets = usdc_bank.get_asset_amount(lender_bank_account.asset_shares.into())?;
+
+    assert_eq_noise!(
+        liabilities,
+        I80F48::from(native!(180, "USDC")),
+        I80F48!(100)
+    );
+    assert_eq_noise!(assets, I80F48::from(native!(190, "USDC")), I80F48!(100));
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn marginfi_group_accrue_interest_rates_success_2() -> anyhow::Result<()> {
     let test_f = TestFixture::new(Some(TestSettings {
@@ -120,7 +226,10 @@ async fn marginfi_group_accrue_interest_rates_success_2() -> anyhow::Result<()>
                 }),
             },
         ],
-        group_config: Some(GroupConfig { admin: None }),
+        group_config: Some(GroupConfig {
+            admin: None,
+            ..GroupConfig::default()
+        }),
         protocol_fees: false,
     }))
     .await;
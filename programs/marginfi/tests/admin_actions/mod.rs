@@ -1,7 +1,11 @@
+mod account_limits;
 mod account_transfer;
+mod bank_config_timelock;
 mod bankruptcy;
 mod bankruptcy_auth;
 mod create_marginfi_group;
+mod group_pause;
+mod group_roles;
 mod interest_accrual;
 mod setup_bank;
 mod withdraw_fees;
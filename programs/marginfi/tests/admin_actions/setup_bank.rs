@@ -85,7 +85,6 @@ async fn add_bank_success() -> anyhow::Result<()> {
             emissions_rate,
             emissions_remaining,
             emissions_mint,
-            _padding_0,
             _padding_1,
             .. // ignore internal padding
         } = bank_f.load().await;
@@ -115,8 +114,7 @@ async fn add_bank_success() -> anyhow::Result<()> {
             assert_eq!(emissions_mint, Pubkey::new_from_array([0; 32]));
             assert_eq!(emissions_remaining, I80F48!(0.0).into());
 
-            assert_eq!(_padding_0, <[[u64; 2]; 27] as Default>::default());
-            assert_eq!(_padding_1, <[[u64; 2]; 32] as Default>::default());
+            assert_eq!(_padding_1, <[[u64; 2]; 22] as Default>::default());
 
             // this is the only loosely checked field
             assert!(last_update >= 0 && last_update <= 5);
@@ -218,7 +216,6 @@ async fn add_bank_with_seed_success() -> anyhow::Result<()> {
             emissions_rate,
             emissions_remaining,
             emissions_mint,
-            _padding_0,
             _padding_1,
             .. // ignore internal padding
         } = bank_f.load().await;
@@ -248,8 +245,7 @@ async fn add_bank_with_seed_success() -> anyhow::Result<()> {
             assert_eq!(emissions_mint, Pubkey::new_from_array([0; 32]));
             assert_eq!(emissions_remaining, I80F48!(0.0).into());
 
-            assert_eq!(_padding_0, <[[u64; 2]; 27] as Default>::default());
-            assert_eq!(_padding_1, <[[u64; 2]; 32] as Default>::default());
+            assert_eq!(_padding_1, <[[u64; 2]; 22] as Default>::default());
 
             // this is the only loosely checked field
             assert!(last_update >= 0 && last_update <= 5);
@@ -320,6 +316,15 @@ async fn configure_bank_success(bank_mint: BankMint) -> anyhow::Result<()> {
             protocol_fixed_fee_apr: Some(I80F48::from_num(0.51).into()),
             protocol_ir_fee: Some(I80F48::from_num(0.011).into()),
             protocol_origination_fee: Some(I80F48::ZERO.into()),
+            curve_type: None,
+            zero_util_rate: None,
+            hundred_util_rate: None,
+            points: None,
+            adaptive_min_plateau_rate: None,
+            adaptive_max_plateau_rate: None,
+            adaptive_speed: None,
+            max_lending_rate: None,
+            max_borrowing_rate: None,
         }),
         ..BankConfigOpt::default()
     };
@@ -344,6 +349,37 @@ async fn configure_bank_success(bank_mint: BankMint) -> anyhow::Result<()> {
         total_asset_value_init_limit,
         oracle_max_age,
         permissionless_bad_debt_settlement,
+        liquidation_liquidator_fee_bps,
+        liquidation_max_liquidator_fee_bps,
+        liquidation_fee_ramp_shortfall_bps,
+        liquidation_insurance_fee_bps,
+        liquidation_close_factor_bps,
+        liquidation_close_factor_shortfall_bps,
+        fixed_term_premium,
+        fixed_term_max_duration,
+        oracle_max_deviation_bps,
+        fixed_price,
+        twap_window,
+        oracle_conf_interval_multiple,
+        oracle_max_conf_interval,
+        oracle_conf_interval_mode,
+        emode_category,
+        emode_asset_weight_init,
+        emode_asset_weight_maint,
+        emode_liability_weight_init,
+        emode_liability_weight_maint,
+        pair_class,
+        collateral_for_classes,
+        accepted_collateral_classes,
+        isolated_debt_ceiling,
+        deposit_limit_usd,
+        borrow_limit_usd,
+        outflow_window,
+        outflow_limit,
+        outflow_limit_bps,
+        price_guard_max_move_bps,
+        price_guard_interval,
+        price_guard_reset,
     } = &config_bank_opt;
     // Compare bank field to opt field if Some, otherwise compare to old bank field
     macro_rules! check_bank_field {
@@ -374,6 +410,15 @@ async fn configure_bank_success(bank_mint: BankMint) -> anyhow::Result<()> {
         check_bank_field!(interest_rate_config, insurance_ir_fee);
         check_bank_field!(interest_rate_config, protocol_fixed_fee_apr);
         check_bank_field!(interest_rate_config, protocol_ir_fee);
+        check_bank_field!(interest_rate_config, curve_type);
+        check_bank_field!(interest_rate_config, zero_util_rate);
+        check_bank_field!(interest_rate_config, hundred_util_rate);
+        check_bank_field!(interest_rate_config, points);
+        check_bank_field!(interest_rate_config, adaptive_min_plateau_rate);
+        check_bank_field!(interest_rate_config, adaptive_max_plateau_rate);
+        check_bank_field!(interest_rate_config, adaptive_speed);
+        check_bank_field!(interest_rate_config, max_lending_rate);
+        check_bank_field!(interest_rate_config, max_borrowing_rate);
 
         check_bank_field!(asset_weight_init);
         check_bank_field!(asset_weight_maint);
@@ -385,6 +430,100 @@ async fn configure_bank_success(bank_mint: BankMint) -> anyhow::Result<()> {
         check_bank_field!(risk_tier);
         check_bank_field!(total_asset_value_init_limit);
         check_bank_field!(oracle_max_age);
+        check_bank_field!(oracle_max_deviation_bps);
+        check_bank_field!(liquidation_liquidator_fee_bps);
+        check_bank_field!(liquidation_max_liquidator_fee_bps);
+        check_bank_field!(liquidation_fee_ramp_shortfall_bps);
+        check_bank_field!(liquidation_insurance_fee_bps);
+        check_bank_field!(liquidation_close_factor_bps);
+        check_bank_field!(liquidation_close_factor_shortfall_bps);
+
+        assert_eq!(
+            bank.fixed_term_premium,
+            fixed_term_premium.unwrap_or(old_bank.fixed_term_premium)
+        );
+        assert_eq!(
+            bank.fixed_term_max_duration,
+            fixed_term_max_duration.unwrap_or(old_bank.fixed_term_max_duration)
+        );
+        assert_eq!(bank.fixed_price, fixed_price.unwrap_or(old_bank.fixed_price));
+        assert_eq!(bank.twap_window, twap_window.unwrap_or(old_bank.twap_window));
+        assert_eq!(
+            bank.oracle_conf_interval_multiple,
+            oracle_conf_interval_multiple.unwrap_or(old_bank.oracle_conf_interval_multiple)
+        );
+        assert_eq!(
+            bank.oracle_max_conf_interval,
+            oracle_max_conf_interval.unwrap_or(old_bank.oracle_max_conf_interval)
+        );
+        assert_eq!(
+            bank.oracle_conf_interval_mode,
+            oracle_conf_interval_mode.unwrap_or(old_bank.oracle_conf_interval_mode)
+        );
+        assert_eq!(
+            bank.emode_category,
+            emode_category.unwrap_or(old_bank.emode_category)
+        );
+        assert_eq!(
+            bank.emode_asset_weight_init,
+            emode_asset_weight_init.unwrap_or(old_bank.emode_asset_weight_init)
+        );
+        assert_eq!(
+            bank.emode_asset_weight_maint,
+            emode_asset_weight_maint.unwrap_or(old_bank.emode_asset_weight_maint)
+        );
+        assert_eq!(
+            bank.emode_liability_weight_init,
+            emode_liability_weight_init.unwrap_or(old_bank.emode_liability_weight_init)
+        );
+        assert_eq!(
+            bank.emode_liability_weight_maint,
+            emode_liability_weight_maint.unwrap_or(old_bank.emode_liability_weight_maint)
+        );
+        assert_eq!(bank.pair_class, pair_class.unwrap_or(old_bank.pair_class));
+        assert_eq!(
+            bank.collateral_for_classes,
+            collateral_for_classes.unwrap_or(old_bank.collateral_for_classes)
+        );
+        assert_eq!(
+            bank.accepted_collateral_classes,
+            accepted_collateral_classes.unwrap_or(old_bank.accepted_collateral_classes)
+        );
+        assert_eq!(
+            bank.isolated_debt_ceiling,
+            isolated_debt_ceiling.unwrap_or(old_bank.isolated_debt_ceiling)
+        );
+        assert_eq!(
+            bank.deposit_limit_usd,
+            deposit_limit_usd.unwrap_or(old_bank.deposit_limit_usd)
+        );
+        assert_eq!(
+            bank.borrow_limit_usd,
+            borrow_limit_usd.unwrap_or(old_bank.borrow_limit_usd)
+        );
+        assert_eq!(
+            bank.outflow_window,
+            outflow_window.unwrap_or(old_bank.outflow_window)
+        );
+        assert_eq!(
+            bank.outflow_limit,
+            outflow_limit.unwrap_or(old_bank.outflow_limit)
+        );
+        assert_eq!(
+            bank.outflow_limit_bps,
+            outflow_limit_bps.unwrap_or(old_bank.outflow_limit_bps)
+        );
+        assert_eq!(
+            bank.price_guard_max_move_bps,
+            price_guard_max_move_bps.unwrap_or(old_bank.price_guard_max_move_bps)
+        );
+        assert_eq!(
+            bank.price_guard_interval,
+            price_guard_interval.unwrap_or(old_bank.price_guard_interval)
+        );
+        if let Some(true) = price_guard_reset {
+            assert_eq!(bank.price_guard_tripped_at, 0);
+        }
 
 
 
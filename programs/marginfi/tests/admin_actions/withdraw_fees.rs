@@ -102,6 +102,7 @@ async fn marginfi_group_withdraw_fees_and_insurance_fund_as_non_admin_failure(
         .marginfi_group
         .try_update(GroupConfig {
             admin: Some(Pubkey::new_unique()),
+            ..GroupConfig::default()
         })
         .await?;
 
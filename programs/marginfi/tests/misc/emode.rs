@@ -0,0 +1,113 @@
+use fixed::types::I80F48;
+use fixtures::{assert_custom_error, prelude::*};
+use marginfi::{prelude::MarginfiError, state::marginfi_group::BankConfigOpt};
+use pretty_assertions::assert_eq;
+use solana_program_test::*;
+
+const SOL_EMODE_CATEGORY: u16 = 1;
+
+fn sol_emode_config() -> BankConfigOpt {
+    BankConfigOpt {
+        asset_weight_init: Some(I80F48::from_num(0.5).into()),
+        asset_weight_maint: Some(I80F48::from_num(0.6).into()),
+        emode_category: Some(SOL_EMODE_CATEGORY),
+        emode_asset_weight_init: Some(I80F48::from_num(0.9).into()),
+        emode_asset_weight_maint: Some(I80F48::from_num(0.95).into()),
+        emode_liability_weight_init: Some(I80F48::ONE.into()),
+        emode_liability_weight_maint: Some(I80F48::ONE.into()),
+        ..BankConfigOpt::default()
+    }
+}
+
+#[tokio::test]
+async fn emode_borrow_within_category() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+
+    let usdc_bank_f = test_f.get_bank(&BankMint::Usdc);
+    let sol_bank_f = test_f.get_bank(&BankMint::Sol);
+    let sol_eq_bank_f = test_f.get_bank(&BankMint::SolEquivalent);
+
+    sol_bank_f.update_config(sol_emode_config()).await?;
+    sol_eq_bank_f.update_config(sol_emode_config()).await?;
+
+    let sol_bank = sol_bank_f.load().await;
+    assert_eq!(sol_bank.emode_category, SOL_EMODE_CATEGORY);
+
+    // LPs
+    let lp_mfi_account_f = test_f.create_marginfi_account().await;
+    let lp_token_account_sol_eq = test_f
+        .sol_equivalent_mint
+        .create_token_account_and_mint_to(100)
+        .await;
+    lp_mfi_account_f
+        .try_bank_deposit(lp_token_account_sol_eq.key, sol_eq_bank_f, 100)
+        .await?;
+    let lp_token_account_usdc = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+    lp_mfi_account_f
+        .try_bank_deposit(lp_token_account_usdc.key, usdc_bank_f, 1_000)
+        .await?;
+
+    // Borrower, 10 SOL are worth $100, $50 with the generic weight, $90 in e-mode
+    let borrower_mfi_account_f = test_f.create_marginfi_account().await;
+    let borrower_token_account_sol = test_f.sol_mint.create_token_account_and_mint_to(10).await;
+    borrower_mfi_account_f
+        .try_bank_deposit(borrower_token_account_sol.key, sol_bank_f, 10)
+        .await?;
+
+    // All liabilities are in the SOL category, e-mode weights apply
+    let borrower_token_account_sol_eq = test_f
+        .sol_equivalent_mint
+        .create_empty_token_account()
+        .await;
+    borrower_mfi_account_f
+        .try_bank_borrow(borrower_token_account_sol_eq.key, sol_eq_bank_f, 8)
+        .await?;
+
+    // A USDC liability takes the account out of e-mode
+    let borrower_token_account_usdc = test_f.usdc_mint.create_empty_token_account().await;
+    let res = borrower_mfi_account_f
+        .try_bank_borrow(borrower_token_account_usdc.key, usdc_bank_f, 1)
+        .await;
+    assert!(res.is_err());
+    assert_custom_error!(res.unwrap_err(), MarginfiError::RiskEngineInitRejected);
+
+    // Outside of e-mode, the generic weights apply
+    let other_borrower_mfi_account_f = test_f.create_marginfi_account().await;
+    let other_borrower_token_account_sol =
+        test_f.sol_mint.create_token_account_and_mint_to(10).await;
+    other_borrower_mfi_account_f
+        .try_bank_deposit(other_borrower_token_account_sol.key, sol_bank_f, 10)
+        .await?;
+
+    let res = other_borrower_mfi_account_f
+        .try_bank_borrow(borrower_token_account_usdc.key, usdc_bank_f, 80)
+        .await;
+    assert!(res.is_err());
+    assert_custom_error!(res.unwrap_err(), MarginfiError::RiskEngineInitRejected);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn emode_weights_must_be_favorable() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+
+    let sol_bank_f = test_f.get_bank(&BankMint::Sol);
+
+    // E-mode asset weight below the generic asset weight
+    let res = sol_bank_f
+        .update_config(BankConfigOpt {
+            emode_asset_weight_init: Some(I80F48::from_num(0.4).into()),
+            ..sol_emode_config()
+        })
+        .await;
+    assert!(res.is_err());
+
+    let sol_bank = sol_bank_f.load().await;
+    assert_eq!(sol_bank.emode_category, 0);
+
+    Ok(())
+}
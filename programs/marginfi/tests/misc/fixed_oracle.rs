@@ -0,0 +1,101 @@
+use fixed::types::I80F48;
+use fixtures::{assert_custom_error, prelude::*};
+use marginfi::{
+    constants::MAX_ORACLE_KEYS,
+    prelude::MarginfiError,
+    state::{
+        marginfi_group::{BankConfigOpt, OracleConfig},
+        price::OracleSetup,
+    },
+};
+use pretty_assertions::assert_eq;
+use solana_program_test::*;
+use solana_sdk::pubkey::Pubkey;
+
+fn fixed_oracle_config() -> OracleConfig {
+    OracleConfig {
+        setup: OracleSetup::Fixed,
+        keys: [Pubkey::default(); MAX_ORACLE_KEYS],
+        source_setups: [OracleSetup::None; MAX_ORACLE_KEYS],
+        fallback_setup: OracleSetup::None,
+    }
+}
+
+#[tokio::test]
+async fn fixed_oracle_price_set_by_admin() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+
+    let usdc_bank_f = test_f.get_bank(&BankMint::Usdc);
+    let sol_bank_f = test_f.get_bank(&BankMint::Sol);
+
+    sol_bank_f
+        .update_config(BankConfigOpt {
+            oracle: Some(fixed_oracle_config()),
+            fixed_price: Some(I80F48::from_num(10).into()),
+            ..BankConfigOpt::default()
+        })
+        .await?;
+
+    assert_eq!(sol_bank_f.get_price().await, 10.0);
+
+    // LP
+    let lp_mfi_account_f = test_f.create_marginfi_account().await;
+    let lp_token_account_usdc = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+    lp_mfi_account_f
+        .try_bank_deposit(lp_token_account_usdc.key, usdc_bank_f, 1_000)
+        .await?;
+
+    // Borrower, no oracle account is passed for the SOL collateral
+    let borrower_mfi_account_f = test_f.create_marginfi_account().await;
+    let borrower_token_account_sol = test_f.sol_mint.create_token_account_and_mint_to(10).await;
+    borrower_mfi_account_f
+        .try_bank_deposit(borrower_token_account_sol.key, sol_bank_f, 10)
+        .await?;
+
+    let borrower_token_account_usdc = test_f.usdc_mint.create_empty_token_account().await;
+    borrower_mfi_account_f
+        .try_bank_borrow(borrower_token_account_usdc.key, usdc_bank_f, 50)
+        .await?;
+
+    // Admin marks SOL down, the collateral no longer covers more borrows
+    sol_bank_f
+        .update_config(BankConfigOpt {
+            fixed_price: Some(I80F48::from_num(5).into()),
+            ..BankConfigOpt::default()
+        })
+        .await?;
+
+    assert_eq!(sol_bank_f.get_price().await, 5.0);
+
+    let res = borrower_mfi_account_f
+        .try_bank_borrow(borrower_token_account_usdc.key, usdc_bank_f, 10)
+        .await;
+    assert!(res.is_err());
+    assert_custom_error!(res.unwrap_err(), MarginfiError::RiskEngineInitRejected);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn fixed_oracle_requires_price() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+
+    let sol_bank_f = test_f.get_bank(&BankMint::Sol);
+
+    let res = sol_bank_f
+        .update_config(BankConfigOpt {
+            oracle: Some(fixed_oracle_config()),
+            ..BankConfigOpt::default()
+        })
+        .await;
+
+    assert!(res.is_err());
+
+    let sol_bank = sol_bank_f.load().await;
+    assert_eq!(sol_bank.config.oracle_setup, OracleSetup::PythLegacy);
+
+    Ok(())
+}
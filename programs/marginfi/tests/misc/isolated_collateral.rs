@@ -0,0 +1,74 @@
+use fixtures::{assert_custom_error, prelude::*};
+use marginfi::{
+    prelude::MarginfiError,
+    state::marginfi_group::{BankConfigOpt, RiskTier},
+};
+use solana_program_test::*;
+
+#[tokio::test]
+async fn isolated_collateral_debt_ceiling() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+
+    let usdc_bank_f = test_f.get_bank(&BankMint::Usdc);
+    let sol_bank_f = test_f.get_bank(&BankMint::Sol);
+
+    // SOL deposits may back $60 of debt across the group
+    sol_bank_f
+        .update_config(BankConfigOpt {
+            risk_tier: Some(RiskTier::IsolatedCollateral),
+            isolated_debt_ceiling: Some(60),
+            ..BankConfigOpt::default()
+        })
+        .await?;
+
+    // LP
+    let lp_mfi_account_f = test_f.create_marginfi_account().await;
+    let lp_token_account_usdc = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+    lp_mfi_account_f
+        .try_bank_deposit(lp_token_account_usdc.key, usdc_bank_f, 1_000)
+        .await?;
+
+    // Two depositors of 10 SOL, worth $100, each holding half of the ceiling
+    let borrower_mfi_account_f = test_f.create_marginfi_account().await;
+    let borrower_token_account_sol = test_f.sol_mint.create_token_account_and_mint_to(10).await;
+    borrower_mfi_account_f
+        .try_bank_deposit(borrower_token_account_sol.key, sol_bank_f, 10)
+        .await?;
+
+    let other_mfi_account_f = test_f.create_marginfi_account().await;
+    let other_token_account_sol = test_f.sol_mint.create_token_account_and_mint_to(10).await;
+    other_mfi_account_f
+        .try_bank_deposit(other_token_account_sol.key, sol_bank_f, 10)
+        .await?;
+
+    let borrower_token_account_usdc = test_f.usdc_mint.create_empty_token_account().await;
+    let res = borrower_mfi_account_f
+        .try_bank_borrow(borrower_token_account_usdc.key, usdc_bank_f, 31)
+        .await;
+    assert!(res.is_err());
+    assert_custom_error!(res.unwrap_err(), MarginfiError::RiskEngineInitRejected);
+
+    borrower_mfi_account_f
+        .try_bank_borrow(borrower_token_account_usdc.key, usdc_bank_f, 30)
+        .await?;
+
+    // Once the other depositor leaves, the whole ceiling backs the borrower's deposit
+    other_mfi_account_f
+        .try_bank_withdraw(other_token_account_sol.key, sol_bank_f, 0, Some(true))
+        .await?;
+
+    borrower_mfi_account_f
+        .try_bank_borrow(borrower_token_account_usdc.key, usdc_bank_f, 25)
+        .await?;
+
+    let res = borrower_mfi_account_f
+        .try_bank_borrow(borrower_token_account_usdc.key, usdc_bank_f, 10)
+        .await;
+    assert!(res.is_err());
+    assert_custom_error!(res.unwrap_err(), MarginfiError::RiskEngineInitRejected);
+
+    Ok(())
+}
@@ -0,0 +1,108 @@
+use fixtures::{assert_custom_error, prelude::*};
+use marginfi::{
+    constants::MAX_ORACLE_KEYS,
+    prelude::MarginfiError,
+    state::{
+        marginfi_group::{BankConfigOpt, OracleConfig},
+        price::OracleSetup,
+    },
+};
+use pretty_assertions::assert_eq;
+use solana_program_test::*;
+use solana_sdk::pubkey::Pubkey;
+
+fn median_oracle_config(sources: &[(OracleSetup, Pubkey)]) -> OracleConfig {
+    let mut keys = [Pubkey::default(); MAX_ORACLE_KEYS];
+    let mut source_setups = [OracleSetup::None; MAX_ORACLE_KEYS];
+
+    for (i, (oracle_setup, key)) in sources.iter().enumerate() {
+        keys[i] = *key;
+        source_setups[i] = *oracle_setup;
+    }
+
+    OracleConfig {
+        setup: OracleSetup::Median,
+        keys,
+        source_setups,
+        fallback_setup: OracleSetup::None,
+    }
+}
+
+#[tokio::test]
+async fn median_oracle_borrow_against_collateral() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+
+    let usdc_bank_f = test_f.get_bank(&BankMint::Usdc);
+    let sol_bank_f = test_f.get_bank(&BankMint::Sol);
+
+    sol_bank_f
+        .update_config(BankConfigOpt {
+            oracle: Some(median_oracle_config(&[
+                (OracleSetup::PythLegacy, PYTH_SOL_FEED),
+                (OracleSetup::SwitchboardV2, SWITCHBOARD_SOL_FEED),
+                (OracleSetup::PythPushOracle, PYTH_PUSH_FULLV_FEED_ID.into()),
+            ])),
+            oracle_max_deviation_bps: Some(100),
+            ..BankConfigOpt::default()
+        })
+        .await?;
+
+    let sol_bank = sol_bank_f.load().await;
+    assert_eq!(sol_bank.config.oracle_setup, OracleSetup::Median);
+    assert_eq!(sol_bank.config.get_oracle_ais_len(), 3);
+
+    // LP
+    let lp_mfi_account_f = test_f.create_marginfi_account().await;
+    let lp_token_account_usdc = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+    lp_mfi_account_f
+        .try_bank_deposit(lp_token_account_usdc.key, usdc_bank_f, 1_000)
+        .await?;
+
+    // Borrower, SOL collateral is priced from the median of all three feeds (all at $10)
+    let borrower_mfi_account_f = test_f.create_marginfi_account().await;
+    let borrower_token_account_sol = test_f.sol_mint.create_token_account_and_mint_to(10).await;
+    borrower_mfi_account_f
+        .try_bank_deposit(borrower_token_account_sol.key, sol_bank_f, 10)
+        .await?;
+
+    let borrower_token_account_usdc = test_f.usdc_mint.create_empty_token_account().await;
+    borrower_mfi_account_f
+        .try_bank_borrow(borrower_token_account_usdc.key, usdc_bank_f, 50)
+        .await?;
+
+    // Over the collateral value
+    let res = borrower_mfi_account_f
+        .try_bank_borrow(borrower_token_account_usdc.key, usdc_bank_f, 60)
+        .await;
+    assert!(res.is_err());
+    assert_custom_error!(res.unwrap_err(), MarginfiError::RiskEngineInitRejected);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn median_oracle_requires_multiple_sources() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+
+    let sol_bank_f = test_f.get_bank(&BankMint::Sol);
+
+    let res = sol_bank_f
+        .update_config(BankConfigOpt {
+            oracle: Some(median_oracle_config(&[(
+                OracleSetup::PythLegacy,
+                PYTH_SOL_FEED,
+            )])),
+            ..BankConfigOpt::default()
+        })
+        .await;
+
+    assert!(res.is_err());
+
+    let sol_bank = sol_bank_f.load().await;
+    assert_eq!(sol_bank.config.oracle_setup, OracleSetup::PythLegacy);
+
+    Ok(())
+}
@@ -1,9 +1,21 @@
 mod bank_ignore_stale_isolated_banks;
 mod bank_variable_oracle_staleness;
 mod collateral_value_cap;
+mod emode;
+mod fixed_oracle;
+mod isolated_collateral;
+mod median_oracle;
 mod operational_state;
+mod oracle_conf_interval;
+mod oracle_fallback;
+mod outflow_limit;
+mod pair_rules;
+mod price_guard;
 mod pyth_push;
 mod real_oracle_data;
 mod regression;
 mod risk_engine_flexible_oracle_checks;
+mod stake_pool_oracle;
 mod token_extensions;
+mod twap_oracle;
+mod usd_limits;
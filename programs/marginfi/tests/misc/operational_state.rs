@@ -14,7 +14,10 @@ async fn marginfi_group_bank_paused_should_error() -> anyhow::Result<()> {
             mint: BankMint::Usdc,
             config: None,
         }],
-        group_config: Some(GroupConfig { admin: None }),
+        group_config: Some(GroupConfig {
+            admin: None,
+            ..GroupConfig::default()
+        }),
         protocol_fees: false,
     }))
     .await;
@@ -54,7 +57,10 @@ async fn marginfi_group_bank_reduce_only_withdraw_success() -> anyhow::Result<()
             mint: BankMint::Usdc,
             config: None,
         }],
-        group_config: Some(GroupConfig { admin: None }),
+        group_config: Some(GroupConfig {
+            admin: None,
+            ..GroupConfig::default()
+        }),
         protocol_fees: false,
     }))
     .await;
@@ -102,7 +108,10 @@ async fn marginfi_group_bank_reduce_only_deposit_success() -> anyhow::Result<()>
                 }),
             },
         ],
-        group_config: Some(GroupConfig { admin: None }),
+        group_config: Some(GroupConfig {
+            admin: None,
+            ..GroupConfig::default()
+        }),
         protocol_fees: false,
     }))
     .await;
@@ -162,7 +171,10 @@ async fn marginfi_group_bank_reduce_only_borrow_failure() -> anyhow::Result<()>
                 }),
             },
         ],
-        group_config: Some(GroupConfig { admin: None }),
+        group_config: Some(GroupConfig {
+            admin: None,
+            ..GroupConfig::default()
+        }),
         protocol_fees: false,
     }))
     .await;
@@ -210,7 +222,10 @@ async fn marginfi_group_bank_reduce_only_deposit_failure() -> anyhow::Result<()>
             mint: BankMint::Usdc,
             config: None,
         }],
-        group_config: Some(GroupConfig { admin: None }),
+        group_config: Some(GroupConfig {
+            admin: None,
+            ..GroupConfig::default()
+        }),
         protocol_fees: false,
     }))
     .await;
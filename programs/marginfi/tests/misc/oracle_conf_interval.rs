@@ -0,0 +1,104 @@
+use fixed::types::I80F48;
+use fixtures::{assert_custom_error, prelude::*};
+use marginfi::{
+    constants::{CONF_INTERVAL_MODE_CAP, CONF_INTERVAL_MODE_REJECT},
+    prelude::MarginfiError,
+    state::marginfi_group::BankConfigOpt,
+};
+use pretty_assertions::assert_eq;
+use solana_program_test::*;
+
+#[tokio::test]
+async fn oracle_conf_interval_reject_mode() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_swb_payer_not_admin())).await;
+
+    let usdc_bank_f = test_f.get_bank(&BankMint::Usdc);
+    let sol_bank_f = test_f.get_bank(&BankMint::Sol);
+
+    // SOL std deviation is ~0.0014 at $10, far wider than 0.01% of the price
+    sol_bank_f
+        .update_config(BankConfigOpt {
+            oracle_max_conf_interval: Some(I80F48::from_num(0.0001).into()),
+            oracle_conf_interval_mode: Some(CONF_INTERVAL_MODE_REJECT),
+            ..BankConfigOpt::default()
+        })
+        .await?;
+
+    let sol_bank = sol_bank_f.load().await;
+    assert_eq!(
+        sol_bank.oracle_conf_interval_mode,
+        CONF_INTERVAL_MODE_REJECT
+    );
+
+    // LP
+    let lp_mfi_account_f = test_f.create_marginfi_account().await;
+    let lp_token_account_usdc = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+    lp_mfi_account_f
+        .try_bank_deposit(lp_token_account_usdc.key, usdc_bank_f, 1_000)
+        .await?;
+
+    // Borrower
+    let borrower_mfi_account_f = test_f.create_marginfi_account().await;
+    let borrower_token_account_sol = test_f.sol_mint.create_token_account_and_mint_to(10).await;
+    borrower_mfi_account_f
+        .try_bank_deposit(borrower_token_account_sol.key, sol_bank_f, 10)
+        .await?;
+
+    // The SOL price is rejected, the collateral can't be valued
+    let borrower_token_account_usdc = test_f.usdc_mint.create_empty_token_account().await;
+    let res = borrower_mfi_account_f
+        .try_bank_borrow(borrower_token_account_usdc.key, usdc_bank_f, 50)
+        .await;
+    assert!(res.is_err());
+    assert_custom_error!(res.unwrap_err(), MarginfiError::OracleConfidenceTooWide);
+
+    // Capping the confidence interval instead, the borrow goes through
+    sol_bank_f
+        .update_config(BankConfigOpt {
+            oracle_conf_interval_mode: Some(CONF_INTERVAL_MODE_CAP),
+            ..BankConfigOpt::default()
+        })
+        .await?;
+
+    borrower_mfi_account_f
+        .try_bank_borrow_with_nonce(borrower_token_account_usdc.key, usdc_bank_f, 50, 1)
+        .await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn oracle_conf_interval_config_validation() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+
+    let sol_bank_f = test_f.get_bank(&BankMint::Sol);
+
+    let res = sol_bank_f
+        .update_config(BankConfigOpt {
+            oracle_conf_interval_mode: Some(2),
+            ..BankConfigOpt::default()
+        })
+        .await;
+    assert!(res.is_err());
+
+    let res = sol_bank_f
+        .update_config(BankConfigOpt {
+            oracle_max_conf_interval: Some(I80F48::from_num(1.5).into()),
+            ..BankConfigOpt::default()
+        })
+        .await;
+    assert!(res.is_err());
+
+    let res = sol_bank_f
+        .update_config(BankConfigOpt {
+            oracle_conf_interval_multiple: Some(I80F48::from_num(-1).into()),
+            ..BankConfigOpt::default()
+        })
+        .await;
+    assert!(res.is_err());
+
+    Ok(())
+}
@@ -0,0 +1,96 @@
+use fixtures::{
+    assert_custom_error,
+    test::{
+        BankMint, TestFixture, TestSettings, PYTH_SOL_EQUIVALENT_FEED, PYTH_SOL_FEED,
+        PYTH_USDC_FEED,
+    },
+};
+use marginfi::{
+    constants::MAX_ORACLE_KEYS,
+    prelude::MarginfiError,
+    state::{
+        marginfi_group::{BankConfigOpt, OracleConfig},
+        price::OracleSetup,
+    },
+};
+use pretty_assertions::assert_eq;
+use solana_program_test::tokio;
+use solana_sdk::pubkey::Pubkey;
+
+#[tokio::test]
+/// A stale primary feed should be replaced by the fresh fallback feed
+async fn bank_oracle_fallback_when_primary_stale() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+
+    let usdc_bank = test_f.get_bank(&BankMint::Usdc);
+    let sol_eq_bank = test_f.get_bank(&BankMint::SolEquivalent);
+
+    // Make SOLE feed stale, SOL feed stays fresh
+    test_f.set_time(0);
+    test_f.set_pyth_oracle_timestamp(PYTH_USDC_FEED, 120).await;
+    test_f
+        .set_pyth_oracle_timestamp(PYTH_SOL_EQUIVALENT_FEED, 0)
+        .await;
+    test_f.set_pyth_oracle_timestamp(PYTH_SOL_FEED, 120).await;
+    test_f.advance_time(120).await;
+
+    // Fund USDC lender
+    let lender_mfi_account_f = test_f.create_marginfi_account().await;
+    let lender_token_account_usdc = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+    lender_mfi_account_f
+        .try_bank_deposit(lender_token_account_usdc.key, usdc_bank, 1_000)
+        .await?;
+
+    // Fund USDC borrower
+    let borrower_mfi_account_f = test_f.create_marginfi_account().await;
+    let borrower_token_account_f_sol_eq = test_f
+        .sol_equivalent_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+    let borrower_token_account_f_usdc = test_f.usdc_mint.create_empty_token_account().await;
+
+    borrower_mfi_account_f
+        .try_bank_deposit(borrower_token_account_f_sol_eq.key, sol_eq_bank, 50)
+        .await?;
+
+    // SOLE collateral is stale, borrowing fails
+    let res = borrower_mfi_account_f
+        .try_bank_borrow(borrower_token_account_f_usdc.key, usdc_bank, 100)
+        .await;
+
+    assert!(res.is_err());
+    assert_custom_error!(res.unwrap_err(), MarginfiError::RiskEngineInitRejected);
+
+    // Fall back to the SOL feed
+    let mut keys = [Pubkey::default(); MAX_ORACLE_KEYS];
+    keys[0] = PYTH_SOL_EQUIVALENT_FEED;
+    keys[1] = PYTH_SOL_FEED;
+
+    sol_eq_bank
+        .update_config(BankConfigOpt {
+            oracle: Some(OracleConfig {
+                setup: OracleSetup::PythLegacy,
+                keys,
+                source_setups: [OracleSetup::None; MAX_ORACLE_KEYS],
+                fallback_setup: OracleSetup::PythLegacy,
+            }),
+            ..Default::default()
+        })
+        .await?;
+
+    let sol_eq_bank_config = sol_eq_bank.load().await.config;
+    assert_eq!(
+        sol_eq_bank_config.oracle_fallback_setup,
+        OracleSetup::PythLegacy
+    );
+    assert_eq!(sol_eq_bank_config.get_oracle_ais_len(), 2);
+
+    borrower_mfi_account_f
+        .try_bank_borrow_with_nonce(borrower_token_account_f_usdc.key, usdc_bank, 100, 1)
+        .await?;
+
+    Ok(())
+}
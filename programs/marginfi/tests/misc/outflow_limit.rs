@@ -0,0 +1,221 @@
+use fixtures::{assert_custom_error, native, prelude::*};
+use marginfi::{
+    prelude::MarginfiError,
+    state::marginfi_group::{BankConfigOpt, BankOperationalState},
+};
+use pretty_assertions::assert_eq;
+use solana_program_test::*;
+
+#[tokio::test]
+async fn outflow_limit_trips_reduce_only() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+
+    let usdc_bank_f = test_f.get_bank(&BankMint::Usdc);
+    let sol_bank_f = test_f.get_bank(&BankMint::Sol);
+
+    usdc_bank_f
+        .update_config(BankConfigOpt {
+            outflow_window: Some(3_600),
+            outflow_limit: Some(native!(500, "USDC")),
+            ..BankConfigOpt::default()
+        })
+        .await?;
+
+    // LP
+    let lp_mfi_account_f = test_f.create_marginfi_account().await;
+    let lp_token_account_usdc = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+    lp_mfi_account_f
+        .try_bank_deposit(lp_token_account_usdc.key, usdc_bank_f, 1_000)
+        .await?;
+
+    // Borrower
+    let borrower_mfi_account_f = test_f.create_marginfi_account().await;
+    let borrower_token_account_sol = test_f.sol_mint.create_token_account_and_mint_to(100).await;
+    borrower_mfi_account_f
+        .try_bank_deposit(borrower_token_account_sol.key, sol_bank_f, 100)
+        .await?;
+
+    let borrower_token_account_usdc = test_f.usdc_mint.create_empty_token_account().await;
+    borrower_mfi_account_f
+        .try_bank_borrow(borrower_token_account_usdc.key, usdc_bank_f, 400)
+        .await?;
+
+    let usdc_bank = usdc_bank_f.load().await;
+    assert_eq!(
+        usdc_bank.config.operational_state,
+        BankOperationalState::Operational
+    );
+    assert_eq!(usdc_bank.outflow_current_window, native!(400, "USDC"));
+
+    // The borrow crossing the limit goes through and trips the bank to reduce only
+    borrower_mfi_account_f
+        .try_bank_borrow(borrower_token_account_usdc.key, usdc_bank_f, 200)
+        .await?;
+
+    let usdc_bank = usdc_bank_f.load().await;
+    assert_eq!(
+        usdc_bank.config.operational_state,
+        BankOperationalState::ReduceOnly
+    );
+
+    let res = borrower_mfi_account_f
+        .try_bank_borrow(borrower_token_account_usdc.key, usdc_bank_f, 10)
+        .await;
+    assert!(res.is_err());
+    assert_custom_error!(res.unwrap_err(), MarginfiError::BankReduceOnly);
+
+    // Withdrawals are rate limited until the window moves on
+    let res = lp_mfi_account_f
+        .try_bank_withdraw(lp_token_account_usdc.key, usdc_bank_f, 100, None)
+        .await;
+    assert!(res.is_err());
+    assert_custom_error!(res.unwrap_err(), MarginfiError::BankOutflowLimitExceeded);
+
+    test_f.advance_time(2 * 3_600).await;
+
+    lp_mfi_account_f
+        .try_bank_withdraw(lp_token_account_usdc.key, usdc_bank_f, 100, None)
+        .await?;
+
+    let usdc_bank = usdc_bank_f.load().await;
+    assert_eq!(usdc_bank.outflow_previous_window, 0);
+    assert_eq!(usdc_bank.outflow_current_window, native!(100, "USDC"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn outflow_limit_relative_to_deposits() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+
+    let usdc_bank_f = test_f.get_bank(&BankMint::Usdc);
+
+    // 10% of the deposits
+    usdc_bank_f
+        .update_config(BankConfigOpt {
+            outflow_window: Some(3_600),
+            outflow_limit_bps: Some(1_000),
+            ..BankConfigOpt::default()
+        })
+        .await?;
+
+    let lp_mfi_account_f = test_f.create_marginfi_account().await;
+    let lp_token_account_usdc = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+    lp_mfi_account_f
+        .try_bank_deposit(lp_token_account_usdc.key, usdc_bank_f, 1_000)
+        .await?;
+
+    lp_mfi_account_f
+        .try_bank_withdraw(lp_token_account_usdc.key, usdc_bank_f, 50, None)
+        .await?;
+
+    // 130 out of 870 remaining deposits trips the bank
+    lp_mfi_account_f
+        .try_bank_withdraw(lp_token_account_usdc.key, usdc_bank_f, 80, None)
+        .await?;
+
+    let usdc_bank = usdc_bank_f.load().await;
+    assert_eq!(
+        usdc_bank.config.operational_state,
+        BankOperationalState::ReduceOnly
+    );
+
+    let res = lp_mfi_account_f
+        .try_bank_withdraw(lp_token_account_usdc.key, usdc_bank_f, 10, None)
+        .await;
+    assert!(res.is_err());
+    assert_custom_error!(res.unwrap_err(), MarginfiError::BankOutflowLimitExceeded);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn outflow_limit_nets_flashloan_repays() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+
+    let usdc_bank_f = test_f.get_bank(&BankMint::Usdc);
+    let sol_bank_f = test_f.get_bank(&BankMint::Sol);
+
+    usdc_bank_f
+        .update_config(BankConfigOpt {
+            outflow_window: Some(3_600),
+            outflow_limit: Some(native!(500, "USDC")),
+            ..BankConfigOpt::default()
+        })
+        .await?;
+
+    // LP
+    let lp_mfi_account_f = test_f.create_marginfi_account().await;
+    let lp_token_account_usdc = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+    lp_mfi_account_f
+        .try_bank_deposit(lp_token_account_usdc.key, usdc_bank_f, 1_000)
+        .await?;
+
+    // A flashloan borrow over the limit, repaid in the same flashloan, doesn't trip the bank
+    let flashloan_mfi_account_f = test_f.create_marginfi_account().await;
+    let flashloan_token_account_usdc = test_f.usdc_mint.create_empty_token_account().await;
+
+    let borrow_ix = flashloan_mfi_account_f
+        .make_bank_borrow_ix(flashloan_token_account_usdc.key, usdc_bank_f, 800)
+        .await;
+    let repay_ix = flashloan_mfi_account_f
+        .make_bank_repay_ix(
+            flashloan_token_account_usdc.key,
+            usdc_bank_f,
+            800,
+            Some(true),
+        )
+        .await;
+
+    flashloan_mfi_account_f
+        .try_flashloan(vec![borrow_ix, repay_ix], vec![], vec![])
+        .await?;
+
+    let usdc_bank = usdc_bank_f.load().await;
+    assert_eq!(
+        usdc_bank.config.operational_state,
+        BankOperationalState::Operational
+    );
+    assert_eq!(usdc_bank.outflow_current_window, 0);
+
+    lp_mfi_account_f
+        .try_bank_withdraw(lp_token_account_usdc.key, usdc_bank_f, 100, None)
+        .await?;
+
+    // Deposits offset the outflow of the window
+    lp_mfi_account_f
+        .try_bank_deposit(lp_token_account_usdc.key, usdc_bank_f, 50)
+        .await?;
+
+    let usdc_bank = usdc_bank_f.load().await;
+    assert_eq!(usdc_bank.outflow_current_window, native!(50, "USDC"));
+
+    // A flashloan borrow over the limit that is kept errors when the flashloan ends
+    let borrower_mfi_account_f = test_f.create_marginfi_account().await;
+    let borrower_token_account_sol = test_f.sol_mint.create_token_account_and_mint_to(100).await;
+    borrower_mfi_account_f
+        .try_bank_deposit(borrower_token_account_sol.key, sol_bank_f, 100)
+        .await?;
+
+    let borrower_token_account_usdc = test_f.usdc_mint.create_empty_token_account().await;
+    let borrow_ix = borrower_mfi_account_f
+        .make_bank_borrow_ix(borrower_token_account_usdc.key, usdc_bank_f, 600)
+        .await;
+
+    let res = borrower_mfi_account_f
+        .try_flashloan(vec![borrow_ix], vec![], vec![usdc_bank_f.key])
+        .await;
+    assert!(res.is_err());
+    assert_custom_error!(res.unwrap_err(), MarginfiError::BankOutflowLimitExceeded);
+
+    Ok(())
+}
@@ -0,0 +1,92 @@
+use fixtures::{assert_custom_error, prelude::*};
+use marginfi::{prelude::MarginfiError, state::marginfi_group::BankConfigOpt};
+use pretty_assertions::assert_eq;
+use solana_program_test::*;
+
+const STABLE_PAIR_CLASS: u8 = 1;
+
+#[tokio::test]
+async fn pair_rules_restrict_collateral() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+
+    let usdc_bank_f = test_f.get_bank(&BankMint::Usdc);
+    let sol_bank_f = test_f.get_bank(&BankMint::Sol);
+    let sol_eq_bank_f = test_f.get_bank(&BankMint::SolEquivalent);
+
+    usdc_bank_f
+        .update_config(BankConfigOpt {
+            pair_class: Some(STABLE_PAIR_CLASS),
+            ..BankConfigOpt::default()
+        })
+        .await?;
+
+    // SOLE deposits may only back stablecoin liabilities
+    sol_eq_bank_f
+        .update_config(BankConfigOpt {
+            collateral_for_classes: Some(1 << STABLE_PAIR_CLASS),
+            ..BankConfigOpt::default()
+        })
+        .await?;
+
+    let sol_eq_bank = sol_eq_bank_f.load().await;
+    assert_eq!(sol_eq_bank.collateral_for_classes, 1 << STABLE_PAIR_CLASS);
+
+    // LPs
+    let lp_mfi_account_f = test_f.create_marginfi_account().await;
+    let lp_token_account_sol = test_f.sol_mint.create_token_account_and_mint_to(100).await;
+    lp_mfi_account_f
+        .try_bank_deposit(lp_token_account_sol.key, sol_bank_f, 100)
+        .await?;
+    let lp_token_account_usdc = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+    lp_mfi_account_f
+        .try_bank_deposit(lp_token_account_usdc.key, usdc_bank_f, 1_000)
+        .await?;
+
+    // Borrower, 10 SOLE are worth $100
+    let borrower_mfi_account_f = test_f.create_marginfi_account().await;
+    let borrower_token_account_sol_eq = test_f
+        .sol_equivalent_mint
+        .create_token_account_and_mint_to(10)
+        .await;
+    borrower_mfi_account_f
+        .try_bank_deposit(borrower_token_account_sol_eq.key, sol_eq_bank_f, 10)
+        .await?;
+
+    let borrower_token_account_usdc = test_f.usdc_mint.create_empty_token_account().await;
+    borrower_mfi_account_f
+        .try_bank_borrow(borrower_token_account_usdc.key, usdc_bank_f, 50)
+        .await?;
+
+    // SOLE may not back a SOL liability, the account has no collateral left for it
+    let borrower_token_account_sol = test_f.sol_mint.create_empty_token_account().await;
+    let res = borrower_mfi_account_f
+        .try_bank_borrow(borrower_token_account_sol.key, sol_bank_f, 1)
+        .await;
+    assert!(res.is_err());
+    assert_custom_error!(res.unwrap_err(), MarginfiError::RiskEngineInitRejected);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn pair_class_out_of_range() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+
+    let sol_bank_f = test_f.get_bank(&BankMint::Sol);
+
+    let res = sol_bank_f
+        .update_config(BankConfigOpt {
+            pair_class: Some(64),
+            ..BankConfigOpt::default()
+        })
+        .await;
+    assert!(res.is_err());
+
+    let sol_bank = sol_bank_f.load().await;
+    assert_eq!(sol_bank.pair_class, 0);
+
+    Ok(())
+}
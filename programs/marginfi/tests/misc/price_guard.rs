@@ -0,0 +1,228 @@
+use fixtures::{assert_custom_error, prelude::*};
+use marginfi::{prelude::MarginfiError, state::marginfi_group::BankConfigOpt};
+use pretty_assertions::assert_eq;
+use solana_program_test::*;
+
+#[tokio::test]
+async fn price_guard_trips_on_sharp_move() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+
+    let usdc_bank_f = test_f.get_bank(&BankMint::Usdc);
+    let sol_bank_f = test_f.get_bank(&BankMint::Sol);
+
+    // Trip on a move over 10% within 10 minutes
+    sol_bank_f
+        .update_config(BankConfigOpt {
+            price_guard_max_move_bps: Some(1_000),
+            price_guard_interval: Some(600),
+            ..BankConfigOpt::default()
+        })
+        .await?;
+
+    // LP
+    let lp_mfi_account_f = test_f.create_marginfi_account().await;
+    let lp_token_account_usdc = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+    let lp_token_account_sol = test_f.sol_mint.create_token_account_and_mint_to(100).await;
+    lp_mfi_account_f
+        .try_bank_deposit(lp_token_account_usdc.key, usdc_bank_f, 1_000)
+        .await?;
+    lp_mfi_account_f
+        .try_bank_deposit(lp_token_account_sol.key, sol_bank_f, 100)
+        .await?;
+
+    // Record the $10 SOL price
+    test_f
+        .marginfi_group
+        .try_accrue_interest(sol_bank_f)
+        .await?;
+    let now = test_f.get_clock().await.unix_timestamp;
+    assert!(!sol_bank_f.load().await.is_price_guard_tripped(now));
+
+    // Borrower of $20 against 10 SOL, worth $100
+    let borrower_mfi_account_f = test_f.create_marginfi_account().await;
+    let borrower_token_account_sol = test_f.sol_mint.create_token_account_and_mint_to(10).await;
+    borrower_mfi_account_f
+        .try_bank_deposit(borrower_token_account_sol.key, sol_bank_f, 10)
+        .await?;
+
+    let borrower_token_account_usdc = test_f.usdc_mint.create_empty_token_account().await;
+    borrower_mfi_account_f
+        .try_bank_borrow(borrower_token_account_usdc.key, usdc_bank_f, 20)
+        .await?;
+
+    // Single tick to $20
+    test_f.context.borrow_mut().set_account(
+        &PYTH_SOL_FEED,
+        &create_pyth_legacy_oracle_account(
+            test_f.sol_mint.key,
+            20.0,
+            SOL_MINT_DECIMALS.into(),
+            None,
+        )
+        .into(),
+    );
+    test_f
+        .marginfi_group
+        .try_accrue_interest(sol_bank_f)
+        .await?;
+
+    let sol_bank = sol_bank_f.load().await;
+    let now = test_f.get_clock().await.unix_timestamp;
+    assert!(sol_bank.is_price_guard_tripped(now));
+    assert_eq!(sol_bank.price_guard_tripped_at, now);
+
+    // SOL deposits don't back new borrows
+    let res = borrower_mfi_account_f
+        .try_bank_borrow(borrower_token_account_usdc.key, usdc_bank_f, 30)
+        .await;
+    assert!(res.is_err());
+    assert_custom_error!(res.unwrap_err(), MarginfiError::RiskEngineInitRejected);
+
+    // But still count as collateral otherwise, and deposits still go through
+    borrower_mfi_account_f
+        .try_bank_withdraw(borrower_token_account_sol.key, sol_bank_f, 5, None)
+        .await?;
+    borrower_mfi_account_f
+        .try_bank_deposit(borrower_token_account_sol.key, sol_bank_f, 5)
+        .await?;
+
+    // SOL can't be borrowed either
+    let sol_borrower_mfi_account_f = test_f.create_marginfi_account().await;
+    let sol_borrower_token_account_usdc = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+    sol_borrower_mfi_account_f
+        .try_bank_deposit(sol_borrower_token_account_usdc.key, usdc_bank_f, 1_000)
+        .await?;
+
+    let sol_borrower_token_account_sol = test_f.sol_mint.create_empty_token_account().await;
+    let res = sol_borrower_mfi_account_f
+        .try_bank_borrow(sol_borrower_token_account_sol.key, sol_bank_f, 1)
+        .await;
+    assert!(res.is_err());
+    assert_custom_error!(res.unwrap_err(), MarginfiError::BankPriceGuardTripped);
+
+    // Admin reset
+    sol_bank_f
+        .update_config(BankConfigOpt {
+            price_guard_reset: Some(true),
+            ..BankConfigOpt::default()
+        })
+        .await?;
+
+    borrower_mfi_account_f
+        .try_bank_borrow(borrower_token_account_usdc.key, usdc_bank_f, 30)
+        .await?;
+    sol_borrower_mfi_account_f
+        .try_bank_borrow(sol_borrower_token_account_sol.key, sol_bank_f, 1)
+        .await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn price_guard_checks_unrecorded_prices() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+
+    let usdc_bank_f = test_f.get_bank(&BankMint::Usdc);
+    let sol_bank_f = test_f.get_bank(&BankMint::Sol);
+
+    sol_bank_f
+        .update_config(BankConfigOpt {
+            price_guard_max_move_bps: Some(1_000),
+            price_guard_interval: Some(600),
+            ..BankConfigOpt::default()
+        })
+        .await?;
+
+    // LP
+    let lp_mfi_account_f = test_f.create_marginfi_account().await;
+    let lp_token_account_usdc = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+    let lp_token_account_sol = test_f.sol_mint.create_token_account_and_mint_to(100).await;
+    lp_mfi_account_f
+        .try_bank_deposit(lp_token_account_usdc.key, usdc_bank_f, 1_000)
+        .await?;
+    lp_mfi_account_f
+        .try_bank_deposit(lp_token_account_sol.key, sol_bank_f, 100)
+        .await?;
+
+    // Record the $10 SOL price
+    test_f
+        .marginfi_group
+        .try_accrue_interest(sol_bank_f)
+        .await?;
+
+    let borrower_mfi_account_f = test_f.create_marginfi_account().await;
+    let borrower_token_account_sol = test_f.sol_mint.create_token_account_and_mint_to(10).await;
+    borrower_mfi_account_f
+        .try_bank_deposit(borrower_token_account_sol.key, sol_bank_f, 10)
+        .await?;
+
+    let sol_borrower_mfi_account_f = test_f.create_marginfi_account().await;
+    let sol_borrower_token_account_usdc = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+    sol_borrower_mfi_account_f
+        .try_bank_deposit(sol_borrower_token_account_usdc.key, usdc_bank_f, 1_000)
+        .await?;
+
+    // Single tick to $20, not recorded by the guard
+    test_f.context.borrow_mut().set_account(
+        &PYTH_SOL_FEED,
+        &create_pyth_legacy_oracle_account(
+            test_f.sol_mint.key,
+            20.0,
+            SOL_MINT_DECIMALS.into(),
+            None,
+        )
+        .into(),
+    );
+
+    // SOL deposits don't count as collateral at the bad tick
+    let borrower_token_account_usdc = test_f.usdc_mint.create_empty_token_account().await;
+    let res = borrower_mfi_account_f
+        .try_bank_borrow(borrower_token_account_usdc.key, usdc_bank_f, 50)
+        .await;
+    assert!(res.is_err());
+    assert_custom_error!(res.unwrap_err(), MarginfiError::RiskEngineInitRejected);
+
+    // SOL can't be borrowed at the bad tick
+    let sol_borrower_token_account_sol = test_f.sol_mint.create_empty_token_account().await;
+    let res = sol_borrower_mfi_account_f
+        .try_bank_borrow(sol_borrower_token_account_sol.key, sol_bank_f, 1)
+        .await;
+    assert!(res.is_err());
+    assert_custom_error!(res.unwrap_err(), MarginfiError::BankPriceGuardTripped);
+
+    let now = test_f.get_clock().await.unix_timestamp;
+    assert!(!sol_bank_f.load().await.is_price_guard_tripped(now));
+
+    // Back to $10
+    test_f.context.borrow_mut().set_account(
+        &PYTH_SOL_FEED,
+        &create_pyth_legacy_oracle_account(
+            test_f.sol_mint.key,
+            10.0,
+            SOL_MINT_DECIMALS.into(),
+            None,
+        )
+        .into(),
+    );
+
+    borrower_mfi_account_f
+        .try_bank_borrow(borrower_token_account_usdc.key, usdc_bank_f, 40)
+        .await?;
+    sol_borrower_mfi_account_f
+        .try_bank_borrow(sol_borrower_token_account_sol.key, sol_bank_f, 2)
+        .await?;
+
+    Ok(())
+}
@@ -27,7 +27,10 @@ async fn pyth_push_fullv_borrow() -> anyhow::Result<()> {
                 config: Some(*DEFAULT_SOL_TEST_PYTH_PUSH_FULLV_BANK_CONFIG),
             },
         ],
-        group_config: Some(GroupConfig { admin: None }),
+        group_config: Some(GroupConfig {
+            admin: None,
+            ..GroupConfig::default()
+        }),
         protocol_fees: false,
     }))
     .await;
@@ -96,7 +99,10 @@ async fn pyth_push_partv_borrow() -> anyhow::Result<()> {
                 config: Some(*DEFAULT_SOL_TEST_PYTH_PUSH_PARTV_BANK_CONFIG),
             },
         ],
-        group_config: Some(GroupConfig { admin: None }),
+        group_config: Some(GroupConfig {
+            admin: None,
+            ..GroupConfig::default()
+        }),
         protocol_fees: false,
     }))
     .await;
@@ -160,7 +166,10 @@ async fn pyth_push_fullv_liquidate() -> anyhow::Result<()> {
                 }),
             },
         ],
-        group_config: Some(GroupConfig { admin: None }),
+        group_config: Some(GroupConfig {
+            admin: None,
+            ..GroupConfig::default()
+        }),
         protocol_fees: false,
     }))
     .await;
@@ -22,7 +22,10 @@ async fn real_oracle_marginfi_account_borrow_success() -> anyhow::Result<()> {
                 config: Some(*DEFAULT_SOL_TEST_REAL_BANK_CONFIG),
             },
         ],
-        group_config: Some(GroupConfig { admin: None }),
+        group_config: Some(GroupConfig {
+            admin: None,
+            ..GroupConfig::default()
+        }),
         protocol_fees: false,
     }))
     .await;
@@ -97,7 +100,10 @@ async fn real_oracle_pyth_push_marginfi_account_borrow_success() -> anyhow::Resu
                 config: Some(*DEFAULT_PYTH_PUSH_SOL_TEST_REAL_BANK_CONFIG),
             },
         ],
-        group_config: Some(GroupConfig { admin: None }),
+        group_config: Some(GroupConfig {
+            admin: None,
+            ..GroupConfig::default()
+        }),
         protocol_fees: false,
     }))
     .await;
@@ -4,10 +4,17 @@ use anchor_lang::AccountDeserialize;
 use anyhow::bail;
 use base64::{prelude::BASE64_STANDARD, Engine};
 use fixed::types::I80F48;
-use marginfi::state::{
-    marginfi_account::MarginfiAccount,
-    marginfi_group::{Bank, BankOperationalState, RiskTier},
-    price::OracleSetup,
+use marginfi::{
+    constants::MAX_ORACLE_KEYS,
+    state::{
+        marginfi_account::{
+            FixedTermBorrow, MarginfiAccount, MAX_ACCOUNT_ALLOWED_BANKS, MAX_FIXED_TERM_BORROWS,
+        },
+        marginfi_group::{
+            Bank, BankOperationalState, PriceObservation, RiskTier, TWAP_OBSERVATIONS,
+        },
+        price::OracleSetup,
+    },
 };
 use solana_account_decoder::UiAccountData;
 use solana_cli_output::CliAccount;
@@ -42,7 +49,18 @@ async fn account_field_values_reg() -> anyhow::Result<()> {
         pubkey!("Dq7wypbedtaqQK9QqEFvfrxc4ppfRGXCeTVd7ee7n2jw")
     );
     assert_eq!(account.account_flags, 0);
-    assert_eq!(account._padding, [0; 63]);
+    assert_eq!(
+        account.fixed_term_borrows,
+        [FixedTermBorrow::default(); MAX_FIXED_TERM_BORROWS]
+    );
+    assert_eq!(account.max_liability_value, 0);
+    assert_eq!(
+        account.allowed_banks,
+        [Pubkey::default(); MAX_ACCOUNT_ALLOWED_BANKS]
+    );
+    assert_eq!(account.delegate, Pubkey::default());
+    assert_eq!(account.delegate_permissions, 0);
+    assert_eq!(account._padding, [0; 21]);
 
     let balance_1 = account.lending_account.balances[0];
     assert!(balance_1.active);
@@ -67,7 +85,8 @@ async fn account_field_values_reg() -> anyhow::Result<()> {
         I80F48::from(balance_1.last_update),
         I80F48::from_str("1711158766").unwrap()
     );
-    assert_eq!(balance_1._padding, [0; 1]);
+    assert!(!balance_1.fixed_term_borrow_active);
+    assert_eq!(balance_1._padding, [0; 7]);
 
     let balance_2 = account.lending_account.balances[1];
     assert!(balance_2.active);
@@ -92,7 +111,8 @@ async fn account_field_values_reg() -> anyhow::Result<()> {
         I80F48::from(balance_2.last_update),
         I80F48::from_str("1711158793").unwrap()
     );
-    assert_eq!(balance_2._padding, [0; 1]);
+    assert!(!balance_2.fixed_term_borrow_active);
+    assert_eq!(balance_2._padding, [0; 7]);
 
     // Sample 2
 
@@ -117,7 +137,18 @@ async fn account_field_values_reg() -> anyhow::Result<()> {
         pubkey!("3T1kGHp7CrdeW9Qj1t8NMc2Ks233RyvzVhoaUPWoBEFK")
     );
     assert_eq!(account.account_flags, 0);
-    assert_eq!(account._padding, [0; 63]);
+    assert_eq!(
+        account.fixed_term_borrows,
+        [FixedTermBorrow::default(); MAX_FIXED_TERM_BORROWS]
+    );
+    assert_eq!(account.max_liability_value, 0);
+    assert_eq!(
+        account.allowed_banks,
+        [Pubkey::default(); MAX_ACCOUNT_ALLOWED_BANKS]
+    );
+    assert_eq!(account.delegate, Pubkey::default());
+    assert_eq!(account.delegate_permissions, 0);
+    assert_eq!(account._padding, [0; 21]);
 
     let balance_1 = account.lending_account.balances[0];
     assert!(balance_1.active);
@@ -142,7 +173,8 @@ async fn account_field_values_reg() -> anyhow::Result<()> {
         I80F48::from(balance_1.last_update),
         I80F48::from_str("1705760628").unwrap()
     );
-    assert_eq!(balance_1._padding, [0; 1]);
+    assert!(!balance_1.fixed_term_borrow_active);
+    assert_eq!(balance_1._padding, [0; 7]);
 
     let balance_2 = account.lending_account.balances[1];
     assert!(!balance_2.active);
@@ -167,7 +199,8 @@ async fn account_field_values_reg() -> anyhow::Result<()> {
         I80F48::from(balance_2.last_update),
         I80F48::from_str("0").unwrap()
     );
-    assert_eq!(balance_2._padding, [0; 1]);
+    assert!(!balance_2.fixed_term_borrow_active);
+    assert_eq!(balance_2._padding, [0; 7]);
 
     // Sample 3
 
@@ -192,7 +225,18 @@ async fn account_field_values_reg() -> anyhow::Result<()> {
         pubkey!("7hmfVTuXc7HeX3YQjpiCXGVQuTeXonzjp795jorZukVR")
     );
     assert_eq!(account.account_flags, 0);
-    assert_eq!(account._padding, [0; 63]);
+    assert_eq!(
+        account.fixed_term_borrows,
+        [FixedTermBorrow::default(); MAX_FIXED_TERM_BORROWS]
+    );
+    assert_eq!(account.max_liability_value, 0);
+    assert_eq!(
+        account.allowed_banks,
+        [Pubkey::default(); MAX_ACCOUNT_ALLOWED_BANKS]
+    );
+    assert_eq!(account.delegate, Pubkey::default());
+    assert_eq!(account.delegate_permissions, 0);
+    assert_eq!(account._padding, [0; 21]);
 
     let balance_1 = account.lending_account.balances[0];
     assert!(!balance_1.active);
@@ -217,7 +261,8 @@ async fn account_field_values_reg() -> anyhow::Result<()> {
         I80F48::from(balance_1.last_update),
         I80F48::from_str("0").unwrap()
     );
-    assert_eq!(balance_1._padding, [0; 1]);
+    assert!(!balance_1.fixed_term_borrow_active);
+    assert_eq!(balance_1._padding, [0; 7]);
 
     Ok(())
 }
@@ -632,13 +677,25 @@ async fn bank_field_values_reg() -> anyhow::Result<()> {
             Pubkey::default()
         ]
     );
-    assert_eq!(bank.config._pad0, [0; 6]);
+    assert_eq!(
+        bank.config.oracle_source_setups,
+        [OracleSetup::None; MAX_ORACLE_KEYS]
+    );
+    assert_eq!(bank.config.oracle_fallback_setup, OracleSetup::None);
     assert_eq!(bank.config.borrow_limit, 2000000000000);
     assert_eq!(bank.config.risk_tier, RiskTier::Collateral);
     assert_eq!(bank.config._pad1, [0; 7]);
     assert_eq!(bank.config.total_asset_value_init_limit, 0);
     assert_eq!(bank.config.oracle_max_age, 300);
-    assert_eq!(bank.config._padding, [0; 38]);
+    assert_eq!(bank.config.oracle_max_deviation_bps, 0);
+    assert_eq!(bank.config.liquidation_liquidator_fee_bps, 0);
+    assert_eq!(bank.config.liquidation_max_liquidator_fee_bps, 0);
+    assert_eq!(bank.config.liquidation_fee_ramp_shortfall_bps, 0);
+    assert_eq!(bank.config.liquidation_insurance_fee_bps, 0);
+    assert_eq!(bank.config.liquidation_close_factor_bps, 0);
+    assert_eq!(bank.config.liquidation_close_factor_shortfall_bps, 0);
+    assert_eq!(bank.config.liquidation_fee_flags, 0);
+    assert_eq!(bank.config._padding, [0; 23]);
 
     assert_eq!(bank.flags, 2);
 
@@ -655,8 +712,69 @@ async fn bank_field_values_reg() -> anyhow::Result<()> {
         pubkey!("2b1kV6DkPAnxd5ixfnxCpjxmKwqjjaYmCZfHsFu24GXo")
     );
 
-    assert_eq!(bank._padding_0, [[0, 0]; 27]);
-    assert_eq!(bank._padding_1, [[0, 0]; 32]);
+    assert_eq!(I80F48::from(bank.adaptive_plateau_rate), I80F48::ZERO);
+
+    assert_eq!(I80F48::from(bank.fixed_term_premium), I80F48::ZERO);
+    assert_eq!(I80F48::from(bank.fixed_term_liability_amount), I80F48::ZERO);
+    assert_eq!(I80F48::from(bank.fixed_term_annual_interest), I80F48::ZERO);
+    assert_eq!(bank.fixed_term_max_duration, 0);
+    assert_eq!(bank._pad3, [0; 8]);
+
+    assert_eq!(I80F48::from(bank.fixed_price), I80F48::ZERO);
+
+    assert_eq!(bank.twap_window, 0);
+    assert_eq!(bank.price_observation_index, 0);
+    assert_eq!(bank._pad4, [0; 7]);
+    assert_eq!(
+        bank.price_observations,
+        [PriceObservation::default(); TWAP_OBSERVATIONS]
+    );
+
+    assert_eq!(
+        I80F48::from(bank.oracle_conf_interval_multiple),
+        I80F48::ZERO
+    );
+    assert_eq!(I80F48::from(bank.oracle_max_conf_interval), I80F48::ZERO);
+    assert_eq!(bank.oracle_conf_interval_mode, 0);
+    assert_eq!(bank._pad5, [0; 15]);
+
+    assert_eq!(bank.emode_category, 0);
+    assert_eq!(bank._pad6, [0; 14]);
+    assert_eq!(I80F48::from(bank.emode_asset_weight_init), I80F48::ZERO);
+    assert_eq!(I80F48::from(bank.emode_asset_weight_maint), I80F48::ZERO);
+    assert_eq!(I80F48::from(bank.emode_liability_weight_init), I80F48::ZERO);
+    assert_eq!(
+        I80F48::from(bank.emode_liability_weight_maint),
+        I80F48::ZERO
+    );
+
+    assert_eq!(bank.collateral_for_classes, 0);
+    assert_eq!(bank.accepted_collateral_classes, 0);
+    assert_eq!(bank.pair_class, 0);
+    assert_eq!(bank._pad7, [0; 15]);
+
+    assert_eq!(bank.isolated_debt_ceiling, 0);
+    assert_eq!(bank._pad8, [0; 8]);
+
+    assert_eq!(bank.deposit_limit_usd, 0);
+    assert_eq!(bank.borrow_limit_usd, 0);
+
+    assert_eq!(bank.outflow_window, 0);
+    assert_eq!(bank.outflow_limit, 0);
+    assert_eq!(bank.outflow_limit_bps, 0);
+    assert_eq!(bank._pad9, [0; 6]);
+    assert_eq!(bank.outflow_window_start, 0);
+    assert_eq!(bank.outflow_current_window, 0);
+    assert_eq!(bank.outflow_previous_window, 0);
+
+    assert_eq!(bank.price_guard_max_move_bps, 0);
+    assert_eq!(bank._pad10, [0; 6]);
+    assert_eq!(bank.price_guard_interval, 0);
+    assert_eq!(I80F48::from(bank.price_guard_last_price), I80F48::ZERO);
+    assert_eq!(bank.price_guard_last_timestamp, 0);
+    assert_eq!(bank.price_guard_tripped_at, 0);
+
+    assert_eq!(bank._padding_1, [[0, 0]; 22]);
 
     Ok(())
 }
@@ -239,7 +239,10 @@ async fn re_liquidaiton_fail() -> anyhow::Result<()> {
                 }),
             },
         ],
-        group_config: Some(GroupConfig { admin: None }),
+        group_config: Some(GroupConfig {
+            admin: None,
+            ..GroupConfig::default()
+        }),
         protocol_fees: false,
     }))
     .await;
@@ -324,7 +327,10 @@ async fn re_liquidaiton_fail() -> anyhow::Result<()> {
 #[tokio::test]
 async fn re_bankruptcy_fail() -> anyhow::Result<()> {
     let mut test_f = TestFixture::new(Some(TestSettings {
-        group_config: Some(GroupConfig { admin: None }),
+        group_config: Some(GroupConfig {
+            admin: None,
+            ..GroupConfig::default()
+        }),
         banks: vec![
             TestBankSetting {
                 mint: BankMint::Usdc,
@@ -0,0 +1,184 @@
+use fixtures::{assert_custom_error, prelude::*};
+use marginfi::{
+    constants::MAX_ORACLE_KEYS,
+    prelude::MarginfiError,
+    state::{
+        marginfi_group::{BankConfigOpt, OracleConfig},
+        price::OracleSetup,
+    },
+};
+use pretty_assertions::assert_eq;
+use solana_program_test::*;
+use solana_sdk::{pubkey, pubkey::Pubkey};
+
+const STAKE_POOL: Pubkey = pubkey!("StakePoo11111111111111111111111111111111111");
+
+fn stake_pool_oracle_config() -> OracleConfig {
+    let mut keys = [Pubkey::default(); MAX_ORACLE_KEYS];
+    keys[0] = PYTH_PUSH_FULLV_FEED_ID.into();
+    keys[1] = STAKE_POOL;
+
+    OracleConfig {
+        setup: OracleSetup::StakePool,
+        keys,
+        source_setups: [OracleSetup::None; MAX_ORACLE_KEYS],
+        fallback_setup: OracleSetup::None,
+    }
+}
+
+async fn set_stake_pool(test_f: &TestFixture, pool_mint: Pubkey) {
+    let epoch = test_f.get_clock().await.epoch;
+
+    // 1.25 SOL per pool token
+    let stake_pool = create_spl_stake_pool_account(pool_mint, 1_250_000, 1_000_000, epoch);
+
+    test_f
+        .context
+        .borrow_mut()
+        .set_account(&STAKE_POOL, &stake_pool.into());
+}
+
+#[tokio::test]
+async fn stake_pool_oracle_price() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+
+    let usdc_bank_f = test_f.get_bank(&BankMint::Usdc);
+    let sol_eq_bank_f = test_f.get_bank(&BankMint::SolEquivalent);
+
+    set_stake_pool(&test_f, test_f.sol_equivalent_mint.key).await;
+
+    sol_eq_bank_f
+        .update_config(BankConfigOpt {
+            oracle: Some(stake_pool_oracle_config()),
+            ..BankConfigOpt::default()
+        })
+        .await?;
+
+    // $10 SOL * 1.25
+    assert_eq!(sol_eq_bank_f.get_price().await, 12.5);
+
+    // LP
+    let lp_mfi_account_f = test_f.create_marginfi_account().await;
+    let lp_token_account_usdc = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+    lp_mfi_account_f
+        .try_bank_deposit(lp_token_account_usdc.key, usdc_bank_f, 1_000)
+        .await?;
+
+    // Borrower, 10 SOLE are worth $125
+    let borrower_mfi_account_f = test_f.create_marginfi_account().await;
+    let borrower_token_account_sol_eq = test_f
+        .sol_equivalent_mint
+        .create_token_account_and_mint_to(10)
+        .await;
+    borrower_mfi_account_f
+        .try_bank_deposit(borrower_token_account_sol_eq.key, sol_eq_bank_f, 10)
+        .await?;
+
+    let borrower_token_account_usdc = test_f.usdc_mint.create_empty_token_account().await;
+    borrower_mfi_account_f
+        .try_bank_borrow(borrower_token_account_usdc.key, usdc_bank_f, 120)
+        .await?;
+
+    let res = borrower_mfi_account_f
+        .try_bank_borrow(borrower_token_account_usdc.key, usdc_bank_f, 10)
+        .await;
+    assert!(res.is_err());
+    assert_custom_error!(res.unwrap_err(), MarginfiError::RiskEngineInitRejected);
+
+    Ok(())
+}
+
+async fn advance_epochs(test_f: &TestFixture, epochs: u64) {
+    let mut clock = test_f.get_clock().await;
+    clock.epoch += epochs;
+    test_f.context.borrow_mut().set_sysvar(&clock);
+}
+
+#[tokio::test]
+async fn stake_pool_oracle_epoch_boundary() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+
+    let usdc_bank_f = test_f.get_bank(&BankMint::Usdc);
+    let sol_eq_bank_f = test_f.get_bank(&BankMint::SolEquivalent);
+
+    set_stake_pool(&test_f, test_f.sol_equivalent_mint.key).await;
+
+    sol_eq_bank_f
+        .update_config(BankConfigOpt {
+            oracle: Some(stake_pool_oracle_config()),
+            ..BankConfigOpt::default()
+        })
+        .await?;
+
+    // LP
+    let lp_mfi_account_f = test_f.create_marginfi_account().await;
+    let lp_token_account_usdc = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+    lp_mfi_account_f
+        .try_bank_deposit(lp_token_account_usdc.key, usdc_bank_f, 1_000)
+        .await?;
+
+    // Borrower, 10 SOLE are worth $125
+    let borrower_mfi_account_f = test_f.create_marginfi_account().await;
+    let borrower_token_account_sol_eq = test_f
+        .sol_equivalent_mint
+        .create_token_account_and_mint_to(10)
+        .await;
+    borrower_mfi_account_f
+        .try_bank_deposit(borrower_token_account_sol_eq.key, sol_eq_bank_f, 10)
+        .await?;
+
+    let borrower_token_account_usdc = test_f.usdc_mint.create_empty_token_account().await;
+
+    // New epoch, the stake pool isn't updated yet: the previous epoch's rate is still used
+    advance_epochs(&test_f, 1).await;
+
+    borrower_mfi_account_f
+        .try_bank_borrow(borrower_token_account_usdc.key, usdc_bank_f, 50)
+        .await?;
+
+    // Two epochs behind, the rate is stale and the collateral no longer backs new borrows
+    advance_epochs(&test_f, 1).await;
+
+    let res = borrower_mfi_account_f
+        .try_bank_borrow(borrower_token_account_usdc.key, usdc_bank_f, 10)
+        .await;
+    assert!(res.is_err());
+    assert_custom_error!(res.unwrap_err(), MarginfiError::RiskEngineInitRejected);
+
+    // Once cranked, the stake pool prices the collateral again
+    set_stake_pool(&test_f, test_f.sol_equivalent_mint.key).await;
+
+    borrower_mfi_account_f
+        .try_bank_borrow(borrower_token_account_usdc.key, usdc_bank_f, 20)
+        .await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn stake_pool_oracle_wrong_mint() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+
+    let sol_eq_bank_f = test_f.get_bank(&BankMint::SolEquivalent);
+
+    // Pool token is not the bank's mint
+    set_stake_pool(&test_f, test_f.sol_mint.key).await;
+
+    let res = sol_eq_bank_f
+        .update_config(BankConfigOpt {
+            oracle: Some(stake_pool_oracle_config()),
+            ..BankConfigOpt::default()
+        })
+        .await;
+
+    assert!(res.is_err());
+    assert_custom_error!(res.unwrap_err(), MarginfiError::InvalidOracleAccount);
+
+    Ok(())
+}
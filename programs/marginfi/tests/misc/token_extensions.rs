@@ -54,7 +54,10 @@ async fn marginfi_account_liquidation_success_with_extension(
                     }),
                 },
             ],
-            group_config: Some(GroupConfig { admin: None }),
+            group_config: Some(GroupConfig {
+                admin: None,
+                ..GroupConfig::default()
+            }),
             protocol_fees: false,
         }),
         &extensions,
@@ -0,0 +1,79 @@
+use fixed::types::I80F48;
+use fixtures::{assert_custom_error, prelude::*};
+use marginfi::{prelude::MarginfiError, state::marginfi_group::BankConfigOpt};
+use pretty_assertions::assert_eq;
+use solana_program_test::*;
+
+#[tokio::test]
+async fn twap_limits_spot_price_pump() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+
+    let usdc_bank_f = test_f.get_bank(&BankMint::Usdc);
+    let sol_bank_f = test_f.get_bank(&BankMint::Sol);
+
+    sol_bank_f
+        .update_config(BankConfigOpt {
+            twap_window: Some(600),
+            ..BankConfigOpt::default()
+        })
+        .await?;
+
+    // Record the $10 SOL price
+    test_f
+        .marginfi_group
+        .try_accrue_interest(sol_bank_f)
+        .await?;
+
+    let sol_bank = sol_bank_f.load().await;
+    let now = test_f.get_clock().await.unix_timestamp;
+    assert_eq!(sol_bank.price_observation_index, 1);
+    assert_eq!(sol_bank.price_observations[1].timestamp, now);
+    assert_eq!(
+        I80F48::from(sol_bank.price_observations[1].price),
+        I80F48::from_num(10)
+    );
+    assert_eq!(sol_bank.get_twap_price(now)?, Some(I80F48::from_num(10)));
+
+    // Spot and EMA prices are pumped to $20
+    test_f.context.borrow_mut().set_account(
+        &PYTH_SOL_FEED,
+        &create_pyth_legacy_oracle_account(
+            test_f.sol_mint.key,
+            20.0,
+            SOL_MINT_DECIMALS.into(),
+            None,
+        )
+        .into(),
+    );
+    assert_eq!(sol_bank_f.get_price().await, 20.0);
+
+    // LP
+    let lp_mfi_account_f = test_f.create_marginfi_account().await;
+    let lp_token_account_usdc = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+    lp_mfi_account_f
+        .try_bank_deposit(lp_token_account_usdc.key, usdc_bank_f, 1_000)
+        .await?;
+
+    // Borrower, 10 SOL are worth $200 at spot, but only $100 at the TWAP
+    let borrower_mfi_account_f = test_f.create_marginfi_account().await;
+    let borrower_token_account_sol = test_f.sol_mint.create_token_account_and_mint_to(10).await;
+    borrower_mfi_account_f
+        .try_bank_deposit(borrower_token_account_sol.key, sol_bank_f, 10)
+        .await?;
+
+    let borrower_token_account_usdc = test_f.usdc_mint.create_empty_token_account().await;
+    let res = borrower_mfi_account_f
+        .try_bank_borrow(borrower_token_account_usdc.key, usdc_bank_f, 150)
+        .await;
+    assert!(res.is_err());
+    assert_custom_error!(res.unwrap_err(), MarginfiError::RiskEngineInitRejected);
+
+    borrower_mfi_account_f
+        .try_bank_borrow(borrower_token_account_usdc.key, usdc_bank_f, 50)
+        .await?;
+
+    Ok(())
+}
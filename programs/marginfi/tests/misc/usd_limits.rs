@@ -0,0 +1,72 @@
+use fixtures::{assert_custom_error, prelude::*};
+use marginfi::{prelude::MarginfiError, state::marginfi_group::BankConfigOpt};
+use pretty_assertions::assert_eq;
+use solana_program_test::*;
+
+#[tokio::test]
+async fn usd_deposit_and_borrow_limits() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+
+    let usdc_bank_f = test_f.get_bank(&BankMint::Usdc);
+    let sol_bank_f = test_f.get_bank(&BankMint::Sol);
+
+    sol_bank_f
+        .update_config(BankConfigOpt {
+            deposit_limit_usd: Some(150),
+            ..BankConfigOpt::default()
+        })
+        .await?;
+    usdc_bank_f
+        .update_config(BankConfigOpt {
+            borrow_limit_usd: Some(100),
+            ..BankConfigOpt::default()
+        })
+        .await?;
+
+    let sol_bank = sol_bank_f.load().await;
+    assert_eq!(sol_bank.deposit_limit_usd, 150);
+
+    // LP
+    let lp_mfi_account_f = test_f.create_marginfi_account().await;
+    let lp_token_account_usdc = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+    lp_mfi_account_f
+        .try_bank_deposit(lp_token_account_usdc.key, usdc_bank_f, 1_000)
+        .await?;
+
+    // 10 SOL are worth $100, 20 SOL would be over the $150 limit
+    let borrower_mfi_account_f = test_f.create_marginfi_account().await;
+    let borrower_token_account_sol = test_f.sol_mint.create_token_account_and_mint_to(20).await;
+    borrower_mfi_account_f
+        .try_bank_deposit(borrower_token_account_sol.key, sol_bank_f, 10)
+        .await?;
+
+    let res = borrower_mfi_account_f
+        .try_bank_deposit(borrower_token_account_sol.key, sol_bank_f, 10)
+        .await;
+    assert!(res.is_err());
+    assert_custom_error!(res.unwrap_err(), MarginfiError::BankAssetCapacityExceeded);
+
+    // $110 of USDC liabilities would be over the $100 limit
+    let borrower_token_account_usdc = test_f.usdc_mint.create_empty_token_account().await;
+    borrower_mfi_account_f
+        .try_bank_borrow(borrower_token_account_usdc.key, usdc_bank_f, 50)
+        .await?;
+
+    let res = borrower_mfi_account_f
+        .try_bank_borrow(borrower_token_account_usdc.key, usdc_bank_f, 60)
+        .await;
+    assert!(res.is_err());
+    assert_custom_error!(
+        res.unwrap_err(),
+        MarginfiError::BankLiabilityCapacityExceeded
+    );
+
+    borrower_mfi_account_f
+        .try_bank_borrow(borrower_token_account_usdc.key, usdc_bank_f, 40)
+        .await?;
+
+    Ok(())
+}
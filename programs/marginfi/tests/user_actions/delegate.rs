@@ -0,0 +1,97 @@
+use fixtures::{assert_custom_error, native, prelude::*};
+use marginfi::{
+    prelude::MarginfiError,
+    state::marginfi_account::{
+        DELEGATE_DEPOSIT_PERMISSION, DELEGATE_REPAY_PERMISSION, DELEGATE_WITHDRAW_PERMISSION,
+    },
+};
+use pretty_assertions::assert_eq;
+use solana_program_test::*;
+use solana_sdk::{pubkey::Pubkey, signature::Keypair, signer::Signer};
+
+#[tokio::test]
+async fn delegate_scoped_permissions() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+
+    let usdc_bank_f = test_f.get_bank(&BankMint::Usdc);
+    let sol_bank_f = test_f.get_bank(&BankMint::Sol);
+
+    // LP
+    let lp_mfi_account_f = test_f.create_marginfi_account().await;
+    let lp_token_account_sol = test_f.sol_mint.create_token_account_and_mint_to(100).await;
+    lp_mfi_account_f
+        .try_bank_deposit(lp_token_account_sol.key, sol_bank_f, 100)
+        .await?;
+
+    // Account owned by the payer, with a deposit/repay/withdraw delegate
+    let mfi_account_f = test_f.create_marginfi_account().await;
+    let authority_token_account_usdc = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+    mfi_account_f
+        .try_bank_deposit(authority_token_account_usdc.key, usdc_bank_f, 500)
+        .await?;
+
+    let delegate = Keypair::new();
+    let permissions =
+        DELEGATE_DEPOSIT_PERMISSION | DELEGATE_REPAY_PERMISSION | DELEGATE_WITHDRAW_PERMISSION;
+
+    let res = mfi_account_f
+        .try_set_delegate(delegate.pubkey(), 1 << 63)
+        .await;
+    assert!(res.is_err());
+    assert_custom_error!(res.unwrap_err(), MarginfiError::IllegalFlag);
+
+    mfi_account_f
+        .try_set_delegate(delegate.pubkey(), permissions)
+        .await?;
+
+    let mfi_account = mfi_account_f.load().await;
+    assert_eq!(mfi_account.delegate, delegate.pubkey());
+    assert_eq!(mfi_account.delegate_permissions, permissions);
+
+    // The delegate withdraws to the authority's token accounts only
+    let ix = mfi_account_f
+        .make_bank_withdraw_ix(authority_token_account_usdc.key, usdc_bank_f, 100, None)
+        .await;
+    mfi_account_f.try_as_delegate(ix, &delegate).await?;
+    assert_eq!(
+        authority_token_account_usdc.balance().await,
+        native!(600, "USDC")
+    );
+
+    let delegate_token_account_usdc = TokenAccountFixture::new(
+        test_f.context.clone(),
+        &test_f.usdc_mint,
+        &delegate.pubkey(),
+    )
+    .await;
+    let ix = mfi_account_f
+        .make_bank_withdraw_ix(delegate_token_account_usdc.key, usdc_bank_f, 100, None)
+        .await;
+    let res = mfi_account_f.try_as_delegate(ix, &delegate).await;
+    assert!(res.is_err());
+
+    // Borrowing isn't allowed
+    let authority_token_account_sol = test_f.sol_mint.create_empty_token_account().await;
+    let ix = mfi_account_f
+        .make_bank_borrow_ix(authority_token_account_sol.key, sol_bank_f, 1)
+        .await;
+    let res = mfi_account_f.try_as_delegate(ix, &delegate).await;
+    assert!(res.is_err());
+
+    // Removed delegates can't sign anymore
+    mfi_account_f
+        .try_set_delegate(Pubkey::default(), permissions)
+        .await?;
+    assert_eq!(mfi_account_f.load().await.delegate_permissions, 0);
+
+    let ix = mfi_account_f
+        .make_bank_withdraw_ix(authority_token_account_usdc.key, usdc_bank_f, 100, None)
+        .await;
+    let res = mfi_account_f.try_as_delegate(ix, &delegate).await;
+    assert!(res.is_err());
+
+    Ok(())
+}
@@ -0,0 +1,211 @@
+use fixed_macro::types::I80F48;
+use fixtures::{assert_custom_error, native, prelude::*};
+use marginfi::{
+    prelude::MarginfiError,
+    state::{
+        marginfi_account::{EXTENDED_MARGINFI_ACCOUNT_LEN, MAX_EXTENDED_BALANCES},
+        marginfi_group::{BankConfig, BankConfigOpt},
+    },
+};
+use pretty_assertions::assert_eq;
+use solana_program_test::*;
+use solana_sdk::{
+    compute_budget::ComputeBudgetInstruction, pubkey::Pubkey, signer::Signer,
+    transaction::Transaction,
+};
+
+#[tokio::test]
+async fn extend_account_past_lending_account_balances() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+
+    // 17 USDC banks, one more than the lending account holds
+    let mut usdc_banks = vec![];
+    for bank_seed in 0..17_u64 {
+        usdc_banks.push(
+            test_f
+                .marginfi_group
+                .try_lending_pool_add_bank_with_seed(
+                    &test_f.usdc_mint,
+                    *DEFAULT_USDC_TEST_BANK_CONFIG,
+                    bank_seed,
+                )
+                .await?,
+        );
+    }
+
+    let mfi_account_f = test_f.create_marginfi_account().await;
+    let token_account_usdc = test_f.usdc_mint.create_token_account_and_mint_to(100).await;
+
+    for bank_f in &usdc_banks[..16] {
+        mfi_account_f
+            .try_bank_deposit(token_account_usdc.key, bank_f, 1)
+            .await?;
+    }
+
+    let res = mfi_account_f
+        .try_bank_deposit(token_account_usdc.key, &usdc_banks[16], 1)
+        .await;
+    assert!(res.is_err());
+    assert_custom_error!(
+        res.unwrap_err(),
+        MarginfiError::LendingAccountBalanceSlotsFull
+    );
+
+    // Extend, the lending account is left as is
+    let lending_account = mfi_account_f.load().await.lending_account;
+    mfi_account_f.try_extend().await?;
+
+    let account = test_f
+        .context
+        .borrow_mut()
+        .banks_client
+        .get_account(mfi_account_f.key)
+        .await?
+        .unwrap();
+    assert_eq!(account.data.len(), EXTENDED_MARGINFI_ACCOUNT_LEN);
+    assert_eq!(mfi_account_f.load().await.lending_account, lending_account);
+
+    let extended_balances = mfi_account_f.load_extended_balances().await;
+    assert_eq!(extended_balances.len(), MAX_EXTENDED_BALANCES);
+    assert!(extended_balances.iter().all(|balance| !balance.active));
+
+    // Extending twice changes nothing
+    mfi_account_f.try_extend().await?;
+    assert_eq!(
+        mfi_account_f.load_extended_balances().await,
+        extended_balances
+    );
+
+    // The 17th balance goes to the extended balances
+    mfi_account_f
+        .try_bank_deposit(token_account_usdc.key, &usdc_banks[16], 1)
+        .await?;
+
+    let extended_balances = mfi_account_f.load_extended_balances().await;
+    assert!(extended_balances[0].active);
+    assert_eq!(extended_balances[0].bank_pk, usdc_banks[16].key);
+    assert_eq!(token_account_usdc.balance().await, native!(83, "USDC"));
+
+    // Withdrawing checks the health of all 17 balances
+    let ix = mfi_account_f
+        .make_bank_withdraw_ix(token_account_usdc.key, &usdc_banks[16], 1, Some(true))
+        .await;
+    {
+        let mut ctx = test_f.context.borrow_mut();
+        let tx = Transaction::new_signed_with_payer(
+            &[
+                ComputeBudgetInstruction::set_compute_unit_limit(1_400_000),
+                ix,
+            ],
+            Some(&ctx.payer.pubkey()),
+            &[&ctx.payer],
+            ctx.last_blockhash,
+        );
+        ctx.banks_client.process_transaction(tx).await?;
+    }
+
+    assert!(!mfi_account_f.load_extended_balances().await[0].active);
+    assert_eq!(token_account_usdc.balance().await, native!(84, "USDC"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn liquidate_account_with_max_balances() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+
+    // A USDC bank per balance, each with its own oracle, so the liquidation loads as many
+    // accounts as it can at the cap
+    let max_balances = 16 + MAX_EXTENDED_BALANCES;
+    let mut usdc_banks = vec![];
+    for bank_seed in 0..max_balances as u64 {
+        let oracle = Pubkey::new_unique();
+        test_f.context.borrow_mut().set_account(
+            &oracle,
+            &create_pyth_legacy_oracle_account(
+                test_f.usdc_mint.key,
+                1.0,
+                USDC_MINT_DECIMALS.into(),
+                None,
+            )
+            .into(),
+        );
+
+        usdc_banks.push(
+            test_f
+                .marginfi_group
+                .try_lending_pool_add_bank_with_seed(
+                    &test_f.usdc_mint,
+                    BankConfig {
+                        oracle_keys: create_oracle_key_array(oracle),
+                        ..*DEFAULT_USDC_TEST_BANK_CONFIG
+                    },
+                    bank_seed,
+                )
+                .await?,
+        );
+    }
+    let (collateral_banks, debt_bank) = usdc_banks.split_at(max_balances - 1);
+    let debt_bank_f = &debt_bank[0];
+
+    // Liquidator, also providing the liquidity
+    let liquidator_mfi_account_f = test_f.create_marginfi_account().await;
+    let liquidator_token_account_usdc =
+        test_f.usdc_mint.create_token_account_and_mint_to(100).await;
+    liquidator_mfi_account_f
+        .try_bank_deposit(liquidator_token_account_usdc.key, debt_bank_f, 100)
+        .await?;
+
+    // Liquidatee, with collateral in every bank but the last, and debt in the last
+    let liquidatee_mfi_account_f = test_f.create_marginfi_account().await;
+    let liquidatee_token_account_usdc =
+        test_f.usdc_mint.create_token_account_and_mint_to(100).await;
+    liquidatee_mfi_account_f.try_extend().await?;
+    for bank_f in collateral_banks {
+        liquidatee_mfi_account_f
+            .try_bank_deposit(liquidatee_token_account_usdc.key, bank_f, 1)
+            .await?;
+    }
+    liquidatee_mfi_account_f
+        .try_bank_borrow(liquidatee_token_account_usdc.key, debt_bank_f, 15)
+        .await?;
+
+    // No room for another balance
+    let res = liquidatee_mfi_account_f
+        .try_bank_deposit(
+            liquidatee_token_account_usdc.key,
+            test_f.get_bank(&BankMint::Usdc),
+            1,
+        )
+        .await;
+    assert!(res.is_err());
+    assert_custom_error!(
+        res.unwrap_err(),
+        MarginfiError::LendingAccountBalanceSlotsFull
+    );
+
+    // 15 USDC of debt weighted at 2 against 23 USDC of collateral
+    debt_bank_f
+        .update_config(BankConfigOpt {
+            liability_weight_init: Some(I80F48!(2.5).into()),
+            liability_weight_maint: Some(I80F48!(2).into()),
+            ..BankConfigOpt::default()
+        })
+        .await?;
+
+    liquidator_mfi_account_f
+        .try_liquidate(
+            &liquidatee_mfi_account_f,
+            &collateral_banks[0],
+            0.5,
+            debt_bank_f,
+        )
+        .await?;
+
+    let liquidator_mfi_ma = liquidator_mfi_account_f.load().await;
+    let liquidator_balance = liquidator_mfi_ma.lending_account.balances[1];
+    assert!(liquidator_balance.active);
+    assert_eq!(liquidator_balance.bank_pk, collateral_banks[0].key);
+
+    Ok(())
+}
@@ -0,0 +1,253 @@
+use fixed::types::I80F48;
+use fixtures::{assert_custom_error, native, prelude::*};
+use marginfi::{
+    assert_eq_with_tolerance,
+    prelude::*,
+    state::marginfi_group::{BankConfigOpt, WrappedI80F48},
+};
+use pretty_assertions::assert_eq;
+use solana_program_test::*;
+
+const ONE_WEEK: u64 = 7 * 24 * 60 * 60;
+
+#[tokio::test]
+async fn marginfi_account_fixed_term_borrow_and_repay() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+
+    let usdc_bank_f = test_f.get_bank(&BankMint::Usdc);
+    let sol_bank_f = test_f.get_bank(&BankMint::Sol);
+
+    sol_bank_f
+        .update_config(BankConfigOpt {
+            fixed_term_max_duration: Some(4 * ONE_WEEK),
+            fixed_term_premium: Some(WrappedI80F48::from(I80F48::from_num(0.02))),
+            ..BankConfigOpt::default()
+        })
+        .await?;
+
+    // LP
+    let lp_mfi_account_f = test_f.create_marginfi_account().await;
+    let lp_token_account_sol = test_f
+        .sol_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+    lp_mfi_account_f
+        .try_bank_deposit(lp_token_account_sol.key, sol_bank_f, 1_000)
+        .await?;
+
+    // Borrower
+    let borrower_mfi_account_f = test_f.create_marginfi_account().await;
+    let borrower_token_account_usdc = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+    borrower_mfi_account_f
+        .try_bank_deposit(borrower_token_account_usdc.key, usdc_bank_f, 1_000)
+        .await?;
+    let borrower_token_account_sol = test_f.sol_mint.create_empty_token_account().await;
+
+    // Durations above the bank's maximum are rejected
+    let res = borrower_mfi_account_f
+        .try_bank_borrow_fixed_term(borrower_token_account_sol.key, sol_bank_f, 10, 5 * ONE_WEEK)
+        .await;
+    assert!(res.is_err());
+    assert_custom_error!(res.unwrap_err(), MarginfiError::InvalidFixedTermBorrow);
+
+    borrower_mfi_account_f
+        .try_bank_borrow_fixed_term(borrower_token_account_sol.key, sol_bank_f, 10, ONE_WEEK)
+        .await?;
+
+    assert_eq!(
+        borrower_token_account_sol.balance().await,
+        native!(10, "SOL")
+    );
+
+    let borrower_mfi_account = borrower_mfi_account_f.load().await;
+    let fixed_term_borrow = borrower_mfi_account.fixed_term_borrows[0];
+    assert_eq!(fixed_term_borrow.bank_pk, sol_bank_f.key);
+    assert_eq!(
+        I80F48::from(fixed_term_borrow.principal),
+        I80F48::from_num(native!(10, "SOL"))
+    );
+    assert!(I80F48::from(fixed_term_borrow.rate) >= I80F48::from_num(0.02));
+    assert!(borrower_mfi_account.lending_account.balances[1].fixed_term_borrow_active);
+
+    let sol_bank = sol_bank_f.load().await;
+    assert_eq!(
+        I80F48::from(sol_bank.fixed_term_liability_amount),
+        I80F48::from_num(native!(10, "SOL"))
+    );
+    assert_eq!(I80F48::from(sol_bank.total_liability_shares), I80F48::ZERO);
+
+    // A healthy fixed-term borrow can't be converted before maturity
+    let res = borrower_mfi_account_f
+        .try_convert_fixed_term(sol_bank_f)
+        .await;
+    assert!(res.is_err());
+    assert_custom_error!(
+        res.unwrap_err(),
+        MarginfiError::FixedTermBorrowNotConvertible
+    );
+
+    test_f.advance_time(ONE_WEEK as i64).await;
+
+    let borrower_mfi_account = borrower_mfi_account_f.load().await;
+    let amount_owed = borrower_mfi_account.fixed_term_borrows[0]
+        .get_amount_owed(test_f.get_clock().await.unix_timestamp)
+        .unwrap();
+    assert!(amount_owed > I80F48::from_num(native!(10, "SOL")));
+
+    // Repay principal plus interest in full
+    let funding_account = test_f.sol_mint.create_token_account_and_mint_to(11).await;
+    borrower_mfi_account_f
+        .try_bank_repay_fixed_term(funding_account.key, sol_bank_f)
+        .await?;
+
+    assert!(funding_account.balance().await < native!(1, "SOL"));
+
+    let res = borrower_mfi_account_f
+        .try_bank_repay_fixed_term(funding_account.key, sol_bank_f)
+        .await;
+    assert!(res.is_err());
+    assert_custom_error!(res.unwrap_err(), MarginfiError::FixedTermBorrowNotFound);
+
+    let borrower_mfi_account = borrower_mfi_account_f.load().await;
+    assert!(borrower_mfi_account.fixed_term_borrows[0].is_empty());
+    assert!(!borrower_mfi_account.lending_account.balances[1].fixed_term_borrow_active);
+
+    let sol_bank = sol_bank_f.load().await;
+    assert_eq!(
+        I80F48::from(sol_bank.fixed_term_liability_amount),
+        I80F48::ZERO
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn marginfi_account_fixed_term_interest_accrues_to_depositors() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+
+    let usdc_bank_f = test_f.get_bank(&BankMint::Usdc);
+    let sol_bank_f = test_f.get_bank(&BankMint::Sol);
+
+    sol_bank_f
+        .update_config(BankConfigOpt {
+            fixed_term_max_duration: Some(4 * ONE_WEEK),
+            fixed_term_premium: Some(WrappedI80F48::from(I80F48::from_num(0.02))),
+            ..BankConfigOpt::default()
+        })
+        .await?;
+
+    // LP
+    let lp_mfi_account_f = test_f.create_marginfi_account().await;
+    let lp_token_account_sol = test_f
+        .sol_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+    lp_mfi_account_f
+        .try_bank_deposit(lp_token_account_sol.key, sol_bank_f, 1_000)
+        .await?;
+
+    // Borrower
+    let borrower_mfi_account_f = test_f.create_marginfi_account().await;
+    let borrower_token_account_usdc = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+    borrower_mfi_account_f
+        .try_bank_deposit(borrower_token_account_usdc.key, usdc_bank_f, 1_000)
+        .await?;
+    let borrower_token_account_sol = test_f.sol_mint.create_empty_token_account().await;
+
+    borrower_mfi_account_f
+        .try_bank_borrow_fixed_term(borrower_token_account_sol.key, sol_bank_f, 10, ONE_WEEK)
+        .await?;
+
+    test_f.advance_time(ONE_WEEK as i64).await;
+
+    // Depositors earn the fixed-term interest while the borrow runs, not when it's settled
+    test_f
+        .marginfi_group
+        .try_accrue_interest(sol_bank_f)
+        .await?;
+
+    let sol_bank = sol_bank_f.load().await;
+    let asset_share_value = I80F48::from(sol_bank.asset_share_value);
+    assert!(asset_share_value > I80F48::ONE);
+
+    let amount_owed = borrower_mfi_account_f.load().await.fixed_term_borrows[0]
+        .get_amount_owed(test_f.get_clock().await.unix_timestamp)
+        .unwrap();
+    assert_eq_with_tolerance!(
+        I80F48::from(sol_bank.fixed_term_liability_amount),
+        amount_owed,
+        I80F48::ONE
+    );
+
+    // Anyone can convert the matured borrow to a variable liability
+    borrower_mfi_account_f
+        .try_convert_fixed_term(sol_bank_f)
+        .await?;
+
+    let borrower_mfi_account = borrower_mfi_account_f.load().await;
+    assert!(borrower_mfi_account.fixed_term_borrows[0].is_empty());
+    let balance = borrower_mfi_account.lending_account.balances[1];
+    assert!(!balance.fixed_term_borrow_active);
+
+    // Converting doesn't credit the interest a second time, a deposit made right before it
+    // captures none of the fixed-term interest
+    let sol_bank = sol_bank_f.load().await;
+    assert_eq!(I80F48::from(sol_bank.asset_share_value), asset_share_value);
+    assert_eq!(
+        I80F48::from(sol_bank.fixed_term_liability_amount),
+        I80F48::ZERO
+    );
+    assert_eq!(
+        I80F48::from(sol_bank.fixed_term_annual_interest),
+        I80F48::ZERO
+    );
+    assert_eq_with_tolerance!(
+        sol_bank.get_liability_amount(balance.liability_shares.into())?,
+        amount_owed,
+        I80F48::ONE
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn marginfi_account_fixed_term_borrow_disabled() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+
+    let usdc_bank_f = test_f.get_bank(&BankMint::Usdc);
+    let sol_bank_f = test_f.get_bank(&BankMint::Sol);
+
+    let lp_mfi_account_f = test_f.create_marginfi_account().await;
+    let lp_token_account_sol = test_f
+        .sol_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+    lp_mfi_account_f
+        .try_bank_deposit(lp_token_account_sol.key, sol_bank_f, 1_000)
+        .await?;
+
+    let borrower_mfi_account_f = test_f.create_marginfi_account().await;
+    let borrower_token_account_usdc = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+    borrower_mfi_account_f
+        .try_bank_deposit(borrower_token_account_usdc.key, usdc_bank_f, 1_000)
+        .await?;
+    let borrower_token_account_sol = test_f.sol_mint.create_empty_token_account().await;
+
+    let res = borrower_mfi_account_f
+        .try_bank_borrow_fixed_term(borrower_token_account_sol.key, sol_bank_f, 10, ONE_WEEK)
+        .await;
+
+    assert!(res.is_err());
+    assert_custom_error!(res.unwrap_err(), MarginfiError::InvalidFixedTermBorrow);
+
+    Ok(())
+}
@@ -324,6 +324,7 @@ async fn flashloan_fail_missing_invalid_sysvar_ixs() -> anyhow::Result<()> {
             marginfi_account: borrower_mfi_account_f.key,
             signer: test_f.context.borrow().payer.pubkey(),
             ixs_sysvar: Pubkey::default(),
+            marginfi_group: test_f.marginfi_group.key,
         }
         .to_account_metas(Some(true)),
         data: marginfi::instruction::LendingAccountStartFlashloan {
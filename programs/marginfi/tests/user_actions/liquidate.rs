@@ -384,6 +384,263 @@ async fn marginfi_account_liquidation_success_many_balances() -> anyhow::Result<
     Ok(())
 }
 
+#[tokio::test]
+async fn marginfi_account_liquidation_success_custom_bank_fees() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+
+    let usdc_bank_f = test_f.get_bank(&BankMint::Usdc);
+    let sol_bank_f = test_f.get_bank(&BankMint::Sol);
+
+    let lender_mfi_account_f = test_f.create_marginfi_account().await;
+    let lender_token_account_usdc = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(2_000)
+        .await;
+    lender_mfi_account_f
+        .try_bank_deposit(lender_token_account_usdc.key, usdc_bank_f, 2_000)
+        .await?;
+
+    let borrower_mfi_account_f = test_f.create_marginfi_account().await;
+    let borrower_token_account_sol = test_f.sol_mint.create_token_account_and_mint_to(100).await;
+    let borrower_token_account_usdc = test_f.usdc_mint.create_empty_token_account().await;
+
+    // Borrower deposits 100 SOL worth of $1000
+    borrower_mfi_account_f
+        .try_bank_deposit(borrower_token_account_sol.key, sol_bank_f, 100)
+        .await?;
+
+    // Borrower borrows $999
+    borrower_mfi_account_f
+        .try_bank_borrow(borrower_token_account_usdc.key, usdc_bank_f, 999)
+        .await?;
+
+    // SOL collateral pays a 5% liquidator fee and a 1% insurance fee
+    sol_bank_f
+        .update_config(BankConfigOpt {
+            liquidation_liquidator_fee_bps: Some(500),
+            liquidation_insurance_fee_bps: Some(100),
+            ..Default::default()
+        })
+        .await?;
+
+    // Synthetically bring down the borrower account health by reducing the asset weights of the SOL bank
+    sol_bank_f
+        .update_config(BankConfigOpt {
+            asset_weight_init: Some(I80F48!(0.25).into()),
+            asset_weight_maint: Some(I80F48!(0.5).into()),
+            ..Default::default()
+        })
+        .await?;
+
+    lender_mfi_account_f
+        .try_liquidate(&borrower_mfi_account_f, sol_bank_f, 1, usdc_bank_f)
+        .await?;
+
+    let usdc_bank: Bank = usdc_bank_f.load().await;
+
+    let depositor_ma = lender_mfi_account_f.load().await;
+    let borrower_ma = borrower_mfi_account_f.load().await;
+
+    // Depositors should have 1990.5 USDC ($10 of SOL bought at a 5% discount)
+    assert_eq_noise!(
+        usdc_bank
+            .get_asset_amount(depositor_ma.lending_account.balances[0].asset_shares.into())
+            .unwrap(),
+        I80F48::from(native!(1990.5, "USDC", f64)),
+        native!(0.00001, "USDC", f64)
+    );
+
+    // Borrower should have 989.6 USDC of debt ($10 of SOL sold at a 6% discount)
+    assert_eq_noise!(
+        usdc_bank
+            .get_liability_amount(
+                borrower_ma.lending_account.balances[1]
+                    .liability_shares
+                    .into()
+            )
+            .unwrap(),
+        I80F48::from(native!(989.6, "USDC", f64)),
+        native!(0.00001, "USDC", f64)
+    );
+
+    // Insurance fund receives the 1% insurance fee
+    let insurance_fund_usdc = usdc_bank_f
+        .get_vault_token_account(BankVaultType::Insurance)
+        .await;
+
+    assert_eq_noise!(
+        insurance_fund_usdc.balance().await as i64,
+        native!(0.1, "USDC", f64) as i64,
+        1
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn marginfi_account_liquidation_success_liquidator_fee_ramp() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+
+    let usdc_bank_f = test_f.get_bank(&BankMint::Usdc);
+    let sol_bank_f = test_f.get_bank(&BankMint::Sol);
+
+    let lender_mfi_account_f = test_f.create_marginfi_account().await;
+    let lender_token_account_usdc = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(2_000)
+        .await;
+    lender_mfi_account_f
+        .try_bank_deposit(lender_token_account_usdc.key, usdc_bank_f, 2_000)
+        .await?;
+
+    let borrower_mfi_account_f = test_f.create_marginfi_account().await;
+    let borrower_token_account_sol = test_f.sol_mint.create_token_account_and_mint_to(100).await;
+    let borrower_token_account_usdc = test_f.usdc_mint.create_empty_token_account().await;
+
+    // Borrower deposits 100 SOL worth of $1000
+    borrower_mfi_account_f
+        .try_bank_deposit(borrower_token_account_sol.key, sol_bank_f, 100)
+        .await?;
+
+    // Borrower borrows $999
+    borrower_mfi_account_f
+        .try_bank_borrow(borrower_token_account_usdc.key, usdc_bank_f, 999)
+        .await?;
+
+    // Liquidator fee ramps from the default 2.5% to 12.5% over a 10% shortfall
+    sol_bank_f
+        .update_config(BankConfigOpt {
+            liquidation_max_liquidator_fee_bps: Some(1250),
+            ..Default::default()
+        })
+        .await?;
+
+    // Bring the borrower slightly below maintenance: $950 - $999 = -$49
+    sol_bank_f
+        .update_config(BankConfigOpt {
+            asset_weight_init: Some(I80F48!(0.9).into()),
+            asset_weight_maint: Some(I80F48!(0.95).into()),
+            ..Default::default()
+        })
+        .await?;
+
+    lender_mfi_account_f
+        .try_liquidate(&borrower_mfi_account_f, sol_bank_f, 1, usdc_bank_f)
+        .await?;
+
+    // Shortfall of 49 / 999 is about halfway up the ramp
+    let liquidator_fee = 0.025 + (0.125 - 0.025) * (49. / 999.) / 0.1;
+    let insurance_fee = 0.025;
+
+    let usdc_bank: Bank = usdc_bank_f.load().await;
+
+    let depositor_ma = lender_mfi_account_f.load().await;
+    let borrower_ma = borrower_mfi_account_f.load().await;
+
+    assert_eq_noise!(
+        usdc_bank
+            .get_asset_amount(depositor_ma.lending_account.balances[0].asset_shares.into())
+            .unwrap(),
+        I80F48::from(native!(2_000. - 10. * (1. - liquidator_fee), "USDC", f64)),
+        native!(0.0001, "USDC", f64)
+    );
+
+    assert_eq_noise!(
+        usdc_bank
+            .get_liability_amount(
+                borrower_ma.lending_account.balances[1]
+                    .liability_shares
+                    .into()
+            )
+            .unwrap(),
+        I80F48::from(native!(
+            999. - 10. * (1. - liquidator_fee - insurance_fee),
+            "USDC",
+            f64
+        )),
+        native!(0.0001, "USDC", f64)
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn marginfi_account_liquidation_close_factor() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+
+    let usdc_bank_f = test_f.get_bank(&BankMint::Usdc);
+    let sol_bank_f = test_f.get_bank(&BankMint::Sol);
+
+    let lender_mfi_account_f = test_f.create_marginfi_account().await;
+    let lender_token_account_usdc = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(2_000)
+        .await;
+    lender_mfi_account_f
+        .try_bank_deposit(lender_token_account_usdc.key, usdc_bank_f, 2_000)
+        .await?;
+
+    let borrower_mfi_account_f = test_f.create_marginfi_account().await;
+    let borrower_token_account_sol = test_f.sol_mint.create_token_account_and_mint_to(100).await;
+    let borrower_token_account_usdc = test_f.usdc_mint.create_empty_token_account().await;
+
+    // Borrower deposits 100 SOL worth of $1000
+    borrower_mfi_account_f
+        .try_bank_deposit(borrower_token_account_sol.key, sol_bank_f, 100)
+        .await?;
+
+    // Borrower borrows $999
+    borrower_mfi_account_f
+        .try_bank_borrow(borrower_token_account_usdc.key, usdc_bank_f, 999)
+        .await?;
+
+    // At most half of the USDC debt can be repaid per liquidation
+    usdc_bank_f
+        .update_config(BankConfigOpt {
+            liquidation_close_factor_bps: Some(5_000),
+            ..Default::default()
+        })
+        .await?;
+
+    // Synthetically bring down the borrower account health by reducing the asset weights of the SOL bank
+    sol_bank_f
+        .update_config(BankConfigOpt {
+            asset_weight_init: Some(I80F48!(0.25).into()),
+            asset_weight_maint: Some(I80F48!(0.5).into()),
+            ..Default::default()
+        })
+        .await?;
+
+    // 60 SOL repays $570 of the $999 debt, over the close factor
+    let res = lender_mfi_account_f
+        .try_liquidate(&borrower_mfi_account_f, sol_bank_f, 60, usdc_bank_f)
+        .await;
+
+    assert_custom_error!(res.unwrap_err(), MarginfiError::IllegalLiquidation);
+
+    // 50 SOL repays $475 of the $999 debt
+    lender_mfi_account_f
+        .try_liquidate(&borrower_mfi_account_f, sol_bank_f, 50, usdc_bank_f)
+        .await?;
+
+    let usdc_bank: Bank = usdc_bank_f.load().await;
+    let borrower_ma = borrower_mfi_account_f.load().await;
+
+    assert_eq_noise!(
+        usdc_bank
+            .get_liability_amount(
+                borrower_ma.lending_account.balances[1]
+                    .liability_shares
+                    .into()
+            )
+            .unwrap(),
+        I80F48::from(native!(524, "USDC")),
+        native!(0.00001, "USDC", f64)
+    );
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn marginfi_account_liquidation_success_swb() -> anyhow::Result<()> {
     let test_f = TestFixture::new(Some(TestSettings {
@@ -401,7 +658,10 @@ async fn marginfi_account_liquidation_success_swb() -> anyhow::Result<()> {
                 }),
             },
         ],
-        group_config: Some(GroupConfig { admin: None }),
+        group_config: Some(GroupConfig {
+            admin: None,
+            ..GroupConfig::default()
+        }),
         protocol_fees: false,
     }))
     .await;
@@ -524,7 +784,10 @@ async fn marginfi_account_liquidation_failure_liquidatee_not_unhealthy() -> anyh
                 }),
             },
         ],
-        group_config: Some(GroupConfig { admin: None }),
+        group_config: Some(GroupConfig {
+            admin: None,
+            ..GroupConfig::default()
+        }),
         protocol_fees: false,
     }))
     .await;
@@ -628,7 +891,10 @@ async fn marginfi_account_liquidation_failure_liquidator_no_collateral() -> anyh
                 config: None,
             },
         ],
-        group_config: Some(GroupConfig { admin: None }),
+        group_config: Some(GroupConfig {
+            admin: None,
+            ..GroupConfig::default()
+        }),
         protocol_fees: false,
     }))
     .await;
@@ -2,7 +2,10 @@ mod borrow;
 mod close_account;
 mod close_balance;
 mod create_account;
+mod delegate;
 mod deposit;
+mod extend_account;
+mod fixed_term;
 mod flash_loan;
 mod liquidate;
 mod repay;
@@ -142,6 +145,7 @@ async fn marginfi_account_correct_balance_selection_after_closing_position() ->
         &usdc_bank_f.key,
         &mut usdc_bank,
         &mut marginfi_account.lending_account,
+        &mut [],
     );
 
     assert!(bank_account.is_ok());
@@ -1,7 +1,7 @@
 use super::utils::load_and_deserialize;
 use crate::prelude::{
-    get_emissions_authority_address, get_emissions_token_account_address, MintFixture,
-    TokenAccountFixture,
+    get_emissions_authority_address, get_emissions_token_account_address, get_oracle_account_keys,
+    MintFixture, TokenAccountFixture,
 };
 use anchor_lang::{
     prelude::{AccountMeta, Pubkey},
@@ -12,10 +12,10 @@ use fixed::types::I80F48;
 use marginfi::{
     bank_authority_seed,
     state::{
-        marginfi_group::{Bank, BankConfigOpt, BankVaultType},
+        marginfi_group::{Bank, BankConfig, BankConfigOpt, BankVaultType, PendingBankConfig},
         price::{OraclePriceFeedAdapter, OraclePriceType, PriceAdapter},
     },
-    utils::{find_bank_vault_authority_pda, find_bank_vault_pda},
+    utils::{find_bank_vault_authority_pda, find_bank_vault_pda, find_pending_bank_config_pda},
 };
 use solana_program::account_info::IntoAccountInfo;
 use solana_program::instruction::Instruction;
@@ -61,19 +61,26 @@ impl BankFixture {
 
     pub async fn get_price(&self) -> f64 {
         let bank = self.load().await;
-        let oracle_key = bank.config.oracle_keys[0];
-        let mut oracle_account = self
-            .ctx
-            .borrow_mut()
-            .banks_client
-            .get_account(oracle_key)
-            .await
-            .unwrap()
-            .unwrap();
-        let ai = (&oracle_key, &mut oracle_account).into_account_info();
-        let oracle_adapter =
-            OraclePriceFeedAdapter::try_from_bank_config(&bank.config, &[ai], &Clock::default())
+
+        let mut oracle_accounts = vec![];
+        for oracle_key in get_oracle_account_keys(&bank.config) {
+            let oracle_account = self
+                .ctx
+                .borrow_mut()
+                .banks_client
+                .get_account(oracle_key)
+                .await
+                .unwrap()
                 .unwrap();
+            oracle_accounts.push((oracle_key, oracle_account));
+        }
+
+        let ais = oracle_accounts
+            .iter_mut()
+            .map(|(oracle_key, oracle_account)| (&*oracle_key, oracle_account).into_account_info())
+            .collect::<Vec<_>>();
+        let oracle_adapter =
+            OraclePriceFeedAdapter::try_from_bank(&bank, &ais, &Clock::default()).unwrap();
 
         oracle_adapter
             .get_price_of_type(OraclePriceType::RealTime, None)
@@ -85,6 +92,31 @@ impl BankFixture {
         load_and_deserialize::<Bank>(self.ctx.clone(), &self.key).await
     }
 
+    /// Oracle accounts validated when applying `config`, whenever it changes the oracle setup or
+    /// the fixed price.
+    async fn get_config_oracle_accounts(&self, config: &BankConfigOpt) -> Vec<AccountMeta> {
+        let oracle_bank_config = match config.oracle {
+            Some(oracle_config) => Some(BankConfig {
+                oracle_setup: oracle_config.setup,
+                oracle_keys: oracle_config.keys,
+                oracle_source_setups: oracle_config.source_setups,
+                oracle_fallback_setup: oracle_config.fallback_setup,
+                ..BankConfig::default()
+            }),
+            None if config.fixed_price.is_some() => Some(self.load().await.config),
+            None => None,
+        };
+
+        oracle_bank_config
+            .map(|bank_config| {
+                get_oracle_account_keys(&bank_config)
+                    .into_iter()
+                    .map(|k| AccountMeta::new_readonly(k, false))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
     pub async fn update_config(&self, config: BankConfigOpt) -> anyhow::Result<()> {
         let mut accounts = marginfi::accounts::LendingPoolConfigureBank {
             marginfi_group: self.load().await.group,
@@ -92,15 +124,7 @@ impl BankFixture {
             bank: self.key,
         }
         .to_account_metas(Some(true));
-
-        if let Some(oracle_config) = config.oracle {
-            accounts.extend(
-                oracle_config
-                    .keys
-                    .iter()
-                    .map(|k| AccountMeta::new_readonly(*k, false)),
-            );
-        }
+        accounts.extend(self.get_config_oracle_accounts(&config).await);
 
         let ix = Instruction {
             program_id: marginfi::id(),
@@ -127,6 +151,102 @@ impl BankFixture {
         Ok(())
     }
 
+    pub fn get_pending_config_address(&self) -> Pubkey {
+        find_pending_bank_config_pda(&self.key).0
+    }
+
+    pub async fn load_pending_config(&self) -> PendingBankConfig {
+        load_and_deserialize::<PendingBankConfig>(
+            self.ctx.clone(),
+            &self.get_pending_config_address(),
+        )
+        .await
+    }
+
+    pub async fn try_queue_config(&self, config: BankConfigOpt) -> Result<(), BanksClientError> {
+        let accounts = marginfi::accounts::LendingPoolQueueBankConfig {
+            marginfi_group: self.load().await.group,
+            admin: self.ctx.borrow().payer.pubkey(),
+            fee_payer: self.ctx.borrow().payer.pubkey(),
+            bank: self.key,
+            pending_bank_config: self.get_pending_config_address(),
+            system_program: solana_program::system_program::id(),
+        }
+        .to_account_metas(Some(true));
+
+        let ix = Instruction {
+            program_id: marginfi::id(),
+            accounts,
+            data: marginfi::instruction::LendingPoolQueueBankConfig {
+                bank_config_opt: config,
+            }
+            .data(),
+        };
+
+        self.process_payer_signed_ix(ix).await
+    }
+
+    pub async fn try_execute_config(&self) -> Result<(), BanksClientError> {
+        let pending_config = self.load_pending_config().await;
+
+        let mut accounts = marginfi::accounts::LendingPoolExecuteBankConfig {
+            marginfi_group: self.load().await.group,
+            admin: self.ctx.borrow().payer.pubkey(),
+            bank: self.key,
+            pending_bank_config: self.get_pending_config_address(),
+            payer: pending_config.payer,
+        }
+        .to_account_metas(Some(true));
+        accounts.extend(
+            self.get_config_oracle_accounts(&pending_config.config)
+                .await,
+        );
+
+        let ix = Instruction {
+            program_id: marginfi::id(),
+            accounts,
+            data: marginfi::instruction::LendingPoolExecuteBankConfig {}.data(),
+        };
+
+        self.process_payer_signed_ix(ix).await
+    }
+
+    pub async fn try_cancel_config(&self) -> Result<(), BanksClientError> {
+        let pending_config = self.load_pending_config().await;
+
+        let accounts = marginfi::accounts::LendingPoolCancelBankConfig {
+            marginfi_group: self.load().await.group,
+            admin: self.ctx.borrow().payer.pubkey(),
+            bank: self.key,
+            pending_bank_config: self.get_pending_config_address(),
+            payer: pending_config.payer,
+        }
+        .to_account_metas(Some(true));
+
+        let ix = Instruction {
+            program_id: marginfi::id(),
+            accounts,
+            data: marginfi::instruction::LendingPoolCancelBankConfig {}.data(),
+        };
+
+        self.process_payer_signed_ix(ix).await
+    }
+
+    async fn process_payer_signed_ix(&self, ix: Instruction) -> Result<(), BanksClientError> {
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&self.ctx.borrow().payer.pubkey()),
+            &[&self.ctx.borrow().payer],
+            self.ctx.borrow().last_blockhash,
+        );
+
+        self.ctx
+            .borrow_mut()
+            .banks_client
+            .process_transaction(tx)
+            .await
+    }
+
     #[cfg(feature = "lip")]
     pub async fn try_create_campaign(
         &self,
@@ -3,9 +3,8 @@ use crate::ui_to_native;
 use anchor_lang::{prelude::*, system_program, InstructionData, ToAccountMetas};
 
 use marginfi::state::{
-    marginfi_account::MarginfiAccount,
+    marginfi_account::{Balance, MarginfiAccount},
     marginfi_group::{Bank, BankVaultType},
-    price::OracleSetup,
 };
 use solana_program::{instruction::Instruction, sysvar};
 use solana_program_test::{BanksClientError, ProgramTestContext};
@@ -69,6 +68,7 @@ impl MarginfiAccountFixture {
         ui_amount: T,
     ) -> Instruction {
         let marginfi_account = self.load().await;
+        let bank_state = bank.load().await;
         let ctx = self.ctx.borrow_mut();
 
         let mut accounts = marginfi::accounts::LendingAccountDeposit {
@@ -84,6 +84,15 @@ impl MarginfiAccountFixture {
         if bank.mint.token_program == spl_token_2022::ID {
             accounts.push(AccountMeta::new_readonly(bank.mint.key, false));
         }
+        // The USD deposit limit is checked with the bank's oracle
+        if bank_state.is_deposit_limit_usd_active() {
+            accounts.push(AccountMeta::new_readonly(bank.key, false));
+            accounts.extend(
+                get_oracle_account_keys(&bank_state.config)
+                    .into_iter()
+                    .map(|oracle_key| AccountMeta::new_readonly(oracle_key, false)),
+            );
+        }
 
         Instruction {
             program_id: marginfi::id(),
@@ -380,6 +389,131 @@ impl MarginfiAccountFixture {
         Ok(())
     }
 
+    pub async fn try_bank_borrow_fixed_term<T: Into<f64>>(
+        &self,
+        destination_account: Pubkey,
+        bank: &BankFixture,
+        ui_amount: T,
+        duration: u64,
+    ) -> anyhow::Result<(), BanksClientError> {
+        let marginfi_account = self.load().await;
+
+        let mut accounts = marginfi::accounts::LendingAccountBorrowFixedTerm {
+            marginfi_group: marginfi_account.group,
+            marginfi_account: self.key,
+            signer: self.ctx.borrow().payer.pubkey(),
+            bank: bank.key,
+            destination_token_account: destination_account,
+            bank_liquidity_vault: bank.get_vault(BankVaultType::Liquidity).0,
+            bank_liquidity_vault_authority: bank.get_vault_authority(BankVaultType::Liquidity).0,
+            token_program: bank.get_token_program(),
+        }
+        .to_account_metas(Some(true));
+        if bank.mint.token_program == spl_token_2022::ID {
+            accounts.push(AccountMeta::new_readonly(bank.mint.key, false));
+        }
+        accounts.extend_from_slice(
+            &self
+                .load_observation_account_metas(vec![bank.key], vec![])
+                .await,
+        );
+
+        let ix = Instruction {
+            program_id: marginfi::id(),
+            accounts,
+            data: marginfi::instruction::LendingAccountBorrowFixedTerm {
+                amount: ui_to_native!(ui_amount.into(), bank.mint.mint.decimals),
+                duration,
+            }
+            .data(),
+        };
+
+        let mut ctx = self.ctx.borrow_mut();
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&ctx.payer.pubkey().clone()),
+            &[&ctx.payer],
+            ctx.last_blockhash,
+        );
+
+        ctx.banks_client.process_transaction(tx).await?;
+
+        Ok(())
+    }
+
+    pub async fn try_bank_repay_fixed_term(
+        &self,
+        funding_account: Pubkey,
+        bank: &BankFixture,
+    ) -> anyhow::Result<(), BanksClientError> {
+        let marginfi_account = self.load().await;
+        let mut ctx = self.ctx.borrow_mut();
+
+        let mut accounts = marginfi::accounts::LendingAccountRepayFixedTerm {
+            marginfi_group: marginfi_account.group,
+            marginfi_account: self.key,
+            signer: ctx.payer.pubkey(),
+            bank: bank.key,
+            signer_token_account: funding_account,
+            bank_liquidity_vault: bank.get_vault(BankVaultType::Liquidity).0,
+            token_program: bank.get_token_program(),
+        }
+        .to_account_metas(Some(true));
+        if bank.mint.token_program == spl_token_2022::ID {
+            accounts.push(AccountMeta::new_readonly(bank.mint.key, false));
+        }
+
+        let ix = Instruction {
+            program_id: marginfi::id(),
+            accounts,
+            data: marginfi::instruction::LendingAccountRepayFixedTerm {}.data(),
+        };
+
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&ctx.payer.pubkey().clone()),
+            &[&ctx.payer],
+            ctx.last_blockhash,
+        );
+
+        ctx.banks_client.process_transaction(tx).await?;
+
+        Ok(())
+    }
+
+    pub async fn try_convert_fixed_term(
+        &self,
+        bank: &BankFixture,
+    ) -> anyhow::Result<(), BanksClientError> {
+        let marginfi_account = self.load().await;
+
+        let mut accounts = marginfi::accounts::LendingAccountConvertFixedTerm {
+            marginfi_group: marginfi_account.group,
+            marginfi_account: self.key,
+            bank: bank.key,
+        }
+        .to_account_metas(Some(true));
+        accounts.extend_from_slice(&self.load_observation_account_metas(vec![], vec![]).await);
+
+        let ix = Instruction {
+            program_id: marginfi::id(),
+            accounts,
+            data: marginfi::instruction::LendingAccountConvertFixedTerm {}.data(),
+        };
+
+        let mut ctx = self.ctx.borrow_mut();
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&ctx.payer.pubkey().clone()),
+            &[&ctx.payer],
+            ctx.last_blockhash,
+        );
+
+        ctx.banks_client.process_transaction(tx).await?;
+
+        Ok(())
+    }
+
     pub async fn try_balance_close(
         &self,
         bank: &BankFixture,
@@ -445,19 +579,8 @@ impl MarginfiAccountFixture {
 
         let oracle_accounts = vec![asset_bank.config, liab_bank.config]
             .iter()
-            .map(|config| {
-                AccountMeta::new_readonly(
-                    {
-                        match config.oracle_setup {
-                            OracleSetup::PythPushOracle => {
-                                get_oracle_id_from_feed_id(config.oracle_keys[0]).unwrap()
-                            }
-                            _ => config.oracle_keys[0],
-                        }
-                    },
-                    false,
-                )
-            })
+            .flat_map(get_oracle_account_keys)
+            .map(|oracle_key| AccountMeta::new_readonly(oracle_key, false))
             .collect::<Vec<AccountMeta>>();
 
         accounts.extend(oracle_accounts);
@@ -613,13 +736,50 @@ impl MarginfiAccountFixture {
         ctx.banks_client.process_transaction(tx).await
     }
 
+    /// Set the account's limits
+    ///
+    /// Function assumes signer is group admin
+    pub async fn try_configure_limits(
+        &self,
+        max_liability_value: u64,
+        allowed_banks: Vec<Pubkey>,
+    ) -> std::result::Result<(), BanksClientError> {
+        let ix = Instruction {
+            program_id: marginfi::id(),
+            accounts: marginfi::accounts::ConfigureAccountLimits {
+                marginfi_group: self.load().await.group,
+                marginfi_account: self.key,
+                admin: self.ctx.borrow().payer.pubkey(),
+            }
+            .to_account_metas(Some(true)),
+            data: marginfi::instruction::ConfigureAccountLimits {
+                max_liability_value,
+                allowed_banks,
+            }
+            .data(),
+        };
+
+        let mut ctx = self.ctx.borrow_mut();
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&ctx.payer.pubkey().clone()),
+            &[&ctx.payer],
+            ctx.last_blockhash,
+        );
+
+        ctx.banks_client.process_transaction(tx).await
+    }
+
     pub async fn make_lending_account_start_flashloan_ix(&self, end_index: u64) -> Instruction {
+        let marginfi_account = self.load().await;
+
         Instruction {
             program_id: marginfi::id(),
             accounts: marginfi::accounts::LendingAccountStartFlashloan {
                 marginfi_account: self.key,
                 signer: self.ctx.borrow().payer.pubkey(),
                 ixs_sysvar: sysvar::instructions::id(),
+                marginfi_group: marginfi_account.group,
             }
             .to_account_metas(Some(true)),
             data: marginfi::instruction::LendingAccountStartFlashloan { end_index }.data(),
@@ -631,9 +791,11 @@ impl MarginfiAccountFixture {
         include_banks: Vec<Pubkey>,
         exclude_banks: Vec<Pubkey>,
     ) -> Instruction {
+        let marginfi_account = self.load().await;
         let mut account_metas = marginfi::accounts::LendingAccountEndFlashloan {
             marginfi_account: self.key,
             signer: self.ctx.borrow().payer.pubkey(),
+            marginfi_group: marginfi_account.group,
         }
         .to_account_metas(Some(true));
 
@@ -686,11 +848,10 @@ impl MarginfiAccountFixture {
         exclude_banks: Vec<Pubkey>,
     ) -> Vec<AccountMeta> {
         let marginfi_account = self.load().await;
+        let extended_balances = self.load_extended_balances().await;
         // Check all active banks in marginfi account balances
         let mut bank_pks = marginfi_account
-            .lending_account
-            .balances
-            .iter()
+            .get_balances(&extended_balances)
             .filter_map(|balance| {
                 if balance.active {
                     Some(balance.bank_pk)
@@ -721,28 +882,21 @@ impl MarginfiAccountFixture {
             .iter()
             .zip(bank_pks.iter())
             .flat_map(|(bank, bank_pk)| {
-                let oracle_key = {
-                    let oracle_key = bank.config.oracle_keys[0];
-                    match bank.config.oracle_setup {
-                        OracleSetup::PythPushOracle => {
-                            get_oracle_id_from_feed_id(oracle_key).unwrap()
-                        }
-                        _ => oracle_key,
-                    }
-                };
-
-                vec![
-                    AccountMeta {
-                        pubkey: *bank_pk,
-                        is_signer: false,
-                        is_writable: false,
-                    },
-                    AccountMeta {
+                let mut account_metas = vec![AccountMeta {
+                    pubkey: *bank_pk,
+                    is_signer: false,
+                    is_writable: false,
+                }];
+
+                account_metas.extend(get_oracle_account_keys(&bank.config).into_iter().map(
+                    |oracle_key| AccountMeta {
                         pubkey: oracle_key,
                         is_signer: false,
                         is_writable: false,
                     },
-                ]
+                ));
+
+                account_metas
             })
             .collect::<Vec<_>>();
         account_metas
@@ -762,13 +916,113 @@ impl MarginfiAccountFixture {
     }
 
     pub async fn load(&self) -> MarginfiAccount {
-        load_and_deserialize::<MarginfiAccount>(self.ctx.clone(), &self.key).await
+        let data = self.load_data().await;
+        // Extended accounts hold their extended balances past the `MarginfiAccount`
+        MarginfiAccount::try_deserialize(&mut &data[..Self::get_size()]).unwrap()
+    }
+
+    pub async fn load_extended_balances(&self) -> Vec<Balance> {
+        MarginfiAccount::decode_extended_balances(&self.load_data().await)
+    }
+
+    async fn load_data(&self) -> Vec<u8> {
+        self.ctx
+            .borrow_mut()
+            .banks_client
+            .get_account(self.key)
+            .await
+            .unwrap()
+            .unwrap()
+            .data
     }
 
     pub fn get_size() -> usize {
         mem::size_of::<MarginfiAccount>() + 8
     }
 
+    /// Migrate the account to the extended layout, signed by the account authority
+    pub async fn try_extend(&self) -> std::result::Result<(), BanksClientError> {
+        let mut ctx = self.ctx.borrow_mut();
+
+        let ix = Instruction {
+            program_id: marginfi::id(),
+            accounts: marginfi::accounts::MarginfiAccountExtend {
+                marginfi_account: self.key,
+                authority: ctx.payer.pubkey(),
+                fee_payer: ctx.payer.pubkey(),
+                system_program: system_program::ID,
+            }
+            .to_account_metas(Some(true)),
+            data: marginfi::instruction::MarginfiAccountExtend {}.data(),
+        };
+
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&ctx.payer.pubkey().clone()),
+            &[&ctx.payer],
+            ctx.last_blockhash,
+        );
+
+        ctx.banks_client.process_transaction(tx).await
+    }
+
+    /// Register a delegate with `permissions`, signed by the account authority
+    pub async fn try_set_delegate(
+        &self,
+        delegate: Pubkey,
+        permissions: u64,
+    ) -> std::result::Result<(), BanksClientError> {
+        let mut ctx = self.ctx.borrow_mut();
+
+        let ix = Instruction {
+            program_id: marginfi::id(),
+            accounts: marginfi::accounts::MarginfiAccountSetDelegate {
+                marginfi_account: self.key,
+                authority: ctx.payer.pubkey(),
+            }
+            .to_account_metas(Some(true)),
+            data: marginfi::instruction::MarginfiAccountSetDelegate {
+                delegate,
+                permissions,
+            }
+            .data(),
+        };
+
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&ctx.payer.pubkey().clone()),
+            &[&ctx.payer],
+            ctx.last_blockhash,
+        );
+
+        ctx.banks_client.process_transaction(tx).await
+    }
+
+    /// Send an ix made by one of the `make_*_ix` functions, signed by `delegate` in place of the
+    /// account authority
+    pub async fn try_as_delegate(
+        &self,
+        mut ix: Instruction,
+        delegate: &Keypair,
+    ) -> std::result::Result<(), BanksClientError> {
+        let mut ctx = self.ctx.borrow_mut();
+        let authority = ctx.payer.pubkey();
+
+        ix.accounts
+            .iter_mut()
+            .filter(|meta| meta.is_signer && meta.pubkey == authority)
+            .for_each(|meta| meta.pubkey = delegate.pubkey());
+
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&ctx.payer.pubkey().clone()),
+            &[&ctx.payer, delegate],
+            ctx.last_blockhash,
+        );
+
+        ctx.banks_client.process_transaction(tx).await
+    }
+
     /// Use the client to send the transfer ix authority transaction
     /// Pass the new authority as an argument
     /// Optional: use a different signer (for negative test case)
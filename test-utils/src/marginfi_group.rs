@@ -1,5 +1,5 @@
 use super::{bank::BankFixture, marginfi_account::MarginfiAccountFixture};
-use crate::prelude::{get_oracle_id_from_feed_id, MintFixture};
+use crate::prelude::{get_oracle_account_keys, get_oracle_id_from_feed_id, MintFixture};
 use crate::utils::*;
 use anchor_lang::{prelude::*, solana_program::system_program, InstructionData};
 
@@ -331,15 +331,25 @@ impl MarginfiGroupFixture {
     }
 
     pub async fn try_accrue_interest(&self, bank: &BankFixture) -> Result<()> {
+        let bank_config = bank.load().await.config;
         let mut ctx = self.ctx.borrow_mut();
 
+        let mut accounts = marginfi::accounts::LendingPoolAccrueBankInterest {
+            marginfi_group: self.key,
+            bank: bank.key,
+        }
+        .to_account_metas(Some(true));
+
+        // Oracle accounts, to record the price of banks with a TWAP or a price guard
+        accounts.extend(
+            get_oracle_account_keys(&bank_config)
+                .into_iter()
+                .map(|oracle_key| AccountMeta::new_readonly(oracle_key, false)),
+        );
+
         let ix = Instruction {
             program_id: marginfi::id(),
-            accounts: marginfi::accounts::LendingPoolAccrueBankInterest {
-                marginfi_group: self.key,
-                bank: bank.key,
-            }
-            .to_account_metas(Some(true)),
+            accounts,
             data: marginfi::instruction::LendingPoolAccrueBankInterest {}.data(),
         };
 
@@ -382,6 +392,38 @@ impl MarginfiGroupFixture {
         Ok(())
     }
 
+    /// Sets the group's pause state, signed by `signer` (the guardian or the admin)
+    pub async fn try_set_pause_state(
+        &self,
+        signer: &Keypair,
+        pause_state: u8,
+    ) -> Result<(), BanksClientError> {
+        let ix = Instruction {
+            program_id: marginfi::id(),
+            accounts: marginfi::accounts::MarginfiGroupSetPauseState {
+                marginfi_group: self.key,
+                signer: signer.pubkey(),
+            }
+            .to_account_metas(Some(true)),
+            data: marginfi::instruction::MarginfiGroupSetPauseState { pause_state }.data(),
+        };
+
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&self.ctx.borrow().payer.pubkey().clone()),
+            &[&self.ctx.borrow().payer, signer],
+            self.ctx.borrow().last_blockhash,
+        );
+
+        self.ctx
+            .borrow_mut()
+            .banks_client
+            .process_transaction(tx)
+            .await?;
+
+        Ok(())
+    }
+
     pub async fn try_collect_fees(&self, bank: &BankFixture) -> Result<()> {
         let mut ctx = self.ctx.borrow_mut();
 
@@ -80,7 +80,10 @@ impl TestSettings {
 
         Self {
             banks,
-            group_config: Some(GroupConfig { admin: None }),
+            group_config: Some(GroupConfig {
+                admin: None,
+                ..GroupConfig::default()
+            }),
             protocol_fees: false,
         }
     }
@@ -98,7 +101,10 @@ impl TestSettings {
                     config: Some(*DEFAULT_SOL_TEST_SW_BANK_CONFIG),
                 },
             ],
-            group_config: Some(GroupConfig { admin: None }),
+            group_config: Some(GroupConfig {
+                admin: None,
+                ..GroupConfig::default()
+            }),
             protocol_fees: false,
         }
     }
@@ -124,7 +130,10 @@ impl TestSettings {
                     }),
                 },
             ],
-            group_config: Some(GroupConfig { admin: None }),
+            group_config: Some(GroupConfig {
+                admin: None,
+                ..GroupConfig::default()
+            }),
             protocol_fees: false,
         }
     }
@@ -173,7 +182,10 @@ impl TestSettings {
                     ..TestBankSetting::default()
                 },
             ],
-            group_config: Some(GroupConfig { admin: None }),
+            group_config: Some(GroupConfig {
+                admin: None,
+                ..GroupConfig::default()
+            }),
             protocol_fees: false,
         }
     }
@@ -262,6 +274,18 @@ pub fn get_oracle_id_from_feed_id(feed_id: Pubkey) -> Option<Pubkey> {
     }
 }
 
+/// Oracle accounts to pass after the bank in remaining accounts, in the order the program expects.
+pub fn get_oracle_account_keys(bank_config: &BankConfig) -> Vec<Pubkey> {
+    bank_config
+        .get_oracle_sources()
+        .into_iter()
+        .map(|(oracle_setup, oracle_key)| match oracle_setup {
+            OracleSetup::PythPushOracle => get_oracle_id_from_feed_id(oracle_key).unwrap(),
+            _ => oracle_key,
+        })
+        .collect()
+}
+
 pub fn create_oracle_key_array(pyth_oracle: Pubkey) -> [Pubkey; MAX_ORACLE_KEYS] {
     let mut keys = [Pubkey::default(); MAX_ORACLE_KEYS];
     keys[0] = pyth_oracle;
@@ -642,8 +666,16 @@ impl TestFixture {
             Rc::clone(&context),
             test_settings
                 .clone()
-                .map(|ts| ts.group_config.unwrap_or(GroupConfig { admin: None }))
-                .unwrap_or(GroupConfig { admin: None }),
+                .map(|ts| {
+                    ts.group_config.unwrap_or(GroupConfig {
+                        admin: None,
+                        ..GroupConfig::default()
+                    })
+                })
+                .unwrap_or(GroupConfig {
+                    admin: None,
+                    ..GroupConfig::default()
+                }),
         )
         .await;
 
@@ -2,6 +2,7 @@ use anchor_lang::prelude::*;
 use anchor_lang_29::Discriminator;
 use anchor_spl::token_2022::spl_token_2022::extension::transfer_fee::MAX_FEE_BASIS_POINTS;
 use marginfi::constants::PYTH_ID;
+use marginfi::constants::SPL_STAKE_POOL_ID;
 use marginfi::constants::SWITCHBOARD_PULL_ID;
 use pyth_sdk_solana::state::{
     AccountType, PriceInfo, PriceStatus, Rational, SolanaPriceAccount, MAGIC, VERSION_2,
@@ -149,6 +150,31 @@ pub fn create_pyth_push_oracle_account(
     create_pyth_push_oracle_account_from_bytes(data)
 }
 
+/// SPL stake pool account, only the fields read by `StakePoolPriceFeed` are set
+pub fn create_spl_stake_pool_account(
+    pool_mint: Pubkey,
+    total_lamports: u64,
+    pool_token_supply: u64,
+    last_update_epoch: u64,
+) -> Account {
+    let mut data = vec![0; 282];
+
+    // AccountType::StakePool
+    data[0] = 1;
+    data[162..194].copy_from_slice(pool_mint.as_ref());
+    data[258..266].copy_from_slice(&total_lamports.to_le_bytes());
+    data[266..274].copy_from_slice(&pool_token_supply.to_le_bytes());
+    data[274..282].copy_from_slice(&last_update_epoch.to_le_bytes());
+
+    Account {
+        lamports: 1_000_000,
+        data,
+        owner: SPL_STAKE_POOL_ID,
+        executable: false,
+        rent_epoch: 361,
+    }
+}
+
 pub fn create_switch_pull_oracle_account_from_bytes(data: Vec<u8>) -> Account {
     Account {
         lamports: 1_000_000,
@@ -164,10 +164,7 @@ impl DefiLammaPoolInfo {
             I80F48::ZERO
         };
 
-        let ir_calc = bank
-            .config
-            .interest_rate_config
-            .create_interest_rate_calculator(group);
+        let ir_calc = bank.create_interest_rate_calculator(group);
 
         let ComputedInterestRates {
             lending_rate_apr,